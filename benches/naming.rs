@@ -0,0 +1,85 @@
+// Released under MIT License.
+// Copyright (c) 2023 Ladislav Bartos
+
+//! Benchmark of the generation of backup names.
+//!
+//! Counts the heap allocations performed per generated name and measures the time per name.
+//! Run using `cargo bench --bench naming`.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::hint::black_box;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+use backitup::{backup_name, BackupOptions};
+use chrono::prelude::*;
+
+/// Allocator counting the number of allocations.
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        // SAFETY: the layout is passed unchanged to the system allocator
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        // SAFETY: the pointer was allocated by the system allocator with the same layout
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        // SAFETY: the pointer was allocated by the system allocator with the same layout
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+const ITERATIONS: usize = 100_000;
+
+/// Calls `f` repeatedly and prints the number of allocations and the time per call.
+fn bench(name: &str, mut f: impl FnMut()) {
+    // warm up
+    f();
+
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let start = Instant::now();
+
+    for _ in 0..ITERATIONS {
+        f();
+    }
+
+    let elapsed = start.elapsed();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - allocations;
+
+    println!(
+        "{}: {:.1} allocations/name, {:.0} ns/name",
+        name,
+        allocations as f64 / ITERATIONS as f64,
+        elapsed.as_nanos() as f64 / ITERATIONS as f64,
+    );
+}
+
+fn main() {
+    let time = Local.with_ymd_and_hms(2023, 6, 27, 21, 1, 13).unwrap();
+
+    bench("backup_name", || {
+        black_box(backup_name(black_box("output/data.txt"), time).unwrap());
+    });
+
+    // the options are created only once
+    let options = BackupOptions::new();
+    bench("BackupOptions::backup_name", || {
+        black_box(
+            options
+                .backup_name(black_box("output/data.txt"), time)
+                .unwrap(),
+        );
+    });
+}
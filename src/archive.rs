@@ -0,0 +1,296 @@
+// Released under MIT License.
+// Copyright (c) 2023 Ladislav Bartos
+
+//! Implementation of backups into zip archives.
+
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{self, ErrorKind, Seek, SeekFrom};
+use std::path::Path;
+
+use chrono::prelude::*;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::{
+    check_file_type, replace_via_temp, trim_trailing_separators, write_via_temp, BackupError,
+    BackupOptions, SystemClock,
+};
+
+/// Backs up the specified file into a zip archive.
+/// Returns the name of the entry in the archive if successful, otherwise returns an error.
+///
+/// # Arguments
+///
+/// * `path` - The path to the file to be backed up.
+/// * `archive` - The path to the zip archive. It is created if it does not exist.
+///
+/// # Errors
+///
+/// This function can return the same errors as [`crate::backup`] and additionally:
+///
+/// * `BackupError::Unsupported` - If the `path` is a directory or a special file (e.g. a FIFO or a socket).
+/// * `BackupError::NotUtf8` - If the filename is not a valid UTF-8 (zip entries are named using strings).
+/// * `BackupError::Io` - If the `archive` can not be read or written, e.g. because it is not a valid zip archive.
+///
+/// # Details
+/// The content of the file is compressed (using deflate) into a new entry of the `archive`
+/// named `#<filename>-<timestamp>(-<microseconds>)#`, where microseconds are only added if the archive already
+/// contains an entry with the same name. The modification time of the entry is set to the time of the backup.
+/// The original file is then **removed**. If the backup fails, the original file is left untouched.
+///
+/// The existing entries of the archive are kept, so all backups accumulate in a single file.
+/// The updated archive is written into a temporary `.tmp` sibling of the archive which then replaces it,
+/// so the existing entries are never lost, even if the backup fails or the process crashes.
+/// Note that backups into the same archive made by multiple processes at once may still overwrite each other.
+///
+/// # Examples
+///
+/// ```no_run
+/// use crate::backitup::backup_into_zip;
+///
+/// let path = "output.log";
+/// match backup_into_zip(path, "backups.zip") {
+///     Ok(entry) => println!("Backup created: {}", entry),
+///     Err(err) => eprintln!("Failed to create backup: {:?}", err),
+/// }
+/// ```
+pub fn backup_into_zip(
+    path: impl AsRef<Path>,
+    archive: impl AsRef<Path>,
+) -> Result<String, BackupError> {
+    let path = trim_trailing_separators(path.as_ref());
+    let archive = archive.as_ref();
+
+    // check if the path exists (broken symbolic links also exist)
+    if fs::symlink_metadata(path).is_err() {
+        return Err(BackupError::NotFound);
+    }
+
+    if path.is_dir() {
+        return Err(BackupError::Unsupported(String::from(
+            "Directories can not be backed up into zip archives.",
+        )));
+    }
+
+    // reading from special files may block indefinitely
+    check_file_type(path, false)?;
+
+    let file = match File::open(archive) {
+        Ok(file) => Some(file),
+        Err(e) if e.kind() == ErrorKind::NotFound => None,
+        Err(e) => return Err(BackupError::Io(e)),
+    };
+
+    let existing = match &file {
+        Some(file) => zip_entries(file)?,
+        None => HashSet::new(),
+    };
+
+    // the name of the entry must be unique in the archive, not in the directory
+    let options = BackupOptions::new();
+    let mut names = options.backup_names(path, &SystemClock)?;
+    let name = loop {
+        let name = names.next_name()?;
+        let name = name
+            .file_name()
+            .and_then(|x| x.to_str())
+            .ok_or(BackupError::NotUtf8)?
+            .to_owned();
+
+        if !existing.contains(&name) {
+            break name;
+        }
+    };
+
+    // the archive is never modified in place, so a failure can not corrupt the existing entries
+    match file {
+        Some(mut file) => replace_via_temp(archive, |temp| {
+            let mut copy = File::options()
+                .read(true)
+                .write(true)
+                .create_new(true)
+                .open(temp)?;
+            file.seek(SeekFrom::Start(0))?;
+            io::copy(&mut file, &mut copy)?;
+            copy.set_permissions(file.metadata()?.permissions())?;
+
+            let writer = ZipWriter::new_append(copy).map_err(io::Error::from)?;
+            write_entry(writer, path, &name, names.time())
+        }),
+        None => write_via_temp(archive, |temp| {
+            write_entry(
+                ZipWriter::new(File::create_new(temp)?),
+                path,
+                &name,
+                names.time(),
+            )
+        }),
+    }?;
+
+    fs::remove_file(path)?;
+    Ok(name)
+}
+
+/// Returns the names of all entries of the zip archive `file`.
+fn zip_entries(file: &File) -> Result<HashSet<String>, BackupError> {
+    let archive = ZipArchive::new(file).map_err(io::Error::from)?;
+
+    let names = archive
+        .file_names()
+        .map(|x| x.map(|name| name.into_owned()))
+        .collect::<Result<HashSet<_>, _>>()
+        .map_err(io::Error::from)?;
+
+    Ok(names)
+}
+
+/// Compresses the content of the file at `path` into a new entry named `name` and finishes the archive.
+fn write_entry(
+    mut writer: ZipWriter<File>,
+    path: &Path,
+    name: &str,
+    time: DateTime<FixedOffset>,
+) -> Result<(), io::Error> {
+    let mut reader = File::open(path)?;
+    let size = reader.metadata()?.len();
+
+    let mut options = SimpleFileOptions::default()
+        .compression_method(CompressionMethod::Deflated)
+        .large_file(size >= u64::from(u32::MAX));
+
+    // zip archives can not store times before 1980
+    if let Ok(time) = zip::DateTime::from_date_and_time(
+        u16::try_from(time.year()).unwrap_or(0),
+        time.month() as u8,
+        time.day() as u8,
+        time.hour() as u8,
+        time.minute() as u8,
+        time.second() as u8,
+    ) {
+        options = options.last_modified_time(time);
+    }
+
+    writer.start_file(name, options).map_err(io::Error::from)?;
+    io::copy(&mut reader, &mut writer)?;
+    writer.finish().map_err(io::Error::from)?.sync_all()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::test_dir;
+    use std::io::Read;
+
+    #[test]
+    fn zip() {
+        let tmp = test_dir();
+        fs::write(tmp.join("file1.txt"), "Content of file 1.").unwrap();
+        fs::write(tmp.join("file2.txt"), "Content of file 2.").unwrap();
+
+        let mut entries = Vec::new();
+        for name in ["file1.txt", "file2.txt"] {
+            let path = tmp.join(name);
+            let entry = match backup_into_zip(&path, tmp.join("backups.zip")) {
+                Ok(x) => x,
+                Err(_) => panic!("Backup failed."),
+            };
+
+            assert!(crate::is_backup_name(&entry));
+            assert!(entry.starts_with(&format!("#{}-", name)));
+            assert!(!path.exists());
+            entries.push(entry);
+        }
+
+        // a second backup of the same file within the same second gets a unique entry name
+        fs::write(tmp.join("file1.txt"), "New content of file 1.").unwrap();
+        let entry = backup_into_zip(tmp.join("file1.txt"), tmp.join("backups.zip")).unwrap();
+        assert!(!entries.contains(&entry));
+        entries.push(entry);
+
+        let mut archive = ZipArchive::new(File::open(tmp.join("backups.zip")).unwrap()).unwrap();
+        assert_eq!(archive.len(), 3);
+
+        for (entry, content) in entries.iter().zip([
+            "Content of file 1.",
+            "Content of file 2.",
+            "New content of file 1.",
+        ]) {
+            let mut read = String::new();
+            archive
+                .by_name(entry)
+                .unwrap()
+                .read_to_string(&mut read)
+                .unwrap();
+            assert_eq!(read, content);
+        }
+    }
+
+    #[test]
+    fn zip_invalid() {
+        let tmp = test_dir();
+        fs::create_dir(tmp.join("test_dir_zip2")).unwrap();
+        fs::write(tmp.join("test_dir_zip2/file.txt"), "Some content to test.").unwrap();
+        fs::write(tmp.join("test_dir_zip2/backups.zip"), "Not a zip archive.").unwrap();
+
+        match backup_into_zip(
+            tmp.join("test_dir_zip2/file.txt"),
+            tmp.join("test_dir_zip2/backups.zip"),
+        ) {
+            Ok(_) => panic!("Backup should have failed, but it was successful."),
+            Err(e) => assert!(matches!(e, BackupError::Io(_))),
+        }
+
+        match backup_into_zip(
+            tmp.join("test_dir_zip2"),
+            tmp.join("test_dir_zip2/backups.zip"),
+        ) {
+            Ok(_) => panic!("Backup should have failed, but it was successful."),
+            Err(e) => assert!(matches!(e, BackupError::Unsupported(_))),
+        }
+
+        // the original and the existing file are left untouched
+        assert!(tmp.join("test_dir_zip2/file.txt").exists());
+        assert_eq!(
+            fs::read_to_string(tmp.join("test_dir_zip2/backups.zip")).unwrap(),
+            "Not a zip archive."
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn zip_not_in_place() {
+        let tmp = test_dir();
+        fs::create_dir(tmp.join("test_dir_zip3")).unwrap();
+        let archive = &tmp.join("test_dir_zip3/backups.zip");
+        fs::write(tmp.join("test_dir_zip3/file1.txt"), "Content of file 1.").unwrap();
+        fs::write(tmp.join("test_dir_zip3/file2.txt"), "Content of file 2.").unwrap();
+
+        backup_into_zip(tmp.join("test_dir_zip3/file1.txt"), archive).unwrap();
+        let original = fs::read(archive).unwrap();
+        // the link keeps the old archive if the archive is replaced instead of modified
+        fs::hard_link(archive, tmp.join("test_dir_zip3/old.zip")).unwrap();
+
+        backup_into_zip(tmp.join("test_dir_zip3/file2.txt"), archive).unwrap();
+        assert_eq!(
+            fs::read(tmp.join("test_dir_zip3/old.zip")).unwrap(),
+            original
+        );
+        assert_eq!(
+            ZipArchive::new(File::open(archive).unwrap()).unwrap().len(),
+            2
+        );
+        assert_eq!(fs::read_dir(tmp.join("test_dir_zip3")).unwrap().count(), 2);
+
+        // a failed backup leaves the archive untouched
+        fs::write(tmp.join("test_dir_zip3/file3.txt"), "Content of file 3.").unwrap();
+        File::create(tmp.join("test_dir_zip3/backups.zip.tmp")).unwrap();
+        let updated = fs::read(archive).unwrap();
+        match backup_into_zip(tmp.join("test_dir_zip3/file3.txt"), archive) {
+            Ok(_) => panic!("Backup should have failed, but it was successful."),
+            Err(e) => assert!(matches!(e, BackupError::Io(_))),
+        }
+        assert_eq!(fs::read(archive).unwrap(), updated);
+        assert!(tmp.join("test_dir_zip3/file3.txt").exists());
+    }
+}
@@ -0,0 +1,326 @@
+// Released under MIT License.
+// Copyright (c) 2023 Ladislav Bartos
+
+//! Implementation of asynchronous backups.
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::{
+    check_directory_link, check_file_type, rename_noreplace, trim_trailing_separators, BackupError,
+    BackupOptions, SystemClock,
+};
+
+/// Asynchronously creates a backup of the specified file or directory.
+/// Returns the path to the backup file if successful, otherwise returns an error.
+///
+/// Available with the `async` feature.
+///
+/// # Arguments
+///
+/// * `path` - The path to the file or directory to be backed up.
+///
+/// # Errors
+///
+/// This function can return the same errors as [`crate::backup`].
+///
+/// # Details
+/// This function behaves like [`crate::backup`], but the filesystem is accessed
+/// using `tokio::fs`, so it does not block the asynchronous executor.
+///
+/// # Examples
+///
+/// ```no_run
+/// use crate::backitup::backup_async;
+///
+/// # async fn example() {
+/// let path = "data.txt";
+/// match backup_async(path).await {
+///     Ok(backup_path) => println!("Backup created: {:?}", backup_path),
+///     Err(err) => eprintln!("Failed to create backup: {:?}", err),
+/// }
+/// # }
+/// ```
+pub async fn backup_async(path: impl AsRef<Path>) -> Result<PathBuf, BackupError> {
+    let path = trim_trailing_separators(path.as_ref());
+
+    // check if the path exists (broken symbolic links also exist)
+    if tokio::fs::symlink_metadata(path).await.is_err() {
+        return Err(BackupError::NotFound);
+    }
+
+    // special files and directory links are rejected in the same way as with `backup`
+    let checked = path.to_path_buf();
+    match tokio::task::spawn_blocking(move || {
+        check_file_type(&checked, false)?;
+        check_directory_link(&checked, false)
+    })
+    .await
+    {
+        Ok(Ok(())) => (),
+        // the original has been moved away in the meantime
+        Ok(Err(BackupError::Io(e))) if e.kind() == io::ErrorKind::NotFound => {
+            return Err(BackupError::NotFound)
+        }
+        Ok(Err(e)) => return Err(e),
+        Err(e) => return Err(BackupError::Io(io::Error::other(e))),
+    }
+
+    let options = BackupOptions::new();
+    let mut names = options.backup_names(path, &SystemClock)?;
+
+    loop {
+        // if a file with the same name already exists, append microseconds
+        // repeat until the name of the backup is unique
+        let mut backup_name = names.next_name()?;
+        while tokio::fs::symlink_metadata(&backup_name).await.is_ok() {
+            backup_name = names.next_name()?;
+        }
+
+        // rename the original file to the backup name
+        // (if the name has been taken in the meantime, try the next one)
+        match rename_noreplace_async(path, &backup_name).await {
+            Ok(()) => return Ok(backup_name),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            // the original has been moved away during the backup (e.g. by a concurrent backup of the same path)
+            Err(e)
+                if e.kind() == io::ErrorKind::NotFound
+                    && tokio::fs::symlink_metadata(path).await.is_err() =>
+            {
+                return Err(BackupError::NotFound)
+            }
+            Err(e) => return Err(BackupError::Io(e)),
+        }
+    }
+}
+
+/// Asynchronously renames `source` to `target`, without overwriting `target` if it exists.
+async fn rename_noreplace_async(source: &Path, target: &Path) -> Result<(), io::Error> {
+    let (source, target) = (source.to_path_buf(), target.to_path_buf());
+
+    match tokio::task::spawn_blocking(move || rename_noreplace(&source, &target)).await {
+        Ok(result) => result,
+        Err(e) => Err(io::Error::other(e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::test_dir;
+    use std::fs::{self, File};
+    use std::io::prelude::*;
+
+    #[tokio::test]
+    async fn file() {
+        let tmp = test_dir();
+        let mut file = File::create(tmp.join("test_file_async1.txt")).unwrap();
+        file.write_all(b"Some content to test.").unwrap();
+
+        let backup = match backup_async(tmp.join("test_file_async1.txt")).await {
+            Ok(x) => x,
+            Err(_) => panic!("Backup failed."),
+        };
+
+        drop(file);
+
+        let mut content = String::new();
+        let mut read = File::open(&backup).unwrap();
+        read.read_to_string(&mut content).unwrap();
+
+        assert_eq!(content, "Some content to test.");
+    }
+
+    #[tokio::test]
+    async fn file_multiple_backups() {
+        let tmp = test_dir();
+        let mut backups = Vec::new();
+        for i in 0..20 {
+            let mut file = File::create(tmp.join("test_file_async2.txt")).unwrap();
+            let text = format!("Unique string for file {}", i);
+            file.write_all(text.as_bytes()).unwrap();
+
+            let backup = match backup_async(tmp.join("test_file_async2.txt")).await {
+                Ok(x) => x,
+                Err(_) => panic!("Backup failed."),
+            };
+
+            backups.push(backup);
+        }
+
+        for (i, path) in backups.iter().enumerate() {
+            let mut content = String::new();
+            let mut read = File::open(path).unwrap();
+
+            read.read_to_string(&mut content).unwrap();
+
+            let test = format!("Unique string for file {}", i);
+            assert_eq!(content, test);
+
+            fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn file_in_different_directory() {
+        let tmp = test_dir();
+        fs::create_dir(tmp.join("test_dir_async")).unwrap();
+
+        let mut file = File::create(tmp.join("test_dir_async/test_file.txt")).unwrap();
+        file.write_all(b"Some content to test.").unwrap();
+
+        let backup = match backup_async(tmp.join("test_dir_async/test_file.txt")).await {
+            Ok(x) => x,
+            Err(_) => panic!("Backup failed."),
+        };
+
+        drop(file);
+
+        let mut content = String::new();
+        let mut read = File::open(&backup).unwrap();
+        read.read_to_string(&mut content).unwrap();
+
+        assert_eq!(content, "Some content to test.");
+    }
+
+    #[tokio::test]
+    async fn directory() {
+        let tmp = test_dir();
+        fs::create_dir(tmp.join("test_dir_async2")).unwrap();
+
+        let mut file = File::create(tmp.join("test_dir_async2/test_file.txt")).unwrap();
+        file.write_all(b"Some content to test.").unwrap();
+
+        let backup = match backup_async(tmp.join("test_dir_async2")).await {
+            Ok(x) => x,
+            Err(_) => panic!("Backup failed."),
+        };
+
+        drop(file);
+
+        let mut content = String::new();
+        let file_in_backup = backup.join("test_file.txt");
+        let mut read = File::open(&file_in_backup).unwrap();
+        read.read_to_string(&mut content).unwrap();
+
+        assert_eq!(content, "Some content to test.");
+    }
+
+    #[tokio::test]
+    async fn directory_multiple_backups() {
+        let tmp = test_dir();
+        let mut backups = Vec::new();
+        for i in 0..10 {
+            fs::create_dir(tmp.join("test_dir_async3")).unwrap();
+
+            let mut file = File::create(tmp.join("test_dir_async3/test_file.txt")).unwrap();
+            let text = format!("Unique string for file {}", i);
+            file.write_all(text.as_bytes()).unwrap();
+
+            let backup = match backup_async(tmp.join("test_dir_async3")).await {
+                Ok(x) => x,
+                Err(_) => panic!("Backup failed."),
+            };
+
+            backups.push(backup);
+        }
+
+        for (i, path) in backups.iter().enumerate() {
+            let file_in_backup = path.join("test_file.txt");
+
+            let mut content = String::new();
+            let mut read = File::open(&file_in_backup).unwrap();
+
+            read.read_to_string(&mut content).unwrap();
+
+            let test = format!("Unique string for file {}", i);
+            assert_eq!(content, test);
+
+            fs::remove_file(&file_in_backup).unwrap();
+            fs::remove_dir(path).unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn nonexistent() {
+        match backup_async("nonexistent.txt").await {
+            Ok(_) => panic!("Backup should have failed, but it was successful."),
+            Err(e) => assert_eq!(e.to_string(), "Path does not exist."),
+        };
+    }
+
+    #[tokio::test]
+    async fn root() {
+        match backup_async("/").await {
+            Ok(_) => panic!("Backup should have failed, but it was successful."),
+            Err(e) => assert_eq!(e.to_string(), "Path is root."),
+        };
+    }
+
+    #[tokio::test]
+    async fn empty() {
+        match backup_async("").await {
+            Ok(_) => panic!("Backup should have failed, but it was successful."),
+            Err(e) => assert_eq!(e.to_string(), "Path does not exist."),
+        };
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn special_file() {
+        let tmp = test_dir();
+        let fifo = tmp.join("test_fifo_async");
+        let status = std::process::Command::new("mkfifo")
+            .arg(&fifo)
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        match backup_async(&fifo).await {
+            Ok(_) => panic!("Backup should have failed, but it was successful."),
+            Err(e) => assert!(matches!(e, BackupError::Unsupported(_))),
+        };
+        assert!(fs::symlink_metadata(&fifo).is_ok());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn trailing_separator() {
+        let tmp = test_dir();
+        fs::write(tmp.join("test_file_async3.txt"), "Some content to test.").unwrap();
+        std::os::unix::fs::symlink("test_file_async3.txt", tmp.join("test_link_async")).unwrap();
+
+        // the link itself is backed up, not the file it points to
+        let backup = match backup_async(format!("{}/", tmp.join("test_link_async").display())).await
+        {
+            Ok(x) => x,
+            Err(_) => panic!("Backup failed."),
+        };
+
+        assert!(fs::symlink_metadata(&backup).unwrap().is_symlink());
+        assert!(tmp.join("test_file_async3.txt").is_file());
+    }
+
+    #[tokio::test]
+    async fn concurrent() {
+        let tmp = test_dir();
+        let path = tmp.join("test_file_async4.txt");
+        fs::write(&path, "Some content to test.").unwrap();
+
+        let (a, b) = tokio::join!(backup_async(&path), backup_async(&path));
+
+        // exactly one backup succeeds, the other one finds the original gone
+        match (a, b) {
+            (Ok(_), Err(e)) | (Err(e), Ok(_)) => assert!(matches!(e, BackupError::NotFound)),
+            _ => panic!("Exactly one backup should have succeeded."),
+        }
+    }
+
+    #[tokio::test]
+    async fn dotdot() {
+        match backup_async("..").await {
+            Ok(_) => panic!("Backup should have failed, but it was successful."),
+            Err(e) => assert_eq!(e.to_string(), "Path ends in '..'."),
+        };
+    }
+}
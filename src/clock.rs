@@ -0,0 +1,49 @@
+// Released under MIT License.
+// Copyright (c) 2023 Ladislav Bartos
+
+//! Implementation of the `Clock` trait.
+
+use chrono::prelude::*;
+
+/// Source of the current time used in the names of the backups.
+///
+/// Implement this trait to control the timestamps of the backups,
+/// e.g. to get predictable names of the backups in tests. See [`crate::backup_with_clock`].
+///
+/// # Examples
+///
+/// ```
+/// use backitup::Clock;
+/// use chrono::prelude::*;
+///
+/// struct FixedClock(DateTime<Local>);
+///
+/// impl Clock for FixedClock {
+///     fn now(&self) -> DateTime<Local> {
+///         self.0
+///     }
+/// }
+/// ```
+pub trait Clock {
+    /// Returns the current time.
+    fn now(&self) -> DateTime<Local>;
+}
+
+/// Clock returning the current time of the system. Used by [`crate::backup`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Local> {
+        Local::now()
+    }
+}
+
+/// Clock that always returns the same time.
+pub(crate) struct FixedClock(pub(crate) DateTime<Local>);
+
+impl Clock for FixedClock {
+    fn now(&self) -> DateTime<Local> {
+        self.0
+    }
+}
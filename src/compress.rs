@@ -0,0 +1,289 @@
+// Released under MIT License.
+// Copyright (c) 2023 Ladislav Bartos
+
+//! Implementation of compressed backups.
+
+use std::ffi::OsStr;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+use crate::{write_via_temp, BackupError, BackupOptions, SystemClock};
+
+/// Creates a gzip-compressed backup of the specified file.
+/// Returns the path to the backup file if successful, otherwise returns an error.
+///
+/// # Arguments
+///
+/// * `path` - The path to the file to be backed up.
+///
+/// # Errors
+///
+/// This function can return the same errors as [`crate::backup`] and additionally:
+///
+/// * `BackupError::Unsupported` - If the `path` is a directory.
+///
+/// # Details
+/// The content of the file is compressed into a new file named `#<filename>-<timestamp>(-<microseconds>).gz#`.
+/// The original file is then **removed**. If the compression fails, the original file is left untouched.
+/// The compressed content is written into a temporary `.tmp` sibling of the backup first,
+/// so the backup only ever appears fully written.
+///
+/// # Examples
+///
+/// ```no_run
+/// use crate::backitup::backup_gzip;
+///
+/// let path = "output.log";
+/// match backup_gzip(path) {
+///     Ok(backup_path) => println!("Backup created: {:?}", backup_path),
+///     Err(err) => eprintln!("Failed to create backup: {:?}", err),
+/// }
+/// ```
+pub fn backup_gzip(path: impl AsRef<Path>) -> Result<PathBuf, BackupError> {
+    let path = path.as_ref();
+    let options = BackupOptions::new().extension(".gz").build();
+    let name = options.get_unique_name(path, &SystemClock)?;
+
+    if path.is_dir() {
+        return Err(BackupError::Unsupported(String::from(
+            "Directories can not be backed up using gzip.",
+        )));
+    }
+
+    let name = options.create_unique(path, name, &SystemClock, |target| {
+        write_via_temp(target, |temp| compress_file(path, temp))
+    })?;
+
+    fs::remove_file(path)?;
+    Ok(name.path)
+}
+
+/// Creates a gzip-compressed tar archive of the specified directory.
+/// Returns the path to the archive if successful, otherwise returns an error.
+///
+/// # Arguments
+///
+/// * `path` - The path to the directory to be backed up.
+///
+/// # Errors
+///
+/// This function can return the same errors as [`crate::backup`] and additionally:
+///
+/// * `BackupError::Unsupported` - If the `path` is not a directory. Use [`backup_gzip`] for regular files.
+///
+/// # Details
+/// The directory is archived into a new file named `#<dirname>-<timestamp>(-<microseconds>)#.tar.gz`.
+/// All entries of the archive are placed in the directory `<dirname>`, so unpacking the archive recreates
+/// the original directory, including all nested files and empty subdirectories.
+/// Symbolic links are archived as links, not as the files they point to.
+/// The archive is written into a temporary `.tmp` sibling of the backup first,
+/// so the backup only ever appears fully written.
+///
+/// The original directory is then **removed**. If the archiving fails, the original directory is left untouched.
+///
+/// # Examples
+///
+/// ```no_run
+/// use crate::backitup::backup_tar_gz;
+///
+/// let path = "output_dir";
+/// match backup_tar_gz(path) {
+///     Ok(backup_path) => println!("Backup created: {:?}", backup_path),
+///     Err(err) => eprintln!("Failed to create backup: {:?}", err),
+/// }
+/// ```
+pub fn backup_tar_gz(path: impl AsRef<Path>) -> Result<PathBuf, BackupError> {
+    let path = path.as_ref();
+    let options = BackupOptions::new().suffix("#.tar.gz").build();
+    let name = options.get_unique_name(path, &SystemClock)?;
+
+    if !path.is_dir() {
+        return Err(BackupError::Unsupported(String::from(
+            "Only directories can be backed up using tar.gz.",
+        )));
+    }
+
+    // the name has already been validated
+    let dirname = path.file_name().unwrap_or_default();
+
+    let name = options.create_unique(path, name, &SystemClock, |target| {
+        write_via_temp(target, |temp| archive_directory(path, dirname, temp))
+    })?;
+
+    fs::remove_dir_all(path)?;
+    Ok(name.path)
+}
+
+/// Writes a gzip-compressed tar archive of the directory at `source` into a new file at `target`.
+/// Entries are placed in the directory `root` inside the archive.
+fn archive_directory(source: &Path, root: &OsStr, target: &Path) -> Result<(), io::Error> {
+    let writer = BufWriter::new(File::create_new(target)?);
+    let encoder = GzEncoder::new(writer, Compression::default());
+
+    let mut builder = tar::Builder::new(encoder);
+    builder.follow_symlinks(false);
+    builder.append_dir_all(root, source)?;
+
+    builder.into_inner()?.finish()?.into_inner()?.sync_all()?;
+
+    Ok(())
+}
+
+/// Writes gzip-compressed content of the file at `source` into a new file at `target`.
+fn compress_file(source: &Path, target: &Path) -> Result<(), io::Error> {
+    let mut reader = BufReader::new(File::open(source)?);
+    let writer = BufWriter::new(File::create_new(target)?);
+
+    let mut encoder = GzEncoder::new(writer, Compression::default());
+    io::copy(&mut reader, &mut encoder)?;
+    encoder.finish()?.into_inner()?.sync_all()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::test_dir;
+    use flate2::read::GzDecoder;
+    use std::io::prelude::*;
+
+    #[test]
+    fn gzip() {
+        let tmp = test_dir();
+        let mut file = File::create(tmp.join("test_file_gzip.txt")).unwrap();
+        file.write_all(b"Some content to test.").unwrap();
+        drop(file);
+
+        let backup = match backup_gzip(tmp.join("test_file_gzip.txt")) {
+            Ok(x) => x,
+            Err(_) => panic!("Backup failed."),
+        };
+
+        let name = backup.file_name().unwrap().to_str().unwrap();
+        assert!(name.starts_with("#test_file_gzip.txt-"));
+        assert!(name.ends_with(".gz#"));
+        assert!(!tmp.join("test_file_gzip.txt").exists());
+
+        let mut content = String::new();
+        let mut read = GzDecoder::new(File::open(&backup).unwrap());
+        read.read_to_string(&mut content).unwrap();
+
+        assert_eq!(content, "Some content to test.");
+    }
+
+    #[test]
+    fn gzip_multiple_backups() {
+        let tmp = test_dir();
+        let mut backups = Vec::new();
+        for i in 0..10 {
+            let mut file = File::create(tmp.join("test_file_gzip2.txt")).unwrap();
+            let text = format!("Unique string for file {}", i);
+            file.write_all(text.as_bytes()).unwrap();
+
+            let backup = match backup_gzip(tmp.join("test_file_gzip2.txt")) {
+                Ok(x) => x,
+                Err(_) => panic!("Backup failed."),
+            };
+
+            backups.push(backup);
+        }
+
+        for (i, path) in backups.iter().enumerate() {
+            assert!(path.to_str().unwrap().ends_with(".gz#"));
+
+            let mut content = String::new();
+            let mut read = GzDecoder::new(File::open(path).unwrap());
+            read.read_to_string(&mut content).unwrap();
+
+            let test = format!("Unique string for file {}", i);
+            assert_eq!(content, test);
+
+            fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn gzip_directory() {
+        let tmp = test_dir();
+        fs::create_dir(tmp.join("test_dir_gzip")).unwrap();
+
+        match backup_gzip(tmp.join("test_dir_gzip")) {
+            Ok(_) => panic!("Backup should have failed, but it was successful."),
+            Err(e) => assert!(matches!(e, BackupError::Unsupported(_))),
+        }
+
+        assert!(tmp.join("test_dir_gzip").is_dir());
+    }
+
+    #[test]
+    fn gzip_nonexistent() {
+        match backup_gzip("nonexistent.txt") {
+            Ok(_) => panic!("Backup should have failed, but it was successful."),
+            Err(e) => assert!(matches!(e, BackupError::NotFound)),
+        }
+    }
+
+    #[test]
+    fn tar_gz() {
+        let tmp = test_dir();
+        fs::create_dir_all(tmp.join("test_dir_tar/nested/deeper")).unwrap();
+        fs::create_dir_all(tmp.join("test_dir_tar/empty")).unwrap();
+
+        let mut file = File::create(tmp.join("test_dir_tar/test_file.txt")).unwrap();
+        file.write_all(b"Some content to test.").unwrap();
+        let mut file = File::create(tmp.join("test_dir_tar/nested/deeper/test_file.txt")).unwrap();
+        file.write_all(b"Some nested content to test.").unwrap();
+        drop(file);
+
+        let backup = match backup_tar_gz(tmp.join("test_dir_tar")) {
+            Ok(x) => x,
+            Err(_) => panic!("Backup failed."),
+        };
+
+        let name = backup.file_name().unwrap().to_str().unwrap();
+        assert!(name.starts_with("#test_dir_tar-"));
+        assert!(name.ends_with("#.tar.gz"));
+        assert!(!tmp.join("test_dir_tar").exists());
+
+        fs::create_dir(tmp.join("test_dir_tar_unpacked")).unwrap();
+        let mut archive = tar::Archive::new(GzDecoder::new(File::open(&backup).unwrap()));
+        archive.unpack(tmp.join("test_dir_tar_unpacked")).unwrap();
+
+        let mut content = String::new();
+        let mut read =
+            File::open(tmp.join("test_dir_tar_unpacked/test_dir_tar/test_file.txt")).unwrap();
+        read.read_to_string(&mut content).unwrap();
+        assert_eq!(content, "Some content to test.");
+
+        let mut content = String::new();
+        let mut read =
+            File::open(tmp.join("test_dir_tar_unpacked/test_dir_tar/nested/deeper/test_file.txt"))
+                .unwrap();
+        read.read_to_string(&mut content).unwrap();
+        assert_eq!(content, "Some nested content to test.");
+
+        assert!(tmp
+            .join("test_dir_tar_unpacked/test_dir_tar/empty")
+            .is_dir());
+    }
+
+    #[test]
+    fn tar_gz_file() {
+        let tmp = test_dir();
+        let mut file = File::create(tmp.join("test_file_tar.txt")).unwrap();
+        file.write_all(b"Some content to test.").unwrap();
+        drop(file);
+
+        match backup_tar_gz(tmp.join("test_file_tar.txt")) {
+            Ok(_) => panic!("Backup should have failed, but it was successful."),
+            Err(e) => assert!(matches!(e, BackupError::Unsupported(_))),
+        }
+
+        assert!(tmp.join("test_file_tar.txt").is_file());
+    }
+}
@@ -0,0 +1,345 @@
+// Released under MIT License.
+// Copyright (c) 2023 Ladislav Bartos
+
+//! Implementation of backups with the `.bak` extension.
+
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use crate::{
+    check_directory_link, check_file_type, dir_or_current, rename_noreplace, split_path,
+    BackupError,
+};
+
+/// Creates a backup of the specified file or directory with the `.bak` extension.
+/// Returns the path to the backup file if successful, otherwise returns an error.
+///
+/// # Arguments
+///
+/// * `path` - The path to the file or directory to be backed up.
+///
+/// # Errors
+///
+/// This function can return the following errors:
+///
+/// * `BackupError::NotFound` - If the specified `path` does not exist.
+/// * `BackupError::IsRoot`, `BackupError::EndsInDotDot` - If the `path` is not valid.
+/// * `BackupError::Unsupported` - If the `path` is a special file (e.g. a FIFO or a socket)
+///   or a directory symbolic link or junction on Windows.
+/// * `BackupError::Io` - If an I/O error occurs during the backup process.
+///
+/// # Details
+/// The original file (or directory) is renamed to `<filename>.bak`. If this name is already taken,
+/// the original is renamed to `<filename>.bak.<N>`, where `N` is by one larger than the largest number
+/// of the existing `.bak` backups of the file. The backups are located in the parent directory of `path`.
+///
+/// For instance, backing up `data.txt` three times creates `data.txt.bak`, `data.txt.bak.1`, and `data.txt.bak.2`.
+///
+/// # Examples
+///
+/// ```no_run
+/// use crate::backitup::backup_dotbak;
+///
+/// let path = "config.toml";
+/// match backup_dotbak(path) {
+///     Ok(backup_path) => println!("Backup created: {:?}", backup_path),
+///     Err(err) => eprintln!("Failed to create backup: {:?}", err),
+/// }
+/// ```
+pub fn backup_dotbak(path: impl AsRef<Path>) -> Result<PathBuf, BackupError> {
+    let path = path.as_ref();
+
+    // check if the path exists
+    if !path.exists() {
+        return Err(BackupError::NotFound);
+    }
+
+    // special files and directory links on Windows can not be backed up
+    check_file_type(path, false)?;
+    check_directory_link(path, false)?;
+
+    let (parent, filename) = split_path(path)?;
+
+    loop {
+        let number = match find_dotbak(parent, filename)?.pop() {
+            Some((n, _)) => n.checked_add(1).ok_or(BackupError::NoUniqueName)?,
+            None => 0,
+        };
+
+        let backup_name = parent.join(dotbak_name(filename, number));
+
+        // check that the backup can be created on Windows
+        #[cfg(windows)]
+        if let Some(name) = backup_name.file_name() {
+            crate::check_windows_name(&name.to_string_lossy())?;
+        }
+
+        // if the name has been taken in the meantime, look for the highest number again
+        match rename_noreplace(path, &backup_name) {
+            Ok(()) => return Ok(backup_name),
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(BackupError::Io(e)),
+        }
+    }
+}
+
+/// Restores the most recent `.bak` backup of the specified file or directory.
+/// Returns the path to the restored file if successful, otherwise returns an error.
+///
+/// # Arguments
+///
+/// * `path` - The path to the original file or directory.
+///
+/// # Errors
+///
+/// This function can return the following errors:
+///
+/// * `BackupError::AlreadyExists` - If the specified `path` already exists.
+/// * `BackupError::NoBackup` - If no `.bak` backup of the `path` exists.
+/// * `BackupError::IsRoot`, `BackupError::EndsInDotDot` - If the `path` is not valid.
+/// * `BackupError::Io` - If an I/O error occurs during the restoration.
+///
+/// # Details
+/// The backup with the largest number (see [`backup_dotbak`]) is **renamed** back to `path`.
+/// If only `<filename>.bak` exists, it is restored.
+///
+/// # Examples
+///
+/// ```no_run
+/// use crate::backitup::restore_dotbak;
+///
+/// let path = "config.toml";
+/// match restore_dotbak(path) {
+///     Ok(restored) => println!("Backup restored: {:?}", restored),
+///     Err(err) => eprintln!("Failed to restore backup: {:?}", err),
+/// }
+/// ```
+pub fn restore_dotbak(path: impl AsRef<Path>) -> Result<PathBuf, BackupError> {
+    let path = path.as_ref();
+    let (parent, filename) = split_path(path)?;
+
+    if path.exists() {
+        return Err(BackupError::AlreadyExists);
+    }
+
+    let newest = match find_dotbak(parent, filename)?.pop() {
+        Some((_, x)) => x,
+        None => return Err(BackupError::NoBackup),
+    };
+
+    match rename_noreplace(&newest, path) {
+        Ok(()) => Ok(path.to_path_buf()),
+        Err(e) if e.kind() == ErrorKind::AlreadyExists => Err(BackupError::AlreadyExists),
+        Err(e) => Err(BackupError::Io(e)),
+    }
+}
+
+/// Lists all existing `.bak` backups of the specified file or directory.
+/// Returns the paths to the backups sorted from the oldest to the newest.
+///
+/// # Arguments
+///
+/// * `path` - The path to the original file or directory. It does not have to exist.
+///
+/// # Errors
+///
+/// This function can return the following errors:
+///
+/// * `BackupError::IsRoot`, `BackupError::EndsInDotDot` - If the `path` is not valid.
+/// * `BackupError::Io` - If the parent directory of `path` can not be read.
+///
+/// # Details
+/// Only entries located in the parent directory of `path` and named `<filename>.bak`
+/// or `<filename>.bak.<N>` are listed. `<filename>.bak` is the oldest backup,
+/// the remaining backups are sorted by their number.
+///
+/// # Examples
+///
+/// ```no_run
+/// use crate::backitup::list_dotbak;
+///
+/// match list_dotbak("config.toml") {
+///     Ok(backups) => println!("Backups: {:?}", backups),
+///     Err(err) => eprintln!("Failed to list backups: {:?}", err),
+/// }
+/// ```
+pub fn list_dotbak(path: impl AsRef<Path>) -> Result<Vec<PathBuf>, BackupError> {
+    let (parent, filename) = split_path(path.as_ref())?;
+
+    Ok(find_dotbak(parent, filename)?
+        .into_iter()
+        .map(|(_, x)| x)
+        .collect())
+}
+
+/// Returns the name of the `.bak` backup of a file named `filename`.
+/// Number zero corresponds to `<filename>.bak`.
+fn dotbak_name(filename: &OsStr, number: u64) -> OsString {
+    let mut name = filename.to_owned();
+    name.push(".bak");
+    if number != 0 {
+        name.push(format!(".{}", number));
+    }
+    name
+}
+
+/// Parses the name of a `.bak` backup of a file named `filename`.
+/// Returns zero for `<filename>.bak` and `N` for `<filename>.bak.<N>`.
+/// Returns `None` if `name` does not match any of these patterns.
+fn parse_dotbak_name(name: &OsStr, filename: &OsStr) -> Option<u64> {
+    let rest = name
+        .as_encoded_bytes()
+        .strip_prefix(filename.as_encoded_bytes())?
+        .strip_prefix(b".bak")?;
+
+    if rest.is_empty() {
+        return Some(0);
+    }
+
+    let number = rest.strip_prefix(b".")?;
+
+    // numbers with leading zeros (and zero itself) are never created
+    if number.is_empty() || number[0] == b'0' || !number.iter().all(u8::is_ascii_digit) {
+        return None;
+    }
+
+    std::str::from_utf8(number).ok()?.parse::<u64>().ok()
+}
+
+/// Finds all `.bak` backups of a file named `filename` located in `dir`.
+/// The backups are sorted by their number, i.e. from the oldest to the newest.
+fn find_dotbak(dir: &Path, filename: &OsStr) -> Result<Vec<(u64, PathBuf)>, BackupError> {
+    let mut backups = Vec::new();
+
+    for entry in fs::read_dir(dir_or_current(dir))? {
+        let entry = entry?;
+        let name = entry.file_name();
+
+        if let Some(number) = parse_dotbak_name(&name, filename) {
+            backups.push((number, dir.join(name)));
+        }
+    }
+
+    backups.sort();
+    Ok(backups)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::test_dir;
+    use std::fs::File;
+    use std::io::prelude::*;
+
+    #[test]
+    fn parse() {
+        let filename = OsStr::new("data.txt");
+
+        assert_eq!(
+            parse_dotbak_name(OsStr::new("data.txt.bak"), filename),
+            Some(0)
+        );
+        assert_eq!(
+            parse_dotbak_name(OsStr::new("data.txt.bak.1"), filename),
+            Some(1)
+        );
+        assert_eq!(
+            parse_dotbak_name(OsStr::new("data.txt.bak.42"), filename),
+            Some(42)
+        );
+
+        for name in [
+            "data.txt",
+            "data.txt.bak.",
+            "data.txt.bak.0",
+            "data.txt.bak.01",
+            "data.txt.bak1",
+            "data.txt.bak.1a",
+            "data.txt.1",
+            "other.txt.bak",
+            "#data.txt-2023-06-27-21-01-13#",
+        ] {
+            assert_eq!(
+                parse_dotbak_name(OsStr::new(name), filename),
+                None,
+                "{}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn dotbak() {
+        let tmp = test_dir();
+
+        let expected = [
+            "test_file.txt.bak",
+            "test_file.txt.bak.1",
+            "test_file.txt.bak.2",
+        ];
+
+        for (i, path) in expected.iter().enumerate() {
+            let mut file = File::create(tmp.join("test_file.txt")).unwrap();
+            let text = format!("Unique string for file {}", i);
+            file.write_all(text.as_bytes()).unwrap();
+
+            let backup = match backup_dotbak(tmp.join("test_file.txt")) {
+                Ok(x) => x,
+                Err(_) => panic!("Backup failed."),
+            };
+
+            assert_eq!(backup, tmp.join(path));
+            assert!(!tmp.join("test_file.txt").exists());
+        }
+
+        for (i, path) in expected.iter().enumerate() {
+            let content = fs::read_to_string(tmp.join(path)).unwrap();
+            assert_eq!(content, format!("Unique string for file {}", i));
+        }
+
+        assert_eq!(
+            list_dotbak(tmp.join("test_file.txt")).unwrap(),
+            expected.iter().map(|x| tmp.join(x)).collect::<Vec<_>>()
+        );
+
+        let restored = match restore_dotbak(tmp.join("test_file.txt")) {
+            Ok(x) => x,
+            Err(_) => panic!("Restoring failed."),
+        };
+
+        assert_eq!(restored, tmp.join("test_file.txt"));
+        let content = fs::read_to_string(tmp.join("test_file.txt")).unwrap();
+        assert_eq!(content, "Unique string for file 2");
+        assert!(!tmp.join("test_file.txt.bak.2").exists());
+    }
+
+    #[test]
+    fn restore_dotbak_errors() {
+        let tmp = test_dir();
+
+        match restore_dotbak(tmp.join("test_file.txt")) {
+            Ok(_) => panic!("Restoring should have failed, but it was successful."),
+            Err(e) => assert!(matches!(e, BackupError::NoBackup)),
+        }
+
+        File::create(tmp.join("test_file.txt")).unwrap();
+        File::create(tmp.join("test_file.txt.bak")).unwrap();
+
+        match restore_dotbak(tmp.join("test_file.txt")) {
+            Ok(_) => panic!("Restoring should have failed, but it was successful."),
+            Err(e) => assert!(matches!(e, BackupError::AlreadyExists)),
+        }
+
+        assert!(tmp.join("test_file.txt.bak").exists());
+    }
+
+    #[test]
+    fn dotbak_nonexistent() {
+        match backup_dotbak("nonexistent.txt") {
+            Ok(_) => panic!("Backup should have failed, but it was successful."),
+            Err(e) => assert!(matches!(e, BackupError::NotFound)),
+        }
+    }
+}
@@ -0,0 +1,348 @@
+// Released under MIT License.
+// Copyright (c) 2023 Ladislav Bartos
+
+//! Implementation of encrypted backups.
+
+use std::ffi::OsStr;
+use std::fs::{self, File};
+use std::io::{self, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+use chacha20poly1305::aead::{Aead, Generate, Nonce};
+use chacha20poly1305::{ChaCha20Poly1305, Key, KeyInit};
+
+use crate::list::split_backup_name;
+use crate::{
+    check_file_type, split_path, write_via_temp, BackupError, BackupOptions, SystemClock,
+    DEFAULT_PREFIX, DEFAULT_SUFFIX,
+};
+
+/// Extension of the encrypted backups placed after the timestamp.
+const ENCRYPTED_EXTENSION: &str = ".enc";
+
+/// Magic bytes at the start of every encrypted backup (including the version of the format).
+const MAGIC: &[u8; 8] = b"BKITENC1";
+
+/// Length of the nonce stored after the magic bytes.
+const NONCE_LENGTH: usize = 12;
+
+/// Creates an encrypted backup of the specified file.
+/// Returns the path to the backup file if successful, otherwise returns an error.
+///
+/// # Arguments
+///
+/// * `path` - The path to the file to be backed up.
+/// * `key` - The 256-bit key used to encrypt the content of the file.
+///
+/// # Errors
+///
+/// This function can return the same errors as [`crate::backup`] and additionally:
+///
+/// * `BackupError::Unsupported` - If the `path` is a directory or a special file (e.g. a FIFO or a socket).
+///
+/// # Details
+/// The content of the file is encrypted using ChaCha20-Poly1305 into a new file named
+/// `#<filename>-<timestamp>(-<microseconds>).enc#`. The backup starts with a short header containing
+/// a random nonce, followed by the encrypted content and the authentication tag.
+/// The original file is then **removed**. If the encryption fails, the original file is left untouched.
+/// The encrypted content is written into a temporary `.tmp` sibling of the backup first,
+/// so the backup only ever appears fully written.
+///
+/// The backup has the same permissions as the original file.
+/// The whole file is encrypted in memory, so this function is not suitable for files larger than the available memory.
+/// Use [`restore_encrypted`] with the same `key` to restore the file.
+///
+/// Requires the `encryption` feature.
+///
+/// # Examples
+///
+/// ```no_run
+/// use crate::backitup::backup_encrypted;
+///
+/// let key = [42u8; 32];
+/// match backup_encrypted("secrets.txt", &key) {
+///     Ok(backup_path) => println!("Backup created: {:?}", backup_path),
+///     Err(err) => eprintln!("Failed to create backup: {:?}", err),
+/// }
+/// ```
+pub fn backup_encrypted(path: impl AsRef<Path>, key: &[u8; 32]) -> Result<PathBuf, BackupError> {
+    let path = path.as_ref();
+    let options = BackupOptions::new().extension(ENCRYPTED_EXTENSION).build();
+    let name = options.get_unique_name(path, &SystemClock)?;
+
+    if path.is_dir() {
+        return Err(BackupError::Unsupported(String::from(
+            "Directories can not be encrypted.",
+        )));
+    }
+
+    // reading from special files may block indefinitely
+    check_file_type(path, false)?;
+
+    let name = options.create_unique(path, name, &SystemClock, |target| {
+        write_via_temp(target, |temp| encrypt_file(path, temp, key))
+    })?;
+
+    fs::remove_file(path)?;
+    Ok(name.path)
+}
+
+/// Restores an encrypted backup created by [`backup_encrypted`].
+/// Returns the path to the restored file if successful, otherwise returns an error.
+///
+/// # Arguments
+///
+/// * `backup` - The path to the encrypted backup.
+/// * `key` - The key used to encrypt the backup.
+///
+/// # Errors
+///
+/// This function can return the following errors:
+///
+/// * `BackupError::NotFound` - If the `backup` does not exist.
+/// * `BackupError::InvalidArgument` - If the name of the `backup` does not match `#<filename>-<timestamp>.enc#`.
+/// * `BackupError::AlreadyExists` - If the original file already exists.
+/// * `BackupError::VerificationFailed` - If the `key` is wrong or the backup has been modified.
+/// * `BackupError::Io` - If the backup is not encrypted or an I/O error occurs during the restoration.
+///
+/// # Details
+/// The name of the original file is taken from the name of the `backup`. The decrypted content is written
+/// into the original file located in the directory of the `backup` (via a temporary `.tmp` sibling)
+/// and the backup is then **removed**. An existing file is never overwritten.
+/// The restored file has the same permissions as the backup (i.e. as the original file).
+///
+/// Requires the `encryption` feature.
+///
+/// # Examples
+///
+/// ```no_run
+/// use crate::backitup::{backup_encrypted, restore_encrypted};
+///
+/// let key = [42u8; 32];
+/// let backup_path = backup_encrypted("secrets.txt", &key).unwrap();
+/// match restore_encrypted(&backup_path, &key) {
+///     Ok(restored) => println!("Backup restored: {:?}", restored),
+///     Err(err) => eprintln!("Failed to restore backup: {:?}", err),
+/// }
+/// ```
+pub fn restore_encrypted(backup: impl AsRef<Path>, key: &[u8; 32]) -> Result<PathBuf, BackupError> {
+    let backup = backup.as_ref();
+    let (parent, name) = split_path(backup)?;
+
+    let suffix = format!("{}{}", ENCRYPTED_EXTENSION, DEFAULT_SUFFIX);
+    let Some((filename, _)) = split_backup_name(name.as_encoded_bytes(), DEFAULT_PREFIX, &suffix)
+    else {
+        return Err(BackupError::InvalidArgument(format!(
+            "'{}' is not the name of an encrypted backup.",
+            backup.display()
+        )));
+    };
+
+    // SAFETY: the filename is split off the name of the backup at an ASCII character
+    let filename = unsafe { OsStr::from_encoded_bytes_unchecked(filename) };
+    let original = parent.join(filename);
+
+    let content = match fs::read(backup) {
+        Ok(x) => x,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Err(BackupError::NotFound),
+        Err(e) => return Err(BackupError::Io(e)),
+    };
+    let plaintext = decrypt(&content, key)?;
+
+    let permissions = fs::metadata(backup)?.permissions();
+    match write_via_temp(&original, |temp| {
+        let mut file = create_private(temp)?;
+        file.write_all(&plaintext)?;
+        file.sync_all()?;
+        // the backup has the permissions of the original file
+        fs::set_permissions(temp, permissions)
+    }) {
+        Ok(()) => (),
+        Err(e) if e.kind() == ErrorKind::AlreadyExists => return Err(BackupError::AlreadyExists),
+        Err(e) => return Err(BackupError::Io(e)),
+    }
+
+    fs::remove_file(backup)?;
+    Ok(original)
+}
+
+/// Writes the encrypted content of the file at `source` (preceded by the header) into a new file at `target`.
+fn encrypt_file(source: &Path, target: &Path, key: &[u8; 32]) -> Result<(), io::Error> {
+    let plaintext = fs::read(source)?;
+
+    let cipher = ChaCha20Poly1305::new(&Key::from(*key));
+    let nonce = Nonce::<ChaCha20Poly1305>::generate();
+    let ciphertext = cipher
+        .encrypt(&nonce, plaintext.as_slice())
+        .map_err(|_| io::Error::other("Encryption failed."))?;
+
+    let mut file = create_private(target)?;
+    file.write_all(MAGIC)?;
+    file.write_all(&nonce)?;
+    file.write_all(&ciphertext)?;
+    file.sync_all()?;
+    fs::set_permissions(target, fs::metadata(source)?.permissions())
+}
+
+/// Creates a new file at `path` which is only accessible by its owner until its permissions are changed.
+/// Fails if the file already exists.
+#[cfg(unix)]
+fn create_private(path: &Path) -> Result<File, io::Error> {
+    use std::os::unix::fs::OpenOptionsExt;
+
+    File::options()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(path)
+}
+
+/// Creates a new file at `path`. Fails if the file already exists.
+#[cfg(not(unix))]
+fn create_private(path: &Path) -> Result<File, io::Error> {
+    File::create_new(path)
+}
+
+/// Decrypts the `content` of an encrypted backup (including the header).
+fn decrypt(content: &[u8], key: &[u8; 32]) -> Result<Vec<u8>, BackupError> {
+    let Some(rest) = content.strip_prefix(MAGIC) else {
+        return Err(BackupError::Io(io::Error::new(
+            ErrorKind::InvalidData,
+            "The file is not an encrypted backup.",
+        )));
+    };
+
+    if rest.len() < NONCE_LENGTH {
+        return Err(BackupError::Io(io::Error::new(
+            ErrorKind::InvalidData,
+            "The header of the encrypted backup is incomplete.",
+        )));
+    }
+
+    let (nonce, ciphertext) = rest.split_at(NONCE_LENGTH);
+    let nonce = Nonce::<ChaCha20Poly1305>::try_from(nonce).map_err(io::Error::other)?;
+
+    ChaCha20Poly1305::new(&Key::from(*key))
+        .decrypt(&nonce, ciphertext)
+        .map_err(|_| BackupError::VerificationFailed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::test_dir;
+
+    #[test]
+    fn encrypted() {
+        let tmp = test_dir();
+        let path = &tmp.join("test_file_encrypted.txt");
+        fs::write(path, "Some content to test.").unwrap();
+        let key = [42u8; 32];
+
+        let backup = match backup_encrypted(path, &key) {
+            Ok(x) => x,
+            Err(_) => panic!("Backup failed."),
+        };
+
+        let name = backup.file_name().unwrap().to_str().unwrap();
+        assert!(name.starts_with("#test_file_encrypted.txt-"));
+        assert!(name.ends_with(".enc#"));
+        assert!(!path.exists());
+
+        // the content is not stored in plain text and the nonce is stored in the header
+        let content = fs::read(&backup).unwrap();
+        assert!(content.starts_with(MAGIC));
+        assert_eq!(content.len(), MAGIC.len() + NONCE_LENGTH + 21 + 16);
+        assert!(!content
+            .windows(b"Some content".len())
+            .any(|x| x == b"Some content"));
+
+        let restored = match restore_encrypted(&backup, &key) {
+            Ok(x) => x,
+            Err(_) => panic!("Restoring failed."),
+        };
+
+        assert_eq!(&restored, path);
+        assert_eq!(fs::read_to_string(path).unwrap(), "Some content to test.");
+        assert!(!backup.exists());
+    }
+
+    #[test]
+    fn encrypted_errors() {
+        let tmp = test_dir();
+        let path = &tmp.join("test_file_encrypted2.txt");
+        fs::write(path, "Some content to test.").unwrap();
+
+        let backup = backup_encrypted(path, &[1u8; 32]).unwrap();
+
+        // wrong key
+        match restore_encrypted(&backup, &[2u8; 32]) {
+            Ok(_) => panic!("Restoring should have failed, but it was successful."),
+            Err(e) => assert!(matches!(e, BackupError::VerificationFailed)),
+        }
+
+        // modified content
+        let mut content = fs::read(&backup).unwrap();
+        let last = content.len() - 1;
+        content[last] ^= 1;
+        fs::write(&backup, &content).unwrap();
+        match restore_encrypted(&backup, &[1u8; 32]) {
+            Ok(_) => panic!("Restoring should have failed, but it was successful."),
+            Err(e) => assert!(matches!(e, BackupError::VerificationFailed)),
+        }
+        content[last] ^= 1;
+        fs::write(&backup, &content).unwrap();
+
+        // the original already exists
+        fs::write(path, "New content.").unwrap();
+        match restore_encrypted(&backup, &[1u8; 32]) {
+            Ok(_) => panic!("Restoring should have failed, but it was successful."),
+            Err(e) => assert!(matches!(e, BackupError::AlreadyExists)),
+        }
+        assert_eq!(fs::read_to_string(path).unwrap(), "New content.");
+        assert!(backup.exists());
+
+        match restore_encrypted(tmp.join("test_file_encrypted2.txt"), &[1u8; 32]) {
+            Ok(_) => panic!("Restoring should have failed, but it was successful."),
+            Err(e) => assert!(matches!(e, BackupError::InvalidArgument(_))),
+        }
+
+        match backup_encrypted(&*tmp, &[1u8; 32]) {
+            Ok(_) => panic!("Backup should have failed, but it was successful."),
+            Err(e) => assert!(matches!(e, BackupError::Unsupported(_))),
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn encrypted_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let tmp = test_dir();
+        let path = &tmp.join("test_file_encrypted3.txt");
+        fs::write(path, "Some content to test.").unwrap();
+        fs::set_permissions(path, fs::Permissions::from_mode(0o600)).unwrap();
+        let key = [42u8; 32];
+
+        let backup = backup_encrypted(path, &key).unwrap();
+        assert_eq!(
+            fs::metadata(&backup).unwrap().permissions().mode() & 0o777,
+            0o600
+        );
+
+        restore_encrypted(&backup, &key).unwrap();
+        assert_eq!(
+            fs::metadata(path).unwrap().permissions().mode() & 0o777,
+            0o600
+        );
+
+        // other permissions are preserved as well
+        fs::set_permissions(path, fs::Permissions::from_mode(0o640)).unwrap();
+        let backup = backup_encrypted(path, &key).unwrap();
+        restore_encrypted(&backup, &key).unwrap();
+        assert_eq!(
+            fs::metadata(path).unwrap().permissions().mode() & 0o777,
+            0o640
+        );
+    }
+}
@@ -0,0 +1,210 @@
+// Released under MIT License.
+// Copyright (c) 2023 Ladislav Bartos
+
+//! Implementation of the `BackupError` type.
+
+use std::fmt;
+use std::io::ErrorKind;
+
+/// Error that can occur when creating a backup.
+///
+/// `BackupError` can be converted into [`std::io::Error`] using `From`/`Into`
+/// so it can be propagated in code that works with I/O errors.
+/// The [`BackupError::kind`] method returns the corresponding [`std::io::ErrorKind`].
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum BackupError {
+    /// The specified path does not exist.
+    NotFound,
+    /// The specified path already exists.
+    AlreadyExists,
+    /// No backup of the specified path exists.
+    NoBackup,
+    /// No unique name for the backup could be found.
+    NoUniqueName,
+    /// The name of the backup would contain characters that are not allowed in filenames.
+    InvalidPath(String),
+    /// The format of the timestamp is not valid.
+    InvalidFormat,
+    /// An argument passed to the function is not valid.
+    InvalidArgument(String),
+    /// The specified path is already a backup.
+    IsBackup,
+    /// The name of the backup would exceed the limit of the filesystem.
+    NameTooLong,
+    /// The directory in which the backup should be created (or from which the original
+    /// should be moved) is not writable. Contains the path to the directory.
+    NotWritable(std::path::PathBuf),
+    /// The directory in which the backup should be created was removed while the backup was being created.
+    /// Contains the path to the directory.
+    DestinationMissing(std::path::PathBuf),
+    /// The lock on the original file could not be acquired within the timeout.
+    Locked,
+    /// The content of the backup does not match the content of the original.
+    VerificationFailed,
+    /// The operation is not supported for the specified path.
+    Unsupported(String),
+    /// The path is not a valid UTF-8.
+    ///
+    /// Paths are handled as [`std::ffi::OsStr`] on all platforms, so non-UTF-8 paths can be backed up.
+    /// This error is only returned by functions returning the path as a string, such as [`crate::backup_str`].
+    NotUtf8,
+    /// The path is root.
+    IsRoot,
+    /// The path ends in '..'.
+    EndsInDotDot,
+    /// An I/O error occurred during the backup process.
+    Io(std::io::Error),
+}
+
+impl BackupError {
+    /// Returns the [`std::io::ErrorKind`] corresponding to this error.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            BackupError::NotFound | BackupError::NoBackup | BackupError::DestinationMissing(_) => {
+                ErrorKind::NotFound
+            }
+            BackupError::AlreadyExists | BackupError::NoUniqueName => ErrorKind::AlreadyExists,
+            BackupError::InvalidPath(_)
+            | BackupError::InvalidFormat
+            | BackupError::InvalidArgument(_)
+            | BackupError::IsBackup
+            | BackupError::NameTooLong => ErrorKind::InvalidInput,
+            BackupError::Unsupported(_)
+            | BackupError::NotUtf8
+            | BackupError::IsRoot
+            | BackupError::EndsInDotDot => ErrorKind::Unsupported,
+            BackupError::NotWritable(_) => ErrorKind::PermissionDenied,
+            BackupError::Locked => ErrorKind::WouldBlock,
+            BackupError::VerificationFailed => ErrorKind::Other,
+            BackupError::Io(e) => e.kind(),
+        }
+    }
+}
+
+impl fmt::Display for BackupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BackupError::NotFound => write!(f, "Path does not exist."),
+            BackupError::AlreadyExists => write!(f, "Path already exists."),
+            BackupError::NoBackup => write!(f, "No backup of the path exists."),
+            BackupError::NoUniqueName => write!(f, "No unique name for the backup could be found."),
+            BackupError::InvalidPath(x) => write!(f, "{}", x),
+            BackupError::InvalidFormat => write!(f, "Timestamp format is not valid."),
+            BackupError::InvalidArgument(x) => write!(f, "{}", x),
+            BackupError::IsBackup => write!(f, "Path is already a backup."),
+            BackupError::NameTooLong => {
+                write!(f, "Resulting backup name exceeds filesystem limit.")
+            }
+            BackupError::NotWritable(x) => {
+                write!(f, "Directory '{}' is not writable.", x.display())
+            }
+            BackupError::DestinationMissing(x) => write!(
+                f,
+                "Backup destination directory '{}' no longer exists.",
+                x.display()
+            ),
+            BackupError::Locked => write!(f, "File is locked by another process."),
+            BackupError::VerificationFailed => {
+                write!(f, "Backup does not match the original.")
+            }
+            BackupError::Unsupported(x) => write!(f, "{}", x),
+            BackupError::NotUtf8 => write!(f, "Path is not a valid UTF-8."),
+            BackupError::IsRoot => write!(f, "Path is root."),
+            BackupError::EndsInDotDot => write!(f, "Path ends in '..'."),
+            BackupError::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for BackupError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            BackupError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for BackupError {
+    fn from(e: std::io::Error) -> Self {
+        // errors of this crate converted into `std::io::Error` are converted back
+        if e.get_ref().is_some_and(|x| x.is::<BackupError>()) {
+            if let Some(Ok(x)) = e.into_inner().map(|x| x.downcast::<BackupError>()) {
+                return *x;
+            }
+            unreachable!("the error has been checked to be `BackupError`");
+        }
+
+        BackupError::Io(e)
+    }
+}
+
+impl From<BackupError> for std::io::Error {
+    fn from(e: BackupError) -> Self {
+        match e {
+            BackupError::Io(e) => e,
+            e => std::io::Error::new(e.kind(), e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kinds() {
+        assert_eq!(BackupError::NotFound.kind(), ErrorKind::NotFound);
+        assert_eq!(BackupError::AlreadyExists.kind(), ErrorKind::AlreadyExists);
+        assert_eq!(BackupError::NoBackup.kind(), ErrorKind::NotFound);
+        assert_eq!(BackupError::NoUniqueName.kind(), ErrorKind::AlreadyExists);
+        assert_eq!(
+            BackupError::InvalidPath(String::from("Invalid.")).kind(),
+            ErrorKind::InvalidInput
+        );
+        assert_eq!(BackupError::InvalidFormat.kind(), ErrorKind::InvalidInput);
+        assert_eq!(
+            BackupError::InvalidArgument(String::from("Invalid.")).kind(),
+            ErrorKind::InvalidInput
+        );
+        assert_eq!(BackupError::IsBackup.kind(), ErrorKind::InvalidInput);
+        assert_eq!(BackupError::NameTooLong.kind(), ErrorKind::InvalidInput);
+        assert_eq!(
+            BackupError::NotWritable(std::path::PathBuf::from("dir")).kind(),
+            ErrorKind::PermissionDenied
+        );
+        assert_eq!(
+            BackupError::DestinationMissing(std::path::PathBuf::from("dir")).kind(),
+            ErrorKind::NotFound
+        );
+        assert_eq!(BackupError::Locked.kind(), ErrorKind::WouldBlock);
+        assert_eq!(BackupError::VerificationFailed.kind(), ErrorKind::Other);
+        assert_eq!(
+            BackupError::Unsupported(String::from("Unsupported.")).kind(),
+            ErrorKind::Unsupported
+        );
+        assert_eq!(BackupError::NotUtf8.kind(), ErrorKind::Unsupported);
+        assert_eq!(BackupError::IsRoot.kind(), ErrorKind::Unsupported);
+        assert_eq!(BackupError::EndsInDotDot.kind(), ErrorKind::Unsupported);
+        assert_eq!(
+            BackupError::Io(std::io::Error::from(ErrorKind::PermissionDenied)).kind(),
+            ErrorKind::PermissionDenied
+        );
+    }
+
+    #[test]
+    fn into_io_error() {
+        let error: std::io::Error = BackupError::IsRoot.into();
+        assert_eq!(error.kind(), ErrorKind::Unsupported);
+        assert_eq!(error.to_string(), "Path is root.");
+
+        let error: std::io::Error = BackupError::Io(std::io::Error::other("Custom error.")).into();
+        assert_eq!(error.kind(), ErrorKind::Other);
+        assert_eq!(error.to_string(), "Custom error.");
+
+        // the original error is recovered when converting back
+        let error: std::io::Error = BackupError::Unsupported(String::from("FIFO.")).into();
+        assert!(matches!(BackupError::from(error), BackupError::Unsupported(x) if x == "FIFO."));
+    }
+}
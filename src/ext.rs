@@ -0,0 +1,91 @@
+// Released under MIT License.
+// Copyright (c) 2023 Ladislav Bartos
+
+//! Implementation of the `BackupExt` trait.
+
+use std::path::{Path, PathBuf};
+
+use crate::{backup, backup_copy, list_backups, BackupError};
+
+/// Extension trait providing the basic operations of this crate as methods of paths.
+///
+/// The trait is implemented for everything that can be converted into a [`Path`],
+/// e.g. `Path`, `PathBuf`, `&str`, and `String`. All methods delegate to the free functions of the same names.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::path::Path;
+/// use crate::backitup::BackupExt;
+///
+/// let backup_path = Path::new("data.txt").backup().unwrap();
+/// let copy_path = "config.toml".backup_copy().unwrap();
+/// println!("Backups of data.txt: {:?}", "data.txt".list_backups().unwrap());
+/// ```
+pub trait BackupExt {
+    /// Creates a backup of the file or directory. See [`crate::backup`].
+    #[must_use = "the returned path is the only record of where the backup has been created"]
+    fn backup(&self) -> Result<PathBuf, BackupError>;
+
+    /// Creates a backup copy of the file or directory. See [`crate::backup_copy`].
+    fn backup_copy(&self) -> Result<PathBuf, BackupError>;
+
+    /// Returns the paths to the existing backups of the file or directory. See [`crate::list_backups`].
+    fn list_backups(&self) -> Result<Vec<PathBuf>, BackupError>;
+}
+
+impl<P: AsRef<Path> + ?Sized> BackupExt for P {
+    fn backup(&self) -> Result<PathBuf, BackupError> {
+        backup(self)
+    }
+
+    fn backup_copy(&self) -> Result<PathBuf, BackupError> {
+        backup_copy(self)
+    }
+
+    fn list_backups(&self) -> Result<Vec<PathBuf>, BackupError> {
+        list_backups(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::test_dir;
+    use std::fs;
+
+    #[test]
+    fn methods() {
+        let tmp = test_dir();
+        let path = tmp.join("test_file_ext.txt");
+        fs::write(&path, "Some content to test.").unwrap();
+
+        let copy = match path.backup_copy() {
+            Ok(x) => x,
+            Err(_) => panic!("Backup failed."),
+        };
+        assert!(path.exists());
+
+        let backup = match path.as_path().backup() {
+            Ok(x) => x,
+            Err(_) => panic!("Backup failed."),
+        };
+        assert!(!path.exists());
+        assert_eq!(fs::read_to_string(&copy).unwrap(), "Some content to test.");
+        assert_eq!(
+            fs::read_to_string(&backup).unwrap(),
+            "Some content to test."
+        );
+
+        let as_string = path.to_str().unwrap().to_owned();
+        let listed = as_string.list_backups().unwrap();
+        assert_eq!(listed, as_string.as_str().list_backups().unwrap());
+        assert_eq!(listed.len(), 2);
+        assert!(listed.contains(&copy) && listed.contains(&backup));
+
+        match path.backup() {
+            Ok(_) => panic!("Backup should have failed, but it was successful."),
+            Err(e) => assert!(matches!(e, BackupError::NotFound)),
+        }
+    }
+}
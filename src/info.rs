@@ -0,0 +1,71 @@
+// Released under MIT License.
+// Copyright (c) 2023 Ladislav Bartos
+
+//! Implementation of the `Backup` and `PrunedBackup` structures.
+
+use std::path::PathBuf;
+
+use chrono::prelude::*;
+
+use crate::list::{to_local_time, BackupTime};
+
+/// Information about a created backup.
+///
+/// Returned by [`crate::backup_detailed`] and [`crate::BackupOptions::backup_detailed`].
+///
+/// With the `serde` feature enabled, the information can be serialized and deserialized.
+/// The timestamp is serialized as an RFC 3339 string.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Backup {
+    /// Path to the backup.
+    pub backup_path: PathBuf,
+    /// Path to the original file or directory.
+    pub original_path: PathBuf,
+    /// Time at which the name of the backup was generated.
+    pub timestamp: DateTime<Local>,
+    /// Whether the original path was a directory.
+    pub was_directory: bool,
+    /// Whether microseconds had to be appended to the name of the backup
+    /// because a backup with the same timestamp already existed.
+    pub had_microsecond_collision: bool,
+}
+
+/// Information about a backup removed by pruning.
+///
+/// Returned by [`crate::prune_backups`], [`crate::prune_backups_older_than`], and [`crate::BackupManager::prune`],
+/// so that the removal of the backups can be logged.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PrunedBackup {
+    /// Path to the removed backup.
+    pub path: PathBuf,
+    /// Time of the backup, as encoded in its name.
+    pub timestamp: DateTime<Local>,
+    /// Why the backup has been removed.
+    pub reason: PruneReason,
+}
+
+/// Reason for the removal of a backup by pruning.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PruneReason {
+    /// More than the allowed number of backups existed and the backup was among the oldest.
+    ExceededCount,
+    /// The backup was older than the allowed age.
+    ExceededAge,
+}
+
+impl PrunedBackup {
+    /// Creates information about the backup at `path` with the time `time` encoded in its name.
+    pub(crate) fn new(path: PathBuf, time: &BackupTime, reason: PruneReason) -> Self {
+        // times skipped by a daylight saving time transition do not exist in local time
+        let timestamp = to_local_time(time).unwrap_or_else(|| Local.from_utc_datetime(&time.0));
+
+        PrunedBackup {
+            path,
+            timestamp,
+            reason,
+        }
+    }
+}
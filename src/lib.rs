@@ -32,9 +32,21 @@
 //!
 //! Note that the content of the file (or directory) is not copied, the file (or directory) is simply **renamed**.
 //!
+//! If you prefer working with strings, `backup_str` returns the path to the backup as a `String`.
+//! To keep the working directory clean, use `backup_to_subdir` to place the backup into a subdirectory
+//! next to the original (e.g. `.backups`, see `DEFAULT_SUBDIR`), which is created if it does not exist.
+//! For long-term archival, `backup_dated_tree` places the backup into a `<root>/YYYY/MM/DD` hierarchy.
+//! To let operators choose the location of the backups, `backup_env` places the backup into the directory
+//! set by the `BACKITUP_DIR` environment variable (or next to the original, if the variable is not set).
+//! To use the method syntax (e.g. `path.backup()?`), import the `BackupExt` trait implemented for all paths and strings.
+//!
 //! ```rust
 //! use crate::backitup::backup;
 //!
+//! # // the example is run in an isolated directory
+//! # let tmp = tempfile::tempdir().unwrap();
+//! # std::env::set_current_dir(tmp.path()).unwrap();
+//! # std::fs::write("data.txt", "Some content.").unwrap();
 //! let path = "data.txt";
 //! match backup(path) {
 //!     Ok(backup_path) => println!("Backup created: {:?}", backup_path),
@@ -42,6 +54,356 @@
 //! }
 //! ```
 //!
+//! ### Handling Errors
+//!
+//! All functions of this crate return `BackupError` on failure. You can match on its variants
+//! to handle specific failures, or convert it into `std::io::Error` using `?` or `From`/`Into`.
+//! If the path may not exist yet, use `backup_if_exists`, which returns `Ok(None)` instead of `BackupError::NotFound`.
+//! On networked filesystems, `backup_with_retries` retries the rename after transient errors such as `ErrorKind::TimedOut`.
+//! `backup` only ever renames the original, which is fast and atomic, but fails with `ErrorKind::CrossesDevices` if the backup would be located on a different filesystem.
+//! `backup_move` (and `backup_to` or `BackupOptions::cross_device(true)`) copies the original and removes it in that case, which is slower and not atomic.
+//! The functions never panic on invalid paths; `try_backup` additionally converts any unexpected panic into an error.
+//! Paths without a filename (e.g. `data/..`) are rejected with `BackupError::EndsInDotDot`, unless they are resolved
+//! into absolute paths first using `backup_canonicalized` (or `BackupOptions::canonicalize`). The root is always rejected.
+//!
+//! ```no_run
+//! use crate::backitup::{backup, BackupError};
+//!
+//! match backup("data.txt") {
+//!     Ok(backup_path) => println!("Backup created: {:?}", backup_path),
+//!     Err(BackupError::NotFound) => println!("Nothing to back up."),
+//!     Err(err) => eprintln!("Failed to create backup: {:?}", err),
+//! }
+//! ```
+//!
+//! ### Creating a Backup Copy
+//!
+//! If you want to keep the original file (or directory) in place, use the `backup_copy` function instead.
+//! The backup is named in the same way, but the content of the file is **copied**. Directories are copied recursively.
+//! Permissions and modification times are preserved.
+//! The content is copied into a temporary `.tmp` sibling of the backup first, which is then renamed,
+//! so a crash never leaves a partially written backup behind. Temporary files left behind by a crash
+//! can be removed using `clean_temp_backups`, e.g. on startup of your application.
+//! Use `backup_copy_verified` to also check that the SHA-256 digests of the backup and the original match.
+//! Use `backup_with_checksum` to store the SHA-256 digest of the backup in a `<backup>.sha256` file next to it,
+//! so that the backup can be checked using `verify_backup` later.
+//! Use `backup_copy_with_progress` to get notified about the number of copied bytes, e.g. to show a progress bar.
+//! To leave some entries of a directory out of the backup (e.g. `target` or `.git`), use `backup_directory_filtered`.
+//! To snapshot in-memory data or a stream without an original file, write it into a timestamped backup using `backup_write`.
+//! To stream the content of a file into any writer instead (e.g. a `Vec<u8>` or a compressing writer), use `backup_to_writer`.
+//!
+//! ```no_run
+//! use crate::backitup::backup_copy;
+//!
+//! let path = "data.txt";
+//! match backup_copy(path) {
+//!     Ok(backup_path) => println!("Backup created: {:?}", backup_path),
+//!     Err(err) => eprintln!("Failed to create backup: {:?}", err),
+//! }
+//! ```
+//!
+//! ### Replacing a File
+//!
+//! Use `backup_and_replace` to back up an existing file and write new content to its path in one step.
+//! If the new content can not be written, the backup is renamed back, so no data is lost.
+//! Use `backup_or_create` to back up a file (if it exists) and leave an empty file at its path, e.g. when rotating logs.
+//!
+//! ```no_run
+//! use crate::backitup::backup_and_replace;
+//!
+//! match backup_and_replace("config.toml", b"verbose = true\n") {
+//!     Ok(backup_path) => println!("Old content backed up: {:?}", backup_path),
+//!     Err(err) => eprintln!("Failed to replace the file: {:?}", err),
+//! }
+//! ```
+//!
+//! ### Hard Link Backups
+//!
+//! On filesystems supporting hard links, `backup_hardlink` creates the backup instantly as a hard link to the original file,
+//! which is left in place. Note that modifying the original file in place also modifies the backup.
+//!
+//! ```no_run
+//! use crate::backitup::backup_hardlink;
+//!
+//! let backup_path = backup_hardlink("data.txt").unwrap();
+//! ```
+//!
+//! ### Copy-on-Write Backups
+//!
+//! `backup_reflink` creates the backup as a copy-on-write clone (reflink) of the original file or directory, which is left in place.
+//! The backup is created almost instantly and shares the data with the original until either of them is modified.
+//! Reflinks are supported on Linux (e.g. Btrfs or XFS) and macOS (APFS). On other filesystems, a regular copy is created instead.
+//!
+//! ```no_run
+//! use crate::backitup::backup_reflink;
+//!
+//! let backup_path = backup_reflink("data.txt").unwrap();
+//! ```
+//!
+//! ### Previewing a Backup
+//!
+//! Use `backup_dry_run` to get the name the backup would have without creating it.
+//! All checks are performed as usual, but nothing is renamed or copied.
+//!
+//! ```no_run
+//! use crate::backitup::backup_dry_run;
+//!
+//! let path = "data.txt";
+//! match backup_dry_run(path) {
+//!     Ok(backup_path) => println!("Backup would be created: {:?}", backup_path),
+//!     Err(err) => eprintln!("Backup would fail: {:?}", err),
+//! }
+//! ```
+//!
+//! ### Numbered Backups
+//!
+//! If you prefer short names independent of the system clock, use `backup_numbered`, which renames `data.txt`
+//! to `data.txt.1`, `data.txt.2`, and so on. The backup with the largest number can be restored using `restore_numbered`.
+//!
+//! ```no_run
+//! use crate::backitup::{backup_numbered, restore_numbered};
+//!
+//! let backup_path = backup_numbered("data.txt").unwrap();
+//! let restored = restore_numbered("data.txt").unwrap();
+//! ```
+//!
+//! ### Custom Naming Strategies
+//!
+//! To name the backups in your own way (e.g. using counters, UUIDs, or git hashes), implement the `NamingStrategy` trait
+//! and use `backup_with_strategy`. If the name is taken, the strategy is asked for an alternate name.
+//! The crate provides `TimestampNaming` (the default naming), `NumberedNaming`, and `UuidNaming`
+//! (which requires the `uuid` feature). To keep the timestamp and only append e.g. a node ID or a ULID to it,
+//! use `backup_with_suffix_fn`.
+//!
+//! ```no_run
+//! use crate::backitup::{backup_with_strategy, NumberedNaming};
+//!
+//! // creates a backup named e.g. 'data.txt.1'
+//! let backup_path = backup_with_strategy("data.txt", &NumberedNaming).unwrap();
+//! ```
+//!
+//! ```bash
+//! $ cargo add backitup --features uuid
+//! ```
+//!
+//! ### `.bak` Backups
+//!
+//! Many editors and configuration-management tools expect backups named `<filename>.bak`.
+//! Use `backup_dotbak` to rename `data.txt` to `data.txt.bak` (or `data.txt.bak.1`, `data.txt.bak.2`, and so on,
+//! if the name is already taken). Such backups can be listed using `list_dotbak` and restored using `restore_dotbak`.
+//!
+//! ```no_run
+//! use crate::backitup::{backup_dotbak, list_dotbak, restore_dotbak};
+//!
+//! let backup_path = backup_dotbak("data.txt").unwrap();
+//! let backups = list_dotbak("data.txt").unwrap();
+//! let restored = restore_dotbak("data.txt").unwrap();
+//! ```
+//!
+//! ### Compressed Backups
+//!
+//! Use `backup_gzip` to compress a file into a gzip backup named `#<filename>-<timestamp>.gz#`
+//! or `backup_tar_gz` to archive a directory into `#<dirname>-<timestamp>#.tar.gz`.
+//! In both cases, the original file (or directory) is removed once the backup is written.
+//!
+//! ```no_run
+//! use crate::backitup::{backup_gzip, backup_tar_gz};
+//!
+//! let file_backup = backup_gzip("output.log").unwrap();
+//! let dir_backup = backup_tar_gz("output_dir").unwrap();
+//! ```
+//!
+//! ### Zip Archives
+//!
+//! Enable the `zip` feature to get `backup_into_zip`, which moves a file into an entry named `#<filename>-<timestamp>#`
+//! of a single zip archive (e.g. `backup_into_zip("output.log", "backups.zip")`). The archive is created if it does not exist,
+//! so all backups accumulate in one file.
+//!
+//! ```bash
+//! $ cargo add backitup --features zip
+//! ```
+//!
+//! ### Encrypted Backups
+//!
+//! Enable the `encryption` feature to get `backup_encrypted`, which encrypts a file using ChaCha20-Poly1305
+//! with a 256-bit key into a backup named `#<filename>-<timestamp>.enc#` and removes the original.
+//! Use `restore_encrypted` with the same key to decrypt the backup back into the original file.
+//!
+//! ```bash
+//! $ cargo add backitup --features encryption
+//! ```
+//!
+//! ### Asynchronous Backups
+//!
+//! Enable the `async` feature to get `backup_async`, which behaves like `backup`
+//! but uses `tokio::fs` so it does not block the asynchronous executor.
+//!
+//! ```bash
+//! $ cargo add backitup --features async
+//! ```
+//!
+//! ### Tracing
+//!
+//! Enable the `tracing` feature to emit [`tracing`](https://docs.rs/tracing) spans and events for every backup
+//! created by `backup` and the related functions. The events contain the original path, the path to the backup,
+//! and the error, if the backup fails. Without the feature, no tracing code is compiled in.
+//!
+//! ```bash
+//! $ cargo add backitup --features tracing
+//! ```
+//!
+//! ### Metrics
+//!
+//! Enable the `metrics` feature to count the backups created by `backup` and the related functions,
+//! e.g. for the metrics endpoint of a long-running service. `backup_stats` returns the number and the total size
+//! of the created backups and `reset_backup_stats` sets both counters to zero. The counters use atomics, so no locks are taken.
+//!
+//! ```bash
+//! $ cargo add backitup --features metrics
+//! ```
+//!
+//! ### Serialization
+//!
+//! Enable the `serde` feature to derive `Serialize` and `Deserialize` for `BackupOptions` and `Backup`.
+//! The options can then be loaded directly from configuration files (missing fields take their default values)
+//! and the information about created backups can be stored as structured data. Paths are serialized as strings
+//! and timestamps as RFC 3339 strings.
+//!
+//! The feature also provides `backup_directory_with_manifest`, which backs up a directory and writes a JSON manifest
+//! listing the paths, sizes, and SHA-256 digests of its files next to the backup. Use `verify_manifest` to find
+//! the files of the backup that have been modified, removed, or added since.
+//!
+//! ```bash
+//! $ cargo add backitup --features serde
+//! ```
+//!
+//! ### Custom Wrapper
+//!
+//! By default, the name of the backup is wrapped in `#`. Use `backup_wrapped` to choose a different prefix and suffix
+//! (which can also be empty). Backups created this way can be listed using `list_backups_wrapped`.
+//!
+//! ```no_run
+//! use crate::backitup::{backup_wrapped, list_backups_wrapped};
+//!
+//! // creates a backup named e.g. 'bak_data.txt-2023-06-27-21-01-13.old'
+//! let backup_path = backup_wrapped("data.txt", "bak_", ".old").unwrap();
+//! let backups = list_backups_wrapped("data.txt", "bak_", ".old").unwrap();
+//! ```
+//!
+//! ### Custom Separator
+//!
+//! By default, the filename and the timestamp are separated by `-`. Use `backup_with_separator` to choose
+//! a separator that does not appear in your filenames, so that names like `report-2024.csv` remain unambiguous.
+//! Use `parse_backup_time_with_separator` to read the time back from the name.
+//!
+//! ```no_run
+//! use crate::backitup::{backup_with_separator, parse_backup_time_with_separator};
+//!
+//! // creates a backup named e.g. '#report-2024.csv@@2023-06-27-21-01-13#'
+//! let backup_path = backup_with_separator("report-2024.csv", "@@").unwrap();
+//! let time = parse_backup_time_with_separator(&backup_path, "@@").unwrap();
+//! ```
+//!
+//! ### Keeping the Extension
+//!
+//! Use `backup_with_suffix_before_extension` to insert the timestamp before the extension of the file and keep
+//! the extension at the end of the name (e.g. `data-2023-06-27-21-01-13.txt`), so that the backup can still be opened
+//! by the applications associated with the extension. `parse_backup_time` recognizes these names as well.
+//!
+//! ### Custom Clock
+//!
+//! The timestamp is obtained from the system clock. Implement the `Clock` trait and use `backup_with_clock`
+//! to provide the time yourself, e.g. to get predictable names of the backups in tests.
+//! Use `backup_with_mtime` to name the backup after the time of the last modification of the original instead.
+//!
+//! ```no_run
+//! use backitup::{backup_with_clock, Clock};
+//! use chrono::prelude::*;
+//!
+//! struct FixedClock;
+//!
+//! impl Clock for FixedClock {
+//!     fn now(&self) -> DateTime<Local> {
+//!         Local.with_ymd_and_hms(2023, 6, 27, 21, 1, 13).unwrap()
+//!     }
+//! }
+//!
+//! let backup_path = backup_with_clock("data.txt", &FixedClock).unwrap();
+//! assert_eq!(backup_path.to_str().unwrap(), "#data.txt-2023-06-27-21-01-13#");
+//! ```
+//!
+//! ### Configuring the Backup
+//!
+//! For more control over the backup, use the `BackupOptions` structure. It allows you to choose
+//! whether the original should be copied or renamed, whether the timestamp should be in UTC or local time,
+//! what the name of the backup should be wrapped in (`#` by default), and the format of the timestamp.
+//! Tools that must survive power loss can set `durable(true)` to flush the backup and its directory to the disk.
+//! To back up files that are being written by other processes (e.g. databases or logs), set `lock(timeout)`
+//! or use `backup_locked` to hold a lock on the original file while the backup is created.
+//!
+//! ```no_run
+//! use crate::backitup::BackupOptions;
+//!
+//! let options = BackupOptions::new()
+//!     .copy(true)
+//!     .utc(true)
+//!     .prefix("bak_")
+//!     .suffix("")
+//!     .build();
+//!
+//! match options.backup("data.txt") {
+//!     Ok(backup_path) => println!("Backup created: {:?}", backup_path),
+//!     Err(err) => eprintln!("Failed to create backup: {:?}", err),
+//! }
+//! ```
+//!
+//! ### Listing and Restoring Backups
+//!
+//! Use `list_backups` to find all backups of a file (or directory), sorted from the oldest to the newest.
+//! The original file does not have to exist. Use `restore` to rename the most recent backup back to the original path.
+//! To restore an older backup, pass its timestamp (e.g. obtained using `parse_backup_time`) to `restore_version`.
+//! Use `restore_safe` to back up the current version of the file before it is replaced by the restored backup.
+//! Use `restore_many` to undo `backup_many`, restoring either all of the files or none of them.
+//! To put a directory backup back into an existing directory, use `restore_dir_with_strategy` with a `RestoreStrategy` deciding which of the conflicting files are kept.
+//! For directories with many backups, `iter_backups` yields the backups lazily without sorting them.
+//!
+//! ```no_run
+//! use crate::backitup::{list_backups, restore};
+//!
+//! let path = "data.txt";
+//! let backups = list_backups(path).unwrap();
+//! println!("Found {} backups.", backups.len());
+//!
+//! match restore(path) {
+//!     Ok(restored) => println!("Backup restored: {:?}", restored),
+//!     Err(err) => eprintln!("Failed to restore backup: {:?}", err),
+//! }
+//! ```
+//!
+//! Old backups can be removed using `prune_backups`, which keeps only the specified number of the newest backups.
+//! Use `prune_backups_older_than` to remove backups older than the specified age instead.
+//! Both functions return the paths, timestamps, and reasons of removal (`PruneReason`) of the removed backups, e.g. for audit logs.
+//! To enforce the retention for all files in a directory tree at once, use `prune_tree`.
+//! To find out how much space the backups take, use `backups_total_size`.
+//! To avoid creating many backups in a loop, `backup_rate_limited` skips the backup if the most recent one is younger than the given interval.
+//! Use `backup_labeled` to include a label describing the purpose of the backup in its name (e.g. `#data.txt-pre-deploy-2023-06-27-21-01-13#`)
+//! and `list_labeled_backups` to list the backups with this label (they are not listed by `list_backups`).
+//!
+//! ### Managing Backups
+//!
+//! Applications managing many backups can use `BackupManager`, which is configured once with the directory
+//! for the backups, the number of backups to keep, and the naming of the backups. The oldest backups are removed
+//! automatically after each backup.
+//!
+//! ```no_run
+//! use crate::backitup::BackupManager;
+//!
+//! let manager = BackupManager::new(".backups").keep(5).build();
+//!
+//! let backup_path = manager.backup("data.txt").unwrap();
+//! let backups = manager.list("data.txt").unwrap();
+//! ```
+//!
 //! ### Name of the Backup
 //! The backup file or directory name is generated based on the original `path`, appending a timestamp
 //! in the format "YYYY-MM-DD-HH-MM-SS". If multiple backups are created within the same second, additional
@@ -54,16 +416,107 @@
 //! For instance, file `data.txt` backed up on 2023/06/27 at 21:01:13 (local time) will be
 //! renamed as `#data.txt-2023-06-27-21-01-13#.
 //!
+//! If names with microseconds are repeatedly taken as well (e.g. because the system clock is frozen),
+//! a counter is appended after the microseconds (`-<microseconds>-<counter>`). The counter is shared by all threads
+//! of the process and only ever increases, so concurrent backups never generate the same name.
+//! If no unique name can be found this way within 1000 attempts (configurable using `BackupOptions::max_collisions`),
+//! the backup fails instead of retrying forever.
+//! Use `backup_with_collision_info` to find out whether microseconds had to be appended, e.g. to warn about rapidly repeated backups.
+//!
+//! The naming scheme can also be used without accessing the filesystem: `compute_backup_name` returns
+//! the name of a backup from the parent directory, the filename, and the time, and `is_backup_name`
+//! checks whether a name matches the pattern. The crate itself requires `std`, since it works with the filesystem.
+//!
 //! ## License
 //!
 //! This crate is distributed under the terms of the MIT license.
 //!
 
-use std::fs;
-use std::io::{Error, ErrorKind};
-use std::path::{Path, PathBuf};
+use std::collections::BTreeMap;
+use std::ffi::OsStr;
+use std::fmt::Write as _;
+use std::fs::{self, File};
+use std::io::{ErrorKind, Read, Write as _};
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
 
 use chrono::prelude::*;
+use filetime::FileTime;
+
+#[cfg(feature = "zip")]
+mod archive;
+#[cfg(feature = "async")]
+mod asynchronous;
+mod clock;
+mod compress;
+mod dotbak;
+#[cfg(feature = "encryption")]
+mod encrypt;
+mod error;
+mod ext;
+mod info;
+mod list;
+mod manager;
+#[cfg(feature = "serde")]
+mod manifest;
+#[cfg(feature = "metrics")]
+mod metrics;
+mod naming;
+mod numbered;
+mod options;
+mod restore;
+#[cfg(test)]
+mod testutil;
+mod verify;
+
+#[cfg(feature = "zip")]
+pub use archive::backup_into_zip;
+#[cfg(feature = "async")]
+pub use asynchronous::backup_async;
+pub use clock::{Clock, SystemClock};
+pub use compress::{backup_gzip, backup_tar_gz};
+pub use dotbak::{backup_dotbak, list_dotbak, restore_dotbak};
+#[cfg(feature = "encryption")]
+pub use encrypt::{backup_encrypted, restore_encrypted};
+pub use error::BackupError;
+pub use ext::BackupExt;
+pub use info::{Backup, PruneReason, PrunedBackup};
+pub use list::{
+    backups_total_size, is_backup_name, iter_backups, list_backups, list_backups_wrapped,
+    list_labeled_backups, parse_backup_time, parse_backup_time_with_separator,
+    parse_backup_time_wrapped,
+};
+pub use manager::BackupManager;
+#[cfg(feature = "serde")]
+pub use manifest::{
+    backup_directory_with_manifest, verify_manifest, Manifest, ManifestEntry, ManifestMismatch,
+};
+#[cfg(feature = "metrics")]
+pub use metrics::{backup_stats, reset_backup_stats, BackupStats};
+#[cfg(feature = "uuid")]
+pub use naming::UuidNaming;
+pub use naming::{
+    backup_with_strategy, backup_with_suffix_fn, NamingStrategy, NumberedNaming, TimestampNaming,
+};
+pub use numbered::{backup_numbered, restore_numbered};
+pub use options::BackupOptions;
+pub use restore::{
+    restore, restore_dir_with_strategy, restore_many, restore_overwrite, restore_safe,
+    restore_version, restore_version_overwrite, RestoreStrategy,
+};
+pub use verify::{backup_copy_verified, backup_if_changed, backup_with_checksum, verify_backup};
+
+/// Default format of the timestamp.
+const DEFAULT_FORMAT: &str = "%Y-%m-%d-%H-%M-%S";
+
+/// Default string placed in front of the name of the backup.
+const DEFAULT_PREFIX: &str = "#";
+
+/// Default string placed at the end of the name of the backup.
+const DEFAULT_SUFFIX: &str = "#";
+
+/// Default string placed between the filename and the timestamp.
+const DEFAULT_SEPARATOR: &str = "-";
 
 /// Creates a backup of the specified file or directory.
 /// Returns the path to the backup file if successful, otherwise returns an error.
@@ -76,9 +529,16 @@ use chrono::prelude::*;
 ///
 /// This function can return the following errors:
 ///
-/// * `NotFound` - If the specified `path` does not exist.
-/// * `Unsupported` - If the `path` is not valid (i.e. not UTF-8, root or ends with '..').
-/// * `Io` - If an I/O error occurs during the backup process.
+/// * `BackupError::NotFound` - If the specified `path` does not exist (or is moved away during the backup).
+/// * `BackupError::IsRoot`, `BackupError::EndsInDotDot` - If the `path` is not valid
+///   (i.e. root or ends with '..').
+/// * `BackupError::NameTooLong` - If the name of the backup exceeds the limit of the filesystem.
+/// * `BackupError::NotWritable` - If the parent directory of `path` is not writable.
+/// * `BackupError::DestinationMissing` - If the directory of the backup is removed while the backup is being created.
+/// * `BackupError::Unsupported` - If the `path` is a special file (e.g. a FIFO or a socket)
+///   or a directory symbolic link or junction on Windows.
+///   See [`BackupOptions::allow_special_files`] and [`BackupOptions::allow_directory_links`].
+/// * `BackupError::Io` - If an I/O error occurs during the backup process.
 ///
 /// # Name of the Backup
 /// The backup file or directory name is generated based on the original `path`, appending a timestamp
@@ -92,6 +552,33 @@ use chrono::prelude::*;
 /// For instance, file `data.txt` backed up on 2023/06/27 at 21:01:13 (local time) will be
 /// renamed as `#data.txt-2023-06-27-21-01-13#.
 ///
+/// The name of the original file does not have to be a valid UTF-8.
+/// The timestamp is appended to the name as is, without converting the name to a string.
+///
+/// If names with microseconds are repeatedly taken as well (e.g. because the system clock is frozen),
+/// a counter is appended after the microseconds (`-<microseconds>-<counter>`). The counter is shared by all threads
+/// of the process and only ever increases, so concurrent backups never generate the same name.
+/// If no unique name can be found this way within 1000 attempts, the backup fails with `BackupError::NoUniqueName`
+/// instead of retrying forever. See [`BackupOptions::max_collisions`].
+///
+/// On Windows, if the path to the backup would exceed the legacy maximal path length (260 characters),
+/// it is converted into an extended-length path, so the returned path may start with `\\?\`.
+///
+/// An existing file is never overwritten by the backup. If another process creates a file with the name
+/// of the backup right before the backup is created, the next unique name is used instead.
+/// On Linux, this is guaranteed by renaming the file atomically (`renameat2` with `RENAME_NOREPLACE`).
+/// On other platforms (and filesystems not supporting atomic renaming), the existence of the name is checked
+/// right before renaming, which leaves a short window in which a concurrently created file may be overwritten.
+///
+/// Backing up the same path from multiple threads at once is safe. The original can only be renamed once,
+/// so exactly one of the concurrent backups gets it and the others fail with `BackupError::NotFound`.
+/// Since no backup is ever overwritten, the content of every successful backup is preserved.
+///
+/// The original is only ever renamed. If it can not be renamed because the backup would be located
+/// on a different filesystem (e.g. due to bind mounts), the backup fails with `BackupError::Io`
+/// of kind `ErrorKind::CrossesDevices` and the original is left untouched.
+/// Use [`backup_move`] to copy the original to the backup (and remove it) in this case.
+///
 /// # Examples
 ///
 /// ```no_run
@@ -103,260 +590,5949 @@ use chrono::prelude::*;
 ///     Err(err) => eprintln!("Failed to create backup: {:?}", err),
 /// }
 /// ```
-pub fn backup(path: impl AsRef<Path>) -> Result<PathBuf, std::io::Error> {
-    // check if the path exists
-    if !path.as_ref().exists() {
-        return Err(Error::new(ErrorKind::NotFound, "Path does not exist."));
+#[must_use = "the returned path is the only record of where the backup has been created"]
+pub fn backup(path: impl AsRef<Path>) -> Result<PathBuf, BackupError> {
+    BackupOptions::new().backup(path)
+}
+
+/// Moves the specified file or directory to its backup, on the same or on a different filesystem.
+/// Returns the path to the backup file if successful, otherwise returns an error.
+///
+/// # Arguments
+///
+/// * `path` - The path to the file or directory to be backed up.
+///
+/// # Errors
+///
+/// This function can return the same errors as [`backup`].
+///
+/// # Details
+/// The backup is created in the same way as with [`backup`]: the original is **renamed** to the backup
+/// if both are located on the same filesystem, which is fast and atomic. Unlike with [`backup`], which fails
+/// in this case, an original located on a different filesystem than its backup is copied to the backup
+/// (via a temporary sibling named `<backup>.tmp`) and then removed, which takes time proportional
+/// to its size and is not atomic, i.e. both the original and the backup exist for a while.
+///
+/// This is the same as `BackupOptions::new().cross_device(true).backup(path)`, see [`BackupOptions::cross_device`].
+/// All other functions creating backups by renaming (except for [`backup_to`]) never copy the original.
+///
+/// # Examples
+///
+/// ```no_run
+/// use crate::backitup::backup_move;
+///
+/// let path = "data.txt";
+/// match backup_move(path) {
+///     Ok(backup_path) => println!("Backup created: {:?}", backup_path),
+///     Err(err) => eprintln!("Failed to create backup: {:?}", err),
+/// }
+/// ```
+pub fn backup_move(path: impl AsRef<Path>) -> Result<PathBuf, BackupError> {
+    BackupOptions::new().cross_device(true).backup(path)
+}
+
+/// Creates a backup of the specified file or directory, guaranteeing that every failure is reported as an error.
+/// Returns the path to the backup file if successful, otherwise returns an error.
+///
+/// # Arguments
+///
+/// * `path` - The path to the file or directory to be backed up.
+///
+/// # Errors
+///
+/// This function can return the same errors as [`backup`] and additionally:
+///
+/// * `BackupError::Io` (with `ErrorKind::Other`) - If the backup panicked.
+///
+/// # Details
+/// The backup is created in the same way as with [`backup`]. The functions of this crate never unwrap
+/// internally: invalid paths, names that can not be generated, and all I/O failures are returned as errors,
+/// and the search for a unique name always terminates. This function additionally catches any unexpected panic
+/// (e.g. inside the standard library or a dependency) and converts it into an error, so it can be called
+/// from panic-sensitive contexts, such as `Drop` implementations.
+///
+/// Note that panics can only be caught if the crate is compiled with `panic = "unwind"` (the default)
+/// and that the panic message is still printed by the panic hook.
+///
+/// # Examples
+///
+/// ```no_run
+/// use crate::backitup::try_backup;
+///
+/// struct Output;
+///
+/// impl Drop for Output {
+///     fn drop(&mut self) {
+///         if let Err(err) = try_backup("output.txt") {
+///             eprintln!("Failed to create backup: {}", err);
+///         }
+///     }
+/// }
+/// ```
+pub fn try_backup(path: impl AsRef<Path>) -> Result<PathBuf, BackupError> {
+    let path = path.as_ref().to_path_buf();
+
+    match std::panic::catch_unwind(move || backup(path)) {
+        Ok(result) => result,
+        Err(payload) => {
+            let message = payload
+                .downcast_ref::<&str>()
+                .map(|x| x.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_default();
+
+            Err(BackupError::Io(std::io::Error::other(format!(
+                "Backup panicked: {}",
+                message
+            ))))
+        }
+    }
+}
+
+/// Creates a backup of the specified file or directory.
+/// Returns the path to the backup file as a `String` if successful, otherwise returns an error.
+///
+/// # Arguments
+///
+/// * `path` - The path to the file or directory to be backed up.
+///
+/// # Errors
+///
+/// This function can return the following errors:
+///
+/// * `BackupError::NotUtf8` - If the `path` (and therefore the path to the backup) is not a valid UTF-8.
+///   No backup is created in this case.
+/// * Any error returned by [`backup`].
+///
+/// # Details
+/// This is a convenience wrapper over [`backup`] for code that works with strings instead of paths.
+///
+/// # Examples
+///
+/// ```no_run
+/// use crate::backitup::backup_str;
+///
+/// match backup_str("data.txt") {
+///     Ok(backup_path) => println!("Backup created: {}", backup_path),
+///     Err(err) => eprintln!("Failed to create backup: {:?}", err),
+/// }
+/// ```
+pub fn backup_str(path: impl AsRef<Path>) -> Result<String, BackupError> {
+    let path = path.as_ref();
+
+    // the backup path consists of the original path and valid UTF-8 strings
+    if path.to_str().is_none() {
+        return Err(BackupError::NotUtf8);
+    }
+
+    backup(path)?
+        .into_os_string()
+        .into_string()
+        .map_err(|_| BackupError::NotUtf8)
+}
+
+/// Creates a backup of the specified file or directory.
+/// Returns information about the created backup if successful, otherwise returns an error.
+///
+/// # Arguments
+///
+/// * `path` - The path to the file or directory to be backed up.
+///
+/// # Errors
+///
+/// This function can return the same errors as [`backup`].
+///
+/// # Details
+/// The backup is created in the same way as with [`backup`]. The returned [`Backup`]
+/// contains the path to the backup, the path to the original, the time of the backup, whether
+/// the original was a directory, and whether microseconds had to be appended to the name of the backup.
+///
+/// # Examples
+///
+/// ```no_run
+/// use crate::backitup::backup_detailed;
+///
+/// let path = "data.txt";
+/// match backup_detailed(path) {
+///     Ok(backup) => println!(
+///         "Backup of {:?} created at {}: {:?}",
+///         backup.original_path, backup.timestamp, backup.backup_path
+///     ),
+///     Err(err) => eprintln!("Failed to create backup: {:?}", err),
+/// }
+/// ```
+pub fn backup_detailed(path: impl AsRef<Path>) -> Result<Backup, BackupError> {
+    BackupOptions::new().backup_detailed(path)
+}
+
+/// Creates a backup of the specified file or directory.
+/// Returns the path to the backup file and whether the name of the backup collided with an existing file
+/// if successful, otherwise returns an error.
+///
+/// # Arguments
+///
+/// * `path` - The path to the file or directory to be backed up.
+///
+/// # Errors
+///
+/// This function can return the same errors as [`backup`].
+///
+/// # Details
+/// The backup is created in the same way as with [`backup`]. The returned flag is `true`
+/// if a backup with the same timestamp already existed, so that microseconds had to be appended
+/// to the name of the backup. This indicates rapidly repeated backups, e.g. for rate-limiting.
+/// Use [`backup_detailed`] to get more information about the backup.
+///
+/// # Examples
+///
+/// ```no_run
+/// use crate::backitup::backup_with_collision_info;
+///
+/// match backup_with_collision_info("data.txt") {
+///     Ok((_, true)) => eprintln!("Backups are created too often."),
+///     Ok((backup_path, false)) => println!("Backup created: {:?}", backup_path),
+///     Err(err) => eprintln!("Failed to create backup: {:?}", err),
+/// }
+/// ```
+pub fn backup_with_collision_info(path: impl AsRef<Path>) -> Result<(PathBuf, bool), BackupError> {
+    backup_detailed(path).map(|x| (x.backup_path, x.had_microsecond_collision))
+}
+
+/// Creates a backup of the specified file or directory using a custom timestamp format.
+/// Returns the path to the backup file if successful, otherwise returns an error.
+///
+/// # Arguments
+///
+/// * `path` - The path to the file or directory to be backed up.
+/// * `fmt` - The format of the timestamp. See [`chrono::format::strftime`] for the supported specifiers.
+///
+/// # Errors
+///
+/// This function can return the same errors as [`backup`] and additionally:
+///
+/// * `BackupError::InvalidFormat` - If `fmt` is not a valid format string.
+/// * `BackupError::InvalidPath` - If the produced timestamp contains characters
+///   that are not allowed in filenames (e.g. '/', or '*' on Windows).
+///
+/// # Details
+/// On Windows, colons in the produced timestamp are replaced with hyphens (e.g. "%H:%M:%S" produces "21-01-13"),
+/// so that the same format can be used on all platforms. If the `tracing` feature is enabled, a warning is emitted.
+///
+/// # Examples
+///
+/// ```no_run
+/// use crate::backitup::backup_with_format;
+///
+/// let path = "data.txt";
+/// match backup_with_format(path, "%s") {
+///     Ok(backup_path) => println!("Backup created: {:?}", backup_path),
+///     Err(err) => eprintln!("Failed to create backup: {:?}", err),
+/// }
+/// ```
+pub fn backup_with_format(path: impl AsRef<Path>, fmt: &str) -> Result<PathBuf, BackupError> {
+    BackupOptions::new().format(fmt).backup(path)
+}
+
+/// Creates a backup copy of the specified file or directory, leaving the original untouched.
+/// Returns the path to the backup file if successful, otherwise returns an error.
+///
+/// # Arguments
+///
+/// * `path` - The path to the file or directory to be backed up.
+///
+/// # Errors
+///
+/// This function can return the same errors as [`backup`].
+///
+/// # Details
+/// The backup is named in the same way as with [`backup`], but the content of the file
+/// is **copied** into the backup instead of the file being renamed.
+/// Directories are copied recursively, preserving their nested structure.
+/// Permissions and modification times of the copied files and directories are preserved.
+/// Symbolic links are never followed; they are copied as links pointing to the same paths
+/// (use [`backup_follow_symlinks`] to copy the target of a link).
+///
+/// The content is first copied into a temporary sibling of the backup named `<backup>.tmp`
+/// which is then renamed to the name of the backup. The backup therefore only ever appears
+/// fully written, even if the process crashes during the copy.
+///
+/// # Examples
+///
+/// ```no_run
+/// use crate::backitup::backup_copy;
+///
+/// let path = "data.txt";
+/// match backup_copy(path) {
+///     Ok(backup_path) => println!("Backup created: {:?}", backup_path),
+///     Err(err) => eprintln!("Failed to create backup: {:?}", err),
+/// }
+/// ```
+pub fn backup_copy(path: impl AsRef<Path>) -> Result<PathBuf, BackupError> {
+    BackupOptions::new().copy(true).backup(path)
+}
+
+/// Size of the chunks in which the content is copied by [`backup_copy_with_progress`].
+const PROGRESS_CHUNK: usize = 64 * 1024;
+
+/// Creates a backup copy of the specified file or directory, reporting the progress of the copying.
+/// Returns the path to the backup file if successful, otherwise returns an error.
+///
+/// # Arguments
+///
+/// * `path` - The path to the file or directory to be backed up.
+/// * `progress` - Function called with the number of bytes copied so far and the total number of bytes.
+///
+/// # Errors
+///
+/// This function can return the same errors as [`backup_copy`].
+///
+/// # Details
+/// The backup is created in the same way as with [`backup_copy`]. Before copying, the total size of the `path`
+/// is computed by walking the directory tree. `progress` is then called once with zero copied bytes
+/// and again after every copied chunk (64 KiB) of every file, so it can be used to drive a progress bar.
+/// Once the copy is complete, the number of copied bytes is equal to the total size.
+///
+/// If the name of the backup is taken while copying and the copy has to be repeated,
+/// the progress starts again from zero.
+///
+/// # Examples
+///
+/// ```no_run
+/// use crate::backitup::backup_copy_with_progress;
+///
+/// let result = backup_copy_with_progress("data", |copied, total| {
+///     println!("Copied {} of {} bytes.", copied, total);
+/// });
+///
+/// match result {
+///     Ok(backup_path) => println!("Backup created: {:?}", backup_path),
+///     Err(err) => eprintln!("Failed to create backup: {:?}", err),
+/// }
+/// ```
+pub fn backup_copy_with_progress(
+    path: impl AsRef<Path>,
+    mut progress: impl FnMut(u64, u64),
+) -> Result<PathBuf, BackupError> {
+    let path = path.as_ref();
+    let options = BackupOptions::new().copy(true).build();
+    let name = options.get_unique_name(path, &SystemClock)?;
+
+    // symbolic links are copied as links
+    let total = path_size(path, false)?;
+    let mut buffer = vec![0; PROGRESS_CHUNK];
+
+    let name = options.create_unique(path, name, &SystemClock, |target| {
+        let mut copied = 0;
+        progress(copied, total);
+
+        write_via_temp(target, |temp| {
+            copy_recursive_with(
+                path,
+                temp,
+                &mut |reader, writer| loop {
+                    let read = match reader.read(&mut buffer) {
+                        Ok(0) => return Ok(()),
+                        Ok(x) => x,
+                        Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                        Err(e) => return Err(e),
+                    };
+
+                    writer.write_all(&buffer[..read])?;
+                    copied += read as u64;
+                    progress(copied, total);
+                },
+                &|_| false,
+            )
+        })
+    })?;
+
+    Ok(name.path)
+}
+
+/// Creates a backup copy of the specified directory, skipping the excluded entries.
+/// Returns the path to the backup directory if successful, otherwise returns an error.
+///
+/// # Arguments
+///
+/// * `path` - The path to the directory to be backed up.
+/// * `exclude` - Function returning `true` for the entries which should not be backed up.
+///   It is called with the path of each entry relative to `path` (e.g. `target` or `src/main.rs`).
+///
+/// # Errors
+///
+/// This function can return the same errors as [`backup_copy`] and additionally:
+///
+/// * `BackupError::Unsupported` - If the `path` is not a directory.
+///
+/// # Details
+/// Unlike [`backup`], which **renames** the directory, this function **copies** the directory
+/// recursively, so that some entries can be left out. The original directory (including the excluded entries)
+/// is left in place. If an excluded entry is a directory, its content is not visited at all.
+/// The backup is named in the same way as with [`backup_copy`] and the same metadata are preserved.
+///
+/// # Examples
+///
+/// ```no_run
+/// use crate::backitup::backup_directory_filtered;
+///
+/// let result = backup_directory_filtered("project", |entry| {
+///     ["target", ".git", "node_modules"].iter().any(|x| entry.ends_with(x))
+/// });
+///
+/// match result {
+///     Ok(backup_path) => println!("Backup created: {:?}", backup_path),
+///     Err(err) => eprintln!("Failed to create backup: {:?}", err),
+/// }
+/// ```
+pub fn backup_directory_filtered(
+    path: impl AsRef<Path>,
+    exclude: impl Fn(&Path) -> bool,
+) -> Result<PathBuf, BackupError> {
+    let path = path.as_ref();
+    let options = BackupOptions::new().copy(true).build();
+    let name = options.get_unique_name(path, &SystemClock)?;
+
+    if !path.is_dir() {
+        return Err(BackupError::Unsupported(String::from(
+            "Only directories can be backed up with excluded entries.",
+        )));
+    }
+
+    // the entries are passed to the filter relative to the backed up directory
+    let exclude = |entry: &Path| exclude(entry.strip_prefix(path).unwrap_or(entry));
+
+    let name = options.create_unique(path, name, &SystemClock, |target| {
+        write_via_temp(target, |temp| {
+            copy_recursive_with(path, temp, &mut copy_file, &exclude)
+        })
+    })?;
+
+    Ok(name.path)
+}
+
+/// Creates a backup of data read from `reader` in the directory `dir`.
+/// Returns the path to the backup file if successful, otherwise returns an error.
+///
+/// # Arguments
+///
+/// * `dir` - The directory in which the backup should be created.
+/// * `basename` - The name of the (possibly nonexistent) original file used in the name of the backup.
+/// * `reader` - The source of the content of the backup.
+///
+/// # Errors
+///
+/// This function can return the following errors:
+///
+/// * `BackupError::NotFound` - If the directory `dir` does not exist.
+/// * `BackupError::InvalidArgument` - If the `basename` is not a plain filename (e.g. it is empty or contains a separator).
+/// * `BackupError::InvalidPath`, `BackupError::NameTooLong` - If the name of the backup would not be valid.
+/// * `BackupError::NoUniqueName` - If no unique name for the backup could be found.
+/// * `BackupError::Io` - If reading from the `reader` or writing the backup fails.
+///
+/// # Details
+/// The backup is named in the same way as with [`backup`], i.e. `#<basename>-<timestamp>(-<microseconds>)#`,
+/// but no original file is needed. This allows snapshotting generated content or streams directly.
+///
+/// The whole content of the `reader` is first written into a temporary sibling of the backup named
+/// `<backup>.tmp` which is then renamed to the name of the backup. The backup therefore only ever appears
+/// fully written. If the name is taken by someone else in the meantime, the next unique name is used,
+/// so the `reader` is only read once.
+///
+/// # Examples
+///
+/// ```no_run
+/// use crate::backitup::backup_write;
+/// use std::io::Cursor;
+///
+/// let mut reader = Cursor::new("Generated report.");
+/// match backup_write("reports", "report.txt", &mut reader) {
+///     Ok(backup_path) => println!("Backup created: {:?}", backup_path),
+///     Err(err) => eprintln!("Failed to create backup: {:?}", err),
+/// }
+/// ```
+pub fn backup_write(
+    dir: impl AsRef<Path>,
+    basename: &str,
+    reader: &mut impl Read,
+) -> Result<PathBuf, BackupError> {
+    let dir = dir.as_ref();
+
+    // the basename must not point to another directory
+    let mut components = Path::new(basename).components();
+    if !matches!(
+        (components.next(), components.next()),
+        (Some(Component::Normal(x)), None) if x == basename
+    ) {
+        return Err(BackupError::InvalidArgument(format!(
+            "'{}' is not a valid filename.",
+            basename
+        )));
+    }
+
+    if !dir.is_dir() {
+        return Err(BackupError::NotFound);
+    }
+
+    let options = BackupOptions::new();
+    let mut names = options.backup_names_in(dir, OsStr::new(basename), &SystemClock)?;
+
+    let mut backup_name = names.next_name()?;
+    while backup_name.exists() {
+        backup_name = names.next_name()?;
+    }
+
+    // deep paths can only be created as extended-length paths on Windows
+    #[cfg(windows)]
+    let mut backup_name = extend_long_path(backup_name);
+
+    let temp = temp_path(&backup_name);
+    match File::create_new(&temp) {
+        Ok(mut file) => {
+            if let Err(e) = std::io::copy(reader, &mut file) {
+                // do not leave a partial backup behind
+                let _ = fs::remove_file(&temp);
+                return Err(BackupError::Io(e));
+            }
+        }
+        Err(e) => return Err(BackupError::Io(e)),
+    }
+
+    // the content can not be read again, so only the name is changed on collision
+    loop {
+        match rename_noreplace(&temp, &backup_name) {
+            Ok(()) => return Ok(backup_name),
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => (),
+            Err(e) => {
+                let _ = fs::remove_file(&temp);
+                return Err(BackupError::Io(e));
+            }
+        }
+
+        backup_name = match names.next_name() {
+            Ok(x) => x,
+            Err(e) => {
+                let _ = fs::remove_file(&temp);
+                return Err(e);
+            }
+        };
+
+        #[cfg(windows)]
+        {
+            backup_name = extend_long_path(backup_name);
+        }
+    }
+}
+
+/// Streams the content of the specified file into `writer`, e.g. a `Vec<u8>` or a compressing writer.
+/// Returns the number of bytes written if successful, otherwise returns an error.
+///
+/// # Arguments
+///
+/// * `path` - The path to the file to be backed up.
+/// * `writer` - The destination of the content of the file.
+/// * `remove` - Whether the original file should be removed once its content has been written.
+///
+/// # Errors
+///
+/// This function can return the following errors:
+///
+/// * `BackupError::NotFound` - If the specified `path` does not exist.
+/// * `BackupError::Unsupported` - If the `path` is a directory or a special file (e.g. a FIFO or a socket).
+/// * `BackupError::Io` - If reading the file, writing into the `writer`, or removing the file fails.
+///
+/// # Details
+/// No backup is created on the filesystem, the destination of the content is entirely up to the `writer`.
+/// The `writer` is flushed after the whole content has been written. The original file is only removed
+/// (if `remove` is set) after the content has been written and flushed successfully, so a failed backup
+/// never loses any data. Symbolic links are followed.
+///
+/// # Examples
+///
+/// ```no_run
+/// use crate::backitup::backup_to_writer;
+///
+/// let mut content = Vec::new();
+/// match backup_to_writer("data.txt", &mut content, false) {
+///     Ok(bytes) => println!("Backed up {} bytes.", bytes),
+///     Err(err) => eprintln!("Failed to create backup: {:?}", err),
+/// }
+/// ```
+pub fn backup_to_writer(
+    path: impl AsRef<Path>,
+    writer: &mut impl std::io::Write,
+    remove: bool,
+) -> Result<u64, BackupError> {
+    let path = path.as_ref();
+
+    // check if the path exists
+    if !path.exists() {
+        return Err(BackupError::NotFound);
+    }
+
+    if path.is_dir() {
+        return Err(BackupError::Unsupported(String::from(
+            "Directories can not be backed up into a writer.",
+        )));
+    }
+
+    // reading from special files may block indefinitely
+    check_file_type(path, false)?;
+
+    let mut reader = File::open(path)?;
+    let bytes = std::io::copy(&mut reader, writer)?;
+    writer.flush()?;
+
+    if remove {
+        fs::remove_file(path)?;
+    }
+
+    Ok(bytes)
+}
+
+/// Creates a backup of the specified file or directory using a UTC timestamp.
+/// Returns the path to the backup file if successful, otherwise returns an error.
+///
+/// # Arguments
+///
+/// * `path` - The path to the file or directory to be backed up.
+///
+/// # Errors
+///
+/// This function can return the same errors as [`backup`].
+///
+/// # Details
+/// The backup is named in the same way as with [`backup`], but the timestamp
+/// in its name is in **UTC**, not in local time. For instance, file `data.txt` backed up
+/// on 2023/06/27 at 21:01:13 (UTC) will be renamed as `#data.txt-2023-06-27-21-01-13#`,
+/// no matter the time zone of the machine.
+///
+/// The names of UTC backups sort in chronological order even if they were created
+/// in different time zones or around daylight saving time transitions.
+///
+/// # Examples
+///
+/// ```no_run
+/// use crate::backitup::backup_utc;
+///
+/// let path = "data.txt";
+/// match backup_utc(path) {
+///     Ok(backup_path) => println!("Backup created: {:?}", backup_path),
+///     Err(err) => eprintln!("Failed to create backup: {:?}", err),
+/// }
+/// ```
+pub fn backup_utc(path: impl AsRef<Path>) -> Result<PathBuf, BackupError> {
+    BackupOptions::new().utc(true).backup(path)
+}
+
+/// Creates a backup of the specified file or directory in the directory `dest_dir`.
+/// Returns the path to the backup file if successful, otherwise returns an error.
+///
+/// # Arguments
+///
+/// * `path` - The path to the file or directory to be backed up.
+/// * `dest_dir` - The directory into which the backup should be placed.
+///
+/// # Errors
+///
+/// This function can return the same errors as [`backup`].
+///
+/// # Details
+/// The backup is named in the same way as with [`backup`], but it is placed in `dest_dir`
+/// instead of the parent directory of the original file. If `dest_dir` does not exist, it is created.
+///
+/// If `dest_dir` is located on a different filesystem than the original file,
+/// the original file (or directory) is copied into `dest_dir` and then removed (see [`backup_move`]).
+///
+/// # Examples
+///
+/// ```no_run
+/// use crate::backitup::backup_to;
+///
+/// let path = "data.txt";
+/// match backup_to(path, "backups") {
+///     Ok(backup_path) => println!("Backup created: {:?}", backup_path),
+///     Err(err) => eprintln!("Failed to create backup: {:?}", err),
+/// }
+/// ```
+pub fn backup_to(
+    path: impl AsRef<Path>,
+    dest_dir: impl AsRef<Path>,
+) -> Result<PathBuf, BackupError> {
+    BackupOptions::new()
+        .destination(dest_dir)
+        .cross_device(true)
+        .backup(path)
+}
+
+/// Name of the environment variable containing the destination directory used by [`backup_env`].
+pub const DIR_ENV_VAR: &str = "BACKITUP_DIR";
+
+/// Creates a backup of the specified file or directory in the directory set by the `BACKITUP_DIR` environment variable.
+/// Returns the path to the backup file if successful, otherwise returns an error.
+///
+/// # Arguments
+///
+/// * `path` - The path to the file or directory to be backed up.
+///
+/// # Errors
+///
+/// This function can return the same errors as [`backup`].
+///
+/// # Details
+/// The environment variable [`DIR_ENV_VAR`] (`BACKITUP_DIR`) is read every time this function is called.
+/// If it is set (and not empty), the backup is created in the same way as with [`backup_to`],
+/// i.e. it is placed in the directory named by the variable, which is created if it does not exist.
+/// Relative paths are resolved against the current working directory. If the variable is not set,
+/// the backup is created in the parent directory of `path`, same as with [`backup`].
+///
+/// This allows the location of the backups to be configured without changing the code.
+/// [`backup`] and the other functions never read the environment.
+///
+/// # Examples
+///
+/// ```no_run
+/// use crate::backitup::backup_env;
+///
+/// // e.g. run with `BACKITUP_DIR=/var/backups`
+/// match backup_env("data.txt") {
+///     Ok(backup_path) => println!("Backup created: {:?}", backup_path),
+///     Err(err) => eprintln!("Failed to create backup: {:?}", err),
+/// }
+/// ```
+pub fn backup_env(path: impl AsRef<Path>) -> Result<PathBuf, BackupError> {
+    match std::env::var_os(DIR_ENV_VAR) {
+        Some(dir) if !dir.is_empty() => backup_to(path, dir),
+        _ => backup(path),
+    }
+}
+
+/// Default name of the subdirectory used by [`backup_to_subdir`].
+pub const DEFAULT_SUBDIR: &str = ".backups";
+
+/// Creates a backup of the specified file or directory in a subdirectory of its parent directory.
+/// Returns the path to the backup file if successful, otherwise returns an error.
+///
+/// # Arguments
+///
+/// * `path` - The path to the file or directory to be backed up.
+/// * `subdir` - The name of the subdirectory into which the backup should be placed, e.g. [`DEFAULT_SUBDIR`].
+///
+/// # Errors
+///
+/// This function can return the same errors as [`backup`] and additionally:
+///
+/// * `BackupError::InvalidArgument` - If `subdir` is not a single normal path component.
+///
+/// # Details
+/// The backup is named in the same way as with [`backup`], but it is placed in `<parent>/<subdir>`,
+/// where `<parent>` is the parent directory of `path`. The subdirectory is created if it does not exist.
+/// This keeps the working directories clean while keeping the backups close to the originals.
+///
+/// For instance, `data/data.txt` backed up using `backup_to_subdir("data/data.txt", ".backups")`
+/// is renamed to `data/.backups/#data.txt-2023-06-27-21-01-13#`.
+///
+/// # Examples
+///
+/// ```no_run
+/// use crate::backitup::{backup_to_subdir, DEFAULT_SUBDIR};
+///
+/// let path = "data.txt";
+/// match backup_to_subdir(path, DEFAULT_SUBDIR) {
+///     Ok(backup_path) => println!("Backup created: {:?}", backup_path),
+///     Err(err) => eprintln!("Failed to create backup: {:?}", err),
+/// }
+/// ```
+pub fn backup_to_subdir(path: impl AsRef<Path>, subdir: &str) -> Result<PathBuf, BackupError> {
+    // the subdirectory must be located directly in the parent directory
+    if Path::new(subdir).file_name() != Some(OsStr::new(subdir)) {
+        return Err(BackupError::InvalidArgument(String::from(
+            "Subdirectory must be a single normal path component.",
+        )));
+    }
+
+    let path = trim_trailing_separators(path.as_ref());
+    let (parent, _) = split_path(path)?;
+
+    BackupOptions::new()
+        .destination(parent.join(subdir))
+        .backup(path)
+}
+
+/// Creates a backup of the specified file or directory in a dated subdirectory of `root`.
+/// Returns the path to the backup file if successful, otherwise returns an error.
+///
+/// # Arguments
+///
+/// * `path` - The path to the file or directory to be backed up.
+/// * `root` - The root directory of the dated hierarchy.
+///
+/// # Errors
+///
+/// This function can return the same errors as [`backup`].
+///
+/// # Details
+/// The backup is named in the same way as with [`backup`], but it is placed in `<root>/YYYY/MM/DD`,
+/// where the date corresponds to the timestamp in the name of the backup (in local time).
+/// The directories are created if they do not exist. This keeps large sets of backups navigable by date.
+///
+/// For instance, `data.txt` backed up on 2023/06/27 at 21:01:13 using `backup_dated_tree("data.txt", "backups")`
+/// is renamed to `backups/2023/06/27/#data.txt-2023-06-27-21-01-13#`.
+///
+/// # Examples
+///
+/// ```no_run
+/// use crate::backitup::backup_dated_tree;
+///
+/// let path = "data.txt";
+/// match backup_dated_tree(path, "backups") {
+///     Ok(backup_path) => println!("Backup created: {:?}", backup_path),
+///     Err(err) => eprintln!("Failed to create backup: {:?}", err),
+/// }
+/// ```
+pub fn backup_dated_tree(
+    path: impl AsRef<Path>,
+    root: impl AsRef<Path>,
+) -> Result<PathBuf, BackupError> {
+    // the same time is used for the directory and the name of the backup
+    let now = Local::now();
+    let dir = dated_dir(root.as_ref(), now);
+
+    BackupOptions::new()
+        .destination(dir)
+        .backup_with_clock(path, &clock::FixedClock(now))
+}
+
+/// Returns the path to the directory `<root>/YYYY/MM/DD` for the date of `time`.
+fn dated_dir(root: &Path, time: DateTime<Local>) -> PathBuf {
+    root.join(format!("{:04}", time.year()))
+        .join(format!("{:02}", time.month()))
+        .join(format!("{:02}", time.day()))
+}
+
+/// Creates a backup of the specified file as a hard link to the original file.
+/// Returns the path to the backup file if successful, otherwise returns an error.
+///
+/// # Arguments
+///
+/// * `path` - The path to the file to be backed up.
+///
+/// # Errors
+///
+/// This function can return the same errors as [`backup`] and additionally:
+///
+/// * `BackupError::Unsupported` - If the `path` is a directory.
+/// * `BackupError::Io` - If the hard link can not be created (e.g. the filesystem does not support hard links).
+///
+/// # Details
+/// The backup is named in the same way as with [`backup`], but the original file is left in place
+/// and the backup is a hard link to it. No data are copied, so the backup is created instantly.
+///
+/// Since the backup shares the content with the original file, modifying the original file in place
+/// also modifies the backup. Writers which replace the file (i.e. write a new file and rename it over the original)
+/// do not affect the backup, which is the common and safe way of updating files.
+///
+/// # Examples
+///
+/// ```no_run
+/// use crate::backitup::backup_hardlink;
+///
+/// let path = "data.txt";
+/// match backup_hardlink(path) {
+///     Ok(backup_path) => println!("Backup created: {:?}", backup_path),
+///     Err(err) => eprintln!("Failed to create backup: {:?}", err),
+/// }
+/// ```
+pub fn backup_hardlink(path: impl AsRef<Path>) -> Result<PathBuf, BackupError> {
+    let path = path.as_ref();
+    let options = BackupOptions::new();
+    let name = options.get_unique_name(path, &SystemClock)?;
+
+    if path.is_dir() {
+        return Err(BackupError::Unsupported(String::from(
+            "Directories can not be backed up using hard links.",
+        )));
+    }
+
+    // creating a hard link never overwrites an existing file
+    let name = options.create_unique(path, name, &SystemClock, |target| {
+        fs::hard_link(path, target)
+    })?;
+    Ok(name.path)
+}
+
+/// Creates a copy-on-write backup (reflink) of the specified file or directory.
+/// Returns the path to the backup file if successful, otherwise returns an error.
+///
+/// # Arguments
+///
+/// * `path` - The path to the file or directory to be backed up.
+///
+/// # Errors
+///
+/// This function can return the same errors as [`backup`].
+///
+/// # Details
+/// The backup is named in the same way as with [`backup`], but the original file (or directory) is left in place.
+/// The backup shares the data with the original until either of them is modified,
+/// so it is created almost instantly and does not take up additional space.
+/// Unlike with [`backup_hardlink`], modifying the original file never modifies the backup.
+///
+/// Reflinks are created using the `FICLONE` ioctl on Linux (supported e.g. by Btrfs, XFS, or bcachefs)
+/// and using `clonefile` on macOS (supported by APFS). If the filesystem does not support reflinks
+/// or on other platforms, a regular copy is created instead, as with [`backup_copy`].
+///
+/// # Examples
+///
+/// ```no_run
+/// use crate::backitup::backup_reflink;
+///
+/// let path = "data.txt";
+/// match backup_reflink(path) {
+///     Ok(backup_path) => println!("Backup created: {:?}", backup_path),
+///     Err(err) => eprintln!("Failed to create backup: {:?}", err),
+/// }
+/// ```
+pub fn backup_reflink(path: impl AsRef<Path>) -> Result<PathBuf, BackupError> {
+    let path = path.as_ref();
+    let options = BackupOptions::new();
+    let name = options.get_unique_name(path, &SystemClock)?;
+
+    let name = options.create_unique(path, name, &SystemClock, |target| {
+        write_via_temp(target, |temp| reflink_recursive(path, temp))
+    })?;
+    Ok(name.path)
+}
+
+/// Creates a backup of the specified file or directory with a custom wrapper around its name.
+/// Returns the path to the backup file if successful, otherwise returns an error.
+///
+/// # Arguments
+///
+/// * `path` - The path to the file or directory to be backed up.
+/// * `prefix` - The string placed in front of the name of the backup. Can be empty.
+/// * `suffix` - The string placed at the end of the name of the backup. Can be empty.
+///
+/// # Errors
+///
+/// This function can return the same errors as [`backup`] and additionally:
+///
+/// * `BackupError::InvalidPath` - If `prefix` or `suffix` contains characters not allowed in filenames.
+///
+/// # Details
+/// The backup is named `<prefix><filename>-<timestamp>(-<microseconds>)<suffix>`.
+/// The same wrapper is used when looking for a unique name of the backup.
+/// Use [`list_backups_wrapped`] and [`parse_backup_time_wrapped`] to work with backups created this way.
+///
+/// # Examples
+///
+/// ```no_run
+/// use crate::backitup::backup_wrapped;
+///
+/// let path = "data.txt";
+/// match backup_wrapped(path, "bak_", ".old") {
+///     Ok(backup_path) => println!("Backup created: {:?}", backup_path),
+///     Err(err) => eprintln!("Failed to create backup: {:?}", err),
+/// }
+/// ```
+pub fn backup_wrapped(
+    path: impl AsRef<Path>,
+    prefix: &str,
+    suffix: &str,
+) -> Result<PathBuf, BackupError> {
+    BackupOptions::new()
+        .prefix(prefix)
+        .suffix(suffix)
+        .backup(path)
+}
+
+/// Creates a backup of the specified file or directory with a custom separator between the filename and the timestamp.
+/// Returns the path to the backup file if successful, otherwise returns an error.
+///
+/// # Arguments
+///
+/// * `path` - The path to the file or directory to be backed up.
+/// * `separator` - The string placed between the filename and the timestamp. The default is `-`.
+///
+/// # Errors
+///
+/// This function can return the same errors as [`backup`] and additionally:
+///
+/// * `BackupError::InvalidPath` - If `separator` contains characters not allowed in filenames.
+///
+/// # Details
+/// The backup is named `#<filename><separator><timestamp>(-<microseconds>)#`.
+/// A separator that does not appear in the filenames (e.g. `__` or `@@`) makes it obvious
+/// where the filename ends, even if the filename itself contains a date.
+/// Use [`parse_backup_time_with_separator`] to parse the names of backups created this way.
+///
+/// # Examples
+///
+/// ```no_run
+/// use crate::backitup::backup_with_separator;
+///
+/// let path = "report-2024.csv";
+/// match backup_with_separator(path, "@@") {
+///     Ok(backup_path) => println!("Backup created: {:?}", backup_path),
+///     Err(err) => eprintln!("Failed to create backup: {:?}", err),
+/// }
+/// ```
+pub fn backup_with_separator(
+    path: impl AsRef<Path>,
+    separator: &str,
+) -> Result<PathBuf, BackupError> {
+    BackupOptions::new().separator(separator).backup(path)
+}
+
+/// Creates a backup of the specified file or directory, retrying the creation of the backup after transient I/O errors.
+/// Returns the path to the backup file if successful, otherwise returns an error.
+///
+/// # Arguments
+///
+/// * `path` - The path to the file or directory to be backed up.
+/// * `attempts` - The maximal number of retries.
+/// * `delay` - The time to wait before each retry.
+///
+/// # Errors
+///
+/// This function can return the same errors as [`backup`].
+///
+/// # Details
+/// On networked filesystems, the rename of the original occasionally fails with transient errors
+/// (e.g. `EAGAIN` or `ETIMEDOUT`) that succeed on retry. If the rename fails with `ErrorKind::Interrupted`,
+/// `ErrorKind::WouldBlock`, or `ErrorKind::TimedOut`, this function waits for `delay`, generates
+/// a new unique name for the backup, and tries again, at most `attempts` times.
+/// Other errors are returned immediately. See also [`BackupOptions::retries`].
+///
+/// # Examples
+///
+/// ```no_run
+/// use crate::backitup::backup_with_retries;
+/// use std::time::Duration;
+///
+/// let path = "data.txt";
+/// match backup_with_retries(path, 3, Duration::from_millis(100)) {
+///     Ok(backup_path) => println!("Backup created: {:?}", backup_path),
+///     Err(err) => eprintln!("Failed to create backup: {:?}", err),
+/// }
+/// ```
+pub fn backup_with_retries(
+    path: impl AsRef<Path>,
+    attempts: u32,
+    delay: Duration,
+) -> Result<PathBuf, BackupError> {
+    BackupOptions::new().retries(attempts, delay).backup(path)
+}
+
+/// Creates a backup of the specified file while holding a lock on it.
+/// Returns the path to the backup file if successful, otherwise returns an error.
+///
+/// # Arguments
+///
+/// * `path` - The path to the file to be backed up.
+/// * `timeout` - The maximal time to wait for the lock.
+///
+/// # Errors
+///
+/// This function can return the same errors as [`backup`] and additionally:
+///
+/// * `BackupError::Locked` - If the lock can not be acquired within `timeout`.
+/// * `BackupError::Unsupported` - If the `path` is a directory.
+///
+/// # Details
+/// A shared advisory lock (`flock` on Unix, `LockFileEx` on Windows) is acquired on the original file
+/// before it is renamed and released once the backup is created. Writers locking the file exclusively
+/// (e.g. databases or loggers) therefore can not modify it while the backup is being created,
+/// so the backup is never torn. If another process holds an exclusive lock on the file,
+/// this function waits for at most `timeout`. See also [`BackupOptions::lock`].
+///
+/// # Examples
+///
+/// ```no_run
+/// use crate::backitup::backup_locked;
+/// use std::time::Duration;
+///
+/// let path = "database.db";
+/// match backup_locked(path, Duration::from_secs(5)) {
+///     Ok(backup_path) => println!("Backup created: {:?}", backup_path),
+///     Err(err) => eprintln!("Failed to create backup: {:?}", err),
+/// }
+/// ```
+pub fn backup_locked(path: impl AsRef<Path>, timeout: Duration) -> Result<PathBuf, BackupError> {
+    BackupOptions::new().lock(timeout).backup(path)
+}
+
+/// Creates a backup of the specified file or directory with a label describing its purpose.
+/// Returns the path to the backup file if successful, otherwise returns an error.
+///
+/// # Arguments
+///
+/// * `path` - The path to the file or directory to be backed up.
+/// * `label` - The label placed between the filename and the timestamp, e.g. `"before-migration"`.
+///
+/// # Errors
+///
+/// This function can return the same errors as [`backup`] and additionally:
+///
+/// * `BackupError::InvalidArgument` - If `label` is empty or contains path separators or `#`.
+///
+/// # Details
+/// The backup is named `#<filename>-<label>-<timestamp>(-<microseconds>)#`.
+/// Labeled backups are listed by [`list_labeled_backups`]. They are not considered by [`list_backups`],
+/// [`restore`], and [`prune_backups`], since a labeled backup of `data.txt` can not be distinguished
+/// from an unlabeled backup of a file named `data.txt-<label>`.
+///
+/// # Examples
+///
+/// ```no_run
+/// use crate::backitup::backup_labeled;
+///
+/// let path = "database.sqlite";
+/// match backup_labeled(path, "before-migration") {
+///     Ok(backup_path) => println!("Backup created: {:?}", backup_path),
+///     Err(err) => eprintln!("Failed to create backup: {:?}", err),
+/// }
+/// ```
+pub fn backup_labeled(path: impl AsRef<Path>, label: &str) -> Result<PathBuf, BackupError> {
+    BackupOptions::new().label(label).backup(path)
+}
+
+/// Creates a backup of the specified file or directory, keeping its extension at the end of the name.
+/// Returns the path to the backup file if successful, otherwise returns an error.
+///
+/// # Arguments
+///
+/// * `path` - The path to the file or directory to be backed up.
+///
+/// # Errors
+///
+/// This function can return the same errors as [`backup`].
+///
+/// # Details
+/// The timestamp is inserted before the extension (as returned by [`Path::extension`]) and the name
+/// is not wrapped in `#`, so the backup can still be opened by the applications associated with the extension.
+/// For instance, `data.txt` backed up on 2023/06/27 at 21:01:13 is renamed to `data-2023-06-27-21-01-13.txt`.
+/// Names without an extension (including dotfiles such as `.bashrc`) get the timestamp appended at the end.
+///
+/// The time can be read back from the name using [`parse_backup_time`]. Note that such backups
+/// are not found by [`list_backups`], since their names do not contain the full name of the original file.
+///
+/// # Examples
+///
+/// ```no_run
+/// use crate::backitup::backup_with_suffix_before_extension;
+///
+/// let path = "data.txt";
+/// match backup_with_suffix_before_extension(path) {
+///     Ok(backup_path) => println!("Backup created: {:?}", backup_path),
+///     Err(err) => eprintln!("Failed to create backup: {:?}", err),
+/// }
+/// ```
+pub fn backup_with_suffix_before_extension(path: impl AsRef<Path>) -> Result<PathBuf, BackupError> {
+    BackupOptions::new()
+        .prefix("")
+        .suffix("")
+        .keep_extension(true)
+        .backup(path)
+}
+
+/// Returns the name the backup of the specified file or directory created at `time` would have.
+/// Returns an error if the name is not valid.
+///
+/// # Arguments
+///
+/// * `path` - The path to the file or directory to be backed up. It does not have to exist.
+/// * `time` - The time of the backup.
+///
+/// # Errors
+///
+/// This function can return the following errors:
+///
+/// * `BackupError::IsRoot`, `BackupError::EndsInDotDot` - If the `path` is not valid.
+/// * `BackupError::InvalidPath` - If the name of the backup is not allowed on the target platform.
+///
+/// # Details
+/// The name is generated in the same way as the first name tried by [`backup`], so it never contains microseconds
+/// (see the section "Name of the Backup" in the documentation of [`backup`]).
+/// The filesystem is never accessed, so the returned name may already be taken.
+///
+/// # Examples
+///
+/// ```
+/// use std::path::Path;
+/// use backitup::backup_name;
+/// use chrono::prelude::*;
+///
+/// let time = Local.with_ymd_and_hms(2023, 6, 27, 21, 1, 13).unwrap();
+/// let name = backup_name("data/data.txt", time).unwrap();
+/// assert_eq!(name, Path::new("data").join("#data.txt-2023-06-27-21-01-13#"));
+/// ```
+pub fn backup_name(path: impl AsRef<Path>, time: DateTime<Local>) -> Result<PathBuf, BackupError> {
+    BackupOptions::new().backup_name(path, time)
+}
+
+/// Returns the name the backup of a file named `filename` located in `parent` created at `time` would have.
+/// Returns an error if the name is not valid.
+///
+/// # Arguments
+///
+/// * `parent` - The directory in which the backup would be located. Can be empty.
+/// * `filename` - The name of the file or directory to be backed up.
+/// * `time` - The time of the backup.
+///
+/// # Errors
+///
+/// This function can return the following errors:
+///
+/// * `BackupError::InvalidArgument` - If `filename` is not a single normal path component
+///   (e.g. it is empty, `..`, or contains a path separator).
+/// * `BackupError::InvalidPath` - If the name of the backup is not allowed on the target platform.
+/// * `BackupError::NameTooLong` - If the name of the backup exceeds the limit of the filesystem.
+///
+/// # Details
+/// This is the pure variant of [`backup_name`] working with already extracted components of the path.
+/// The filesystem is never accessed, so the function can be used to reuse the naming scheme
+/// of this crate without creating any backups. An empty `parent` corresponds to the current directory,
+/// in which case the name of the backup is returned without any parent component.
+///
+/// # Examples
+///
+/// ```
+/// use std::path::Path;
+/// use backitup::compute_backup_name;
+/// use chrono::prelude::*;
+///
+/// let time = Local.with_ymd_and_hms(2023, 6, 27, 21, 1, 13).unwrap();
+/// let name = compute_backup_name("data", "data.txt", time).unwrap();
+/// assert_eq!(name, Path::new("data").join("#data.txt-2023-06-27-21-01-13#"));
+/// ```
+pub fn compute_backup_name(
+    parent: impl AsRef<Path>,
+    filename: impl AsRef<OsStr>,
+    time: DateTime<Local>,
+) -> Result<PathBuf, BackupError> {
+    let parent = parent.as_ref();
+    let filename = filename.as_ref();
+
+    // the filename must be a single normal component of a path
+    if Path::new(filename).file_name() != Some(filename) {
+        return Err(BackupError::InvalidArgument(String::from(
+            "Filename must be a single normal path component.",
+        )));
+    }
+
+    let options = BackupOptions::new();
+    let clock = clock::FixedClock(time);
+    options
+        .backup_names_in(parent, filename, &clock)?
+        .next_name()
+}
+
+/// Creates a backup of the specified file or directory, taking the time of the backup from `clock`.
+/// Returns the path to the backup file if successful, otherwise returns an error.
+///
+/// # Arguments
+///
+/// * `path` - The path to the file or directory to be backed up.
+/// * `clock` - The source of the current time.
+///
+/// # Errors
+///
+/// This function can return the same errors as [`backup`].
+///
+/// # Details
+/// This function behaves like [`backup`], which uses [`SystemClock`], but the timestamp in the name
+/// of the backup is obtained from `clock`. This is useful e.g. for testing, since the name of the backup
+/// created with a fixed clock is known in advance.
+///
+/// # Examples
+///
+/// ```no_run
+/// use backitup::{backup_with_clock, Clock};
+/// use chrono::prelude::*;
+///
+/// struct FixedClock;
+///
+/// impl Clock for FixedClock {
+///     fn now(&self) -> DateTime<Local> {
+///         Local.with_ymd_and_hms(2023, 6, 27, 21, 1, 13).unwrap()
+///     }
+/// }
+///
+/// let backup_path = backup_with_clock("data.txt", &FixedClock).unwrap();
+/// assert_eq!(backup_path.to_str().unwrap(), "#data.txt-2023-06-27-21-01-13#");
+/// ```
+pub fn backup_with_clock(
+    path: impl AsRef<Path>,
+    clock: &impl Clock,
+) -> Result<PathBuf, BackupError> {
+    BackupOptions::new().backup_with_clock(path, clock)
+}
+
+/// Creates a backup of the specified file or directory named after the time of its last modification.
+/// Returns the path to the backup file if successful, otherwise returns an error.
+///
+/// # Arguments
+///
+/// * `path` - The path to the file or directory to be backed up.
+///
+/// # Errors
+///
+/// This function can return the same errors as [`backup`].
+///
+/// # Details
+/// This function behaves like [`backup`], but the timestamp in the name of the backup is the modification time
+/// of `path` (converted to local time) instead of the current time, so the name describes the age of the data.
+/// Symbolic links are followed when reading the modification time. If the modification time is not available
+/// (e.g. on platforms not supporting it or for broken symbolic links), the current time is used.
+///
+/// # Examples
+///
+/// ```no_run
+/// use crate::backitup::backup_with_mtime;
+///
+/// let path = "output.log";
+/// match backup_with_mtime(path) {
+///     Ok(backup_path) => println!("Backup created: {:?}", backup_path),
+///     Err(err) => eprintln!("Failed to create backup: {:?}", err),
+/// }
+/// ```
+pub fn backup_with_mtime(path: impl AsRef<Path>) -> Result<PathBuf, BackupError> {
+    let path = path.as_ref();
+
+    let time = match fs::metadata(path).and_then(|x| x.modified()) {
+        Ok(time) => DateTime::<Local>::from(time),
+        Err(_) => Local::now(),
+    };
+
+    BackupOptions::new().backup_with_clock(path, &clock::FixedClock(time))
+}
+
+/// Creates a backup of the specified file or directory unless its most recent backup is younger than `min_interval`.
+/// Returns the path to the backup file if it has been created, `None` if the backup has been skipped,
+/// otherwise returns an error.
+///
+/// # Arguments
+///
+/// * `path` - The path to the file or directory to be backed up.
+/// * `min_interval` - The minimal time between two backups of the `path`.
+///
+/// # Errors
+///
+/// This function can return the same errors as [`backup`].
+///
+/// # Details
+/// The age of the most recent backup is determined from the timestamp in its name (see [`list_backups`]),
+/// so no additional state is needed. If the backup is younger than `min_interval`, nothing is changed
+/// and `None` is returned. Otherwise (or if there is no backup yet), the backup is created in the same way
+/// as with [`backup`]. Backups with timestamps in the future (e.g. after the system clock has been set back)
+/// are not considered recent, so they never prevent new backups.
+///
+/// This is useful for limiting the number of backups created in a loop.
+///
+/// # Examples
+///
+/// ```no_run
+/// use crate::backitup::backup_rate_limited;
+/// use std::time::Duration;
+///
+/// let path = "output.log";
+/// match backup_rate_limited(path, Duration::from_secs(60 * 60)) {
+///     Ok(Some(backup_path)) => println!("Backup created: {:?}", backup_path),
+///     Ok(None) => println!("Recent backup exists, no backup created."),
+///     Err(err) => eprintln!("Failed to create backup: {:?}", err),
+/// }
+/// ```
+pub fn backup_rate_limited(
+    path: impl AsRef<Path>,
+    min_interval: Duration,
+) -> Result<Option<PathBuf>, BackupError> {
+    let path = trim_trailing_separators(path.as_ref());
+
+    // check if the path exists (broken symbolic links also exist)
+    if fs::symlink_metadata(path).is_err() {
+        return Err(BackupError::NotFound);
+    }
+
+    let (dir, filename) = split_path(path)?;
+    let newest = list::find_backups(dir, filename, DEFAULT_PREFIX, DEFAULT_SUFFIX)?
+        .pop()
+        .and_then(|(time, _)| list::to_local_time(&time));
+
+    if let Some(time) = newest {
+        let age = Local::now().signed_duration_since(time);
+        // intervals too long to be represented are never exceeded
+        let recent = chrono::Duration::from_std(min_interval).map_or(true, |x| age < x);
+
+        if age >= chrono::Duration::zero() && recent {
+            return Ok(None);
+        }
+    }
+
+    backup(path).map(Some)
+}
+
+/// Creates a backup of the specified file, directory, or symbolic link, optionally following symbolic links.
+/// Returns the path to the backup file if successful, otherwise returns an error.
+///
+/// # Arguments
+///
+/// * `path` - The path to the file, directory, or symbolic link to be backed up.
+/// * `follow` - Whether the target of the symbolic link should be backed up instead of the link.
+///
+/// # Errors
+///
+/// This function can return the same errors as [`backup`].
+///
+/// # Details
+/// If `path` is not a symbolic link, this function behaves like [`backup`].
+///
+/// If `follow` is `false`, the symbolic link itself is renamed, same as with [`backup`] (which never follows
+/// symbolic links). The file the link points to is not touched, so the link in the backup keeps pointing to it.
+///
+/// If `follow` is `true`, the target of the link is resolved using [`std::fs::canonicalize`] and the target
+/// is backed up by **copying** it (see [`backup_copy`]). The backup is placed next to the target
+/// and named after it. Neither the link nor the target are modified.
+///
+/// # Examples
+///
+/// ```no_run
+/// use crate::backitup::backup_follow_symlinks;
+///
+/// let path = "link_to_data.txt";
+/// match backup_follow_symlinks(path, true) {
+///     Ok(backup_path) => println!("Backup created: {:?}", backup_path),
+///     Err(err) => eprintln!("Failed to create backup: {:?}", err),
+/// }
+/// ```
+pub fn backup_follow_symlinks(
+    path: impl AsRef<Path>,
+    follow: bool,
+) -> Result<PathBuf, BackupError> {
+    BackupOptions::new().follow_symlinks(follow).backup(path)
+}
+
+/// Creates a backup of the specified file or directory, resolving paths without a filename first.
+/// Returns the path to the backup file if successful, otherwise returns an error.
+///
+/// # Arguments
+///
+/// * `path` - The path to the file or directory to be backed up.
+///
+/// # Errors
+///
+/// This function can return the same errors as [`backup`], except for `BackupError::EndsInDotDot`.
+/// `BackupError::IsRoot` is returned if the `path` resolves to the root of the filesystem.
+///
+/// # Details
+/// Paths ending in `..` (or consisting only of `.`) are rejected by [`backup`], since they have no filename.
+/// This function resolves such paths using [`std::fs::canonicalize`] into absolute paths with a real filename
+/// and parent (e.g. `data/nested/..` into `/home/user/data`) and backs up the resolved path.
+/// The returned path to the backup is then absolute as well. Other paths are backed up in the same way as with [`backup`].
+///
+/// # Examples
+///
+/// ```no_run
+/// use crate::backitup::backup_canonicalized;
+///
+/// let path = "output_dir/nested/..";
+/// match backup_canonicalized(path) {
+///     Ok(backup_path) => println!("Backup created: {:?}", backup_path),
+///     Err(err) => eprintln!("Failed to create backup: {:?}", err),
+/// }
+/// ```
+pub fn backup_canonicalized(path: impl AsRef<Path>) -> Result<PathBuf, BackupError> {
+    BackupOptions::new().canonicalize(true).backup(path)
+}
+
+/// Creates a backup of the specified file or directory, calling `hook` right before the file is renamed.
+/// Returns the path to the backup file if successful, otherwise returns an error.
+///
+/// # Arguments
+///
+/// * `path` - The path to the file or directory to be backed up.
+/// * `hook` - Function called with the original path and the path to the backup.
+///
+/// # Errors
+///
+/// This function can return the same errors as [`backup`] and additionally:
+///
+/// * `BackupError::Io` - If the `hook` returns an error.
+///
+/// # Details
+/// The `hook` is called once a unique name for the backup has been found, but before the original file
+/// (or directory) is renamed. Returning an error from the `hook` aborts the backup
+/// without modifying the filesystem. This can be used e.g. for checking the available disk space
+/// or asking the user for confirmation.
+///
+/// # Examples
+///
+/// ```no_run
+/// use crate::backitup::backup_with_hook;
+///
+/// let path = "data.txt";
+/// match backup_with_hook(path, |original, backup| {
+///     println!("Renaming {:?} to {:?}", original, backup);
+///     Ok(())
+/// }) {
+///     Ok(backup_path) => println!("Backup created: {:?}", backup_path),
+///     Err(err) => eprintln!("Failed to create backup: {:?}", err),
+/// }
+/// ```
+pub fn backup_with_hook(
+    path: impl AsRef<Path>,
+    hook: impl FnOnce(&Path, &Path) -> std::io::Result<()>,
+) -> Result<PathBuf, BackupError> {
+    BackupOptions::new().backup_with_hook(path, hook)
+}
+
+/// Creates a backup of the specified file or directory, unless it is already a backup.
+/// Returns the path to the backup file if successful, otherwise returns an error.
+///
+/// # Arguments
+///
+/// * `path` - The path to the file or directory to be backed up.
+///
+/// # Errors
+///
+/// This function can return the same errors as [`backup`] and additionally:
+///
+/// * `BackupError::IsBackup` - If the name of `path` matches the pattern `#<filename>-<timestamp>(-<microseconds>)#`.
+///
+/// # Details
+/// Backing up a backup with [`backup`] produces a doubly-wrapped name
+/// (e.g. `##data.txt-2023-06-27-21-01-13#-2023-06-27-21-05-44#`). This function refuses to do so.
+/// The names are recognized in the same way as in [`parse_backup_time`].
+///
+/// # Examples
+///
+/// ```no_run
+/// use crate::backitup::backup_checked;
+///
+/// let path = "#data.txt-2023-06-27-21-01-13#";
+/// match backup_checked(path) {
+///     Ok(backup_path) => println!("Backup created: {:?}", backup_path),
+///     Err(err) => eprintln!("Failed to create backup: {:?}", err),
+/// }
+/// ```
+pub fn backup_checked(path: impl AsRef<Path>) -> Result<PathBuf, BackupError> {
+    BackupOptions::new().reject_backups(true).backup(path)
+}
+
+/// Creates a backup of the specified file or directory and removes the oldest backups
+/// so that at most `keep` backups of the file remain.
+/// Returns the path to the backup file if successful, otherwise returns an error.
+///
+/// # Arguments
+///
+/// * `path` - The path to the file or directory to be backed up.
+/// * `keep` - The maximal number of backups of the file to keep (including the new one).
+///
+/// # Errors
+///
+/// This function can return the same errors as [`backup`] and additionally:
+///
+/// * `BackupError::InvalidArgument` - If `keep` is 0.
+///
+/// # Details
+/// Only the backups named `#<filename>-<timestamp>(-<microseconds>)#` and located in the
+/// parent directory of `path` are considered. Other files are never touched.
+/// The age of a backup is determined from the timestamp in its name.
+/// Directory backups are removed recursively.
+///
+/// # Examples
+///
+/// ```no_run
+/// use crate::backitup::backup_with_retention;
+///
+/// let path = "data.txt";
+/// match backup_with_retention(path, 5) {
+///     Ok(backup_path) => println!("Backup created: {:?}", backup_path),
+///     Err(err) => eprintln!("Failed to create backup: {:?}", err),
+/// }
+/// ```
+pub fn backup_with_retention(path: impl AsRef<Path>, keep: usize) -> Result<PathBuf, BackupError> {
+    if keep == 0 {
+        return Err(BackupError::InvalidArgument(String::from(
+            "Number of backups to keep must be at least 1.",
+        )));
+    }
+
+    let backup_name = backup(path.as_ref())?;
+
+    let (dir, filename) = split_path(trim_trailing_separators(path.as_ref()))?;
+
+    // the new backup is always kept, even if its name suggests that it is older
+    // (this happens when a name freed by a previous pruning is reused within the same second)
+    let backups = list::find_backups(dir, filename, DEFAULT_PREFIX, DEFAULT_SUFFIX)?
+        .into_iter()
+        .filter(|(_, x)| *x != backup_name)
+        .collect::<Vec<_>>();
+
+    let n_remove = backups.len().saturating_sub(keep - 1);
+    for (_, old) in backups.into_iter().take(n_remove) {
+        remove_path(&old)?;
+    }
+
+    Ok(backup_name)
+}
+
+/// Removes the oldest backups of the specified file or directory so that at most `keep` backups remain.
+/// Returns information about the removed backups (from the oldest to the newest) if successful,
+/// otherwise returns an error.
+///
+/// # Arguments
+///
+/// * `path` - The path to the original file or directory. It does not have to exist.
+/// * `keep` - The maximal number of backups to keep. If 0, all backups are removed.
+///
+/// # Errors
+///
+/// This function can return the same errors as [`list_backups`] and additionally:
+///
+/// * `BackupError::Io` - If a backup can not be removed.
+///
+/// # Details
+/// The backups are found in the same way as with [`list_backups`] and the oldest of them are removed.
+/// No new backup is created. Directory backups are removed recursively.
+/// If there are at most `keep` backups, nothing is removed.
+/// The reason of every removal is [`PruneReason::ExceededCount`].
+///
+/// # Examples
+///
+/// ```no_run
+/// use crate::backitup::prune_backups;
+///
+/// match prune_backups("data.txt", 5) {
+///     Ok(removed) => {
+///         for backup in removed {
+///             println!("Removed {:?} ({:?}): {:?}", backup.path, backup.timestamp, backup.reason);
+///         }
+///     }
+///     Err(err) => eprintln!("Failed to remove backups: {:?}", err),
+/// }
+/// ```
+pub fn prune_backups(
+    path: impl AsRef<Path>,
+    keep: usize,
+) -> Result<Vec<PrunedBackup>, BackupError> {
+    let (dir, filename) = split_path(trim_trailing_separators(path.as_ref()))?;
+    let backups = list::find_backups(dir, filename, DEFAULT_PREFIX, DEFAULT_SUFFIX)?;
+
+    let n_remove = backups.len().saturating_sub(keep);
+    let mut removed = Vec::with_capacity(n_remove);
+    for (time, old) in backups.into_iter().take(n_remove) {
+        remove_path(&old)?;
+        removed.push(PrunedBackup::new(old, &time, PruneReason::ExceededCount));
+    }
+
+    Ok(removed)
+}
+
+/// Removes the backups of the specified file or directory that are older than `max_age`.
+/// Returns information about the removed backups (from the oldest to the newest) if successful,
+/// otherwise returns an error.
+///
+/// # Arguments
+///
+/// * `path` - The path to the original file or directory. It does not have to exist.
+/// * `max_age` - The maximal age of the backups to keep.
+///
+/// # Errors
+///
+/// This function can return the same errors as [`prune_backups`].
+///
+/// # Details
+/// The age of a backup is determined from the timestamp in its name, which is interpreted as local time
+/// (same as in [`parse_backup_time`]). The metadata of the backups (e.g. the modification time) are never used,
+/// since they do not have to correspond to the time of the backup (e.g. for copied backups).
+/// Backups with a timestamp that does not exist in the local time zone are never removed.
+/// The reason of every removal is [`PruneReason::ExceededAge`].
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::time::Duration;
+/// use crate::backitup::prune_backups_older_than;
+///
+/// // remove backups older than 30 days
+/// match prune_backups_older_than("data.txt", Duration::from_secs(30 * 24 * 60 * 60)) {
+///     Ok(removed) => println!("Backups removed: {:?}", removed),
+///     Err(err) => eprintln!("Failed to remove backups: {:?}", err),
+/// }
+/// ```
+pub fn prune_backups_older_than(
+    path: impl AsRef<Path>,
+    max_age: Duration,
+) -> Result<Vec<PrunedBackup>, BackupError> {
+    let (dir, filename) = split_path(trim_trailing_separators(path.as_ref()))?;
+    let backups = list::find_backups(dir, filename, DEFAULT_PREFIX, DEFAULT_SUFFIX)?;
+
+    // if the cutoff can not be represented, no backup is old enough
+    let cutoff = match chrono::Duration::from_std(max_age)
+        .ok()
+        .and_then(|x| Local::now().checked_sub_signed(x))
+    {
+        Some(x) => x,
+        None => return Ok(Vec::new()),
+    };
+
+    let mut removed = Vec::new();
+    for (time, old) in backups {
+        if matches!(list::to_local_time(&time), Some(x) if x < cutoff) {
+            remove_path(&old)?;
+            removed.push(PrunedBackup::new(old, &time, PruneReason::ExceededAge));
+        }
+    }
+
+    Ok(removed)
+}
+
+/// Removes the oldest backups of every file in the specified directory tree so that at most `keep` backups
+/// of each file remain.
+/// Returns information about the removed backups if successful, otherwise returns an error.
+///
+/// # Arguments
+///
+/// * `root` - The directory to be pruned, including all of its subdirectories.
+/// * `keep` - The maximal number of backups of each file to keep. If 0, all backups are removed.
+///
+/// # Errors
+///
+/// This function can return the following errors:
+///
+/// * `BackupError::Io` - If a directory can not be read or a backup can not be removed.
+///
+/// # Details
+/// All directories in `root` are traversed (symbolic links to directories are not followed).
+/// The backups named `#<filename>-<timestamp>(-<microseconds>)#` located in each directory are grouped
+/// by `<filename>` and each group is pruned in the same way as with [`prune_backups`], i.e. the oldest backups
+/// are removed. The originals do not have to exist. Files that are not backups are never touched,
+/// and directory backups are removed as a whole without being traversed.
+///
+/// The removed backups are returned grouped by their directories and original filenames (both sorted by name),
+/// and from the oldest to the newest within each group. The reason of every removal is [`PruneReason::ExceededCount`].
+///
+/// # Examples
+///
+/// ```no_run
+/// use crate::backitup::prune_tree;
+///
+/// match prune_tree("data", 5) {
+///     Ok(removed) => println!("Backups removed: {:?}", removed),
+///     Err(err) => eprintln!("Failed to remove backups: {:?}", err),
+/// }
+/// ```
+pub fn prune_tree(root: impl AsRef<Path>, keep: usize) -> Result<Vec<PrunedBackup>, BackupError> {
+    let mut removed = Vec::new();
+    prune_dir(root.as_ref(), keep, &mut removed)?;
+    Ok(removed)
+}
+
+/// Prunes the backups in `dir` and in its subdirectories as described in [`prune_tree`],
+/// appending information about the removed backups to `removed`.
+fn prune_dir(dir: &Path, keep: usize, removed: &mut Vec<PrunedBackup>) -> Result<(), BackupError> {
+    // backups grouped by the names of their originals
+    let mut groups: BTreeMap<Vec<u8>, Vec<(list::BackupTime, PathBuf)>> = BTreeMap::new();
+    let mut subdirs = Vec::new();
+
+    for entry in fs::read_dir(dir_or_current(dir))? {
+        let entry = entry?;
+        let name = entry.file_name();
+
+        match list::split_backup_name(name.as_encoded_bytes(), DEFAULT_PREFIX, DEFAULT_SUFFIX) {
+            Some((filename, time)) => groups
+                .entry(filename.to_vec())
+                .or_default()
+                .push((time, dir.join(&name))),
+            None if entry.file_type()?.is_dir() => subdirs.push(dir.join(&name)),
+            None => (),
+        }
+    }
+
+    for backups in groups.values_mut() {
+        backups.sort();
+
+        let n_remove = backups.len().saturating_sub(keep);
+        for (time, old) in backups.drain(..n_remove) {
+            remove_path(&old)?;
+            removed.push(PrunedBackup::new(old, &time, PruneReason::ExceededCount));
+        }
+    }
+
+    subdirs.sort();
+    for subdir in subdirs {
+        prune_dir(&subdir, keep, removed)?;
+    }
+
+    Ok(())
+}
+
+/// Removes temporary files left behind by interrupted backups in the specified directory.
+/// Returns the paths to the removed files (sorted by name) if successful, otherwise returns an error.
+///
+/// # Arguments
+///
+/// * `dir` - The directory to be cleaned.
+///
+/// # Errors
+///
+/// This function can return the following errors:
+///
+/// * `BackupError::Io` - If the directory can not be read or a temporary file can not be removed.
+///
+/// # Details
+/// Copied (and compressed) backups are written into a temporary `<backup>.tmp` sibling of the backup first,
+/// which is renamed to the name of the backup once complete. If the process crashes in the meantime,
+/// the temporary file is left behind. This function removes all entries of `dir` named `<backup>.tmp`,
+/// where `<backup>` is the name of a backup created with the default wrapper `#` (including the compressed backups).
+/// Temporary directories are removed recursively. The backups themselves and all other files are left untouched.
+///
+/// The temporary files of backups which are being created at the moment are removed as well,
+/// so this function should only be called when no backup is being created in `dir`, e.g. on startup.
+///
+/// # Examples
+///
+/// ```no_run
+/// use crate::backitup::clean_temp_backups;
+///
+/// match clean_temp_backups(".") {
+///     Ok(removed) => println!("Temporary files removed: {:?}", removed),
+///     Err(err) => eprintln!("Failed to remove temporary files: {:?}", err),
+/// }
+/// ```
+pub fn clean_temp_backups(dir: impl AsRef<Path>) -> Result<Vec<PathBuf>, BackupError> {
+    let dir = dir.as_ref();
+
+    let mut removed = Vec::new();
+    for entry in fs::read_dir(dir_or_current(dir))? {
+        let entry = entry?;
+        if is_temp_backup_name(&entry.file_name()) {
+            let path = dir.join(entry.file_name());
+            remove_path(&path)?;
+            removed.push(path);
+        }
+    }
+
+    removed.sort();
+    Ok(removed)
+}
+
+/// Creates a backup of every file in the specified directory individually.
+/// Returns the paths to the created backups if successful, otherwise returns an error.
+///
+/// # Arguments
+///
+/// * `dir` - The directory containing the files to be backed up.
+/// * `recursive` - Whether the files in the subdirectories of `dir` should be backed up as well.
+///
+/// # Errors
+///
+/// This function can return the same errors as [`backup`] and additionally:
+///
+/// * `BackupError::Io` - If `dir` (or one of its subdirectories) can not be read.
+///
+/// # Details
+/// Every regular file located in `dir` is backed up in the same way as with [`backup`], i.e. it is renamed
+/// to `#<filename>-<timestamp>#` in its own directory. Files which are already backups (see [`is_backup_name`])
+/// or temporary files of backups are skipped, so the backups are never nested. Symbolic links and other
+/// special files are skipped as well. If `recursive` is set, the subdirectories (but not the symbolic links
+/// to directories) are traversed and their files are backed up in the same way, otherwise the subdirectories are left untouched.
+///
+/// The entries of every directory are collected (and sorted by name) before any of them is backed up,
+/// so the created backups are never visited. The paths to the backups are returned in the same order.
+/// If the backup of a file fails, the error is returned and the backups that have already been created are kept.
+///
+/// # Examples
+///
+/// ```no_run
+/// use crate::backitup::backup_all_in_dir;
+///
+/// match backup_all_in_dir("outputs", false) {
+///     Ok(backup_paths) => println!("Backups created: {:?}", backup_paths),
+///     Err(err) => eprintln!("Failed to create backups: {:?}", err),
+/// }
+/// ```
+pub fn backup_all_in_dir(
+    dir: impl AsRef<Path>,
+    recursive: bool,
+) -> Result<Vec<PathBuf>, BackupError> {
+    let mut backups = Vec::new();
+    backup_files_in(dir.as_ref(), recursive, &mut backups)?;
+    Ok(backups)
+}
+
+/// Backs up every regular file in `dir` (and in its subdirectories if `recursive` is set)
+/// as described in [`backup_all_in_dir`], appending the paths to the backups to `backups`.
+fn backup_files_in(
+    dir: &Path,
+    recursive: bool,
+    backups: &mut Vec<PathBuf>,
+) -> Result<(), BackupError> {
+    let mut entries = Vec::new();
+    for entry in fs::read_dir(dir_or_current(dir))? {
+        let entry = entry?;
+        entries.push((entry.file_name(), entry.file_type()?));
+    }
+
+    entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    for (name, file_type) in entries {
+        let path = dir.join(&name);
+
+        if file_type.is_dir() {
+            if recursive {
+                backup_files_in(&path, recursive, backups)?;
+            }
+        } else if file_type.is_file() && !is_backup_name(&name) && !is_temp_backup_name(&name) {
+            backups.push(backup(path)?);
+        }
+    }
+
+    Ok(())
+}
+
+/// Creates backups of multiple files or directories. Either all of them are backed up or none.
+/// Returns the paths to the backups in the same order as `paths` if successful, otherwise returns an error.
+///
+/// # Arguments
+///
+/// * `paths` - The paths to the files or directories to be backed up.
+///
+/// # Errors
+///
+/// This function can return the same errors as [`backup`]. The first error that occurs is returned.
+///
+/// # Details
+/// The paths are backed up one by one in the same way as with [`backup`].
+/// If the backup of any path fails, the backups that have already been created are
+/// renamed back to their original paths (in reverse order) before the error is returned.
+/// Rolling back is performed on a best-effort basis: if a backup can not be renamed back,
+/// the remaining backups are still restored and the original error is returned.
+/// A backup is never renamed over a file that has been created at its original path in the meantime;
+/// such a backup keeps its name (and a warning is emitted if the `tracing` feature is enabled).
+///
+/// Note that the backups are not created atomically, i.e. other processes may observe
+/// a state in which only some of the paths have been backed up.
+///
+/// # Examples
+///
+/// ```no_run
+/// use crate::backitup::backup_many;
+///
+/// match backup_many(&["data.txt", "metadata.json"]) {
+///     Ok(backup_paths) => println!("Backups created: {:?}", backup_paths),
+///     Err(err) => eprintln!("Failed to create backups: {:?}", err),
+/// }
+/// ```
+pub fn backup_many<P: AsRef<Path>>(paths: &[P]) -> Result<Vec<PathBuf>, BackupError> {
+    let mut backups = Vec::with_capacity(paths.len());
+
+    for path in paths {
+        match backup(path) {
+            Ok(x) => backups.push(x),
+            Err(e) => {
+                // restore the original state
+                for (original, backup) in paths.iter().zip(backups).rev() {
+                    rename_back(&backup, original.as_ref());
+                }
+
+                return Err(e);
+            }
+        }
+    }
+
+    Ok(backups)
+}
+
+/// Renames `from` back to `to` when rolling back, without overwriting `to` if it exists.
+/// Failures are only reported as warnings (if the `tracing` feature is enabled), since rolling back is best-effort.
+pub(crate) fn rename_back(from: &Path, to: &Path) {
+    if let Err(_e) = rename_noreplace(from, to) {
+        #[cfg(feature = "tracing")]
+        tracing::warn!(
+            from = %from.display(),
+            to = %to.display(),
+            error = %_e,
+            "rolling back failed"
+        );
+    }
+}
+
+/// Returns the path the backup of the specified file or directory would have, without creating the backup.
+/// Returns an error if the backup could not be created.
+///
+/// # Arguments
+///
+/// * `path` - The path to the file or directory to be backed up.
+///
+/// # Errors
+///
+/// This function can return the same errors as [`backup`].
+///
+/// # Details
+/// All the checks performed by [`backup`] are also performed here, including the search for
+/// a name that is not taken yet, so the returned path does not exist at the time of the call.
+/// The original file (or directory) is never renamed.
+///
+/// Note that the backup created later by [`backup`] may get a different name,
+/// e.g. if it is created in a different second.
+///
+/// # Examples
+///
+/// ```no_run
+/// use crate::backitup::backup_dry_run;
+///
+/// let path = "data.txt";
+/// match backup_dry_run(path) {
+///     Ok(backup_path) => println!("Backup would be created: {:?}", backup_path),
+///     Err(err) => eprintln!("Backup would fail: {:?}", err),
+/// }
+/// ```
+pub fn backup_dry_run(path: impl AsRef<Path>) -> Result<PathBuf, BackupError> {
+    BackupOptions::new().dry_run(path)
+}
+
+/// Creates a backup of the specified file or directory, if it exists.
+/// Returns the path to the backup file if the backup was created, `None` if the `path` does not exist,
+/// otherwise returns an error.
+///
+/// # Arguments
+///
+/// * `path` - The path to the file or directory to be backed up. It does not have to exist.
+///
+/// # Errors
+///
+/// This function can return the same errors as [`backup`], except for `BackupError::NotFound`.
+///
+/// # Details
+/// The backup is created in the same way as with [`backup`].
+/// If the `path` does not exist, nothing is done and `Ok(None)` is returned.
+///
+/// # Examples
+///
+/// ```no_run
+/// use crate::backitup::backup_if_exists;
+///
+/// match backup_if_exists("output.log") {
+///     Ok(Some(backup_path)) => println!("Backup created: {:?}", backup_path),
+///     Ok(None) => println!("Nothing to back up."),
+///     Err(err) => eprintln!("Failed to create backup: {:?}", err),
+/// }
+/// ```
+pub fn backup_if_exists(path: impl AsRef<Path>) -> Result<Option<PathBuf>, BackupError> {
+    match backup(path) {
+        Ok(x) => Ok(Some(x)),
+        Err(BackupError::NotFound) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Creates a backup of the specified file or directory, if it is larger than `max_bytes`.
+/// Returns the path to the backup file if the backup was created, `None` if the `path` is not large enough,
+/// otherwise returns an error.
+///
+/// # Arguments
+///
+/// * `path` - The path to the file or directory to be backed up.
+/// * `max_bytes` - The largest size (in bytes) of the `path` which is not backed up.
+///
+/// # Errors
+///
+/// This function can return the same errors as [`backup`].
+///
+/// # Details
+/// The size of a file is obtained from its metadata. The size of a directory is the sum of the sizes
+/// of all files it contains (recursively). Symbolic links are not followed.
+/// If the size is larger than `max_bytes`, the backup is created in the same way as with [`backup`],
+/// otherwise nothing is done and `Ok(None)` is returned. This can be used for size-based rotation of logs.
+///
+/// # Examples
+///
+/// ```no_run
+/// use crate::backitup::backup_if_larger_than;
+///
+/// // rotate the log once it exceeds 10 MB
+/// match backup_if_larger_than("output.log", 10_000_000) {
+///     Ok(Some(backup_path)) => println!("Log rotated: {:?}", backup_path),
+///     Ok(None) => println!("Log is small enough."),
+///     Err(err) => eprintln!("Failed to rotate the log: {:?}", err),
+/// }
+/// ```
+pub fn backup_if_larger_than(
+    path: impl AsRef<Path>,
+    max_bytes: u64,
+) -> Result<Option<PathBuf>, BackupError> {
+    let path = path.as_ref();
+
+    let size = match path_size(path, false) {
+        Ok(x) => x,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Err(BackupError::NotFound),
+        Err(e) => return Err(BackupError::Io(e)),
+    };
+
+    if size <= max_bytes {
+        return Ok(None);
+    }
+
+    backup(path).map(Some)
+}
+
+/// Creates a backup of the specified file and writes new content to the original path.
+/// Returns the path to the backup file if successful, otherwise returns an error.
+///
+/// # Arguments
+///
+/// * `path` - The path to the file to be backed up and replaced.
+/// * `new_content` - The content written to `path` after the backup is created.
+///
+/// # Errors
+///
+/// This function can return the same errors as [`backup`] and additionally:
+///
+/// * `BackupError::Unsupported` - If the `path` is a directory.
+/// * `BackupError::Io` - If the new content can not be written.
+///
+/// # Details
+/// The original file is **renamed** in the same way as with [`backup`].
+/// The new content is then written into a temporary sibling of `path` which is renamed to `path`
+/// once complete, so `path` never contains partially written content. The new file gets
+/// the permissions of the original file.
+///
+/// If writing the new content fails, the backup is renamed back to `path`, so no data is lost.
+///
+/// # Examples
+///
+/// ```no_run
+/// use crate::backitup::backup_and_replace;
+///
+/// let path = "config.toml";
+/// match backup_and_replace(path, b"verbose = true\n") {
+///     Ok(backup_path) => println!("Old content backed up: {:?}", backup_path),
+///     Err(err) => eprintln!("Failed to replace the file: {:?}", err),
+/// }
+/// ```
+pub fn backup_and_replace(
+    path: impl AsRef<Path>,
+    new_content: &[u8],
+) -> Result<PathBuf, BackupError> {
+    let path = path.as_ref();
+
+    if path.is_dir() {
+        return Err(BackupError::Unsupported(String::from(
+            "Directories can not be replaced with new content.",
+        )));
+    }
+
+    let backup_path = backup(path)?;
+
+    let written = write_via_temp(path, |temp| {
+        let mut file = File::create_new(temp)?;
+        file.write_all(new_content)?;
+        file.sync_all()?;
+        fs::set_permissions(temp, fs::symlink_metadata(&backup_path)?.permissions())
+    });
+
+    if let Err(e) = written {
+        // restore the original file
+        let _ = rename_noreplace(&backup_path, path);
+        return Err(BackupError::Io(e));
+    }
+
+    Ok(backup_path)
+}
+
+/// Creates a backup of the specified file if it exists and then creates an empty file at its path.
+/// Returns the path to the backup file if it was created, otherwise returns an error.
+///
+/// # Arguments
+///
+/// * `path` - The path to the file to be backed up and recreated.
+///
+/// # Errors
+///
+/// This function can return the same errors as [`backup`] and additionally:
+///
+/// * `BackupError::Unsupported` - If the `path` is a directory.
+/// * `BackupError::Io` - If the empty file can not be created.
+///
+/// # Details
+/// If the `path` exists, the original file is **renamed** in the same way as with [`backup`]
+/// and `Ok(Some(backup))` is returned. Otherwise, `Ok(None)` is returned. In both cases, an empty file
+/// is then created at `path`, so writers expecting the file to exist can keep writing to it (e.g. when rotating logs).
+/// The new file gets the permissions of the backed up file.
+///
+/// If the file is created by another process between the backup and the creation of the empty file,
+/// the file is kept as is. If the empty file can not be created, the backup is renamed back to `path`.
+///
+/// # Examples
+///
+/// ```no_run
+/// use crate::backitup::backup_or_create;
+///
+/// match backup_or_create("output.log") {
+///     Ok(Some(backup_path)) => println!("Log rotated: {:?}", backup_path),
+///     Ok(None) => println!("Log created."),
+///     Err(err) => eprintln!("Failed to rotate the log: {:?}", err),
+/// }
+/// ```
+pub fn backup_or_create(path: impl AsRef<Path>) -> Result<Option<PathBuf>, BackupError> {
+    let path = path.as_ref();
+
+    if path.is_dir() {
+        return Err(BackupError::Unsupported(String::from(
+            "Directories can not be replaced with empty files.",
+        )));
+    }
+
+    // broken symbolic links also exist
+    let backup_path = match fs::symlink_metadata(path) {
+        Ok(_) => Some(backup(path)?),
+        Err(e) if e.kind() == ErrorKind::NotFound => None,
+        Err(e) => return Err(BackupError::Io(e)),
+    };
+
+    // the file is not truncated if it has been created in the meantime
+    let mut created = false;
+    let result = match File::options().write(true).create_new(true).open(path) {
+        Ok(file) => {
+            created = true;
+            Ok(file)
+        }
+        Err(e) if e.kind() == ErrorKind::AlreadyExists => File::options().append(true).open(path),
+        Err(e) => Err(e),
+    }
+    .and_then(|file| match &backup_path {
+        Some(backup_path) => file.set_permissions(fs::metadata(backup_path)?.permissions()),
+        None => Ok(()),
+    });
+
+    if let Err(e) = result {
+        // only the file created by this call is removed, a file created in the meantime is kept
+        if created {
+            let _ = fs::remove_file(path);
+        }
+
+        // restore the original file
+        if let Some(backup_path) = &backup_path {
+            let _ = rename_noreplace(backup_path, path);
+        }
+        return Err(BackupError::Io(e));
+    }
+
+    Ok(backup_path)
+}
+
+/// Removes trailing separators (and trailing `.` components) from the `path`,
+/// so that e.g. `data/` and `data` refer to the same entry.
+///
+/// Without this, system calls resolve a symbolic link given with a trailing separator,
+/// so `link/` would refer to the target of the link, while `link` refers to the link itself.
+fn trim_trailing_separators(path: &Path) -> &Path {
+    path.components().as_path()
+}
+
+/// Splits the `filename` into its stem and extension (without the dot), as done by [`Path::extension`].
+/// Names without an extension (e.g. `.bashrc`) are returned as is.
+fn split_extension(filename: &OsStr) -> (&OsStr, Option<&OsStr>) {
+    let path = Path::new(filename);
+
+    match (path.file_stem(), path.extension()) {
+        (Some(stem), Some(extension)) => (stem, Some(extension)),
+        _ => (filename, None),
+    }
+}
+
+/// Splits the `path` into its parent directory and filename.
+/// Parent of a relative path consisting of a single component is empty, so that joining names
+/// to it does not add a `.` component. Use [`dir_or_current`] to access the parent directory.
+fn split_path(path: &Path) -> Result<(&Path, &OsStr), BackupError> {
+    // get the parent directory of the path
+    let parent = match path.parent() {
+        Some(x) => x,
+        None => return Err(BackupError::IsRoot),
+    };
+
+    // get the filename from the path
+    let filename = match path.file_name() {
+        Some(x) => x,
+        None => return Err(BackupError::EndsInDotDot),
+    };
+
+    Ok((parent, filename))
+}
+
+/// Returns `dir`, or `"."` if `dir` is empty (i.e. the parent of a relative path consisting of a single component).
+fn dir_or_current(dir: &Path) -> &Path {
+    match dir.as_os_str().is_empty() {
+        true => Path::new("."),
+        false => dir,
+    }
+}
+
+/// Returns the directory containing `path`.
+fn parent_dir(path: &Path) -> &Path {
+    dir_or_current(path.parent().unwrap_or(Path::new("")))
+}
+
+/// Formats `time` using `fmt` into `formatted` and checks that the result can be used in a filename.
+/// The previous content of `formatted` is cleared, but its capacity is reused.
+/// On Windows, colons in the formatted timestamp are replaced with hyphens.
+fn format_time(
+    time: &DateTime<FixedOffset>,
+    fmt: &str,
+    formatted: &mut String,
+) -> Result<(), BackupError> {
+    formatted.clear();
+    if write!(formatted, "{}", time.format(fmt)).is_err() {
+        return Err(BackupError::InvalidFormat);
+    }
+
+    // colons are common in time formats, but they are not allowed in filenames on Windows
+    #[cfg(windows)]
+    if formatted.contains(':') {
+        #[cfg(feature = "tracing")]
+        tracing::warn!(
+            format = fmt,
+            "colons in the timestamp are not allowed on Windows and are replaced with hyphens"
+        );
+        *formatted = formatted.replace(':', "-");
+    }
+
+    if formatted.chars().any(is_illegal_char) {
+        return Err(BackupError::InvalidPath(String::from(
+            "Timestamp contains characters that are not allowed in filenames.",
+        )));
+    }
+
+    Ok(())
+}
+
+/// Returns `true` if the character can not be used in a filename on the target platform.
+#[cfg(not(windows))]
+fn is_illegal_char(c: char) -> bool {
+    c == '/' || c == '\0'
+}
+
+/// Returns `true` if the character can not be used in a filename on the target platform.
+#[cfg(windows)]
+fn is_illegal_char(c: char) -> bool {
+    matches!(c, '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*') || c.is_control()
+}
+
+/// Checks that `name` can be used as a filename on Windows.
+/// Returns an error if `name` contains characters that are not allowed in filenames,
+/// ends with a dot or a space, or if it is a name reserved for devices (e.g. `CON` or `NUL.txt`).
+#[cfg(windows)]
+fn check_windows_name(name: &str) -> Result<(), BackupError> {
+    const RESERVED: [&str; 22] = [
+        "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+        "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+    ];
+
+    if name.chars().any(is_illegal_char) {
+        return Err(BackupError::InvalidPath(format!(
+            "Name of the backup '{}' contains characters that are not allowed in filenames.",
+            name
+        )));
+    }
+
+    if name.ends_with('.') || name.ends_with(' ') {
+        return Err(BackupError::InvalidPath(format!(
+            "Name of the backup '{}' ends with a dot or a space.",
+            name
+        )));
+    }
+
+    // reserved names are not allowed even if they are followed by an extension
+    let stem = name.split('.').next().unwrap_or(name).trim_end();
+    if RESERVED.iter().any(|x| x.eq_ignore_ascii_case(stem)) {
+        return Err(BackupError::InvalidPath(format!(
+            "Name of the backup '{}' is reserved on Windows.",
+            name
+        )));
+    }
+
+    Ok(())
+}
+
+/// Maximal length of a filename (in bytes on Unix, in UTF-16 code units on Windows).
+const MAX_NAME_LENGTH: usize = 255;
+
+/// Maximal length of a path in UTF-16 code units, including the terminating nul character
+/// (for paths without the `\\?\` prefix).
+#[cfg(windows)]
+const MAX_PATH_LENGTH: usize = 260;
+
+/// Checks that the name of the backup at `path` does not exceed the limits of the filesystem.
+#[cfg(not(windows))]
+fn check_name_length(path: &Path) -> Result<(), BackupError> {
+    match path.file_name() {
+        Some(x) if x.len() > MAX_NAME_LENGTH => Err(BackupError::NameTooLong),
+        _ => Ok(()),
+    }
+}
+
+/// Checks that the name of the backup at `path` does not exceed the limits of the filesystem.
+/// The length of the whole path is not limited, see [`extend_long_path`].
+#[cfg(windows)]
+fn check_name_length(path: &Path) -> Result<(), BackupError> {
+    use std::os::windows::ffi::OsStrExt;
+
+    match path.file_name() {
+        Some(x) if x.encode_wide().count() > MAX_NAME_LENGTH => Err(BackupError::NameTooLong),
+        _ => Ok(()),
+    }
+}
+
+/// Converts `path` into an extended-length path (prefixed with `\\?\`), if it exceeds the legacy maximal path length.
+/// Shorter paths (and paths that already are verbatim) are returned unchanged.
+///
+/// Relative paths are resolved against the current directory. UNC paths (`\\server\share\...`)
+/// are converted into `\\?\UNC\server\share\...`.
+#[cfg(windows)]
+fn extend_long_path(path: PathBuf) -> PathBuf {
+    use std::os::windows::ffi::OsStrExt;
+
+    // verbatim and device paths are not limited by the maximal path length
+    let lossy = path.as_os_str().to_string_lossy();
+    if lossy.starts_with(r"\\?\") || lossy.starts_with(r"\\.\") {
+        return path;
+    }
+
+    // the resolved path is normalized, as required for verbatim paths
+    let Ok(absolute) = std::path::absolute(&path) else {
+        return path;
+    };
+    if absolute.as_os_str().encode_wide().count() < MAX_PATH_LENGTH {
+        return path;
+    }
+
+    let absolute = absolute.into_os_string();
+    let mut extended = std::ffi::OsString::with_capacity(absolute.len() + 8);
+    match absolute.to_str().and_then(|x| x.strip_prefix(r"\\")) {
+        Some(unc) => {
+            extended.push(r"\\?\UNC\");
+            extended.push(unc);
+        }
+        None => {
+            extended.push(r"\\?\");
+            extended.push(absolute);
+        }
+    }
+
+    PathBuf::from(extended)
+}
+
+/// Checks that the entry at `path` is a regular file, a directory, or a symbolic link.
+/// Returns `BackupError::Unsupported` identifying the type of the entry otherwise, unless `allow_special` is set.
+pub(crate) fn check_file_type(path: &Path, allow_special: bool) -> Result<(), BackupError> {
+    let file_type = fs::symlink_metadata(path)?.file_type();
+
+    if allow_special || file_type.is_file() || file_type.is_dir() || file_type.is_symlink() {
+        return Ok(());
+    }
+
+    #[cfg(unix)]
+    let kind = {
+        use std::os::unix::fs::FileTypeExt;
+
+        if file_type.is_fifo() {
+            "FIFO"
+        } else if file_type.is_socket() {
+            "socket"
+        } else if file_type.is_block_device() {
+            "block device"
+        } else if file_type.is_char_device() {
+            "character device"
+        } else {
+            "special file"
+        }
+    };
+
+    #[cfg(not(unix))]
+    let kind = "special file";
+
+    Err(BackupError::Unsupported(format!(
+        "Path '{}' is a {} and can not be backed up.",
+        path.display(),
+        kind
+    )))
+}
+
+/// Checks that the file or directory at `path` is not a directory symbolic link or junction,
+/// unless `allow_links` is set.
+///
+/// Renaming a directory link on Windows only moves the link node, which is easily confused
+/// with moving the linked directory, so directory links are refused by default.
+#[cfg(windows)]
+pub(crate) fn check_directory_link(path: &Path, allow_links: bool) -> Result<(), BackupError> {
+    use std::os::windows::fs::FileTypeExt;
+
+    // junctions are reported as directory symbolic links
+    if allow_links || !fs::symlink_metadata(path)?.file_type().is_symlink_dir() {
+        return Ok(());
+    }
+
+    Err(BackupError::Unsupported(format!(
+        "Path '{}' is a directory symbolic link or junction and can not be backed up.",
+        path.display()
+    )))
+}
+
+/// Checks that the file or directory at `path` is not a directory link.
+/// Symbolic links are always renamed as link nodes on this platform, so no path is refused.
+#[cfg(not(windows))]
+pub(crate) fn check_directory_link(_path: &Path, _allow_links: bool) -> Result<(), BackupError> {
+    Ok(())
+}
+
+/// Interval between the attempts to acquire the lock in [`lock_file`].
+const LOCK_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Opens the file at `path` and acquires a shared lock on it, waiting for at most `timeout`.
+/// The lock is released once the returned file is dropped.
+/// Returns `BackupError::Locked` if the lock can not be acquired within `timeout`.
+pub(crate) fn lock_file(path: &Path, timeout: Duration) -> Result<File, BackupError> {
+    if path.is_dir() {
+        return Err(BackupError::Unsupported(String::from(
+            "Directories can not be locked.",
+        )));
+    }
+
+    let file = File::open(path).map_err(|e| match e.kind() {
+        ErrorKind::NotFound => BackupError::NotFound,
+        _ => BackupError::Io(e),
+    })?;
+    let deadline = std::time::Instant::now() + timeout;
+
+    loop {
+        match file.try_lock_shared() {
+            Ok(()) => return Ok(file),
+            Err(fs::TryLockError::WouldBlock) => (),
+            Err(fs::TryLockError::Error(e)) => return Err(BackupError::Io(e)),
+        }
+
+        let remaining = deadline.saturating_duration_since(std::time::Instant::now());
+        if remaining.is_zero() {
+            return Err(BackupError::Locked);
+        }
+
+        std::thread::sleep(remaining.min(LOCK_INTERVAL));
+    }
+}
+
+/// Checks that new entries can be created in (and removed from) the directory at `dir`.
+/// Returns `BackupError::NotWritable` if the directory is not writable by the current user.
+///
+/// On Linux, the check is performed using `access`, taking the user and group of the process into account.
+/// On other Unix platforms, only the permission bits of the directory are checked.
+/// On Windows, the check is not performed, since the read-only attribute is ignored for directories.
+#[cfg(target_os = "linux")]
+fn check_writable(dir: &Path) -> Result<(), BackupError> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let dir = dir_or_current(dir);
+
+    // paths that can not be converted are left for the rename to report
+    let Ok(c_dir) = CString::new(dir.as_os_str().as_bytes()) else {
+        return Ok(());
+    };
+
+    // SAFETY: the path is a valid nul-terminated string that outlives the call
+    let result = unsafe { libc::access(c_dir.as_ptr(), libc::W_OK) };
+
+    if result != 0 && std::io::Error::last_os_error().kind() == ErrorKind::PermissionDenied {
+        return Err(BackupError::NotWritable(dir.to_path_buf()));
+    }
+
+    Ok(())
+}
+
+/// Checks that new entries can be created in (and removed from) the directory at `dir`.
+/// Returns `BackupError::NotWritable` if the directory is not writable by the current user.
+#[cfg(not(target_os = "linux"))]
+fn check_writable(dir: &Path) -> Result<(), BackupError> {
+    #[cfg(unix)]
+    if fs::metadata(dir_or_current(dir)).is_ok_and(|x| x.permissions().readonly()) {
+        return Err(BackupError::NotWritable(dir.to_path_buf()));
+    }
+
+    #[cfg(not(unix))]
+    let _ = dir;
+
+    Ok(())
+}
+
+/// Flushes the entries of the directory at `dir` to the storage device,
+/// so that files created in (or renamed into or out of) the directory survive a crash.
+///
+/// On Unix, the directory is opened and `fsync`ed. On Windows, directories can not be flushed
+/// and the metadata are journaled by the filesystem, so nothing is done.
+fn sync_dir(dir: &Path) -> Result<(), std::io::Error> {
+    #[cfg(unix)]
+    File::open(dir_or_current(dir))?.sync_all()?;
+
+    #[cfg(not(unix))]
+    let _ = dir;
+
+    Ok(())
+}
+
+/// Moves the file or directory at `source` to `target`.
+/// If `source` and `target` are located on different filesystems,
+/// `source` is copied to `target` and then removed.
+///
+/// If the filesystems are known to differ (see [`same_device`]), the rename is not attempted at all.
+fn move_path(source: &Path, target: &Path) -> Result<(), std::io::Error> {
+    move_path_with(source, target, rename_noreplace)
+}
+
+/// Moves the file or directory at `source` to `target` in the same way as [`move_path`], renaming using `rename`.
+/// Falls back to copying if `rename` fails with `ErrorKind::CrossesDevices`.
+fn move_path_with(
+    source: &Path,
+    target: &Path,
+    rename: impl FnOnce(&Path, &Path) -> Result<(), std::io::Error>,
+) -> Result<(), std::io::Error> {
+    if same_device(source, parent_dir(target)) == Some(false) {
+        return copy_and_remove(source, target);
+    }
+
+    match rename(source, target) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == ErrorKind::CrossesDevices => copy_and_remove(source, target),
+        Err(e) => Err(e),
+    }
+}
+
+/// Copies the file or directory at `source` to `target` and then removes `source`.
+/// Symbolic links are moved as links (see [`copy_recursive`]), so the files they point to are never copied or removed.
+fn copy_and_remove(source: &Path, target: &Path) -> Result<(), std::io::Error> {
+    write_via_temp(target, |temp| copy_recursive(source, temp))?;
+    remove_path(source)
+}
+
+/// Checks whether the entries at `a` and `b` are located on the same filesystem.
+/// Symbolic links are not followed.
+/// Returns `None` if this can not be determined, e.g. because one of the entries does not exist.
+///
+/// On Unix, the device IDs of the entries are compared.
+#[cfg(unix)]
+fn same_device(a: &Path, b: &Path) -> Option<bool> {
+    use std::os::unix::fs::MetadataExt;
+
+    let a = fs::symlink_metadata(a).ok()?;
+    let b = fs::symlink_metadata(b).ok()?;
+    Some(a.dev() == b.dev())
+}
+
+/// Checks whether the entries at `a` and `b` are located on the same filesystem.
+/// Returns `None` if this can not be determined.
+///
+/// On Windows, the volumes (drive letters or network shares) of the absolute paths are compared
+/// without accessing the filesystem. Since volumes can be mounted into folders of other volumes,
+/// paths on the same drive are not guaranteed to share the volume, so `Some(true)` is never returned.
+#[cfg(windows)]
+fn same_device(a: &Path, b: &Path) -> Option<bool> {
+    use std::ffi::OsString;
+    use std::path::{Component, Prefix};
+
+    fn volume(path: &Path) -> Option<OsString> {
+        let path = std::path::absolute(path).ok()?;
+        let Some(Component::Prefix(prefix)) = path.components().next() else {
+            return None;
+        };
+
+        match prefix.kind() {
+            Prefix::Disk(letter) | Prefix::VerbatimDisk(letter) => Some(OsString::from(
+                char::from(letter).to_ascii_uppercase().to_string(),
+            )),
+            Prefix::UNC(server, share) | Prefix::VerbatimUNC(server, share) => {
+                let mut volume = server.to_ascii_lowercase();
+                volume.push("\\");
+                volume.push(share.to_ascii_lowercase());
+                Some(volume)
+            }
+            _ => None,
+        }
+    }
+
+    if volume(a)? != volume(b)? {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+/// Checks whether the entries at `a` and `b` are located on the same filesystem.
+/// This can not be determined on this platform, so `None` is always returned.
+#[cfg(not(any(unix, windows)))]
+fn same_device(_a: &Path, _b: &Path) -> Option<bool> {
+    None
+}
+
+/// Renames the file or directory at `source` to `target`.
+/// Fails with `ErrorKind::AlreadyExists` if `target` already exists.
+///
+/// On Linux, the check and the rename are performed atomically using `renameat2` with `RENAME_NOREPLACE`,
+/// so an existing `target` is never overwritten. If the filesystem does not support this,
+/// the best-effort approach of [`rename_checked`] is used.
+#[cfg(target_os = "linux")]
+fn rename_noreplace(source: &Path, target: &Path) -> Result<(), std::io::Error> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_source = CString::new(source.as_os_str().as_bytes())?;
+    let c_target = CString::new(target.as_os_str().as_bytes())?;
+
+    // SAFETY: both paths are valid nul-terminated strings that outlive the call
+    let result = unsafe {
+        libc::renameat2(
+            libc::AT_FDCWD,
+            c_source.as_ptr(),
+            libc::AT_FDCWD,
+            c_target.as_ptr(),
+            libc::RENAME_NOREPLACE,
+        )
+    };
+
+    if result == 0 {
+        return Ok(());
+    }
+
+    let error = std::io::Error::last_os_error();
+    match error.raw_os_error() {
+        // the kernel or the filesystem does not support the flag
+        Some(libc::EINVAL) | Some(libc::ENOSYS) => rename_checked(source, target),
+        _ => Err(error),
+    }
+}
+
+/// Renames the file or directory at `source` to `target`.
+/// Fails with `ErrorKind::AlreadyExists` if `target` already exists.
+///
+/// There is no portable primitive for renaming without replacing the target on this platform,
+/// so the best-effort approach of [`rename_checked`] is used.
+#[cfg(not(target_os = "linux"))]
+fn rename_noreplace(source: &Path, target: &Path) -> Result<(), std::io::Error> {
+    rename_checked(source, target)
+}
+
+/// Renames the file or directory at `source` to `target` if `target` does not exist.
+///
+/// This is a best-effort approach: the existence of `target` is checked right before renaming,
+/// so a `target` created by another process between the check and the rename may still be overwritten.
+fn rename_checked(source: &Path, target: &Path) -> Result<(), std::io::Error> {
+    // broken symbolic links also count as existing
+    if fs::symlink_metadata(target).is_ok() {
+        return Err(std::io::Error::from(ErrorKind::AlreadyExists));
+    }
+
+    fs::rename(source, target)
+}
+
+/// Extension of the temporary siblings of the backups used by [`write_via_temp`].
+const TEMP_EXTENSION: &str = ".tmp";
+
+/// Returns the path of the temporary sibling of `target` used by [`write_via_temp`].
+fn temp_path(target: &Path) -> PathBuf {
+    let mut path = target.as_os_str().to_owned();
+    path.push(TEMP_EXTENSION);
+    PathBuf::from(path)
+}
+
+/// Returns `true` if `name` is the name of the temporary sibling of a backup
+/// created with the default wrapper (including the compressed backups).
+fn is_temp_backup_name(name: &OsStr) -> bool {
+    let Some(name) = name
+        .as_encoded_bytes()
+        .strip_suffix(TEMP_EXTENSION.as_bytes())
+    else {
+        return false;
+    };
+
+    // suffixes of the plain, gzip, and tar.gz backups
+    [DEFAULT_SUFFIX, ".gz#", "#.tar.gz"]
+        .iter()
+        .any(|suffix| list::split_backup_name(name, DEFAULT_PREFIX, suffix).is_some())
+}
+
+/// Creates `target` by letting `write` create a file or directory at a temporary path
+/// which is then renamed to `target`. Fails with `ErrorKind::AlreadyExists` if `target` already exists.
+///
+/// The temporary path is a sibling of `target` named `<target>.tmp`, so the rename never crosses filesystems.
+/// `target` therefore only ever appears fully written, even if the process crashes in the middle of `write`.
+/// The temporary file (or directory) is removed if anything fails, unless it has been created by someone else.
+pub(crate) fn write_via_temp(
+    target: &Path,
+    write: impl FnOnce(&Path) -> Result<(), std::io::Error>,
+) -> Result<(), std::io::Error> {
+    write_temp_and_rename(target, write, rename_noreplace)
+}
+
+/// Same as [`write_via_temp`], but an existing `target` is atomically replaced by the temporary file.
+/// `target` is never modified in place, so it is left untouched if anything fails.
+#[cfg(feature = "zip")]
+pub(crate) fn replace_via_temp(
+    target: &Path,
+    write: impl FnOnce(&Path) -> Result<(), std::io::Error>,
+) -> Result<(), std::io::Error> {
+    write_temp_and_rename(target, write, |temp, target| fs::rename(temp, target))
+}
+
+/// Lets `write` create the temporary sibling of `target` which is then renamed to `target` using `rename`.
+fn write_temp_and_rename(
+    target: &Path,
+    write: impl FnOnce(&Path) -> Result<(), std::io::Error>,
+    rename: impl FnOnce(&Path, &Path) -> Result<(), std::io::Error>,
+) -> Result<(), std::io::Error> {
+    let temp = temp_path(target);
+
+    match write(&temp) {
+        Ok(()) => (),
+        // the temporary file has been created by someone else and must not be removed
+        Err(e) if e.kind() == ErrorKind::AlreadyExists => return Err(e),
+        Err(e) => {
+            // do not leave a partial copy behind
+            let _ = remove_path(&temp);
+            return Err(e);
+        }
+    }
+
+    rename(&temp, target).inspect_err(|_| {
+        let _ = remove_path(&temp);
+    })
+}
+
+/// Replaces the file or directory at `target` (if it exists) by the file or directory at `source`.
+///
+/// Files are replaced atomically by renaming. Since directories can not be renamed over, an existing directory
+/// (or a file replaced by a directory) is first renamed aside to the temporary sibling of `source`
+/// and only removed once `source` is in place. If that fails, it is renamed back to `target`.
+fn replace_path(source: &Path, target: &Path) -> Result<(), std::io::Error> {
+    let existing = match fs::symlink_metadata(target) {
+        Ok(x) => x,
+        Err(e) if e.kind() == ErrorKind::NotFound => return rename_noreplace(source, target),
+        Err(e) => return Err(e),
+    };
+
+    if !existing.is_dir() && !fs::symlink_metadata(source)?.is_dir() {
+        return fs::rename(source, target);
+    }
+
+    let aside = temp_path(source);
+    rename_noreplace(target, &aside)?;
+    if let Err(e) = rename_noreplace(source, target) {
+        let _ = rename_noreplace(&aside, target);
+        return Err(e);
+    }
+
+    remove_path(&aside)
+}
+
+/// Removes the file or directory at `path`. Directories are removed recursively.
+/// Symbolic links are not followed, i.e. a link to a directory is removed as a link.
+fn remove_path(path: &Path) -> Result<(), std::io::Error> {
+    if fs::symlink_metadata(path)?.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    }
+}
+
+/// Returns the size of the file at `path` in bytes.
+/// The size of a directory is the sum of the sizes of all entries it contains (recursively).
+/// Symbolic links are followed only if `follow_symlinks` is set.
+fn path_size(path: &Path, follow_symlinks: bool) -> Result<u64, std::io::Error> {
+    let metadata = if follow_symlinks {
+        fs::metadata(path)?
+    } else {
+        fs::symlink_metadata(path)?
+    };
+
+    if !metadata.is_dir() {
+        return Ok(metadata.len());
+    }
+
+    let mut size = 0;
+    for entry in fs::read_dir(path)? {
+        size += path_size(&entry?.path(), follow_symlinks)?;
+    }
+
+    Ok(size)
+}
+
+/// Function copying the content of an opened file into a newly created file.
+type CopyFile<'a> = dyn FnMut(&mut File, &mut File) -> Result<(), std::io::Error> + 'a;
+
+/// Copies the file or directory at `source` to `target`.
+/// Directories are copied recursively. Symbolic links are never followed and are copied as links.
+/// Special files (e.g. FIFOs or sockets) are never opened; the copy fails with `BackupError::Unsupported` instead.
+/// Permissions and access and modification times of all copied entries are preserved.
+fn copy_recursive(source: &Path, target: &Path) -> Result<(), std::io::Error> {
+    copy_recursive_with(source, target, &mut copy_file, &|_| false)
+}
+
+/// Copies the content of `reader` into `writer`.
+fn copy_file(reader: &mut File, writer: &mut File) -> Result<(), std::io::Error> {
+    std::io::copy(reader, writer).map(|_| ())
+}
+
+/// Copies the content of `reader` into `writer` by cloning it (reflink) if possible.
+/// Falls back to a regular copy if the filesystem does not support reflinks.
+#[cfg(target_os = "linux")]
+fn reflink_file(reader: &mut File, writer: &mut File) -> Result<(), std::io::Error> {
+    use std::os::fd::AsRawFd;
+
+    // SAFETY: both file descriptors are valid and open for the duration of the call
+    let result = unsafe { libc::ioctl(writer.as_raw_fd(), libc::FICLONE, reader.as_raw_fd()) };
+
+    if result == 0 {
+        return Ok(());
+    }
+
+    let error = std::io::Error::last_os_error();
+    match error.raw_os_error() {
+        // the filesystem does not support reflinks or the files are located on different filesystems
+        Some(libc::EOPNOTSUPP)
+        | Some(libc::ENOTTY)
+        | Some(libc::EXDEV)
+        | Some(libc::EINVAL)
+        | Some(libc::ENOSYS) => copy_file(reader, writer),
+        _ => Err(error),
+    }
+}
+
+/// Copies the file or directory at `source` to `target` in the same way as [`copy_recursive`],
+/// cloning the content of the individual files (reflink) if possible.
+///
+/// On Linux, every file is cloned using the `FICLONE` ioctl, falling back to a regular copy.
+#[cfg(target_os = "linux")]
+fn reflink_recursive(source: &Path, target: &Path) -> Result<(), std::io::Error> {
+    copy_recursive_with(source, target, &mut reflink_file, &|_| false)
+}
+
+/// Copies the file or directory at `source` to `target` in the same way as [`copy_recursive`],
+/// cloning it (reflink) if possible.
+///
+/// On macOS, the whole file or directory is cloned using `clonefile`, which never overwrites an existing `target`.
+/// If the filesystem does not support cloning, a regular copy is created.
+#[cfg(target_os = "macos")]
+fn reflink_recursive(source: &Path, target: &Path) -> Result<(), std::io::Error> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_source = CString::new(source.as_os_str().as_bytes())?;
+    let c_target = CString::new(target.as_os_str().as_bytes())?;
+
+    // SAFETY: both paths are valid nul-terminated strings that outlive the call
+    let result = unsafe { libc::clonefile(c_source.as_ptr(), c_target.as_ptr(), 0) };
+
+    if result == 0 {
+        return Ok(());
+    }
+
+    let error = std::io::Error::last_os_error();
+    match error.raw_os_error() {
+        // the filesystem does not support cloning or the paths are located on different filesystems
+        Some(libc::ENOTSUP) | Some(libc::EXDEV) => copy_recursive(source, target),
+        _ => Err(error),
+    }
+}
+
+/// Copies the file or directory at `source` to `target` using [`copy_recursive`].
+/// Reflinks are not supported on this platform.
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn reflink_recursive(source: &Path, target: &Path) -> Result<(), std::io::Error> {
+    copy_recursive(source, target)
+}
+
+/// Copies the file or directory at `source` to `target` in the same way as [`copy_recursive`],
+/// copying the content of the individual files using `copy_file`.
+/// Entries of directories for which `exclude` returns `true` (called with the path of the entry) are skipped.
+fn copy_recursive_with(
+    source: &Path,
+    target: &Path,
+    copy_file: &mut CopyFile,
+    exclude: &dyn Fn(&Path) -> bool,
+) -> Result<(), std::io::Error> {
+    let metadata = fs::symlink_metadata(source)?;
+
+    if metadata.is_symlink() {
+        // copying the target of the link would fail for dangling links and loop for links to parent directories
+        copy_symlink(source, target)?;
+        // the times of the link itself are set and the permissions of links are not used (or can not be changed)
+        return filetime::set_symlink_file_times(
+            target,
+            FileTime::from_last_access_time(&metadata),
+            FileTime::from_last_modification_time(&metadata),
+        );
+    }
+
+    if metadata.is_dir() {
+        fs::create_dir(target)?;
+
+        for entry in fs::read_dir(source)? {
+            let entry = entry?;
+            let path = entry.path();
+            if exclude(&path) {
+                continue;
+            }
+
+            copy_recursive_with(&path, &target.join(entry.file_name()), copy_file, exclude)?;
+        }
+    } else {
+        // reading from special files nested in directories may block indefinitely as well
+        check_file_type(source, false)?;
+
+        // the target is never overwritten
+        let mut reader = File::open(source)?;
+        let mut writer = File::create_new(target)?;
+        copy_file(&mut reader, &mut writer)?;
+    }
+
+    // times must be set after the content is copied, since copying entries
+    // into a directory changes its modification time
+    filetime::set_file_times(
+        target,
+        FileTime::from_last_access_time(&metadata),
+        FileTime::from_last_modification_time(&metadata),
+    )?;
+    // permissions are set last, since the copy may be read-only
+    fs::set_permissions(target, metadata.permissions())?;
+
+    Ok(())
+}
+
+/// Creates a symbolic link at `target` pointing to the same path as the symbolic link at `source`.
+#[cfg(unix)]
+fn copy_symlink(source: &Path, target: &Path) -> Result<(), std::io::Error> {
+    std::os::unix::fs::symlink(fs::read_link(source)?, target)
+}
+
+/// Creates a symbolic link at `target` pointing to the same path as the symbolic link at `source`.
+#[cfg(windows)]
+fn copy_symlink(source: &Path, target: &Path) -> Result<(), std::io::Error> {
+    use std::os::windows::fs::FileTypeExt;
+
+    let link = fs::read_link(source)?;
+    if fs::symlink_metadata(source)?.file_type().is_symlink_dir() {
+        std::os::windows::fs::symlink_dir(link, target)
+    } else {
+        std::os::windows::fs::symlink_file(link, target)
+    }
+}
+
+/// Symbolic links can not be created on this platform.
+#[cfg(not(any(unix, windows)))]
+fn copy_symlink(_source: &Path, _target: &Path) -> Result<(), std::io::Error> {
+    Err(std::io::Error::new(
+        ErrorKind::Unsupported,
+        "Symbolic links can not be copied on this platform.",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::{set_current_dir, test_dir};
+    use std::collections::HashSet;
+    use std::fs::{self, File};
+
+    #[test]
+    fn file() {
+        let tmp = test_dir();
+        let mut file = File::create(tmp.join("test_file1.txt")).unwrap();
+        file.write_all(b"Some content to test.").unwrap();
+
+        let backup = match backup(tmp.join("test_file1.txt")) {
+            Ok(x) => x,
+            Err(_) => panic!("Backup failed."),
+        };
+
+        drop(file);
+
+        let mut content = String::new();
+        let mut read = File::open(&backup).unwrap();
+        read.read_to_string(&mut content).unwrap();
+
+        assert_eq!(content, "Some content to test.");
+    }
+
+    #[test]
+    fn file_multiple_backups() {
+        let tmp = test_dir();
+        let mut backups = Vec::new();
+        for i in 0..20 {
+            let mut file = File::create(tmp.join("test_file2.txt")).unwrap();
+            let text = format!("Unique string for file {}", i);
+            file.write_all(text.as_bytes()).unwrap();
+
+            let backup = match backup(tmp.join("test_file2.txt")) {
+                Ok(x) => x,
+                Err(_) => panic!("Backup failed."),
+            };
+
+            backups.push(backup);
+        }
+
+        for (i, path) in backups.iter().enumerate() {
+            let mut content = String::new();
+            let mut read = File::open(path).unwrap();
+
+            read.read_to_string(&mut content).unwrap();
+
+            let test = format!("Unique string for file {}", i);
+            assert_eq!(content, test);
+
+            fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn file_in_different_directory() {
+        let tmp = test_dir();
+        fs::create_dir(tmp.join("test_dir")).unwrap();
+
+        let mut file = File::create(tmp.join("test_dir/test_file.txt")).unwrap();
+        file.write_all(b"Some content to test.").unwrap();
+
+        let backup = match backup(tmp.join("test_dir/test_file.txt")) {
+            Ok(x) => x,
+            Err(_) => panic!("Backup failed."),
+        };
+
+        drop(file);
+
+        let mut content = String::new();
+        let mut read = File::open(&backup).unwrap();
+        read.read_to_string(&mut content).unwrap();
+
+        assert_eq!(content, "Some content to test.");
+    }
+
+    #[test]
+    fn file_multiple_backups_in_different_directory() {
+        let tmp = test_dir();
+        fs::create_dir(tmp.join("test_dir2")).unwrap();
+
+        let mut backups = Vec::new();
+        for i in 0..20 {
+            let mut file = File::create(tmp.join("test_dir2/test_file.txt")).unwrap();
+            let text = format!("Unique string for file {}", i);
+            file.write_all(text.as_bytes()).unwrap();
+
+            let backup = match backup(tmp.join("test_dir2/test_file.txt")) {
+                Ok(x) => x,
+                Err(_) => panic!("Backup failed."),
+            };
+
+            backups.push(backup);
+        }
+
+        for (i, path) in backups.iter().enumerate() {
+            let mut content = String::new();
+            let mut read = File::open(path).unwrap();
+
+            read.read_to_string(&mut content).unwrap();
+
+            let test = format!("Unique string for file {}", i);
+            assert_eq!(content, test);
+
+            fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn directory() {
+        let tmp = test_dir();
+        fs::create_dir(tmp.join("test_dir3")).unwrap();
+
+        let mut file = File::create(tmp.join("test_dir3/test_file.txt")).unwrap();
+        file.write_all(b"Some content to test.").unwrap();
+
+        let backup = match backup(tmp.join("test_dir3")) {
+            Ok(x) => x,
+            Err(_) => panic!("Backup failed."),
+        };
+
+        drop(file);
+
+        let mut content = String::new();
+        let file_in_backup = backup.join("test_file.txt");
+        let mut read = File::open(&file_in_backup).unwrap();
+        read.read_to_string(&mut content).unwrap();
+
+        assert_eq!(content, "Some content to test.");
+    }
+
+    #[test]
+    fn directory_multiple_backups() {
+        let tmp = test_dir();
+        let mut backups = Vec::new();
+        for i in 0..10 {
+            fs::create_dir(tmp.join("test_dir4")).unwrap();
+
+            let mut file = File::create(tmp.join("test_dir4/test_file.txt")).unwrap();
+            let text = format!("Unique string for file {}", i);
+            file.write_all(text.as_bytes()).unwrap();
+
+            let backup = match backup(tmp.join("test_dir4")) {
+                Ok(x) => x,
+                Err(_) => panic!("Backup failed."),
+            };
+
+            backups.push(backup);
+        }
+
+        for (i, path) in backups.iter().enumerate() {
+            let file_in_backup = path.join("test_file.txt");
+
+            let mut content = String::new();
+            let mut read = File::open(&file_in_backup).unwrap();
+
+            read.read_to_string(&mut content).unwrap();
+
+            let test = format!("Unique string for file {}", i);
+            assert_eq!(content, test);
+
+            fs::remove_file(&file_in_backup).unwrap();
+            fs::remove_dir(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn file_copy() {
+        let tmp = test_dir();
+        let mut file = File::create(tmp.join("test_file_copy.txt")).unwrap();
+        file.write_all(b"Some content to test.").unwrap();
+        drop(file);
+
+        let backup = match backup_copy(tmp.join("test_file_copy.txt")) {
+            Ok(x) => x,
+            Err(_) => panic!("Backup failed."),
+        };
+
+        for path in [&tmp.join("test_file_copy.txt"), &backup] {
+            let mut content = String::new();
+            let mut read = File::open(path).unwrap();
+            read.read_to_string(&mut content).unwrap();
+
+            assert_eq!(content, "Some content to test.");
+        }
+    }
+
+    #[test]
+    fn file_copy_multiple_backups() {
+        let tmp = test_dir();
+        let mut backups = Vec::new();
+        for i in 0..20 {
+            let mut file = File::create(tmp.join("test_file_copy2.txt")).unwrap();
+            let text = format!("Unique string for file {}", i);
+            file.write_all(text.as_bytes()).unwrap();
+
+            let backup = match backup_copy(tmp.join("test_file_copy2.txt")) {
+                Ok(x) => x,
+                Err(_) => panic!("Backup failed."),
+            };
+
+            backups.push(backup);
+        }
+
+        for (i, path) in backups.iter().enumerate() {
+            let mut content = String::new();
+            let mut read = File::open(path).unwrap();
+
+            read.read_to_string(&mut content).unwrap();
+
+            let test = format!("Unique string for file {}", i);
+            assert_eq!(content, test);
+
+            fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn directory_copy() {
+        let tmp = test_dir();
+        fs::create_dir_all(tmp.join("test_dir_copy/nested")).unwrap();
+
+        let mut file = File::create(tmp.join("test_dir_copy/test_file.txt")).unwrap();
+        file.write_all(b"Some content to test.").unwrap();
+        let mut file = File::create(tmp.join("test_dir_copy/nested/test_file.txt")).unwrap();
+        file.write_all(b"Some nested content to test.").unwrap();
+        drop(file);
+
+        let backup = match backup_copy(tmp.join("test_dir_copy")) {
+            Ok(x) => x,
+            Err(_) => panic!("Backup failed."),
+        };
+
+        for dir in [&tmp.join("test_dir_copy"), &backup] {
+            let mut content = String::new();
+            let mut read = File::open(dir.join("test_file.txt")).unwrap();
+            read.read_to_string(&mut content).unwrap();
+            assert_eq!(content, "Some content to test.");
+
+            let mut content = String::new();
+            let mut read = File::open(dir.join("nested/test_file.txt")).unwrap();
+            read.read_to_string(&mut content).unwrap();
+            assert_eq!(content, "Some nested content to test.");
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn copy_preserves_metadata() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let tmp = test_dir();
+        fs::create_dir_all(tmp.join("test_dir_copy_metadata/nested")).unwrap();
+        let mut file =
+            File::create(tmp.join("test_dir_copy_metadata/nested/test_file.txt")).unwrap();
+        file.write_all(b"Some content to test.").unwrap();
+        drop(file);
+
+        let time = FileTime::from_unix_time(1_687_892_473, 0);
+        for (path, mode) in [
+            ("test_dir_copy_metadata/nested/test_file.txt", 0o600),
+            ("test_dir_copy_metadata/nested", 0o700),
+            ("test_dir_copy_metadata", 0o750),
+        ] {
+            let path = tmp.join(path);
+            fs::set_permissions(&path, fs::Permissions::from_mode(mode)).unwrap();
+            filetime::set_file_mtime(&path, time).unwrap();
+        }
+
+        let backup = match backup_copy(tmp.join("test_dir_copy_metadata")) {
+            Ok(x) => x,
+            Err(_) => panic!("Backup failed."),
+        };
+
+        for (path, mode) in [
+            (backup.join("nested/test_file.txt"), 0o600),
+            (backup.join("nested"), 0o700),
+            (backup.clone(), 0o750),
+        ] {
+            let metadata = fs::metadata(&path).unwrap();
+            assert_eq!(metadata.permissions().mode() & 0o777, mode);
+            assert_eq!(FileTime::from_last_modification_time(&metadata), time);
+        }
+    }
+
+    #[test]
+    fn copy_nonexistent() {
+        match backup_copy("nonexistent.txt") {
+            Ok(_) => panic!("Backup should have failed, but it was successful."),
+            Err(e) => assert_eq!(e.to_string(), "Path does not exist."),
+        };
+    }
+
+    #[test]
+    fn file_detailed() {
+        let tmp = test_dir();
+        let mut file = File::create(tmp.join("test_file_detailed.txt")).unwrap();
+        file.write_all(b"Some content to test.").unwrap();
+        drop(file);
+
+        let first = match backup_detailed(tmp.join("test_file_detailed.txt")) {
+            Ok(x) => x,
+            Err(_) => panic!("Backup failed."),
+        };
+
+        assert_eq!(first.original_path, tmp.join("test_file_detailed.txt"));
+        assert!(!first.was_directory);
+        assert!(!first.had_microsecond_collision);
+        assert_eq!(
+            first.backup_path,
+            tmp.join(format!(
+                "#test_file_detailed.txt-{}#",
+                first.timestamp.format(DEFAULT_FORMAT)
+            ))
+        );
+
+        let difference = Local::now() - first.timestamp;
+        assert!(difference >= chrono::Duration::zero());
+        assert!(difference < chrono::Duration::seconds(1));
+
+        // the name of the first backup is taken, so the second backup within the same second collides
+        File::create(tmp.join("test_file_detailed.txt")).unwrap();
+        let second = match backup_detailed(tmp.join("test_file_detailed.txt")) {
+            Ok(x) => x,
+            Err(_) => panic!("Backup failed."),
+        };
+
+        let same_second = first.timestamp.format(DEFAULT_FORMAT).to_string()
+            == second.timestamp.format(DEFAULT_FORMAT).to_string();
+        assert_eq!(second.had_microsecond_collision, same_second);
+    }
+
+    #[test]
+    fn directory_detailed() {
+        let tmp = test_dir();
+        fs::create_dir(tmp.join("test_dir_detailed")).unwrap();
+
+        let backup = match backup_detailed(tmp.join("test_dir_detailed")) {
+            Ok(x) => x,
+            Err(_) => panic!("Backup failed."),
+        };
+
+        assert!(backup.was_directory);
+        assert!(backup.backup_path.is_dir());
+    }
+
+    #[test]
+    fn file_custom_format() {
+        let tmp = test_dir();
+        let mut file = File::create(tmp.join("test_file_format.txt")).unwrap();
+        file.write_all(b"Some content to test.").unwrap();
+        drop(file);
+
+        let backup = match backup_with_format(tmp.join("test_file_format.txt"), "%Y%m%dT%H%M%S") {
+            Ok(x) => x,
+            Err(_) => panic!("Backup failed."),
+        };
+
+        let name = backup.file_name().unwrap().to_str().unwrap();
+        assert!(name.starts_with("#test_file_format.txt-"));
+        assert!(name.contains('T'));
+        assert!(name.ends_with('#'));
+
+        let mut content = String::new();
+        let mut read = File::open(&backup).unwrap();
+        read.read_to_string(&mut content).unwrap();
+
+        assert_eq!(content, "Some content to test.");
+    }
+
+    #[test]
+    fn format_illegal_character() {
+        let tmp = test_dir();
+        let mut file = File::create(tmp.join("test_file_format2.txt")).unwrap();
+        file.write_all(b"Some content to test.").unwrap();
+        drop(file);
+
+        match backup_with_format(tmp.join("test_file_format2.txt"), "%Y/%m/%d") {
+            Ok(_) => panic!("Backup should have failed, but it was successful."),
+            Err(e) => assert_eq!(e.kind(), ErrorKind::InvalidInput),
+        };
+
+        // the original file is not touched
+        assert!(tmp.join("test_file_format2.txt").exists());
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn format_colon_sanitized() {
+        let tmp = test_dir();
+        File::create(tmp.join("test_file_format_colon.txt")).unwrap();
+
+        let backup =
+            match backup_with_format(tmp.join("test_file_format_colon.txt"), "%Y-%m-%d %H:%M:%S") {
+                Ok(x) => x,
+                Err(_) => panic!("Backup failed."),
+            };
+
+        let name = backup.file_name().unwrap().to_str().unwrap();
+        assert!(name.starts_with("#test_file_format_colon.txt-"));
+        assert!(!name.contains(':'));
+        assert_eq!(name.matches('-').count(), 5);
+    }
+
+    #[test]
+    fn format_invalid_specifier() {
+        let tmp = test_dir();
+        let mut file = File::create(tmp.join("test_file_format3.txt")).unwrap();
+        file.write_all(b"Some content to test.").unwrap();
+        drop(file);
+
+        match backup_with_format(tmp.join("test_file_format3.txt"), "%Q") {
+            Ok(_) => panic!("Backup should have failed, but it was successful."),
+            Err(e) => assert_eq!(e.kind(), ErrorKind::InvalidInput),
+        };
+
+        assert!(tmp.join("test_file_format3.txt").exists());
+    }
+
+    #[test]
+    fn file_utc() {
+        let tmp = test_dir();
+        let mut file = File::create(tmp.join("test_file_utc.txt")).unwrap();
+        file.write_all(b"Some content to test.").unwrap();
+        drop(file);
+
+        let before = format!("#test_file_utc.txt-{}#", Utc::now().format(DEFAULT_FORMAT));
+        let backup = match backup_utc(tmp.join("test_file_utc.txt")) {
+            Ok(x) => x,
+            Err(_) => panic!("Backup failed."),
+        };
+        let after = format!("#test_file_utc.txt-{}#", Utc::now().format(DEFAULT_FORMAT));
+
+        let name = backup.file_name().unwrap().to_str().unwrap();
+        assert!(name == before || name == after);
+
+        let mut content = String::new();
+        let mut read = File::open(&backup).unwrap();
+        read.read_to_string(&mut content).unwrap();
+
+        assert_eq!(content, "Some content to test.");
+    }
+
+    #[test]
+    fn file_to_directory() {
+        let tmp = test_dir();
+        let mut file = File::create(tmp.join("test_file_to.txt")).unwrap();
+        file.write_all(b"Some content to test.").unwrap();
+        drop(file);
+
+        let backup = match backup_to(
+            tmp.join("test_file_to.txt"),
+            tmp.join("test_dir_to/backups"),
+        ) {
+            Ok(x) => x,
+            Err(_) => panic!("Backup failed."),
+        };
+
+        assert_eq!(backup.parent().unwrap(), tmp.join("test_dir_to/backups"));
+        assert!(!tmp.join("test_file_to.txt").exists());
+
+        let mut content = String::new();
+        let mut read = File::open(&backup).unwrap();
+        read.read_to_string(&mut content).unwrap();
+
+        assert_eq!(content, "Some content to test.");
+    }
+
+    #[test]
+    fn directory_to_directory() {
+        let tmp = test_dir();
+        fs::create_dir(tmp.join("test_dir_to2")).unwrap();
+        fs::create_dir(tmp.join("test_dir_to2_backups")).unwrap();
+
+        let mut file = File::create(tmp.join("test_dir_to2/test_file.txt")).unwrap();
+        file.write_all(b"Some content to test.").unwrap();
+        drop(file);
+
+        let mut backups = Vec::new();
+        for _ in 0..2 {
+            fs::create_dir_all(tmp.join("test_dir_to2")).unwrap();
+            let backup = match backup_to(tmp.join("test_dir_to2"), tmp.join("test_dir_to2_backups"))
+            {
+                Ok(x) => x,
+                Err(_) => panic!("Backup failed."),
+            };
+            backups.push(backup);
+        }
+
+        assert_ne!(backups[0], backups[1]);
+
+        let mut content = String::new();
+        let mut read = File::open(backups[0].join("test_file.txt")).unwrap();
+        read.read_to_string(&mut content).unwrap();
+
+        assert_eq!(content, "Some content to test.");
+    }
+
+    #[test]
+    fn retention() {
+        let tmp = test_dir();
+        fs::create_dir(tmp.join("test_dir_retention")).unwrap();
+        File::create(tmp.join("test_dir_retention/#other.txt-2023-06-27-21-01-13#")).unwrap();
+        File::create(tmp.join("test_dir_retention/#test_file.txt-garbage#")).unwrap();
+        File::create(tmp.join("test_dir_retention/test_file.txt.bak")).unwrap();
+
+        for i in 0..5 {
+            let mut file = File::create(tmp.join("test_dir_retention/test_file.txt")).unwrap();
+            let text = format!("Unique string for file {}", i);
+            file.write_all(text.as_bytes()).unwrap();
+
+            if backup_with_retention(tmp.join("test_dir_retention/test_file.txt"), 3).is_err() {
+                panic!("Backup failed.");
+            }
+        }
+
+        // only the three newest backups remain
+        // (names of removed backups can be reused, so the content is checked instead)
+        let mut contents = Vec::new();
+        for entry in fs::read_dir(tmp.join("test_dir_retention")).unwrap() {
+            let path = entry.unwrap().path();
+            let name = path.file_name().unwrap().to_str().unwrap();
+            if name.starts_with("#test_file.txt-2") {
+                contents.push(fs::read_to_string(&path).unwrap());
+            }
+        }
+        contents.sort();
+
+        let expected = (2..5)
+            .map(|i| format!("Unique string for file {}", i))
+            .collect::<Vec<_>>();
+        assert_eq!(contents, expected);
+
+        // unrelated files are not touched
+        assert!(tmp
+            .join("test_dir_retention/#other.txt-2023-06-27-21-01-13#")
+            .exists());
+        assert!(tmp
+            .join("test_dir_retention/#test_file.txt-garbage#")
+            .exists());
+        assert!(tmp.join("test_dir_retention/test_file.txt.bak").exists());
+        assert_eq!(
+            fs::read_dir(tmp.join("test_dir_retention"))
+                .unwrap()
+                .count(),
+            6
+        );
+    }
+
+    #[test]
+    fn prune() {
+        let tmp = test_dir();
+        File::create(tmp.join("#test_file.txt-2023-06-27-21-01-13-12#")).unwrap();
+        File::create(tmp.join("#test_file.txt-2023-06-27-21-01-13#")).unwrap();
+        File::create(tmp.join("#test_file.txt-2022-12-31-23-59-59#")).unwrap();
+        File::create(tmp.join("#other.txt-2020-01-01-00-00-00#")).unwrap();
+        fs::create_dir(tmp.join("#test_file.txt-2023-01-01-00-00-00#")).unwrap();
+        File::create(tmp.join("#test_file.txt-2023-01-01-00-00-00#/test_file.txt")).unwrap();
+
+        let removed = match prune_backups(tmp.join("test_file.txt"), 2) {
+            Ok(x) => x,
+            Err(_) => panic!("Pruning failed."),
+        };
+
+        assert_eq!(
+            removed.iter().map(|x| x.path.clone()).collect::<Vec<_>>(),
+            vec![
+                tmp.join("#test_file.txt-2022-12-31-23-59-59#"),
+                tmp.join("#test_file.txt-2023-01-01-00-00-00#"),
+            ]
+        );
+        assert!(removed.iter().all(|x| !x.path.exists()));
+        assert!(removed
+            .iter()
+            .all(|x| x.reason == PruneReason::ExceededCount));
+        assert_eq!(
+            removed[0].timestamp,
+            Local.with_ymd_and_hms(2022, 12, 31, 23, 59, 59).unwrap()
+        );
+
+        assert_eq!(
+            list::list_backups(tmp.join("test_file.txt")).unwrap(),
+            vec![
+                tmp.join("#test_file.txt-2023-06-27-21-01-13#"),
+                tmp.join("#test_file.txt-2023-06-27-21-01-13-12#"),
+            ]
+        );
+        assert!(tmp.join("#other.txt-2020-01-01-00-00-00#").exists());
+
+        // nothing to remove
+        assert!(prune_backups(tmp.join("test_file.txt"), 5)
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            list::list_backups(tmp.join("test_file.txt")).unwrap().len(),
+            2
+        );
+
+        // trailing separators are ignored
+        assert_eq!(
+            prune_backups(tmp.join("test_file.txt/"), 1).unwrap().len(),
+            1
+        );
+        assert_eq!(
+            list::list_backups(tmp.join("test_file.txt")).unwrap(),
+            vec![tmp.join("#test_file.txt-2023-06-27-21-01-13-12#")]
+        );
+    }
+
+    #[test]
+    fn prune_whole_tree() {
+        let tmp = test_dir();
+        fs::create_dir_all(tmp.join("test_dir_prune_tree/nested/deeper")).unwrap();
+        for (dir, name) in [
+            ("", "a.txt"),
+            ("", "b.txt"),
+            ("nested", "a.txt"),
+            ("nested/deeper", "c.txt"),
+        ] {
+            for second in 10..13 {
+                let backup = format!("#{}-2023-06-27-21-01-{}#", name, second);
+                File::create(tmp.join("test_dir_prune_tree").join(dir).join(backup)).unwrap();
+            }
+        }
+        // the only backup of a file is kept
+        File::create(tmp.join("test_dir_prune_tree/nested/#d.txt-2023-06-27-21-01-10#")).unwrap();
+        // directory backups are not traversed
+        fs::create_dir(tmp.join("test_dir_prune_tree/#dir-2023-06-27-21-01-10#")).unwrap();
+        File::create(
+            tmp.join("test_dir_prune_tree/#dir-2023-06-27-21-01-10#/#e.txt-2023-06-27-21-01-10#"),
+        )
+        .unwrap();
+        File::create(
+            tmp.join("test_dir_prune_tree/#dir-2023-06-27-21-01-10#/#e.txt-2023-06-27-21-01-11#"),
+        )
+        .unwrap();
+        // other files are not touched
+        File::create(tmp.join("test_dir_prune_tree/a.txt")).unwrap();
+
+        let removed = match prune_tree(tmp.join("test_dir_prune_tree"), 1) {
+            Ok(x) => x,
+            Err(_) => panic!("Pruning failed."),
+        };
+
+        let expected = [
+            "test_dir_prune_tree/#a.txt-2023-06-27-21-01-10#",
+            "test_dir_prune_tree/#a.txt-2023-06-27-21-01-11#",
+            "test_dir_prune_tree/#b.txt-2023-06-27-21-01-10#",
+            "test_dir_prune_tree/#b.txt-2023-06-27-21-01-11#",
+            "test_dir_prune_tree/nested/#a.txt-2023-06-27-21-01-10#",
+            "test_dir_prune_tree/nested/#a.txt-2023-06-27-21-01-11#",
+            "test_dir_prune_tree/nested/deeper/#c.txt-2023-06-27-21-01-10#",
+            "test_dir_prune_tree/nested/deeper/#c.txt-2023-06-27-21-01-11#",
+        ];
+
+        assert_eq!(
+            removed.iter().map(|x| x.path.clone()).collect::<Vec<_>>(),
+            expected.iter().map(|x| tmp.join(x)).collect::<Vec<_>>()
+        );
+        assert!(removed.iter().all(|x| !x.path.exists()));
+
+        // the newest backup of each file is kept in each directory
+        for (dir, name) in [
+            ("", "a.txt"),
+            ("", "b.txt"),
+            ("nested", "a.txt"),
+            ("nested/deeper", "c.txt"),
+            ("nested", "d.txt"),
+            ("", "dir"),
+        ] {
+            let path = tmp.join("test_dir_prune_tree").join(dir).join(name);
+            assert_eq!(list_backups(path).unwrap().len(), 1);
+        }
+
+        assert!(tmp.join("test_dir_prune_tree/a.txt").exists());
+        assert_eq!(
+            fs::read_dir(tmp.join("test_dir_prune_tree/#dir-2023-06-27-21-01-10#"))
+                .unwrap()
+                .count(),
+            2
+        );
+    }
+
+    #[test]
+    fn prune_older_than() {
+        let tmp = test_dir();
+
+        let now = Local::now();
+        let mut backups = Vec::new();
+        for days in [40, 31, 29, 1] {
+            let time = now - chrono::Duration::days(days);
+            let path = tmp.join(format!("#test_file.txt-{}#", time.format(DEFAULT_FORMAT)));
+            File::create(&path).unwrap();
+            backups.push(path);
+        }
+
+        let removed = match prune_backups_older_than(
+            tmp.join("test_file.txt"),
+            Duration::from_secs(30 * 24 * 60 * 60),
+        ) {
+            Ok(x) => x,
+            Err(_) => panic!("Pruning failed."),
+        };
+
+        assert_eq!(
+            removed.iter().map(|x| x.path.clone()).collect::<Vec<_>>(),
+            backups[..2]
+        );
+        assert!(removed.iter().all(|x| !x.path.exists()));
+        assert!(removed.iter().all(|x| x.reason == PruneReason::ExceededAge));
+        assert!(removed
+            .iter()
+            .all(|x| now - x.timestamp > chrono::Duration::days(30)));
+        assert_eq!(
+            list::list_backups(tmp.join("test_file.txt")).unwrap(),
+            backups[2..]
+        );
+    }
+
+    #[test]
+    fn retention_keep_zero() {
+        let tmp = test_dir();
+        let mut file = File::create(tmp.join("test_file_retention.txt")).unwrap();
+        file.write_all(b"Some content to test.").unwrap();
+        drop(file);
+
+        match backup_with_retention(tmp.join("test_file_retention.txt"), 0) {
+            Ok(_) => panic!("Backup should have failed, but it was successful."),
+            Err(e) => assert!(matches!(e, BackupError::InvalidArgument(_))),
+        };
+
+        assert!(tmp.join("test_file_retention.txt").exists());
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn windows_reserved_names() {
+        for name in ["CON.txt", "con", "NUL.tar.gz", "LPT1", "com9 .txt", "AUX"] {
+            assert!(matches!(
+                check_windows_name(name),
+                Err(BackupError::InvalidPath(_))
+            ));
+        }
+
+        for name in ["data.txt.", "data.txt ", "data:txt", "da?ta"] {
+            assert!(matches!(
+                check_windows_name(name),
+                Err(BackupError::InvalidPath(_))
+            ));
+        }
+
+        for name in [
+            "#CON.txt-2023-06-27-21-01-13#",
+            "CONSOLE.txt",
+            "data.txt",
+            "COM10",
+        ] {
+            assert!(check_windows_name(name).is_ok());
+        }
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn windows_reserved_backup_name() {
+        // `CON.txt` can not be created on Windows, so a reserved name is produced using an empty prefix
+        let tmp = test_dir();
+        let mut file = File::create(tmp.join("CON_test.txt")).unwrap();
+        file.write_all(b"Some content to test.").unwrap();
+        drop(file);
+
+        match BackupOptions::new()
+            .prefix("CON.")
+            .suffix("")
+            .backup(tmp.join("CON_test.txt"))
+        {
+            Ok(_) => panic!("Backup should have failed, but it was successful."),
+            Err(e) => assert_eq!(e.kind(), ErrorKind::InvalidInput),
+        };
+
+        assert!(tmp.join("CON_test.txt").exists());
+    }
+
+    #[test]
+    fn nonexistent() {
+        match backup("nonexistent.txt") {
+            Ok(_) => panic!("Backup should have failed, but it was successful."),
+            Err(e) => assert_eq!(e.to_string(), "Path does not exist."),
+        };
+    }
+
+    #[test]
+    fn root() {
+        match backup("/") {
+            Ok(_) => panic!("Backup should have failed, but it was successful."),
+            Err(e) => assert_eq!(e.to_string(), "Path is root."),
+        };
+    }
+
+    #[test]
+    fn empty() {
+        match backup("") {
+            Ok(_) => panic!("Backup should have failed, but it was successful."),
+            Err(e) => assert_eq!(e.to_string(), "Path does not exist."),
+        };
+    }
+
+    #[test]
+    fn error_variants() {
+        assert!(matches!(
+            backup("nonexistent.txt"),
+            Err(BackupError::NotFound)
+        ));
+        assert!(matches!(backup("/"), Err(BackupError::IsRoot)));
+        assert!(matches!(backup(".."), Err(BackupError::EndsInDotDot)));
+
+        let error: std::io::Error = backup("nonexistent.txt").unwrap_err().into();
+        assert_eq!(error.kind(), ErrorKind::NotFound);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn file_not_utf8() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let tmp = test_dir();
+        let path = &tmp.join(OsStr::from_bytes(b"test_file_\xff.txt"));
+        let mut file = File::create(path).unwrap();
+        file.write_all(b"Some content to test.").unwrap();
+
+        let backup = match backup(path) {
+            Ok(x) => x,
+            Err(_) => panic!("Backup failed."),
+        };
+
+        let name = backup.file_name().unwrap().as_bytes();
+        assert!(name.starts_with(b"#test_file_\xff.txt-"));
+        assert!(name.ends_with(b"#"));
+        assert!(!path.exists());
+
+        assert_eq!(list::list_backups(path).unwrap(), vec![backup.clone()]);
+        assert!(list::parse_backup_time(&backup).is_some());
+
+        let mut content = String::new();
+        let mut read = File::open(&backup).unwrap();
+        read.read_to_string(&mut content).unwrap();
+        assert_eq!(content, "Some content to test.");
+    }
+
+    #[test]
+    fn many() {
+        let tmp = test_dir();
+        for i in 0..3 {
+            let mut file = File::create(tmp.join(format!("test_file_many{}.txt", i))).unwrap();
+            file.write_all(format!("Unique string for file {}", i).as_bytes())
+                .unwrap();
+        }
+
+        let paths = [
+            "test_file_many0.txt",
+            "test_file_many1.txt",
+            "test_file_many2.txt",
+        ]
+        .map(|x| tmp.join(x));
+        let backups = match backup_many(&paths) {
+            Ok(x) => x,
+            Err(_) => panic!("Backup failed."),
+        };
+
+        assert_eq!(backups.len(), 3);
+        for (i, path) in backups.iter().enumerate() {
+            let name = path.file_name().unwrap().to_str().unwrap();
+            assert!(name.starts_with(&format!("#test_file_many{}.txt-", i)));
+            assert!(!paths[i].exists());
+
+            let content = fs::read_to_string(path).unwrap();
+            assert_eq!(content, format!("Unique string for file {}", i));
+        }
+    }
+
+    #[test]
+    fn many_rollback() {
+        let tmp = test_dir();
+        File::create(tmp.join("test_file_many_rollback0.txt")).unwrap();
+        File::create(tmp.join("test_file_many_rollback2.txt")).unwrap();
+
+        match backup_many(&[
+            tmp.join("test_file_many_rollback0.txt"),
+            tmp.join("test_file_many_rollback1.txt"),
+            tmp.join("test_file_many_rollback2.txt"),
+        ]) {
+            Ok(_) => panic!("Backup should have failed, but it was successful."),
+            Err(e) => assert!(matches!(e, BackupError::NotFound)),
+        }
+
+        // the first file has been restored, the third file has never been backed up
+        for path in [
+            tmp.join("test_file_many_rollback0.txt"),
+            tmp.join("test_file_many_rollback2.txt"),
+        ] {
+            assert!(path.is_file());
+            assert!(list::list_backups(path).unwrap().is_empty());
+        }
+
+        // a file created at the original path in the meantime is not overwritten
+        let path = &tmp.join("test_file_many_rollback0.txt");
+        fs::write(path, "Old content.").unwrap();
+        let backup = backup(path).unwrap();
+        fs::write(path, "New content.").unwrap();
+        rename_back(&backup, path);
+        assert_eq!(fs::read_to_string(path).unwrap(), "New content.");
+        assert_eq!(fs::read_to_string(&backup).unwrap(), "Old content.");
+    }
+
+    #[test]
+    fn file_wrapped() {
+        let tmp = test_dir();
+        fs::create_dir(tmp.join("test_dir_wrapped")).unwrap();
+
+        let mut backups = Vec::new();
+        for i in 0..3 {
+            let mut file = File::create(tmp.join("test_dir_wrapped/test_file.txt")).unwrap();
+            file.write_all(format!("Unique string for file {}", i).as_bytes())
+                .unwrap();
+
+            let backup =
+                match backup_wrapped(tmp.join("test_dir_wrapped/test_file.txt"), "bak_", ".old") {
+                    Ok(x) => x,
+                    Err(_) => panic!("Backup failed."),
+                };
+
+            let name = backup.file_name().unwrap().to_str().unwrap();
+            assert!(name.starts_with("bak_test_file.txt-"));
+            assert!(name.ends_with(".old"));
+            assert!(list::parse_backup_time_wrapped(&backup, "bak_", ".old").is_some());
+            assert!(list::parse_backup_time(&backup).is_none());
+
+            backups.push(backup);
+        }
+
+        let listed =
+            list::list_backups_wrapped(tmp.join("test_dir_wrapped/test_file.txt"), "bak_", ".old")
+                .unwrap();
+        assert_eq!(listed.len(), backups.len());
+        assert!(backups.iter().all(|x| listed.contains(x)));
+        assert!(
+            list::list_backups(tmp.join("test_dir_wrapped/test_file.txt"))
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn file_wrapped_empty() {
+        let tmp = test_dir();
+        File::create(tmp.join("test_file_wrapped_empty.txt")).unwrap();
+
+        let backup = match backup_wrapped(tmp.join("test_file_wrapped_empty.txt"), "", "") {
+            Ok(x) => x,
+            Err(_) => panic!("Backup failed."),
+        };
+
+        let name = backup.file_name().unwrap().to_str().unwrap();
+        assert!(name.starts_with("test_file_wrapped_empty.txt-"));
+        assert!(list::parse_backup_time_wrapped(&backup, "", "").is_some());
+    }
+
+    #[test]
+    fn file_hook() {
+        let tmp = test_dir();
+        let mut file = File::create(tmp.join("test_file_hook.txt")).unwrap();
+        file.write_all(b"Some content to test.").unwrap();
+
+        let mut called = false;
+        let backup = match backup_with_hook(tmp.join("test_file_hook.txt"), |original, backup| {
+            assert_eq!(original, tmp.join("test_file_hook.txt"));
+            assert!(original.exists());
+            assert!(!backup.exists());
+            called = true;
+            Ok(())
+        }) {
+            Ok(x) => x,
+            Err(_) => panic!("Backup failed."),
+        };
+
+        assert!(called);
+        assert!(!tmp.join("test_file_hook.txt").exists());
+
+        let content = fs::read_to_string(&backup).unwrap();
+        assert_eq!(content, "Some content to test.");
+    }
+
+    #[test]
+    fn file_hook_abort() {
+        let tmp = test_dir();
+        let mut file = File::create(tmp.join("test_file_hook_abort.txt")).unwrap();
+        file.write_all(b"Some content to test.").unwrap();
+
+        match backup_with_hook(tmp.join("test_file_hook_abort.txt"), |_, _| {
+            Err(std::io::Error::other("Aborted."))
+        }) {
+            Ok(_) => panic!("Backup should have failed, but it was successful."),
+            Err(e) => assert_eq!(e.to_string(), "Aborted."),
+        }
+
+        let content = fs::read_to_string(tmp.join("test_file_hook_abort.txt")).unwrap();
+        assert_eq!(content, "Some content to test.");
+        assert!(list::list_backups(tmp.join("test_file_hook_abort.txt"))
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn file_checked() {
+        let tmp = test_dir();
+        File::create(tmp.join("test_file_checked.txt")).unwrap();
+
+        let backup = match backup_checked(tmp.join("test_file_checked.txt")) {
+            Ok(x) => x,
+            Err(_) => panic!("Backup failed."),
+        };
+
+        match backup_checked(&backup) {
+            Ok(_) => panic!("Backup should have failed, but it was successful."),
+            Err(e) => assert!(matches!(e, BackupError::IsBackup)),
+        }
+
+        assert!(backup.is_file());
+    }
+
+    #[test]
+    fn file_hardlink() {
+        let tmp = test_dir();
+        let mut file = File::create(tmp.join("test_file_hardlink.txt")).unwrap();
+        file.write_all(b"Some content to test.").unwrap();
+
+        let backup = match backup_hardlink(tmp.join("test_file_hardlink.txt")) {
+            Ok(x) => x,
+            Err(_) => panic!("Backup failed."),
+        };
+
+        let name = backup.file_name().unwrap().to_str().unwrap();
+        assert!(name.starts_with("#test_file_hardlink.txt-"));
+
+        // content written to the original is visible in the backup
+        file.write_all(b" More content.").unwrap();
+        drop(file);
+
+        let content = fs::read_to_string(tmp.join("test_file_hardlink.txt")).unwrap();
+        assert_eq!(content, "Some content to test. More content.");
+        let content = fs::read_to_string(&backup).unwrap();
+        assert_eq!(content, "Some content to test. More content.");
+    }
+
+    #[test]
+    fn directory_hardlink() {
+        let tmp = test_dir();
+        fs::create_dir(tmp.join("test_dir_hardlink")).unwrap();
+
+        match backup_hardlink(tmp.join("test_dir_hardlink")) {
+            Ok(_) => panic!("Backup should have failed, but it was successful."),
+            Err(e) => assert!(matches!(e, BackupError::Unsupported(_))),
+        }
+
+        assert!(list::list_backups(tmp.join("test_dir_hardlink"))
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn rename_no_overwrite() {
+        let tmp = test_dir();
+        let mut file = File::create(tmp.join("test_file_noreplace1.txt")).unwrap();
+        file.write_all(b"Some content to test.").unwrap();
+        let mut file = File::create(tmp.join("test_file_noreplace2.txt")).unwrap();
+        file.write_all(b"Some other content.").unwrap();
+        drop(file);
+
+        match rename_noreplace(
+            &tmp.join("test_file_noreplace1.txt"),
+            &tmp.join("test_file_noreplace2.txt"),
+        ) {
+            Ok(_) => panic!("Rename should have failed, but it was successful."),
+            Err(e) => assert_eq!(e.kind(), ErrorKind::AlreadyExists),
+        }
+
+        let content = fs::read_to_string(tmp.join("test_file_noreplace2.txt")).unwrap();
+        assert_eq!(content, "Some other content.");
+    }
+
+    #[test]
+    fn file_clock() {
+        let tmp = test_dir();
+        let time = Local.with_ymd_and_hms(2023, 6, 27, 21, 1, 13).unwrap();
+
+        let mut backups = Vec::new();
+        for i in 0..2 {
+            let mut file = File::create(tmp.join("test_file_clock.txt")).unwrap();
+            file.write_all(format!("Unique string for file {}", i).as_bytes())
+                .unwrap();
+
+            let backup = match backup_with_clock(
+                tmp.join("test_file_clock.txt"),
+                &clock::FixedClock(time),
+            ) {
+                Ok(x) => x,
+                Err(_) => panic!("Backup failed."),
+            };
+
+            backups.push(backup);
+        }
+
+        assert_eq!(
+            backups,
+            vec![
+                tmp.join("#test_file_clock.txt-2023-06-27-21-01-13#"),
+                tmp.join("#test_file_clock.txt-2023-06-27-21-01-13-0#"),
+            ]
+        );
+
+        for (i, path) in backups.iter().enumerate() {
+            let content = fs::read_to_string(path).unwrap();
+            assert_eq!(content, format!("Unique string for file {}", i));
+            fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlink() {
+        let tmp = test_dir();
+        fs::create_dir(tmp.join("test_dir_symlink")).unwrap();
+        let mut file = File::create(tmp.join("test_dir_symlink/test_file.txt")).unwrap();
+        file.write_all(b"Some content to test.").unwrap();
+        std::os::unix::fs::symlink("test_file.txt", tmp.join("test_dir_symlink/test_link.txt"))
+            .unwrap();
+
+        // the link itself is backed up
+        let backup = match backup_follow_symlinks(tmp.join("test_dir_symlink/test_link.txt"), false)
+        {
+            Ok(x) => x,
+            Err(_) => panic!("Backup failed."),
+        };
+
+        let name = backup.file_name().unwrap().to_str().unwrap();
+        assert!(name.starts_with("#test_link.txt-"));
+        assert!(fs::symlink_metadata(&backup).unwrap().is_symlink());
+        assert!(fs::symlink_metadata(tmp.join("test_dir_symlink/test_link.txt")).is_err());
+
+        let content = fs::read_to_string(&backup).unwrap();
+        assert_eq!(content, "Some content to test.");
+
+        // the target of the link is backed up
+        std::os::unix::fs::symlink("test_file.txt", tmp.join("test_dir_symlink/test_link.txt"))
+            .unwrap();
+        let backup = match backup_follow_symlinks(tmp.join("test_dir_symlink/test_link.txt"), true)
+        {
+            Ok(x) => x,
+            Err(_) => panic!("Backup failed."),
+        };
+
+        let name = backup.file_name().unwrap().to_str().unwrap();
+        assert!(name.starts_with("#test_file.txt-"));
+        assert!(backup.is_absolute());
+        assert!(!fs::symlink_metadata(&backup).unwrap().is_symlink());
+        assert!(
+            fs::symlink_metadata(tmp.join("test_dir_symlink/test_link.txt"))
+                .unwrap()
+                .is_symlink()
+        );
+        assert!(tmp.join("test_dir_symlink/test_file.txt").is_file());
+
+        let content = fs::read_to_string(&backup).unwrap();
+        assert_eq!(content, "Some content to test.");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn broken_symlink() {
+        let tmp = test_dir();
+        std::os::unix::fs::symlink("nonexistent.txt", tmp.join("test_link_broken.txt")).unwrap();
+
+        match backup_follow_symlinks(tmp.join("test_link_broken.txt"), true) {
+            Ok(_) => panic!("Backup should have failed, but it was successful."),
+            Err(e) => assert!(matches!(e, BackupError::NotFound)),
+        }
+
+        let backup = match backup_follow_symlinks(tmp.join("test_link_broken.txt"), false) {
+            Ok(x) => x,
+            Err(_) => panic!("Backup failed."),
+        };
+        assert!(fs::symlink_metadata(&backup).unwrap().is_symlink());
+    }
+
+    #[test]
+    fn name() {
+        let tmp = test_dir();
+        let time = Local.with_ymd_and_hms(2023, 6, 27, 21, 1, 13).unwrap();
+
+        assert_eq!(
+            backup_name("test_file_name.txt", time).unwrap(),
+            Path::new("#test_file_name.txt-2023-06-27-21-01-13#")
+        );
+        assert_eq!(
+            backup_name("test_dir_name/test_file.txt", time).unwrap(),
+            Path::new("test_dir_name/#test_file.txt-2023-06-27-21-01-13#")
+        );
+        assert!(matches!(backup_name("/", time), Err(BackupError::IsRoot)));
+
+        // the name is not affected by existing files
+        File::create(tmp.join("test_file_name2.txt")).unwrap();
+        let expected = backup_name(tmp.join("test_file_name2.txt"), time).unwrap();
+        let backup =
+            backup_with_clock(tmp.join("test_file_name2.txt"), &clock::FixedClock(time)).unwrap();
+        assert_eq!(backup, expected);
+        assert_eq!(
+            backup_name(tmp.join("test_file_name2.txt"), time).unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn name_too_long() {
+        let tmp = test_dir();
+        // the filename itself is allowed, but the name of the backup is not
+        let path = tmp.join(format!("test_file_{}.txt", "x".repeat(230)));
+        File::create(&path).unwrap();
+
+        match backup(&path) {
+            Ok(_) => panic!("Backup should have failed, but it was successful."),
+            Err(e) => {
+                assert!(matches!(e, BackupError::NameTooLong));
+                assert_eq!(e.kind(), ErrorKind::InvalidInput);
+                assert_eq!(
+                    e.to_string(),
+                    "Resulting backup name exceeds filesystem limit."
+                );
+            }
+        }
+
+        assert!(Path::new(&path).is_file());
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn long_path() {
+        let tmp = test_dir();
+        let mut dir = tmp.join("test_dir_long_path");
+        for i in 0..6 {
+            dir.push(format!("{}_{}", "d".repeat(40), i));
+        }
+        fs::create_dir_all(&dir).unwrap();
+
+        let path = dir.join("test_file.txt");
+        let mut file = File::create(&path).unwrap();
+        file.write_all(b"Some content to test.").unwrap();
+        drop(file);
+
+        let backup = match backup(&path) {
+            Ok(x) => x,
+            Err(_) => panic!("Backup failed."),
+        };
+
+        assert!(backup.as_os_str().to_string_lossy().starts_with(r"\\?\"));
+        assert_eq!(
+            fs::read_to_string(&backup).unwrap(),
+            "Some content to test."
+        );
+        assert!(!path.exists());
+
+        // short paths are not converted
+        assert_eq!(
+            extend_long_path(PathBuf::from("data.txt")),
+            Path::new("data.txt")
+        );
+    }
+
+    #[test]
+    fn write_via_temp_cleanup() {
+        let tmp = test_dir();
+        // a failed write leaves neither the target nor the temporary file behind
+        let target = &tmp.join("test_file_temp_write.txt");
+        let result = write_via_temp(target, |temp| {
+            fs::write(temp, "Partial content.")?;
+            Err(std::io::Error::other("Write failed."))
+        });
+
+        assert!(result.is_err());
+        assert!(!target.exists());
+        assert!(!temp_path(target).exists());
+
+        // a successful write only leaves the target behind
+        write_via_temp(target, |temp| fs::write(temp, "Full content.")).unwrap();
+        assert_eq!(fs::read_to_string(target).unwrap(), "Full content.");
+        assert!(!temp_path(target).exists());
+
+        // the existing target is not overwritten
+        let result = write_via_temp(target, |temp| fs::write(temp, "Other content."));
+        assert_eq!(result.unwrap_err().kind(), ErrorKind::AlreadyExists);
+        assert_eq!(fs::read_to_string(target).unwrap(), "Full content.");
+        assert!(!temp_path(target).exists());
+    }
+
+    #[test]
+    fn file_copy_no_temp() {
+        let tmp = test_dir();
+        fs::write(tmp.join("test_file_copy_temp.txt"), "Some content to test.").unwrap();
+
+        let backup = match backup_copy(tmp.join("test_file_copy_temp.txt")) {
+            Ok(x) => x,
+            Err(_) => panic!("Backup failed."),
+        };
+
+        assert_eq!(
+            fs::read_to_string(&backup).unwrap(),
+            "Some content to test."
+        );
+        assert!(!temp_path(&backup).exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn not_writable() {
+        let tmp = test_dir();
+        use std::os::unix::fs::PermissionsExt;
+
+        fs::create_dir(tmp.join("test_dir_not_writable")).unwrap();
+        File::create(tmp.join("test_dir_not_writable/test_file.txt")).unwrap();
+        fs::set_permissions(
+            tmp.join("test_dir_not_writable"),
+            fs::Permissions::from_mode(0o555),
+        )
+        .unwrap();
+
+        // privileged users can write into read-only directories
+        let privileged = File::create(tmp.join("test_dir_not_writable/probe")).is_ok();
+
+        let result = backup(tmp.join("test_dir_not_writable/test_file.txt"));
+        fs::set_permissions(
+            tmp.join("test_dir_not_writable"),
+            fs::Permissions::from_mode(0o755),
+        )
+        .unwrap();
+
+        if !privileged {
+            match result {
+                Ok(_) => panic!("Backup should have failed, but it was successful."),
+                Err(e) => {
+                    assert_eq!(e.kind(), ErrorKind::PermissionDenied);
+                    assert_eq!(
+                        e.to_string(),
+                        "Directory 'test_dir_not_writable' is not writable."
+                    );
+                }
+            }
+
+            assert!(tmp.join("test_dir_not_writable/test_file.txt").is_file());
+        }
+    }
+
+    #[test]
+    fn file_and_replace() {
+        let tmp = test_dir();
+        fs::write(tmp.join("test_file_replace.txt"), "Old content.").unwrap();
+
+        let backup = match backup_and_replace(tmp.join("test_file_replace.txt"), b"New content.") {
+            Ok(x) => x,
+            Err(_) => panic!("Backup failed."),
+        };
+
+        assert_eq!(fs::read_to_string(&backup).unwrap(), "Old content.");
+        assert_eq!(
+            fs::read_to_string(tmp.join("test_file_replace.txt")).unwrap(),
+            "New content."
+        );
+        assert!(!temp_path(&tmp.join("test_file_replace.txt")).exists());
+    }
+
+    #[test]
+    fn file_and_replace_restores() {
+        let tmp = test_dir();
+        fs::write(tmp.join("test_file_replace2.txt"), "Old content.").unwrap();
+        // the temporary file is taken, so the new content can not be written
+        fs::create_dir(tmp.join("test_file_replace2.txt.tmp")).unwrap();
+
+        match backup_and_replace(tmp.join("test_file_replace2.txt"), b"New content.") {
+            Ok(_) => panic!("Backup should have failed, but it was successful."),
+            Err(e) => assert_eq!(e.kind(), ErrorKind::AlreadyExists),
+        }
+
+        // the original file has been restored
+        assert_eq!(
+            fs::read_to_string(tmp.join("test_file_replace2.txt")).unwrap(),
+            "Old content."
+        );
+        assert!(list_backups(tmp.join("test_file_replace2.txt"))
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn directory_and_replace() {
+        let tmp = test_dir();
+        fs::create_dir(tmp.join("test_dir_replace")).unwrap();
+
+        match backup_and_replace(tmp.join("test_dir_replace"), b"New content.") {
+            Ok(_) => panic!("Backup should have failed, but it was successful."),
+            Err(e) => assert!(matches!(e, BackupError::Unsupported(_))),
+        }
+
+        assert!(tmp.join("test_dir_replace").is_dir());
+    }
+
+    #[test]
+    fn file_or_create() {
+        let tmp = test_dir();
+        fs::write(tmp.join("test_file_or_create.txt"), "Some content to test.").unwrap();
+
+        let backup = match backup_or_create(tmp.join("test_file_or_create.txt")) {
+            Ok(Some(x)) => x,
+            Ok(None) => panic!("Backup should have been created."),
+            Err(_) => panic!("Backup failed."),
+        };
+
+        assert_eq!(
+            fs::read_to_string(&backup).unwrap(),
+            "Some content to test."
+        );
+        // the original is recreated empty
+        assert!(tmp.join("test_file_or_create.txt").is_file());
+        assert_eq!(
+            fs::read(tmp.join("test_file_or_create.txt")).unwrap().len(),
+            0
+        );
+    }
+
+    #[test]
+    fn nonexistent_or_create() {
+        let tmp = test_dir();
+        let path = &tmp.join("test_file_or_create2.txt");
+
+        match backup_or_create(path) {
+            Ok(None) => (),
+            Ok(Some(_)) => panic!("Backup of a nonexistent file should not be created."),
+            Err(_) => panic!("Backup failed."),
+        }
+
+        assert!(path.is_file());
+        assert_eq!(fs::read(path).unwrap().len(), 0);
+        assert!(list_backups(path).unwrap().is_empty());
+    }
+
+    #[test]
+    fn directory_or_create() {
+        let tmp = test_dir();
+        fs::create_dir(tmp.join("test_dir_or_create")).unwrap();
+
+        match backup_or_create(tmp.join("test_dir_or_create")) {
+            Ok(_) => panic!("Backup should have failed, but it was successful."),
+            Err(e) => assert!(matches!(e, BackupError::Unsupported(_))),
+        }
+
+        assert!(tmp.join("test_dir_or_create").is_dir());
+    }
+
+    #[test]
+    fn file_with_separator() {
+        let tmp = test_dir();
+        fs::write(tmp.join("test_file_separator.txt"), "Some content to test.").unwrap();
+
+        let before = Local::now().with_nanosecond(0).unwrap();
+        let backup = match backup_with_separator(tmp.join("test_file_separator.txt"), "@@") {
+            Ok(x) => x,
+            Err(_) => panic!("Backup failed."),
+        };
+        let after = Local::now();
+
+        let name = backup.file_name().unwrap().to_str().unwrap();
+        assert!(name.starts_with("#test_file_separator.txt@@"));
+        assert_eq!(
+            fs::read_to_string(&backup).unwrap(),
+            "Some content to test."
+        );
+
+        // the name can be parsed back
+        let time = parse_backup_time_with_separator(&backup, "@@").unwrap();
+        assert!(time >= before && time <= after);
+        assert!(parse_backup_time(&backup).is_none());
+    }
+
+    #[test]
+    fn file_with_invalid_separator() {
+        let tmp = test_dir();
+        File::create(tmp.join("test_file_separator2.txt")).unwrap();
+
+        match backup_with_separator(tmp.join("test_file_separator2.txt"), "/") {
+            Ok(_) => panic!("Backup should have failed, but it was successful."),
+            Err(e) => assert!(matches!(e, BackupError::InvalidPath(_))),
+        }
+
+        assert!(tmp.join("test_file_separator2.txt").is_file());
+    }
+
+    #[test]
+    fn file_move() {
+        let tmp = test_dir();
+        fs::write(tmp.join("test_file_move.txt"), "Some content to test.").unwrap();
+
+        let backup = match backup_move(tmp.join("test_file_move.txt")) {
+            Ok(x) => x,
+            Err(_) => panic!("Backup failed."),
+        };
+
+        assert!(!tmp.join("test_file_move.txt").exists());
+        assert!(is_backup_name(backup.file_name().unwrap()));
+        assert_eq!(
+            fs::read_to_string(&backup).unwrap(),
+            "Some content to test."
+        );
+    }
+
+    #[test]
+    fn move_across_devices() {
+        let tmp = test_dir();
+        fs::create_dir_all(tmp.join("original/nested")).unwrap();
+        fs::write(tmp.join("original/a.txt"), "File A.").unwrap();
+        fs::write(tmp.join("original/nested/b.txt"), "File B.").unwrap();
+
+        // simulate a rename across filesystems
+        let source = &tmp.join("original");
+        let target = &tmp.join("backup");
+        move_path_with(source, target, |_, _| {
+            Err(std::io::Error::from(ErrorKind::CrossesDevices))
+        })
+        .unwrap();
+
+        assert!(!source.exists());
+        assert_eq!(fs::read_to_string(target.join("a.txt")).unwrap(), "File A.");
+        assert_eq!(
+            fs::read_to_string(target.join("nested/b.txt")).unwrap(),
+            "File B."
+        );
+        assert!(!temp_path(target).exists());
+
+        // other errors are not handled
+        fs::write(source, "Original.").unwrap();
+        let result = move_path_with(source, &tmp.join("other"), |_, _| {
+            Err(std::io::Error::from(ErrorKind::PermissionDenied))
+        });
+        assert_eq!(result.unwrap_err().kind(), ErrorKind::PermissionDenied);
+        assert!(source.is_file());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn move_symlinks_across_devices() {
+        let tmp = test_dir();
+        fs::create_dir_all(tmp.join("original_links/nested")).unwrap();
+        fs::create_dir(tmp.join("outside_links")).unwrap();
+        fs::write(tmp.join("outside_links/c.txt"), "File C.").unwrap();
+        fs::write(tmp.join("original_links/a.txt"), "File A.").unwrap();
+        std::os::unix::fs::symlink("nonexistent.txt", tmp.join("original_links/dangling")).unwrap();
+        std::os::unix::fs::symlink("../outside_links", tmp.join("original_links/outside")).unwrap();
+        std::os::unix::fs::symlink("..", tmp.join("original_links/nested/parent")).unwrap();
+
+        // simulate a rename across filesystems
+        let source = &tmp.join("original_links");
+        let target = &tmp.join("backup_links");
+        move_path_with(source, target, |_, _| {
+            Err(std::io::Error::from(ErrorKind::CrossesDevices))
+        })
+        .unwrap();
+
+        assert!(fs::symlink_metadata(source).is_err());
+        assert_eq!(fs::read_to_string(target.join("a.txt")).unwrap(), "File A.");
+        for (link, points_to) in [
+            ("dangling", "nonexistent.txt"),
+            ("outside", "../outside_links"),
+            ("nested/parent", ".."),
+        ] {
+            let link = target.join(link);
+            assert!(fs::symlink_metadata(&link).unwrap().is_symlink());
+            assert_eq!(fs::read_link(&link).unwrap(), Path::new(points_to));
+        }
+
+        // the directory the link points to is neither copied nor removed
+        assert_eq!(
+            fs::read_to_string(tmp.join("outside_links/c.txt")).unwrap(),
+            "File C."
+        );
+        assert!(!temp_path(target).exists());
+
+        // a link to a directory is moved as a link
+        let source = &tmp.join("original_links_dir");
+        std::os::unix::fs::symlink("outside_links", source).unwrap();
+        move_path_with(source, &tmp.join("backup_links_dir"), |_, _| {
+            Err(std::io::Error::from(ErrorKind::CrossesDevices))
+        })
+        .unwrap();
+        assert!(fs::symlink_metadata(source).is_err());
+        assert!(fs::symlink_metadata(tmp.join("backup_links_dir"))
+            .unwrap()
+            .is_symlink());
+        assert!(tmp.join("outside_links/c.txt").is_file());
+    }
+
+    #[test]
+    fn devices() {
+        let tmp = test_dir();
+        fs::create_dir(tmp.join("test_dir_devices")).unwrap();
+        fs::write(
+            tmp.join("test_dir_devices/test_file.txt"),
+            "Some content to test.",
+        )
+        .unwrap();
+
+        let file = &tmp.join("test_dir_devices/test_file.txt");
+        assert_eq!(
+            same_device(file, &tmp.join("test_dir_nonexistent_devices")),
+            None
+        );
+
+        #[cfg(unix)]
+        assert_eq!(same_device(file, &tmp.join("test_dir_devices")), Some(true));
+
+        // procfs is always mounted separately
+        #[cfg(target_os = "linux")]
+        assert_eq!(same_device(file, Path::new("/proc")), Some(false));
+
+        #[cfg(windows)]
+        {
+            assert_eq!(
+                same_device(Path::new(r"C:\data"), Path::new(r"D:\data")),
+                Some(false)
+            );
+            assert_eq!(
+                same_device(Path::new(r"C:\data"), Path::new(r"c:\backups")),
+                None
+            );
+            assert_eq!(
+                same_device(Path::new(r"\\server\share\data"), Path::new(r"C:\data")),
+                Some(false)
+            );
+        }
+
+        // moving the file does not depend on the check
+        move_path(file, &tmp.join("test_dir_devices/moved.txt")).unwrap();
+        assert_eq!(
+            fs::read_to_string(tmp.join("test_dir_devices/moved.txt")).unwrap(),
+            "Some content to test."
+        );
+    }
+
+    #[test]
+    fn file_if_exists() {
+        let tmp = test_dir();
+        match backup_if_exists(tmp.join("test_file_if_exists.txt")) {
+            Ok(None) => (),
+            Ok(Some(_)) => panic!("Backup of a nonexistent file should not be created."),
+            Err(_) => panic!("Backup failed."),
+        }
+
+        fs::write(tmp.join("test_file_if_exists.txt"), "Some content to test.").unwrap();
+
+        let backup = match backup_if_exists(tmp.join("test_file_if_exists.txt")) {
+            Ok(Some(x)) => x,
+            Ok(None) => panic!("Backup should have been created."),
+            Err(_) => panic!("Backup failed."),
+        };
+
+        assert!(!tmp.join("test_file_if_exists.txt").exists());
+        assert_eq!(
+            fs::read_to_string(&backup).unwrap(),
+            "Some content to test."
+        );
+    }
+
+    #[test]
+    fn root_if_exists() {
+        match backup_if_exists("/") {
+            Ok(_) => panic!("Backup should have failed, but it was successful."),
+            Err(e) => assert!(matches!(e, BackupError::IsRoot)),
+        }
+    }
+
+    #[test]
+    fn file_if_larger_than() {
+        let tmp = test_dir();
+        fs::write(tmp.join("test_file_larger.txt"), "Small.").unwrap();
+
+        match backup_if_larger_than(tmp.join("test_file_larger.txt"), 100) {
+            Ok(None) => (),
+            Ok(Some(_)) => panic!("Backup of a small file should not be created."),
+            Err(_) => panic!("Backup failed."),
+        }
+
+        // the size is exactly the threshold
+        match backup_if_larger_than(tmp.join("test_file_larger.txt"), 6) {
+            Ok(None) => (),
+            Ok(Some(_)) => panic!("Backup of a small file should not be created."),
+            Err(_) => panic!("Backup failed."),
+        }
+
+        assert!(tmp.join("test_file_larger.txt").is_file());
+
+        let backup = match backup_if_larger_than(tmp.join("test_file_larger.txt"), 5) {
+            Ok(Some(x)) => x,
+            Ok(None) => panic!("Backup should have been created."),
+            Err(_) => panic!("Backup failed."),
+        };
+
+        assert!(!tmp.join("test_file_larger.txt").exists());
+        assert_eq!(fs::read_to_string(&backup).unwrap(), "Small.");
+    }
+
+    #[test]
+    fn directory_if_larger_than() {
+        let tmp = test_dir();
+        fs::create_dir_all(tmp.join("test_dir_larger/nested")).unwrap();
+        fs::write(tmp.join("test_dir_larger/a.txt"), "12345").unwrap();
+        fs::write(tmp.join("test_dir_larger/nested/b.txt"), "67890").unwrap();
+
+        assert!(backup_if_larger_than(tmp.join("test_dir_larger"), 10)
+            .unwrap()
+            .is_none());
+
+        let backup = backup_if_larger_than(tmp.join("test_dir_larger"), 9)
+            .unwrap()
+            .unwrap();
+        assert!(!tmp.join("test_dir_larger").exists());
+        assert!(backup.join("nested/b.txt").is_file());
+    }
+
+    #[test]
+    fn nonexistent_if_larger_than() {
+        match backup_if_larger_than("nonexistent.txt", 0) {
+            Ok(_) => panic!("Backup should have failed, but it was successful."),
+            Err(e) => assert!(matches!(e, BackupError::NotFound)),
+        }
+    }
+
+    #[test]
+    fn compute_name() {
+        let time = Local.with_ymd_and_hms(2023, 6, 27, 21, 1, 13).unwrap();
+
+        assert_eq!(
+            compute_backup_name("dir", "data.txt", time).unwrap(),
+            Path::new("dir").join("#data.txt-2023-06-27-21-01-13#")
+        );
+        assert_eq!(
+            compute_backup_name("", "data.txt", time).unwrap(),
+            Path::new("#data.txt-2023-06-27-21-01-13#")
+        );
+
+        // the same name is produced by the path-based function
+        assert_eq!(
+            compute_backup_name("dir", "data.txt", time).unwrap(),
+            backup_name(Path::new("dir").join("data.txt"), time).unwrap()
+        );
+
+        // the computed name is recognized as a backup
+        let name = compute_backup_name("dir", "data.txt", time).unwrap();
+        assert!(is_backup_name(name.file_name().unwrap()));
+
+        for filename in ["", "..", ".", "dir/data.txt"] {
+            match compute_backup_name("dir", filename, time) {
+                Ok(_) => panic!("Invalid filename '{}' was accepted.", filename),
+                Err(e) => assert!(matches!(e, BackupError::InvalidArgument(_))),
+            }
+        }
+    }
+
+    #[test]
+    fn directory_copy_with_progress() {
+        let tmp = test_dir();
+        fs::create_dir_all(tmp.join("test_dir_progress/nested")).unwrap();
+        fs::write(tmp.join("test_dir_progress/small.txt"), "Small file.").unwrap();
+        fs::write(
+            tmp.join("test_dir_progress/nested/large.bin"),
+            vec![42u8; 200_000],
+        )
+        .unwrap();
+        File::create(tmp.join("test_dir_progress/nested/empty.txt")).unwrap();
+        let size = 200_000 + "Small file.".len() as u64;
+
+        let mut calls = Vec::new();
+        let backup =
+            match backup_copy_with_progress(tmp.join("test_dir_progress"), |copied, total| {
+                calls.push((copied, total))
+            }) {
+                Ok(x) => x,
+                Err(_) => panic!("Backup failed."),
+            };
+
+        // the progress is reported periodically, not only once per file
+        assert!(calls.len() > 3);
+        assert_eq!(calls.first(), Some(&(0, size)));
+        assert_eq!(calls.last(), Some(&(size, size)));
+        assert!(calls.windows(2).all(|x| x[0].0 <= x[1].0));
+        assert!(calls.iter().all(|&(_, total)| total == size));
+
+        assert!(tmp.join("test_dir_progress").is_dir());
+        assert_eq!(
+            fs::read_to_string(backup.join("small.txt")).unwrap(),
+            "Small file."
+        );
+        assert_eq!(
+            fs::read(backup.join("nested/large.bin")).unwrap(),
+            vec![42u8; 200_000]
+        );
+        assert!(backup.join("nested/empty.txt").is_file());
+    }
+
+    #[test]
+    fn directory_filtered() {
+        let tmp = test_dir();
+        fs::create_dir_all(tmp.join("test_dir_filtered/src")).unwrap();
+        fs::create_dir_all(tmp.join("test_dir_filtered/target/debug")).unwrap();
+        fs::write(tmp.join("test_dir_filtered/src/main.rs"), "fn main() {}").unwrap();
+        fs::write(tmp.join("test_dir_filtered/src/skip.rs"), "Skipped.").unwrap();
+        fs::write(tmp.join("test_dir_filtered/target/debug/app"), "Binary.").unwrap();
+        fs::write(tmp.join("test_dir_filtered/Cargo.toml"), "[package]").unwrap();
+
+        let backup = match backup_directory_filtered(tmp.join("test_dir_filtered"), |entry| {
+            entry == Path::new("target") || entry == Path::new("src/skip.rs")
+        }) {
+            Ok(x) => x,
+            Err(_) => panic!("Backup failed."),
+        };
+
+        assert!(!backup.join("target").exists());
+        assert!(!backup.join("src/skip.rs").exists());
+        assert_eq!(
+            fs::read_to_string(backup.join("src/main.rs")).unwrap(),
+            "fn main() {}"
+        );
+        assert_eq!(
+            fs::read_to_string(backup.join("Cargo.toml")).unwrap(),
+            "[package]"
+        );
+
+        // the original directory is left untouched
+        assert!(tmp.join("test_dir_filtered/target/debug/app").is_file());
+        assert!(tmp.join("test_dir_filtered/src/skip.rs").is_file());
+    }
+
+    #[test]
+    fn file_filtered() {
+        let tmp = test_dir();
+        File::create(tmp.join("test_file_filtered.txt")).unwrap();
+
+        match backup_directory_filtered(tmp.join("test_file_filtered.txt"), |_| false) {
+            Ok(_) => panic!("Backup should have failed, but it was successful."),
+            Err(e) => assert!(matches!(e, BackupError::Unsupported(_))),
+        }
+    }
+
+    #[test]
+    fn file_labeled() {
+        let tmp = test_dir();
+        fs::write(tmp.join("test_file.txt"), "Some content to test.").unwrap();
+
+        let before = Local::now().with_nanosecond(0).unwrap();
+        let labeled = match backup_labeled(tmp.join("test_file.txt"), "pre-deploy") {
+            Ok(x) => x,
+            Err(_) => panic!("Backup failed."),
+        };
+        let after = Local::now();
+
+        let name = labeled.file_name().unwrap().to_str().unwrap();
+        assert!(name.starts_with("#test_file.txt-pre-deploy-"));
+        assert_eq!(
+            fs::read_to_string(&labeled).unwrap(),
+            "Some content to test."
+        );
+
+        let time = parse_backup_time(&labeled).unwrap();
+        assert!(time >= before && time <= after);
+
+        // labeled backups are listed separately from the unlabeled backups
+        fs::write(tmp.join("test_file.txt"), "Other content.").unwrap();
+        let unlabeled = backup(tmp.join("test_file.txt")).unwrap();
+        assert_eq!(
+            list_backups(tmp.join("test_file.txt")).unwrap(),
+            vec![unlabeled]
+        );
+        assert_eq!(
+            list_labeled_backups(tmp.join("test_file.txt"), "pre-deploy").unwrap(),
+            vec![labeled]
+        );
+    }
+
+    #[test]
+    fn file_invalid_label() {
+        let tmp = test_dir();
+        File::create(tmp.join("test_file_labeled2.txt")).unwrap();
+
+        for label in ["", "pre/deploy", "pre#deploy", "pre\\deploy"] {
+            match backup_labeled(tmp.join("test_file_labeled2.txt"), label) {
+                Ok(_) => panic!("Backup should have failed, but it was successful."),
+                Err(e) => {
+                    assert!(matches!(e, BackupError::InvalidArgument(_)));
+                    assert_eq!(e.kind(), ErrorKind::InvalidInput);
+                }
+            }
+        }
+
+        assert!(tmp.join("test_file_labeled2.txt").is_file());
+    }
+
+    #[test]
+    fn directory_trailing_separator() {
+        let tmp = test_dir();
+        fs::create_dir_all(tmp.join("test_dir_slash/a")).unwrap();
+        fs::create_dir_all(tmp.join("test_dir_slash/b")).unwrap();
+
+        let time = Local.with_ymd_and_hms(2023, 6, 27, 21, 1, 13).unwrap();
+        let clock = clock::FixedClock(time);
+
+        let with_slash = backup_with_clock(tmp.join("test_dir_slash/a/"), &clock).unwrap();
+        let without_slash = backup_with_clock(tmp.join("test_dir_slash/b"), &clock).unwrap();
+
+        assert_eq!(
+            with_slash,
+            tmp.join("test_dir_slash/#a-2023-06-27-21-01-13#")
+        );
+        assert_eq!(
+            without_slash,
+            tmp.join("test_dir_slash/#b-2023-06-27-21-01-13#")
+        );
+        assert!(with_slash.is_dir());
+        assert!(!tmp.join("test_dir_slash/a").exists());
+
+        // the original path is reported without the trailing separator
+        fs::create_dir(tmp.join("test_dir_slash/c")).unwrap();
+        let detailed = backup_detailed(tmp.join("test_dir_slash/c/")).unwrap();
+        assert_eq!(detailed.original_path, tmp.join("test_dir_slash/c"));
+        assert!(detailed.was_directory);
+
+        assert_eq!(
+            backup_dry_run(tmp.join("test_dir_slash/#a-2023-06-27-21-01-13#/"))
+                .unwrap()
+                .parent(),
+            Some(tmp.join("test_dir_slash").as_path())
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlink_trailing_separator() {
+        let tmp = test_dir();
+        fs::create_dir_all(tmp.join("target")).unwrap();
+        std::os::unix::fs::symlink("target", tmp.join("link")).unwrap();
+
+        // the link itself is backed up, not the directory it points to
+        let backup = backup(tmp.join("link/")).unwrap();
+        assert!(backup.is_symlink());
+        assert!(tmp.join("target").is_dir());
+        assert!(!tmp.join("link").exists());
+    }
+
+    #[test]
+    fn file_before_extension() {
+        let tmp = test_dir();
+        fs::write(tmp.join("data.txt"), "Some content to test.").unwrap();
+        fs::write(tmp.join(".bashrc"), "Dotfile.").unwrap();
+
+        let before = Local::now().with_nanosecond(0).unwrap();
+        let backup = match backup_with_suffix_before_extension(tmp.join("data.txt")) {
+            Ok(x) => x,
+            Err(_) => panic!("Backup failed."),
+        };
+        let after = Local::now();
+
+        assert_eq!(backup.extension(), Some(OsStr::new("txt")));
+        let name = backup.file_name().unwrap().to_str().unwrap();
+        assert!(name.starts_with("data-"));
+        assert_eq!(
+            fs::read_to_string(&backup).unwrap(),
+            "Some content to test."
+        );
+
+        let time = parse_backup_time(&backup).unwrap();
+        assert!(time >= before && time <= after);
+
+        // dotfiles have no extension
+        let dotfile = backup_with_suffix_before_extension(tmp.join(".bashrc")).unwrap();
+        let name = dotfile.file_name().unwrap().to_str().unwrap();
+        assert!(name.starts_with(".bashrc-"));
+        assert_eq!(dotfile.extension(), None);
+        assert!(parse_backup_time_wrapped(&dotfile, "", "").is_some());
+    }
+
+    #[test]
+    fn file_str() {
+        let tmp = test_dir();
+        File::create(tmp.join("test_file_str.txt")).unwrap();
+
+        let backup = match backup_str(tmp.join("test_file_str.txt")) {
+            Ok(x) => x,
+            Err(_) => panic!("Backup failed."),
+        };
+
+        assert!(backup.contains("test_file_str.txt"));
+        assert!(Path::new(&backup).is_file());
+        assert!(!tmp.join("test_file_str.txt").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn file_str_not_utf8() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let tmp = test_dir();
+        let path = &tmp.join(OsStr::from_bytes(b"test_file_str_\xff.txt"));
+        File::create(path).unwrap();
+
+        match backup_str(path) {
+            Ok(_) => panic!("Backup should have failed, but it was successful."),
+            Err(e) => assert!(matches!(e, BackupError::NotUtf8)),
+        }
+
+        assert!(path.is_file());
+        assert!(list::list_backups(path).unwrap().is_empty());
+    }
+
+    #[test]
+    fn file_subdir() {
+        let tmp = test_dir();
+        let mut file = File::create(tmp.join("test_file.txt")).unwrap();
+        file.write_all(b"Some content to test.").unwrap();
+        drop(file);
+
+        let backup = match backup_to_subdir(tmp.join("test_file.txt"), DEFAULT_SUBDIR) {
+            Ok(x) => x,
+            Err(_) => panic!("Backup failed."),
+        };
+
+        assert!(tmp.join(".backups").is_dir());
+        assert_eq!(backup.parent(), Some(tmp.join(".backups").as_path()));
+        assert!(!tmp.join("test_file.txt").exists());
+        assert_eq!(
+            fs::read_to_string(&backup).unwrap(),
+            "Some content to test."
+        );
+
+        // the subdirectory already exists
+        File::create(tmp.join("test_file.txt")).unwrap();
+        let backup = backup_to_subdir(tmp.join("test_file.txt"), DEFAULT_SUBDIR).unwrap();
+        assert_eq!(backup.parent(), Some(tmp.join(".backups").as_path()));
+
+        // invalid subdirectories
+        File::create(tmp.join("test_file.txt")).unwrap();
+        for subdir in ["", "..", "a/b", "/tmp"] {
+            match backup_to_subdir(tmp.join("test_file.txt"), subdir) {
+                Ok(_) => panic!("Backup should have failed, but it was successful."),
+                Err(e) => assert!(matches!(e, BackupError::InvalidArgument(_)), "{}", subdir),
+            }
+        }
+        assert!(tmp.join("test_file.txt").exists());
+    }
+
+    #[test]
+    fn temp_names() {
+        for name in [
+            "#data.txt-2023-06-27-21-01-13#.tmp",
+            "#data.txt-2023-06-27-21-01-13-45123#.tmp",
+            "#data.txt-pre-deploy-2023-06-27-21-01-13#.tmp",
+            "#output.log-2023-06-27-21-01-13.gz#.tmp",
+            "#dir-2023-06-27-21-01-13#.tar.gz.tmp",
+        ] {
+            assert!(is_temp_backup_name(OsStr::new(name)), "{}", name);
+        }
+
+        for name in [
+            "#data.txt-2023-06-27-21-01-13#",
+            "data.txt.tmp",
+            "#data.txt#.tmp",
+            "#data.txt-2023-06-27#.tmp",
+            "#data.txt-2023-06-27-21-01-13#.tmp.tmp",
+        ] {
+            assert!(!is_temp_backup_name(OsStr::new(name)), "{}", name);
+        }
+    }
+
+    #[test]
+    fn clean_temp() {
+        let tmp = test_dir();
+        fs::create_dir(tmp.join("test_dir_clean_temp")).unwrap();
+        File::create(tmp.join("test_dir_clean_temp/test_file.txt")).unwrap();
+        File::create(tmp.join("test_dir_clean_temp/other.tmp")).unwrap();
+
+        let backup = match backup_copy(tmp.join("test_dir_clean_temp/test_file.txt")) {
+            Ok(x) => x,
+            Err(_) => panic!("Backup failed."),
+        };
+
+        // leftovers of interrupted backups
+        let temp_file =
+            temp_path(&tmp.join("test_dir_clean_temp/#test_file.txt-2023-06-27-21-01-13#"));
+        File::create(&temp_file).unwrap();
+        let temp_dir = temp_path(&tmp.join("test_dir_clean_temp/#dir-2023-06-27-21-01-13#"));
+        fs::create_dir(&temp_dir).unwrap();
+        File::create(temp_dir.join("file.txt")).unwrap();
+
+        let removed = match clean_temp_backups(tmp.join("test_dir_clean_temp")) {
+            Ok(x) => x,
+            Err(_) => panic!("Cleaning failed."),
+        };
+
+        assert_eq!(removed, vec![temp_dir.clone(), temp_file.clone()]);
+        assert!(!temp_file.exists());
+        assert!(!temp_dir.exists());
+        assert!(backup.is_file());
+        assert!(tmp.join("test_dir_clean_temp/test_file.txt").is_file());
+        assert!(tmp.join("test_dir_clean_temp/other.tmp").is_file());
+
+        // nothing else to clean
+        assert!(clean_temp_backups(tmp.join("test_dir_clean_temp"))
+            .unwrap()
+            .is_empty());
     }
 
-    // get the parent directory of the path
-    let parent = match path.as_ref().parent() {
-        Some(x) => match x.to_str() {
-            Some("") => ".",
-            Some(x) => x,
-            None => {
-                return Err(Error::new(
-                    ErrorKind::Unsupported,
-                    "Path is not a valid UTF-8.",
-                ))
-            }
-        },
-        None => return Err(Error::new(ErrorKind::Unsupported, "Path is root.")),
-    };
+    #[test]
+    fn file_collision_info() {
+        let tmp = test_dir();
+        let path = &tmp.join("test_file.txt");
+        File::create(path).unwrap();
 
-    // get the filename from the path
-    let filename = match path.as_ref().file_name() {
-        Some(x) => match x.to_str() {
-            Some(x) => x,
-            None => {
-                return Err(Error::new(
-                    ErrorKind::Unsupported,
-                    "Path is not a valid UTF-8.",
-                ))
-            }
-        },
-        None => return Err(Error::new(ErrorKind::Unsupported, "Path ends in '..'.")),
-    };
+        let (backup, collision) = match backup_with_collision_info(path) {
+            Ok(x) => x,
+            Err(_) => panic!("Backup failed."),
+        };
+        assert!(backup.is_file());
+        assert!(!collision);
 
-    // generate the backup file name with a timestamp
-    let time = Local::now().format("%Y-%m-%d-%H-%M-%S").to_string();
-    let mut backup_name = Path::new(&format!("{}/#{}-{}#", parent, filename, &time)).to_path_buf();
+        // take the names without microseconds for the current and the next second
+        File::create(path).unwrap();
+        let now = Local::now();
+        for time in [now, now + chrono::Duration::seconds(1)] {
+            File::create(backup_name(path, time).unwrap()).unwrap();
+        }
 
-    // if a file with the same name already exists, append microseconds
-    // repeat until the name of the backup is unique
-    while backup_name.exists() {
-        let time = Local::now();
-        let micros = time.timestamp_subsec_micros();
-        let time_fmt = time.format("%Y-%m-%d-%H-%M-%S").to_string();
+        let (backup, collision) = match backup_with_collision_info(path) {
+            Ok(x) => x,
+            Err(_) => panic!("Backup failed."),
+        };
+        assert!(backup.is_file());
+        assert!(collision);
+    }
+
+    #[test]
+    fn file_dated_tree() {
+        let tmp = test_dir();
+        fs::write(tmp.join("test_file.txt"), "Some content to test.").unwrap();
+
+        let backup = match backup_dated_tree(tmp.join("test_file.txt"), tmp.join("backups")) {
+            Ok(x) => x,
+            Err(_) => panic!("Backup failed."),
+        };
 
-        backup_name = Path::new(&format!(
-            "{}/#{}-{}-{}#",
-            parent, filename, &time_fmt, micros
-        ))
-        .to_path_buf();
+        // the directory matches the timestamp of the backup
+        let time = parse_backup_time(&backup).unwrap();
+        let expected = tmp
+            .join("backups")
+            .join(time.format("%Y").to_string())
+            .join(time.format("%m").to_string())
+            .join(time.format("%d").to_string());
+        assert_eq!(backup.parent(), Some(expected.as_path()));
+        assert_eq!(
+            fs::read_to_string(&backup).unwrap(),
+            "Some content to test."
+        );
+        assert!(!tmp.join("test_file.txt").exists());
+
+        let time = Local.with_ymd_and_hms(2024, 6, 7, 21, 1, 13).unwrap();
+        assert_eq!(
+            dated_dir(Path::new("backups"), time),
+            Path::new("backups").join("2024").join("06").join("07")
+        );
     }
 
-    // rename the original file to the backup name
-    match fs::rename(path, &backup_name) {
-        Ok(()) => Ok(backup_name),
-        Err(e) => Err(e),
+    #[test]
+    fn weird_paths() {
+        let tmp = test_dir();
+        // the relative paths are resolved in the test directory
+        let _cwd = set_current_dir(&tmp);
+        fs::create_dir_all(tmp.join("a/b")).unwrap();
+        for name in ["with spaces.txt", "ünïcödé 日本語.txt", " ", "-"] {
+            File::create(tmp.join(name)).unwrap();
+        }
+
+        let long = "x".repeat(1000);
+        let paths = [
+            "",
+            ".",
+            "./",
+            "..",
+            "../",
+            "/",
+            "//",
+            "///..",
+            "a/b/../b",
+            "nonexistent with spaces.txt",
+            "nul\0byte.txt",
+            long.as_str(),
+            "with spaces.txt",
+            "ünïcödé 日本語.txt",
+            " ",
+            "-",
+            "./-/",
+            "a/b/../b",
+            "a/b/..",
+            "a/./b/",
+        ];
+
+        for path in paths {
+            // every path either succeeds or fails with an error
+            let result = std::panic::catch_unwind(|| backup(path));
+            assert!(result.is_ok(), "Backup of '{}' panicked.", path);
+
+            match try_backup(path) {
+                Ok(x) => assert!(x.exists(), "{}", path),
+                Err(e) => assert!(
+                    !matches!(&e, BackupError::Io(x) if x.to_string().starts_with("Backup panicked")),
+                    "{}",
+                    path
+                ),
+            }
+        }
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::fs::{self, File};
-    use std::io::prelude::*;
+    #[test]
+    fn try_backup_nonexistent() {
+        match try_backup("nonexistent.txt") {
+            Ok(_) => panic!("Backup should have failed, but it was successful."),
+            Err(e) => assert!(matches!(e, BackupError::NotFound)),
+        }
+    }
 
     #[test]
-    fn file() {
-        let mut file = File::create("test_file1.txt").unwrap();
-        file.write_all(b"Some content to test.").unwrap();
+    fn reflink() {
+        let tmp = test_dir();
+        fs::create_dir(tmp.join("test_dir_reflink")).unwrap();
+        fs::write(
+            tmp.join("test_dir_reflink/test_file.txt"),
+            "Some content to test.",
+        )
+        .unwrap();
 
-        let backup = match backup("test_file1.txt") {
+        // most filesystems used for testing do not support reflinks, so the fallback is used
+        let backup = match backup_reflink(tmp.join("test_dir_reflink/test_file.txt")) {
             Ok(x) => x,
             Err(_) => panic!("Backup failed."),
         };
 
-        drop(file);
+        assert_eq!(backup.parent().unwrap(), tmp.join("test_dir_reflink"));
+        assert_eq!(
+            fs::read_to_string(&backup).unwrap(),
+            "Some content to test."
+        );
 
-        let mut content = String::new();
-        let mut read = File::open(&backup).unwrap();
-        read.read_to_string(&mut content).unwrap();
+        // the original is left in place and is independent of the backup
+        fs::write(&backup, "Modified content.").unwrap();
+        assert_eq!(
+            fs::read_to_string(tmp.join("test_dir_reflink/test_file.txt")).unwrap(),
+            "Some content to test."
+        );
 
-        assert_eq!(content, "Some content to test.");
+        // directories are also supported
+        let backup_dir = match backup_reflink(tmp.join("test_dir_reflink")) {
+            Ok(x) => x,
+            Err(_) => panic!("Backup failed."),
+        };
 
-        fs::remove_file(backup).unwrap();
+        assert!(tmp.join("test_dir_reflink").is_dir());
+        assert_eq!(
+            fs::read_to_string(backup_dir.join("test_file.txt")).unwrap(),
+            "Some content to test."
+        );
+        assert!(!temp_path(&backup).exists());
     }
 
     #[test]
-    fn file_multiple_backups() {
-        let mut backups = Vec::new();
-        for i in 0..20 {
-            let mut file = File::create("test_file2.txt").unwrap();
-            let text = format!("Unique string for file {}", i);
-            file.write_all(text.as_bytes()).unwrap();
+    fn locked() {
+        let tmp = test_dir();
+        fs::create_dir(tmp.join("test_dir_locked")).unwrap();
+        fs::write(
+            tmp.join("test_dir_locked/test_file.txt"),
+            "Some content to test.",
+        )
+        .unwrap();
 
-            let backup = match backup("test_file2.txt") {
-                Ok(x) => x,
-                Err(_) => panic!("Backup failed."),
-            };
+        // a writer holds an exclusive lock on the file
+        let writer = File::open(tmp.join("test_dir_locked/test_file.txt")).unwrap();
+        writer.lock().unwrap();
 
-            backups.push(backup);
+        match backup_locked(
+            tmp.join("test_dir_locked/test_file.txt"),
+            Duration::from_millis(50),
+        ) {
+            Ok(_) => panic!("Backup should have failed, but it was successful."),
+            Err(e) => {
+                assert!(matches!(e, BackupError::Locked));
+                assert_eq!(e.kind(), ErrorKind::WouldBlock);
+            }
         }
 
-        for (i, path) in backups.iter().enumerate() {
-            let mut content = String::new();
-            let mut read = File::open(&path).unwrap();
+        assert!(tmp.join("test_dir_locked/test_file.txt").exists());
 
-            read.read_to_string(&mut content).unwrap();
+        // the backup waits until the writer releases the lock
+        let start = std::time::Instant::now();
+        let handle = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(100));
+            writer.unlock().unwrap();
+        });
 
-            let test = format!("Unique string for file {}", i);
-            assert_eq!(content, test);
+        let backup = match backup_locked(
+            tmp.join("test_dir_locked/test_file.txt"),
+            Duration::from_secs(10),
+        ) {
+            Ok(x) => x,
+            Err(_) => panic!("Backup failed."),
+        };
 
-            fs::remove_file(path).unwrap();
+        handle.join().unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(100));
+        assert!(!tmp.join("test_dir_locked/test_file.txt").exists());
+        assert_eq!(fs::read_to_string(backup).unwrap(), "Some content to test.");
+
+        match backup_locked(tmp.join("test_dir_locked"), Duration::ZERO) {
+            Ok(_) => panic!("Backup should have failed, but it was successful."),
+            Err(e) => assert!(matches!(e, BackupError::Unsupported(_))),
         }
     }
 
     #[test]
-    fn file_in_different_directory() {
-        fs::create_dir("test_dir").unwrap();
+    fn all_in_dir() {
+        let tmp = test_dir();
+        fs::create_dir_all(tmp.join("test_dir_all_in_dir/subdir")).unwrap();
+        for name in ["file1.txt", "file2.txt", "file3.txt", "subdir/file4.txt"] {
+            fs::write(tmp.join("test_dir_all_in_dir").join(name), name).unwrap();
+        }
+        // existing backups are not backed up again
+        File::create(tmp.join("test_dir_all_in_dir/#file0.txt-2023-06-27-21-01-13#")).unwrap();
 
-        let mut file = File::create("test_dir/test_file.txt").unwrap();
-        file.write_all(b"Some content to test.").unwrap();
+        let backups = match backup_all_in_dir(tmp.join("test_dir_all_in_dir"), false) {
+            Ok(x) => x,
+            Err(_) => panic!("Backup failed."),
+        };
+
+        assert_eq!(backups.len(), 3);
+        for (backup, name) in backups.iter().zip(["file1.txt", "file2.txt", "file3.txt"]) {
+            assert_eq!(backup.parent().unwrap(), tmp.join("test_dir_all_in_dir"));
+            assert_eq!(fs::read_to_string(backup).unwrap(), name);
+            assert!(!tmp.join("test_dir_all_in_dir").join(name).exists());
+        }
 
-        let backup = match backup("test_dir/test_file.txt") {
+        assert!(tmp.join("test_dir_all_in_dir/subdir/file4.txt").exists());
+        assert_eq!(
+            list_backups(tmp.join("test_dir_all_in_dir/file0.txt"))
+                .unwrap()
+                .len(),
+            1
+        );
+
+        // the subdirectories are traversed, but the created backups are not backed up again
+        let backups = match backup_all_in_dir(tmp.join("test_dir_all_in_dir"), true) {
             Ok(x) => x,
             Err(_) => panic!("Backup failed."),
         };
 
-        drop(file);
+        assert_eq!(backups.len(), 1);
+        assert_eq!(
+            backups[0].parent().unwrap(),
+            tmp.join("test_dir_all_in_dir/subdir")
+        );
+        assert!(!tmp.join("test_dir_all_in_dir/subdir/file4.txt").exists());
+    }
 
-        let mut content = String::new();
-        let mut read = File::open(&backup).unwrap();
-        read.read_to_string(&mut content).unwrap();
+    #[test]
+    fn env_destination() {
+        let tmp = test_dir();
+        fs::create_dir(tmp.join("test_dir_env")).unwrap();
+        fs::write(
+            tmp.join("test_dir_env/test_file.txt"),
+            "Some content to test.",
+        )
+        .unwrap();
 
-        assert_eq!(content, "Some content to test.");
+        // this is the only test modifying the variable
+        std::env::set_var(DIR_ENV_VAR, tmp.join("test_dir_env/backups"));
+        let backup = backup_env(tmp.join("test_dir_env/test_file.txt"));
+        std::env::remove_var(DIR_ENV_VAR);
+
+        let backup = match backup {
+            Ok(x) => x,
+            Err(_) => panic!("Backup failed."),
+        };
 
-        fs::remove_file(&backup).unwrap();
-        fs::remove_dir("test_dir").unwrap();
+        assert_eq!(backup.parent().unwrap(), tmp.join("test_dir_env/backups"));
+        assert_eq!(
+            fs::read_to_string(&backup).unwrap(),
+            "Some content to test."
+        );
+
+        // the parent directory is used if the variable is not set
+        fs::write(tmp.join("test_dir_env/test_file.txt"), "New content.").unwrap();
+        let backup = match backup_env(tmp.join("test_dir_env/test_file.txt")) {
+            Ok(x) => x,
+            Err(_) => panic!("Backup failed."),
+        };
+
+        assert_eq!(backup.parent().unwrap(), tmp.join("test_dir_env"));
+        assert_eq!(fs::read_to_string(&backup).unwrap(), "New content.");
     }
 
     #[test]
-    fn file_multiple_backups_in_different_directory() {
-        fs::create_dir("test_dir2").unwrap();
+    fn write_from_reader() {
+        let tmp = test_dir();
+        fs::create_dir(tmp.join("test_dir_write")).unwrap();
 
-        let mut backups = Vec::new();
-        for i in 0..20 {
-            let mut file = File::create("test_dir2/test_file.txt").unwrap();
-            let text = format!("Unique string for file {}", i);
-            file.write_all(text.as_bytes()).unwrap();
+        let mut reader = std::io::Cursor::new(b"Generated content to test.".to_vec());
+        let backup = match backup_write(tmp.join("test_dir_write"), "report.txt", &mut reader) {
+            Ok(x) => x,
+            Err(_) => panic!("Backup failed."),
+        };
 
-            let backup = match backup("test_dir2/test_file.txt") {
-                Ok(x) => x,
-                Err(_) => panic!("Backup failed."),
-            };
+        assert_eq!(backup.parent(), Some(tmp.join("test_dir_write").as_path()));
+        assert!(is_backup_name(backup.file_name().unwrap()));
+        assert!(backup
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .starts_with("#report.txt-"));
+        assert_eq!(fs::read(&backup).unwrap(), b"Generated content to test.");
 
-            backups.push(backup);
+        // a second backup within the same second gets a unique name
+        let mut reader = std::io::Cursor::new(b"Other content.".to_vec());
+        let other = backup_write(tmp.join("test_dir_write"), "report.txt", &mut reader).unwrap();
+        assert_ne!(backup, other);
+        assert_eq!(fs::read(&other).unwrap(), b"Other content.");
+        assert_eq!(fs::read_dir(tmp.join("test_dir_write")).unwrap().count(), 2);
+
+        for basename in ["", ".", "..", "dir/report.txt"] {
+            match backup_write(tmp.join("test_dir_write"), basename, &mut std::io::empty()) {
+                Ok(_) => panic!("Backup should have failed, but it was successful."),
+                Err(e) => assert!(matches!(e, BackupError::InvalidArgument(_)), "{}", basename),
+            }
         }
 
-        for (i, path) in backups.iter().enumerate() {
-            let mut content = String::new();
-            let mut read = File::open(&path).unwrap();
+        match backup_write(
+            tmp.join("test_dir_write/nonexistent"),
+            "report.txt",
+            &mut std::io::empty(),
+        ) {
+            Ok(_) => panic!("Backup should have failed, but it was successful."),
+            Err(e) => assert!(matches!(e, BackupError::NotFound)),
+        }
+    }
 
-            read.read_to_string(&mut content).unwrap();
+    #[test]
+    fn file_to_writer() {
+        let tmp = test_dir();
+        let path = &tmp.join("test_file_writer.txt");
+        fs::write(path, "Some content to test.").unwrap();
 
-            let test = format!("Unique string for file {}", i);
-            assert_eq!(content, test);
+        let mut content = Vec::new();
+        let bytes = match backup_to_writer(path, &mut content, false) {
+            Ok(x) => x,
+            Err(_) => panic!("Backup failed."),
+        };
 
-            fs::remove_file(path).unwrap();
+        assert_eq!(bytes, 21);
+        assert_eq!(content, b"Some content to test.");
+        assert!(path.exists());
+
+        // the content is appended to the writer and the original is removed
+        let bytes = backup_to_writer(path, &mut content, true).unwrap();
+        assert_eq!(bytes, 21);
+        assert_eq!(content, b"Some content to test.Some content to test.");
+        assert!(!path.exists());
+
+        match backup_to_writer(path, &mut content, false) {
+            Ok(_) => panic!("Backup should have failed, but it was successful."),
+            Err(e) => assert!(matches!(e, BackupError::NotFound)),
         }
 
-        fs::remove_dir("test_dir2").unwrap();
+        match backup_to_writer(&*tmp, &mut content, true) {
+            Ok(_) => panic!("Backup should have failed, but it was successful."),
+            Err(e) => assert!(matches!(e, BackupError::Unsupported(_))),
+        }
+        assert!(tmp.exists());
     }
 
     #[test]
-    fn directory() {
-        fs::create_dir("test_dir3").unwrap();
+    fn file_mtime() {
+        let tmp = test_dir();
+        let path = &tmp.join("test_file_mtime.txt");
+        fs::write(path, "Some content to test.").unwrap();
 
-        let mut file = File::create("test_dir3/test_file.txt").unwrap();
-        file.write_all(b"Some content to test.").unwrap();
+        let time = Local.with_ymd_and_hms(2023, 6, 27, 21, 1, 13).unwrap();
+        filetime::set_file_mtime(path, FileTime::from_unix_time(time.timestamp(), 0)).unwrap();
 
-        let backup = match backup("test_dir3") {
+        let backup = match backup_with_mtime(path) {
             Ok(x) => x,
             Err(_) => panic!("Backup failed."),
         };
 
-        drop(file);
+        assert_eq!(
+            backup,
+            tmp.join("#test_file_mtime.txt-2023-06-27-21-01-13#")
+        );
+        assert_eq!(
+            fs::read_to_string(&backup).unwrap(),
+            "Some content to test."
+        );
+        assert!(!path.exists());
+    }
 
-        let mut content = String::new();
-        let file_in_backup = backup.join(Path::new("test_file.txt"));
-        let mut read = File::open(&file_in_backup).unwrap();
-        read.read_to_string(&mut content).unwrap();
+    #[test]
+    fn file_rate_limited() {
+        let tmp = test_dir();
+        let path = &tmp.join("test_file_rate.txt");
+        let interval = Duration::from_secs(60 * 60);
 
-        assert_eq!(content, "Some content to test.");
+        fs::write(path, "Some content to test.").unwrap();
+        let backup = match backup_rate_limited(path, interval) {
+            Ok(Some(x)) => x,
+            Ok(None) => panic!("Backup should have been created."),
+            Err(_) => panic!("Backup failed."),
+        };
+        assert!(!path.exists());
+
+        // the second backup is skipped
+        fs::write(path, "Some other content.").unwrap();
+        match backup_rate_limited(path, interval) {
+            Ok(x) => assert_eq!(x, None),
+            Err(_) => panic!("Backup failed."),
+        }
+        assert!(path.is_file());
+        assert_eq!(list_backups(path).unwrap(), vec![backup]);
+
+        // old backups do not prevent new backups
+        File::create(tmp.join("#test_file_rate2.txt-2023-06-27-21-01-13#")).unwrap();
+        File::create(tmp.join("test_file_rate2.txt")).unwrap();
+        assert!(matches!(
+            backup_rate_limited(tmp.join("test_file_rate2.txt"), interval),
+            Ok(Some(_))
+        ));
+
+        assert!(matches!(
+            backup_rate_limited(tmp.join("nonexistent.txt"), interval),
+            Err(BackupError::NotFound)
+        ));
+
+        // trailing separators are ignored
+        File::create(tmp.join("test_file_rate3.txt")).unwrap();
+        assert!(matches!(
+            backup_rate_limited(tmp.join("test_file_rate3.txt/"), interval),
+            Ok(Some(_))
+        ));
+        File::create(tmp.join("test_file_rate3.txt")).unwrap();
+        assert!(matches!(
+            backup_rate_limited(tmp.join("test_file_rate3.txt/"), interval),
+            Ok(None)
+        ));
+    }
+
+    /// Returns pseudo-random names built from characters that often need special care in paths.
+    fn random_names(count: usize) -> Vec<String> {
+        const CHARS: &[char] = &[
+            'a', 'Z', '0', '.', '-', '_', '#', ' ', '~', '/', '\\', ':', 'é', '日',
+        ];
 
-        fs::remove_file(&file_in_backup).unwrap();
-        fs::remove_dir(&backup).unwrap();
+        // xorshift, so the names are the same in every run
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+
+        (0..count)
+            .map(|i| {
+                // some of the names are too long for a backup
+                let len = if i % 10 == 0 { 240 } else { next() % 16 } as usize;
+                (0..len)
+                    .map(|_| CHARS[(next() % CHARS.len() as u64) as usize])
+                    .collect()
+            })
+            .collect()
     }
 
     #[test]
-    fn directory_multiple_backups() {
-        let mut backups = Vec::new();
-        for i in 0..10 {
-            fs::create_dir("test_dir4").unwrap();
+    fn random_names_never_panic() {
+        let time = Local.with_ymd_and_hms(2023, 6, 27, 21, 1, 13).unwrap();
+        for name in random_names(500) {
+            if let Ok(backup) = backup_name(&name, time) {
+                assert_ne!(backup, Path::new(&name));
+            }
+        }
+    }
 
-            let mut file = File::create("test_dir4/test_file.txt").unwrap();
-            let text = format!("Unique string for file {}", i);
-            file.write_all(text.as_bytes()).unwrap();
+    #[test]
+    fn random_files() {
+        let tmp = test_dir();
+        let mut count = 0;
+        for name in random_names(500) {
+            // the backups must stay inside the directory of the test
+            let name = name.replace(['/', '\\'], "");
+            if name.chars().all(|x| x == '.') {
+                continue;
+            }
 
-            let backup = match backup("test_dir4") {
-                Ok(x) => x,
-                Err(_) => panic!("Backup failed."),
-            };
+            let path = tmp.join(&name);
+            let created = File::create(&path).is_ok();
 
-            backups.push(backup);
+            match backup(&path) {
+                Ok(backup) => {
+                    assert!(created, "{:?}", name);
+                    assert_ne!(backup, path);
+                    assert!(backup.is_file(), "{:?}", name);
+                    assert!(!path.exists(), "{:?}", name);
+                    assert_eq!(backup.parent(), Some(&*tmp));
+                    count += 1;
+                }
+                Err(e) => assert!(!created || e.kind() != ErrorKind::NotFound, "{:?}", name),
+            }
         }
 
-        for (i, path) in backups.iter().enumerate() {
-            let file_in_backup = path.join(Path::new("test_file.txt"));
+        assert!(count > 100);
+    }
 
-            let mut content = String::new();
-            let mut read = File::open(&file_in_backup).unwrap();
+    #[test]
+    fn relative_parent() {
+        let tmp = test_dir();
 
-            read.read_to_string(&mut content).unwrap();
+        // a bare filename is backed up in the current directory without a `.` component
+        let cwd = set_current_dir(&tmp);
+        fs::write("test_file_relative.txt", "Some content to test.").unwrap();
+        let bare = backup("test_file_relative.txt").unwrap();
+        let result = fs::read_to_string(&bare);
+        let restored = restore("test_file_relative.txt");
+        drop(cwd);
 
-            let test = format!("Unique string for file {}", i);
-            assert_eq!(content, test);
+        assert_eq!(bare.parent(), Some(Path::new("")));
+        assert!(bare
+            .components()
+            .all(|x| matches!(x, std::path::Component::Normal(_))));
+        assert_eq!(result.unwrap(), "Some content to test.");
+        assert_eq!(restored.unwrap(), Path::new("test_file_relative.txt"));
 
-            fs::remove_file(&file_in_backup).unwrap();
-            fs::remove_dir(&path).unwrap();
-        }
+        // a nested path keeps its parent directory
+        fs::create_dir(tmp.join("dir")).unwrap();
+        fs::write(tmp.join("dir/test_file.txt"), "Some content to test.").unwrap();
+        let nested = backup(tmp.join("dir/test_file.txt")).unwrap();
+
+        assert_eq!(nested.parent(), Some(tmp.join("dir").as_path()));
+        assert!(nested.starts_with(tmp.join("dir")));
+        assert_eq!(
+            list_backups(tmp.join("dir/test_file.txt")).unwrap(),
+            vec![nested]
+        );
     }
 
     #[test]
-    fn nonexistent() {
-        match backup("nonexistent.txt") {
+    fn concurrent_same_file() {
+        let tmp = test_dir();
+        fs::create_dir(tmp.join("test_dir_concurrent")).unwrap();
+        let path = &tmp.join("test_dir_concurrent/test_file.txt");
+
+        let successes = std::thread::scope(|scope| {
+            let handles = (0..8)
+                .map(|i| {
+                    let tmp = &tmp;
+                    scope.spawn(move || {
+                        let mut successes = Vec::new();
+                        for j in 0..50 {
+                            // the file is recreated atomically, so every backup gets a complete content
+                            let temp = tmp.join(format!("temp_{}", i));
+                            fs::write(&temp, format!("Content {} {}", i, j)).unwrap();
+                            fs::rename(&temp, path).unwrap();
+
+                            match backup(path) {
+                                Ok(x) => successes.push(x),
+                                // the file has been backed up by another thread in the meantime
+                                Err(BackupError::NotFound) => (),
+                                Err(e) => panic!("Backup failed: {:?}", e),
+                            }
+                        }
+                        successes
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            handles
+                .into_iter()
+                .flat_map(|x| x.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+
+        assert!(!successes.is_empty());
+        let unique = successes.iter().collect::<HashSet<_>>();
+        assert_eq!(unique.len(), successes.len());
+        assert_eq!(list_backups(path).unwrap().len(), successes.len());
+
+        // every backup contains a different version of the file
+        let contents = successes
+            .iter()
+            .map(|x| fs::read_to_string(x).unwrap())
+            .collect::<HashSet<_>>();
+        assert_eq!(contents.len(), successes.len());
+        assert!(contents.iter().all(|x| x.starts_with("Content ")));
+    }
+
+    #[test]
+    fn canonicalized() {
+        let tmp = test_dir();
+        fs::create_dir_all(tmp.join("test_dir_canonical/nested")).unwrap();
+        fs::write(
+            tmp.join("test_dir_canonical/test_file.txt"),
+            "Some content to test.",
+        )
+        .unwrap();
+        let path = &tmp.join("test_dir_canonical/nested/..");
+
+        match backup(path) {
             Ok(_) => panic!("Backup should have failed, but it was successful."),
-            Err(e) => assert_eq!(e.to_string(), "Path does not exist."),
+            Err(e) => assert!(matches!(e, BackupError::EndsInDotDot)),
+        }
+
+        let backup = match backup_canonicalized(path) {
+            Ok(x) => x,
+            Err(_) => panic!("Backup failed."),
         };
+
+        // the path resolves to the directory containing `nested`
+        let parent = fs::canonicalize(&*tmp).unwrap();
+        assert_eq!(backup.parent(), Some(parent.as_path()));
+        assert!(backup
+            .file_name()
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .starts_with("#test_dir_canonical-"));
+        assert!(!tmp.join("test_dir_canonical").exists());
+        assert_eq!(
+            fs::read_to_string(backup.join("test_file.txt")).unwrap(),
+            "Some content to test."
+        );
+        assert!(backup.join("nested").is_dir());
+
+        match backup_canonicalized(tmp.join("nonexistent/..")) {
+            Ok(_) => panic!("Backup should have failed, but it was successful."),
+            Err(e) => assert!(matches!(e, BackupError::NotFound)),
+        }
+
+        // the root is still rejected
+        #[cfg(unix)]
+        match backup_canonicalized("/") {
+            Ok(_) => panic!("Backup should have failed, but it was successful."),
+            Err(e) => assert!(matches!(e, BackupError::IsRoot)),
+        }
     }
 
+    #[cfg(unix)]
     #[test]
-    fn root() {
-        match backup("/") {
-            Ok(_) => panic!("Backup should have failed, but it was successful."),
-            Err(e) => assert_eq!(e.to_string(), "Path is root."),
+    fn copy_dangling_symlink() {
+        let tmp = test_dir();
+        let dir = tmp.join("test_dir_copy_dangling");
+        fs::create_dir(&dir).unwrap();
+        fs::write(dir.join("test_file.txt"), "Some content to test.").unwrap();
+        std::os::unix::fs::symlink("nonexistent.txt", dir.join("test_link.txt")).unwrap();
+
+        let backup = match backup_copy(&dir) {
+            Ok(x) => x,
+            Err(_) => panic!("Backup failed."),
         };
+
+        assert_eq!(
+            fs::read_to_string(backup.join("test_file.txt")).unwrap(),
+            "Some content to test."
+        );
+        assert_eq!(
+            fs::read_link(backup.join("test_link.txt")).unwrap(),
+            Path::new("nonexistent.txt")
+        );
+        assert!(fs::symlink_metadata(dir.join("test_link.txt"))
+            .unwrap()
+            .is_symlink());
     }
 
+    #[cfg(unix)]
     #[test]
-    fn empty() {
-        match backup("") {
+    fn copy_symlink_times() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let tmp = test_dir();
+        let dir = tmp.join("test_dir_copy_symlink_times");
+        fs::create_dir(&dir).unwrap();
+        fs::write(dir.join("test_file.txt"), "Some content to test.").unwrap();
+        std::os::unix::fs::symlink("test_file.txt", dir.join("test_link.txt")).unwrap();
+
+        let file_time = FileTime::from_unix_time(1_000_000_000, 0);
+        let link_time = FileTime::from_unix_time(1_500_000_000, 0);
+        filetime::set_file_times(dir.join("test_file.txt"), file_time, file_time).unwrap();
+        filetime::set_symlink_file_times(dir.join("test_link.txt"), link_time, link_time).unwrap();
+        fs::set_permissions(dir.join("test_file.txt"), fs::Permissions::from_mode(0o600)).unwrap();
+
+        let backup = match backup_copy(&dir) {
+            Ok(x) => x,
+            Err(_) => panic!("Backup failed."),
+        };
+
+        let link = fs::symlink_metadata(backup.join("test_link.txt")).unwrap();
+        assert_eq!(FileTime::from_last_modification_time(&link), link_time);
+
+        // the target of the link is copied without being modified through the link
+        let file = fs::metadata(backup.join("test_file.txt")).unwrap();
+        assert_eq!(FileTime::from_last_modification_time(&file), file_time);
+        assert_eq!(file.permissions().mode() & 0o777, 0o600);
+        let original = fs::metadata(dir.join("test_file.txt")).unwrap();
+        assert_eq!(FileTime::from_last_modification_time(&original), file_time);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn copy_nested_fifo() {
+        let tmp = test_dir();
+        let dir = tmp.join("test_dir_copy_fifo");
+        fs::create_dir_all(dir.join("nested")).unwrap();
+        fs::write(dir.join("test_file.txt"), "Some content to test.").unwrap();
+        let status = std::process::Command::new("mkfifo")
+            .arg(dir.join("nested/fifo"))
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        // the FIFO is never opened, so the copy does not block
+        match backup_copy(&dir) {
             Ok(_) => panic!("Backup should have failed, but it was successful."),
-            Err(e) => assert_eq!(e.to_string(), "Path does not exist."),
+            Err(e) => {
+                assert!(matches!(e, BackupError::Unsupported(_)));
+                assert!(e.to_string().contains("FIFO"));
+            }
+        }
+
+        // no partial copy is left behind
+        assert_eq!(fs::read_dir(&*tmp).unwrap().count(), 1);
+        assert!(dir.join("test_file.txt").is_file());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn copy_symlink_loop() {
+        let tmp = test_dir();
+        let dir = tmp.join("test_dir_copy_loop");
+        fs::create_dir_all(dir.join("nested")).unwrap();
+        std::os::unix::fs::symlink("..", dir.join("nested/parent")).unwrap();
+
+        let backup = match backup_copy(&dir) {
+            Ok(x) => x,
+            Err(_) => panic!("Backup failed."),
+        };
+
+        let link = backup.join("nested/parent");
+        assert!(fs::symlink_metadata(&link).unwrap().is_symlink());
+        assert_eq!(fs::read_link(&link).unwrap(), Path::new(".."));
+        assert!(dir.join("nested/parent").is_dir());
+    }
+
+    #[test]
+    fn file_dry_run() {
+        let tmp = test_dir();
+        let mut file = File::create(tmp.join("test_file_dry_run.txt")).unwrap();
+        file.write_all(b"Some content to test.").unwrap();
+
+        let backup = match backup_dry_run(tmp.join("test_file_dry_run.txt")) {
+            Ok(x) => x,
+            Err(_) => panic!("Dry run failed."),
         };
+
+        let name = backup.file_name().unwrap().to_str().unwrap();
+        assert!(name.starts_with("#test_file_dry_run.txt-"));
+        assert!(name.ends_with('#'));
+        assert!(!backup.exists());
+        assert!(tmp.join("test_file_dry_run.txt").is_file());
+    }
+
+    #[test]
+    fn dry_run_nonexistent() {
+        match backup_dry_run("nonexistent.txt") {
+            Ok(_) => panic!("Dry run should have failed, but it was successful."),
+            Err(e) => assert!(matches!(e, BackupError::NotFound)),
+        }
     }
 
     #[test]
@@ -0,0 +1,916 @@
+// Released under MIT License.
+// Copyright (c) 2023 Ladislav Bartos
+
+//! Implementation of functions for discovering existing backups.
+
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::prelude::*;
+
+use crate::{
+    dir_or_current, is_illegal_char, path_size, split_extension, split_path,
+    trim_trailing_separators, BackupError, DEFAULT_FORMAT, DEFAULT_PREFIX, DEFAULT_SEPARATOR,
+    DEFAULT_SUFFIX,
+};
+
+/// Lists all existing backups of the specified file or directory.
+/// Returns the paths to the backups sorted from the oldest to the newest.
+///
+/// # Arguments
+///
+/// * `path` - The path to the original file or directory. It does not have to exist.
+///
+/// # Errors
+///
+/// This function can return the following errors:
+///
+/// * `BackupError::IsRoot`, `BackupError::EndsInDotDot` - If the `path` is not valid.
+/// * `BackupError::Io` - If the parent directory of `path` can not be read.
+///
+/// # Details
+/// Only entries located in the parent directory of `path` and named
+/// `#<filename>-<timestamp>(-<microseconds>)#` are listed. The entries are sorted by the timestamp in their name.
+/// Entries that do not match this pattern are skipped.
+///
+/// Labeled backups (see [`crate::backup_labeled`]) are not listed, since a labeled backup of `data.txt`
+/// can not be distinguished from a backup of a file named `data.txt-<label>`. Use [`list_labeled_backups`] instead.
+///
+/// # Examples
+///
+/// ```no_run
+/// use crate::backitup::list_backups;
+///
+/// match list_backups("data.txt") {
+///     Ok(backups) => println!("Backups: {:?}", backups),
+///     Err(err) => eprintln!("Failed to list backups: {:?}", err),
+/// }
+/// ```
+pub fn list_backups(path: impl AsRef<Path>) -> Result<Vec<PathBuf>, BackupError> {
+    list_backups_wrapped(path, DEFAULT_PREFIX, DEFAULT_SUFFIX)
+}
+
+/// Lists all existing backups of the specified file or directory created with the specified label.
+/// Returns the paths to the backups sorted from the oldest to the newest.
+///
+/// # Arguments
+///
+/// * `path` - The path to the original file or directory. It does not have to exist.
+/// * `label` - The label of the backups, e.g. `"pre-deploy"`.
+///
+/// # Errors
+///
+/// This function can return the same errors as [`list_backups`] and additionally:
+///
+/// * `BackupError::InvalidArgument` - If `label` is empty or contains path separators or `#`.
+///
+/// # Details
+/// Only entries located in the parent directory of `path` and named
+/// `#<filename>-<label>-<timestamp>(-<microseconds>)#` (see [`crate::backup_labeled`]) are listed.
+/// Unlabeled backups and backups with other labels are skipped.
+///
+/// # Examples
+///
+/// ```no_run
+/// use crate::backitup::list_labeled_backups;
+///
+/// match list_labeled_backups("data.txt", "pre-deploy") {
+///     Ok(backups) => println!("Backups: {:?}", backups),
+///     Err(err) => eprintln!("Failed to list backups: {:?}", err),
+/// }
+/// ```
+pub fn list_labeled_backups(
+    path: impl AsRef<Path>,
+    label: &str,
+) -> Result<Vec<PathBuf>, BackupError> {
+    if !is_valid_label(label, DEFAULT_PREFIX, DEFAULT_SUFFIX) {
+        return Err(BackupError::InvalidArgument(format!(
+            "Label '{}' is empty or contains path separators or wrapper characters.",
+            label
+        )));
+    }
+
+    let (parent, filename) = split_path(trim_trailing_separators(path.as_ref()))?;
+    let mut labeled = filename.to_owned();
+    labeled.push(DEFAULT_SEPARATOR);
+    labeled.push(label);
+
+    Ok(
+        find_backups(parent, &labeled, DEFAULT_PREFIX, DEFAULT_SUFFIX)?
+            .into_iter()
+            .map(|(_, x)| x)
+            .collect(),
+    )
+}
+
+/// Lists all existing backups of the specified file or directory that were created with a custom wrapper.
+/// Returns the paths to the backups sorted from the oldest to the newest.
+///
+/// # Arguments
+///
+/// * `path` - The path to the original file or directory. It does not have to exist.
+/// * `prefix` - The string placed in front of the name of the backups.
+/// * `suffix` - The string placed at the end of the name of the backups.
+///
+/// # Errors
+///
+/// This function can return the same errors as [`list_backups`].
+///
+/// # Details
+/// Only entries located in the parent directory of `path` and named
+/// `<prefix><filename>-<timestamp>(-<microseconds>)<suffix>` are listed.
+/// See [`crate::backup_wrapped`] for creating backups with a custom wrapper.
+///
+/// # Examples
+///
+/// ```no_run
+/// use crate::backitup::list_backups_wrapped;
+///
+/// match list_backups_wrapped("data.txt", "bak_", ".old") {
+///     Ok(backups) => println!("Backups: {:?}", backups),
+///     Err(err) => eprintln!("Failed to list backups: {:?}", err),
+/// }
+/// ```
+pub fn list_backups_wrapped(
+    path: impl AsRef<Path>,
+    prefix: &str,
+    suffix: &str,
+) -> Result<Vec<PathBuf>, BackupError> {
+    let (parent, filename) = split_path(trim_trailing_separators(path.as_ref()))?;
+
+    Ok(find_backups(parent, filename, prefix, suffix)?
+        .into_iter()
+        .map(|(_, x)| x)
+        .collect())
+}
+
+/// Returns the total size of all existing backups of the specified file or directory in bytes.
+///
+/// # Arguments
+///
+/// * `path` - The path to the original file or directory. It does not have to exist.
+///
+/// # Errors
+///
+/// This function can return the same errors as [`list_backups`].
+///
+/// # Details
+/// The backups are found in the same way as in [`list_backups`]. The size of a directory backup
+/// is the sum of the sizes of all files it contains (recursively). Symbolic links are not followed.
+///
+/// # Examples
+///
+/// ```no_run
+/// use crate::backitup::backups_total_size;
+///
+/// let size = backups_total_size("data.txt").unwrap();
+/// println!("You have {:.1} GB of backups for data.txt.", size as f64 / 1e9);
+/// ```
+pub fn backups_total_size(path: impl AsRef<Path>) -> Result<u64, BackupError> {
+    let mut size = 0;
+    for backup in list_backups(path)? {
+        size += path_size(&backup, false)?;
+    }
+
+    Ok(size)
+}
+
+/// Returns an iterator over all existing backups of the specified file or directory.
+/// The backups are found lazily while iterating, in the order in which the directory is read.
+///
+/// # Arguments
+///
+/// * `path` - The path to the original file or directory. It does not have to exist.
+///
+/// # Errors
+///
+/// This function can return the same errors as [`list_backups`].
+/// The iterator yields `BackupError::Io` if an entry of the parent directory can not be read.
+///
+/// # Details
+/// The backups are recognized in the same way as in [`list_backups`], but the paths are yielded
+/// one at a time without collecting all of them, so memory usage stays bounded even for directories
+/// containing thousands of backups. The backups are **not** sorted.
+///
+/// # Examples
+///
+/// ```no_run
+/// use crate::backitup::iter_backups;
+///
+/// for backup in iter_backups("data.txt").unwrap() {
+///     match backup {
+///         Ok(path) => println!("Backup: {:?}", path),
+///         Err(err) => eprintln!("Failed to read entry: {:?}", err),
+///     }
+/// }
+/// ```
+pub fn iter_backups(
+    path: impl AsRef<Path>,
+) -> Result<impl Iterator<Item = Result<PathBuf, BackupError>>, BackupError> {
+    let (parent, filename) = split_path(trim_trailing_separators(path.as_ref()))?;
+    let (parent, filename) = (parent.to_path_buf(), filename.to_owned());
+
+    let entries = fs::read_dir(dir_or_current(&parent))?;
+
+    Ok(entries.filter_map(move |entry| match entry {
+        Ok(entry) => {
+            let name = entry.file_name();
+            parse_backup_name(&name, &filename, DEFAULT_PREFIX, DEFAULT_SUFFIX)
+                .map(|_| Ok(parent.join(name)))
+        }
+        Err(e) => Some(Err(BackupError::Io(e))),
+    }))
+}
+
+/// Time at which a backup was created, as encoded in its name.
+/// The second item is the number of microseconds and the third item is the collision counter
+/// (zero, if not part of the name).
+pub(crate) type BackupTime = (NaiveDateTime, u32, u32);
+
+/// Parses the name of a backup of a file named `filename`.
+/// Returns `None` if `name` does not match the pattern `<prefix><filename>-<timestamp>(-<microseconds>)<suffix>`.
+pub(crate) fn parse_backup_name(
+    name: &OsStr,
+    filename: &OsStr,
+    prefix: &str,
+    suffix: &str,
+) -> Option<BackupTime> {
+    match split_backup_name(name.as_encoded_bytes(), prefix, suffix) {
+        Some((x, time)) if x == filename.as_encoded_bytes() => Some(time),
+        _ => None,
+    }
+}
+
+/// Returns `true` if `label` can be used in the name of a backup wrapped in `prefix` and `suffix`.
+/// The label must not be empty and must not contain path separators or the characters of the wrapper.
+pub(crate) fn is_valid_label(label: &str, prefix: &str, suffix: &str) -> bool {
+    !label.is_empty()
+        && !label.chars().any(|c| {
+            c == '/' || c == '\\' || is_illegal_char(c) || prefix.contains(c) || suffix.contains(c)
+        })
+}
+
+/// Splits the name of a backup into the name of the original file and the time of the backup.
+/// Returns `None` if `name` does not match the pattern `<prefix><filename>-<timestamp>(-<microseconds>)<suffix>`.
+///
+/// The name is processed as raw bytes, so the name of the original file does not have to be a valid UTF-8.
+pub(crate) fn split_backup_name<'a>(
+    name: &'a [u8],
+    prefix: &str,
+    suffix: &str,
+) -> Option<(&'a [u8], BackupTime)> {
+    split_backup_name_separated(name, prefix, DEFAULT_SEPARATOR, suffix)
+}
+
+/// Splits the name of a backup into the name of the original file and the time of the backup.
+/// Returns `None` if `name` does not match the pattern
+/// `<prefix><filename><separator><timestamp>(-<microseconds>)<suffix>`.
+pub(crate) fn split_backup_name_separated<'a>(
+    name: &'a [u8],
+    prefix: &str,
+    separator: &str,
+    suffix: &str,
+) -> Option<(&'a [u8], BackupTime)> {
+    let inner = name
+        .strip_prefix(prefix.as_bytes())?
+        .strip_suffix(suffix.as_bytes())?;
+
+    if let Some((filename, time)) = split_timestamp(inner, separator) {
+        return Some((filename, (time, 0, 0)));
+    }
+
+    // the timestamp may be followed by microseconds
+    let (rest, micros) = split_number(inner)?;
+    if micros >= 1_000_000 {
+        return None;
+    }
+
+    if let Some((filename, time)) = split_timestamp(rest, separator) {
+        return Some((filename, (time, micros, 0)));
+    }
+
+    // the microseconds may be followed by a counter
+    let counter = micros;
+    let (rest, micros) = split_number(rest)?;
+    if micros >= 1_000_000 || counter == 0 {
+        return None;
+    }
+
+    let (filename, time) = split_timestamp(rest, separator)?;
+    Some((filename, (time, micros, counter)))
+}
+
+/// Splits `<string>-<number>` into the string and the parsed number.
+fn split_number(string: &[u8]) -> Option<(&[u8], u32)> {
+    let split = string.iter().rposition(|&b| b == b'-')?;
+    let (rest, number) = (&string[..split], &string[split + 1..]);
+    if number.is_empty() || !number.iter().all(u8::is_ascii_digit) {
+        return None;
+    }
+
+    Some((rest, std::str::from_utf8(number).ok()?.parse::<u32>().ok()?))
+}
+
+/// Splits `<filename><separator><timestamp>` into the filename and the parsed timestamp.
+fn split_timestamp<'a>(string: &'a [u8], separator: &str) -> Option<(&'a [u8], NaiveDateTime)> {
+    // the default timestamp always has the same length
+    const STAMP_LEN: usize = "YYYY-MM-DD-HH-MM-SS".len();
+
+    let split = string.len().checked_sub(STAMP_LEN)?;
+    let (filename, stamp) = string.split_at(split);
+
+    // a timestamp that is not a valid UTF-8 is not a valid timestamp either
+    let stamp = std::str::from_utf8(stamp).ok()?;
+    let filename = filename
+        .strip_suffix(separator.as_bytes())
+        .filter(|x| !x.is_empty())?;
+    let time = NaiveDateTime::parse_from_str(stamp, DEFAULT_FORMAT).ok()?;
+
+    Some((filename, time))
+}
+
+/// Extracts the time at which a backup was created from its name.
+/// Returns `None` if the name of the backup does not match the pattern `#<name>-<timestamp>(-<microseconds>)#`.
+///
+/// # Arguments
+///
+/// * `path` - The path to the backup. It does not have to exist.
+///
+/// # Details
+/// The timestamp is interpreted as local time (as produced by [`crate::backup`]).
+/// The microseconds, if present in the name, are included in the returned time.
+/// Only the name of the backup is used, the metadata of the backup are never read.
+///
+/// Names of backups with the extension kept at the end (`<stem>-<timestamp>.<extension>` or
+/// `#<stem>-<timestamp>#.<extension>`, see [`crate::backup_with_suffix_before_extension`]) are recognized as well,
+/// as long as the stem contains no dots.
+///
+/// # Examples
+///
+/// ```
+/// use crate::backitup::parse_backup_time;
+///
+/// let time = parse_backup_time("#data.txt-2023-06-27-21-01-13#").unwrap();
+/// assert_eq!(time.format("%Y/%m/%d %H:%M:%S").to_string(), "2023/06/27 21:01:13");
+///
+/// let time = parse_backup_time("data-2023-06-27-21-01-13.txt").unwrap();
+/// assert_eq!(time.format("%Y/%m/%d %H:%M:%S").to_string(), "2023/06/27 21:01:13");
+///
+/// assert!(parse_backup_time("data.txt").is_none());
+/// ```
+pub fn parse_backup_time(path: impl AsRef<Path>) -> Option<DateTime<Local>> {
+    let path = path.as_ref();
+
+    parse_backup_time_wrapped(path, DEFAULT_PREFIX, DEFAULT_SUFFIX)
+        .or_else(|| parse_backup_time_extension(path))
+}
+
+/// Extracts the time at which a backup with the extension kept at the end of its name was created.
+/// Both the default wrapper and no wrapper are accepted.
+///
+/// To avoid mistaking backups with custom suffixes (e.g. `data.txt-<timestamp>.old`) for this layout,
+/// the stem of the original file must not contain any further dots (except a leading one).
+fn parse_backup_time_extension(path: &Path) -> Option<DateTime<Local>> {
+    let stem = match split_extension(path.file_name()?) {
+        (stem, Some(_)) => stem.as_encoded_bytes(),
+        (_, None) => return None,
+    };
+
+    let (original, time) = split_backup_name(stem, DEFAULT_PREFIX, DEFAULT_SUFFIX)
+        .or_else(|| split_backup_name(stem, "", ""))?;
+
+    if original.iter().skip(1).any(|&c| c == b'.') {
+        return None;
+    }
+
+    to_local_time(&time)
+}
+
+/// Extracts the time at which a backup created with a custom wrapper was created from its name.
+/// Returns `None` if the name of the backup does not match the pattern
+/// `<prefix><name>-<timestamp>(-<microseconds>)<suffix>`.
+///
+/// # Arguments
+///
+/// * `path` - The path to the backup. It does not have to exist.
+/// * `prefix` - The string placed in front of the name of the backup.
+/// * `suffix` - The string placed at the end of the name of the backup.
+///
+/// # Details
+/// The timestamp is interpreted in the same way as in [`parse_backup_time`].
+///
+/// # Examples
+///
+/// ```
+/// use crate::backitup::parse_backup_time_wrapped;
+///
+/// let time = parse_backup_time_wrapped("bak_data.txt-2023-06-27-21-01-13.old", "bak_", ".old").unwrap();
+/// assert_eq!(time.format("%Y/%m/%d %H:%M:%S").to_string(), "2023/06/27 21:01:13");
+/// ```
+pub fn parse_backup_time_wrapped(
+    path: impl AsRef<Path>,
+    prefix: &str,
+    suffix: &str,
+) -> Option<DateTime<Local>> {
+    let name = path.as_ref().file_name()?;
+    let (_, time) = split_backup_name(name.as_encoded_bytes(), prefix, suffix)?;
+
+    to_local_time(&time)
+}
+
+/// Extracts the time at which a backup created with a custom separator was created from its name.
+/// Returns `None` if the name of the backup does not match the pattern
+/// `#<name><separator><timestamp>(-<microseconds>)#`.
+///
+/// # Arguments
+///
+/// * `path` - The path to the backup. It does not have to exist.
+/// * `separator` - The string placed between the name and the timestamp.
+///
+/// # Details
+/// The timestamp is interpreted in the same way as in [`parse_backup_time`].
+/// See [`crate::backup_with_separator`] for creating backups with a custom separator.
+///
+/// # Examples
+///
+/// ```
+/// use crate::backitup::parse_backup_time_with_separator;
+///
+/// let time = parse_backup_time_with_separator("#report-2024.csv@@2023-06-27-21-01-13#", "@@").unwrap();
+/// assert_eq!(time.format("%Y/%m/%d %H:%M:%S").to_string(), "2023/06/27 21:01:13");
+/// ```
+pub fn parse_backup_time_with_separator(
+    path: impl AsRef<Path>,
+    separator: &str,
+) -> Option<DateTime<Local>> {
+    let name = path.as_ref().file_name()?;
+    let (_, time) = split_backup_name_separated(
+        name.as_encoded_bytes(),
+        DEFAULT_PREFIX,
+        separator,
+        DEFAULT_SUFFIX,
+    )?;
+
+    to_local_time(&time)
+}
+
+/// Returns `true` if `name` is a name of a backup created by [`crate::backup`].
+///
+/// # Arguments
+///
+/// * `name` - The name of the file or directory (not a path).
+///
+/// # Details
+/// The name must match the pattern `#<filename>-<timestamp>(-<microseconds>)#`.
+/// Only the name itself is examined, the filesystem is never accessed.
+///
+/// # Examples
+///
+/// ```
+/// use crate::backitup::is_backup_name;
+///
+/// assert!(is_backup_name("#data.txt-2023-06-27-21-01-13#"));
+/// assert!(is_backup_name("#data.txt-2023-06-27-21-01-13-45123#"));
+/// assert!(!is_backup_name("data.txt"));
+/// ```
+pub fn is_backup_name(name: impl AsRef<OsStr>) -> bool {
+    split_backup_name(
+        name.as_ref().as_encoded_bytes(),
+        DEFAULT_PREFIX,
+        DEFAULT_SUFFIX,
+    )
+    .is_some()
+}
+
+/// Converts the time encoded in the name of a backup into local time.
+/// The microseconds are included in the returned time.
+pub(crate) fn to_local_time(time: &BackupTime) -> Option<DateTime<Local>> {
+    let (time, micros, _) = time;
+
+    let time = time.with_nanosecond(micros * 1000)?;
+    // ambiguous times (daylight saving time transitions) are resolved to the earlier time
+    Local.from_local_datetime(&time).earliest()
+}
+
+/// Finds all backups of a file named `filename` located in `dir`.
+/// The backups are sorted from the oldest to the newest.
+pub(crate) fn find_backups(
+    dir: &Path,
+    filename: &OsStr,
+    prefix: &str,
+    suffix: &str,
+) -> Result<Vec<(BackupTime, PathBuf)>, BackupError> {
+    let mut backups = Vec::new();
+
+    for entry in fs::read_dir(dir_or_current(dir))? {
+        let entry = entry?;
+        let name = entry.file_name();
+
+        if let Some(time) = parse_backup_name(&name, filename, prefix, suffix) {
+            backups.push((time, dir.join(name)));
+        }
+    }
+
+    backups.sort();
+    Ok(backups)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backup;
+    use crate::testutil::{set_current_dir, test_dir};
+    use chrono::NaiveDate;
+    use std::fs::File;
+
+    #[test]
+    fn parse_seconds() {
+        let expected = NaiveDate::from_ymd_opt(2023, 6, 27)
+            .unwrap()
+            .and_hms_opt(21, 1, 13)
+            .unwrap();
+
+        assert_eq!(
+            parse_backup_name(
+                OsStr::new("#data.txt-2023-06-27-21-01-13#"),
+                OsStr::new("data.txt"),
+                "#",
+                "#"
+            ),
+            Some((expected, 0, 0))
+        );
+    }
+
+    #[test]
+    fn parse_microseconds() {
+        let expected = NaiveDate::from_ymd_opt(2023, 6, 27)
+            .unwrap()
+            .and_hms_opt(21, 1, 13)
+            .unwrap();
+
+        assert_eq!(
+            parse_backup_name(
+                OsStr::new("#data.txt-2023-06-27-21-01-13-45123#"),
+                OsStr::new("data.txt"),
+                "#",
+                "#"
+            ),
+            Some((expected, 45123, 0))
+        );
+    }
+
+    #[test]
+    fn parse_invalid() {
+        for name in [
+            "data.txt",
+            "#data.txt#",
+            "#data.txt-2023-06-27-21-01-13",
+            "data.txt-2023-06-27-21-01-13#",
+            "#data.txt-2023-06-27-21-01-13-#",
+            "#data.txt-2023-06-27-21-01-13-1234567#",
+            "#data.txt-2023-06-27-21-01-13-+5#",
+            "#data.txt-2023-13-27-21-01-13#",
+            "#data.txt-garbage#",
+            "#other.txt-2023-06-27-21-01-13#",
+            "#data.txt.gz-2023-06-27-21-01-13#",
+        ] {
+            assert_eq!(
+                parse_backup_name(OsStr::new(name), OsStr::new("data.txt"), "#", "#"),
+                None,
+                "{}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn split() {
+        let expected = NaiveDate::from_ymd_opt(2023, 6, 27)
+            .unwrap()
+            .and_hms_opt(21, 1, 13)
+            .unwrap();
+
+        assert_eq!(
+            split_backup_name("#data.txt-2023-06-27-21-01-13#".as_bytes(), "#", "#"),
+            Some(("data.txt".as_bytes(), (expected, 0, 0)))
+        );
+        assert_eq!(
+            split_backup_name(
+                "#data-2024.txt-2023-06-27-21-01-13-45123#".as_bytes(),
+                "#",
+                "#"
+            ),
+            Some(("data-2024.txt".as_bytes(), (expected, 45123, 0)))
+        );
+        assert_eq!(
+            split_backup_name(
+                "#x-2022-01-01-00-00-00-2023-06-27-21-01-13#".as_bytes(),
+                "#",
+                "#"
+            ),
+            Some(("x-2022-01-01-00-00-00".as_bytes(), (expected, 0, 0)))
+        );
+        assert_eq!(
+            split_backup_name("#č-2023-06-27-21-01-13-1#".as_bytes(), "#", "#"),
+            Some(("č".as_bytes(), (expected, 1, 0)))
+        );
+        assert_eq!(
+            split_backup_name(
+                "#data.txt-2023-06-27-21-01-13-45123-7#".as_bytes(),
+                "#",
+                "#"
+            ),
+            Some(("data.txt".as_bytes(), (expected, 45123, 7)))
+        );
+
+        assert_eq!(
+            split_backup_name("#-2023-06-27-21-01-13#".as_bytes(), "#", "#"),
+            None
+        );
+        assert_eq!(
+            split_backup_name("#2023-06-27-21-01-13#".as_bytes(), "#", "#"),
+            None
+        );
+        assert_eq!(
+            split_backup_name("#č023-06-27-21-01-13#".as_bytes(), "#", "#"),
+            None
+        );
+        assert_eq!(split_backup_name("#data.txt#".as_bytes(), "#", "#"), None);
+        assert_eq!(
+            split_backup_name(
+                "#data.txt-2023-06-27-21-01-13-45123-0#".as_bytes(),
+                "#",
+                "#"
+            ),
+            None
+        );
+        assert_eq!(
+            split_backup_name(
+                "#data.txt-2023-06-27-21-01-13-1234567-1#".as_bytes(),
+                "#",
+                "#"
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn backup_names() {
+        assert!(is_backup_name("#data.txt-2023-06-27-21-01-13#"));
+        assert!(is_backup_name("#data.txt-2023-06-27-21-01-13-45123-7#"));
+        assert!(is_backup_name(OsStr::new("#č-2023-06-27-21-01-13#")));
+
+        assert!(!is_backup_name("data.txt"));
+        assert!(!is_backup_name("#data.txt#"));
+        assert!(!is_backup_name("#-2023-06-27-21-01-13#"));
+        assert!(!is_backup_name("dir/#data.txt-2023-06-27-21-01-13#"));
+    }
+
+    #[test]
+    fn split_separated() {
+        let expected = NaiveDate::from_ymd_opt(2023, 6, 27)
+            .unwrap()
+            .and_hms_opt(21, 1, 13)
+            .unwrap();
+
+        assert_eq!(
+            split_backup_name_separated(
+                "#report-2024.csv@@2023-06-27-21-01-13-45123#".as_bytes(),
+                "#",
+                "@@",
+                "#"
+            ),
+            Some(("report-2024.csv".as_bytes(), (expected, 45123, 0)))
+        );
+        assert_eq!(
+            split_backup_name_separated(
+                "#report-2024.csv-2023-06-27-21-01-13#".as_bytes(),
+                "#",
+                "@@",
+                "#"
+            ),
+            None
+        );
+        assert_eq!(
+            split_backup_name_separated("#@@2023-06-27-21-01-13#".as_bytes(), "#", "@@", "#"),
+            None
+        );
+    }
+
+    #[test]
+    fn parse_time() {
+        let time = parse_backup_time("test_dir/#data.txt-2023-06-27-21-01-13-45123#").unwrap();
+        assert_eq!(
+            time.naive_local(),
+            NaiveDate::from_ymd_opt(2023, 6, 27)
+                .unwrap()
+                .and_hms_micro_opt(21, 1, 13, 45123)
+                .unwrap()
+        );
+
+        assert!(parse_backup_time("test_dir/data.txt").is_none());
+        assert!(parse_backup_time("#data.txt-2023-06-27#").is_none());
+    }
+
+    #[test]
+    fn parse_time_extension() {
+        let expected = NaiveDate::from_ymd_opt(2023, 6, 27)
+            .unwrap()
+            .and_hms_opt(21, 1, 13)
+            .unwrap();
+
+        for name in [
+            "test_dir/data-2023-06-27-21-01-13.txt",
+            "test_dir/#data-2023-06-27-21-01-13#.txt",
+            "test_dir/.config-2023-06-27-21-01-13.toml",
+        ] {
+            let time = parse_backup_time(name).unwrap();
+            assert_eq!(time.naive_local(), expected, "{}", name);
+        }
+
+        assert!(parse_backup_time("test_dir/data.txt-2023-06-27-21-01-13.old").is_none());
+        assert!(parse_backup_time("test_dir/data-2023-06-27.txt").is_none());
+        assert!(parse_backup_time("test_dir/data-2023-06-27-21-01-13").is_none());
+    }
+
+    #[test]
+    fn parse_time_of_backup() {
+        let tmp = test_dir();
+        File::create(tmp.join("test_file_parse_time.txt")).unwrap();
+
+        let backup = backup(tmp.join("test_file_parse_time.txt")).unwrap();
+        let time = parse_backup_time(&backup).unwrap();
+
+        let difference = Local::now() - time;
+        assert!(difference >= chrono::Duration::zero());
+        assert!(difference < chrono::Duration::seconds(1));
+    }
+
+    #[test]
+    fn list() {
+        let tmp = test_dir();
+        File::create(tmp.join("#test_file.txt-2023-06-27-21-01-13-12#")).unwrap();
+        File::create(tmp.join("#test_file.txt-2023-06-27-21-01-13#")).unwrap();
+        File::create(tmp.join("#test_file.txt-2022-12-31-23-59-59#")).unwrap();
+        File::create(tmp.join("#test_file.txt-2023-06-27-21-01-13-3#")).unwrap();
+        File::create(tmp.join("#test_file.txt-2023-06-27-21-01-13-3-10#")).unwrap();
+        File::create(tmp.join("#test_file.txt-2023-06-27-21-01-13-3-2#")).unwrap();
+        File::create(tmp.join("#test_file.txt-garbage#")).unwrap();
+        File::create(tmp.join("#other.txt-2023-06-27-21-01-13#")).unwrap();
+        fs::create_dir(tmp.join("#test_file.txt-2023-01-01-00-00-00#")).unwrap();
+
+        let backups = list_backups(tmp.join("test_file.txt")).unwrap();
+
+        let expected = [
+            "#test_file.txt-2022-12-31-23-59-59#",
+            "#test_file.txt-2023-01-01-00-00-00#",
+            "#test_file.txt-2023-06-27-21-01-13#",
+            "#test_file.txt-2023-06-27-21-01-13-3#",
+            "#test_file.txt-2023-06-27-21-01-13-3-2#",
+            "#test_file.txt-2023-06-27-21-01-13-3-10#",
+            "#test_file.txt-2023-06-27-21-01-13-12#",
+        ]
+        .iter()
+        .map(|x| tmp.join(x))
+        .collect::<Vec<_>>();
+
+        assert_eq!(backups, expected);
+
+        // trailing separators are ignored
+        assert_eq!(list_backups(tmp.join("test_file.txt/")).unwrap(), expected);
+    }
+
+    #[test]
+    fn list_created_backups() {
+        let tmp = test_dir();
+
+        let mut backups = Vec::new();
+        for _ in 0..5 {
+            File::create(tmp.join("test_file.txt")).unwrap();
+            backups.push(backup(tmp.join("test_file.txt")).unwrap());
+        }
+
+        // the original file does not exist
+        let listed = list_backups(tmp.join("test_file.txt")).unwrap();
+        assert_eq!(listed.len(), backups.len());
+        assert!(backups.iter().all(|x| listed.contains(x)));
+    }
+
+    #[test]
+    fn list_current_directory() {
+        let tmp = test_dir();
+        // the guard is dropped before the directory
+        let _cwd = set_current_dir(&tmp);
+        File::create("#test_file_list3.txt-2023-06-27-21-01-13#").unwrap();
+
+        assert_eq!(
+            list_backups("test_file_list3.txt").unwrap(),
+            vec![PathBuf::from("#test_file_list3.txt-2023-06-27-21-01-13#")]
+        );
+        assert!(tmp
+            .join("#test_file_list3.txt-2023-06-27-21-01-13#")
+            .is_file());
+    }
+
+    #[test]
+    fn iterate() {
+        let tmp = test_dir();
+        fs::create_dir(tmp.join("test_dir_list5")).unwrap();
+        for i in 0..100 {
+            File::create(tmp.join(format!(
+                "test_dir_list5/#test_file.txt-2023-06-27-21-01-13-{}#",
+                i + 1
+            )))
+            .unwrap();
+        }
+        File::create(tmp.join("test_dir_list5/test_file.txt")).unwrap();
+        File::create(tmp.join("test_dir_list5/#test_file.txt-garbage#")).unwrap();
+        File::create(tmp.join("test_dir_list5/#other.txt-2023-06-27-21-01-13#")).unwrap();
+
+        let mut count = 0;
+        for backup in iter_backups(tmp.join("test_dir_list5/test_file.txt")).unwrap() {
+            let backup = backup.unwrap();
+            assert!(backup.starts_with(tmp.join("test_dir_list5")));
+            count += 1;
+        }
+
+        assert_eq!(count, 100);
+
+        // the iterator yields the same backups as the list
+        let mut iterated = iter_backups(tmp.join("test_dir_list5/test_file.txt"))
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        iterated.sort();
+        let mut listed = list_backups(tmp.join("test_dir_list5/test_file.txt")).unwrap();
+        listed.sort();
+        assert_eq!(iterated, listed);
+    }
+
+    #[test]
+    fn total_size() {
+        let tmp = test_dir();
+
+        fs::write(tmp.join("test_file.txt"), "Some content.").unwrap();
+        backup(tmp.join("test_file.txt")).unwrap();
+        fs::write(tmp.join("test_file.txt"), "Some other content.").unwrap();
+        backup(tmp.join("test_file.txt")).unwrap();
+
+        // directory backups are included recursively
+        fs::create_dir_all(tmp.join("#test_file.txt-2023-06-27-21-01-13#/nested")).unwrap();
+        fs::write(
+            tmp.join("#test_file.txt-2023-06-27-21-01-13#/nested/file.txt"),
+            "Nested.",
+        )
+        .unwrap();
+
+        // neither the original nor other files are included
+        fs::write(tmp.join("test_file.txt"), "Original.").unwrap();
+        fs::write(tmp.join("other.txt"), "Other.").unwrap();
+
+        let expected = "Some content.".len() + "Some other content.".len() + "Nested.".len();
+        assert_eq!(
+            backups_total_size(tmp.join("test_file.txt")).unwrap(),
+            expected as u64
+        );
+    }
+
+    #[test]
+    fn list_labeled() {
+        let tmp = test_dir();
+        File::create(tmp.join("#data.txt-2023-06-27-21-01-13#")).unwrap();
+        File::create(tmp.join("#data.txt-old-2023-06-27-21-01-14#")).unwrap();
+        fs::write(tmp.join("#data.txt-old-2023-06-27-21-01-15#"), "Other.").unwrap();
+
+        // the backups of `data.txt-old` are not the backups of `data.txt`
+        let path = tmp.join("data.txt");
+        assert_eq!(
+            list_backups(&path).unwrap(),
+            vec![tmp.join("#data.txt-2023-06-27-21-01-13#")]
+        );
+        assert_eq!(iter_backups(&path).unwrap().count(), 1);
+        assert_eq!(backups_total_size(&path).unwrap(), 0);
+        assert_eq!(list_backups(tmp.join("data.txt-old")).unwrap().len(), 2);
+
+        // the backups with the label `old` are only listed on request
+        assert_eq!(
+            list_labeled_backups(&path, "old").unwrap(),
+            vec![
+                tmp.join("#data.txt-old-2023-06-27-21-01-14#"),
+                tmp.join("#data.txt-old-2023-06-27-21-01-15#"),
+            ]
+        );
+        assert!(list_labeled_backups(&path, "new").unwrap().is_empty());
+
+        match list_labeled_backups(&path, "o#ld") {
+            Ok(_) => panic!("Listing should have failed, but it was successful."),
+            Err(e) => assert!(matches!(e, BackupError::InvalidArgument(_))),
+        }
+    }
+
+    #[test]
+    fn list_empty() {
+        assert!(list_backups("test_file_list4.txt").unwrap().is_empty());
+    }
+}
@@ -0,0 +1,384 @@
+// Released under MIT License.
+// Copyright (c) 2023 Ladislav Bartos
+
+//! Implementation of the `BackupManager` structure.
+
+use std::path::{Path, PathBuf};
+
+use crate::info::{PruneReason, PrunedBackup};
+use crate::list::{find_backups, BackupTime};
+use crate::restore::restore_newest_from;
+use crate::{
+    remove_path, split_path, trim_trailing_separators, BackupError, BackupOptions, DEFAULT_PREFIX,
+    DEFAULT_SUFFIX,
+};
+
+/// Manager of the backups stored in a single directory.
+///
+/// The manager is configured once with the directory into which the backups are placed,
+/// the number of backups to keep, and the naming of the backups. The backups are then created,
+/// listed, pruned, and restored by calling the methods of the manager with the paths to the original files.
+/// The settings are chained similarly to [`BackupOptions`].
+///
+/// # Examples
+///
+/// ```no_run
+/// use crate::backitup::BackupManager;
+///
+/// let manager = BackupManager::new("backups").keep(5).copy(true).build();
+///
+/// // creates a backup in 'backups' and removes the oldest backups of 'data.txt', keeping 5 of them
+/// match manager.backup("data.txt") {
+///     Ok(backup_path) => println!("Backup created: {:?}", backup_path),
+///     Err(err) => eprintln!("Failed to create backup: {:?}", err),
+/// }
+///
+/// let backups = manager.list("data.txt").unwrap();
+/// println!("Found {} backups.", backups.len());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BackupManager {
+    destination: PathBuf,
+    keep: Option<usize>,
+    copy: bool,
+    prefix: String,
+    suffix: String,
+}
+
+impl BackupManager {
+    /// Creates a new manager placing the backups into `destination`.
+    ///
+    /// The directory is created once the first backup is created. By default, all backups are kept,
+    /// the original files are renamed, and the names of the backups are wrapped in `#`.
+    pub fn new(destination: impl AsRef<Path>) -> Self {
+        BackupManager {
+            destination: destination.as_ref().to_path_buf(),
+            keep: None,
+            copy: false,
+            prefix: String::from(DEFAULT_PREFIX),
+            suffix: String::from(DEFAULT_SUFFIX),
+        }
+    }
+
+    /// Sets the maximal number of backups of each file that are kept.
+    ///
+    /// The oldest backups are removed automatically after each backup. The number must be at least 1.
+    /// Default: `None`, i.e. all backups are kept.
+    pub fn keep(&mut self, keep: usize) -> &mut Self {
+        self.keep = Some(keep);
+        self
+    }
+
+    /// Sets whether the original file (or directory) should be copied instead of renamed.
+    ///
+    /// Default: `false`.
+    pub fn copy(&mut self, copy: bool) -> &mut Self {
+        self.copy = copy;
+        self
+    }
+
+    /// Sets the string placed in front of the name of the backups.
+    ///
+    /// Default: `"#"`.
+    pub fn prefix(&mut self, prefix: &str) -> &mut Self {
+        self.prefix = prefix.to_owned();
+        self
+    }
+
+    /// Sets the string placed at the end of the name of the backups.
+    ///
+    /// Default: `"#"`.
+    pub fn suffix(&mut self, suffix: &str) -> &mut Self {
+        self.suffix = suffix.to_owned();
+        self
+    }
+
+    /// Returns the configured manager.
+    pub fn build(&self) -> Self {
+        self.clone()
+    }
+
+    /// Returns the directory into which the backups are placed.
+    pub fn destination(&self) -> &Path {
+        &self.destination
+    }
+
+    /// Creates a backup of the specified file or directory in the directory of the manager
+    /// and removes the oldest backups of it, if the number of backups is limited.
+    /// Returns the path to the backup file if successful, otherwise returns an error.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to the file or directory to be backed up.
+    ///
+    /// # Errors
+    ///
+    /// This function can return the same errors as [`BackupOptions::backup`] and additionally:
+    ///
+    /// * `BackupError::InvalidArgument` - If the number of backups to keep is 0.
+    ///   No backup is created in this case.
+    /// * `BackupError::Io` - If an old backup can not be removed.
+    ///
+    /// # Details
+    /// The new backup is always kept, even if its name suggests that it is older than the other backups.
+    pub fn backup(&self, path: impl AsRef<Path>) -> Result<PathBuf, BackupError> {
+        let path = path.as_ref();
+
+        if self.keep == Some(0) {
+            return Err(BackupError::InvalidArgument(String::from(
+                "Number of backups to keep must be at least 1.",
+            )));
+        }
+
+        let backup_name = BackupOptions::new()
+            .copy(self.copy)
+            .prefix(&self.prefix)
+            .suffix(&self.suffix)
+            .destination(&self.destination)
+            .backup(path)?;
+
+        if let Some(keep) = self.keep {
+            let backups = self
+                .find(path)?
+                .into_iter()
+                .filter(|x| *x != backup_name)
+                .collect::<Vec<_>>();
+
+            let n_remove = backups.len().saturating_sub(keep - 1);
+            for old in backups.into_iter().take(n_remove) {
+                remove_path(&old)?;
+            }
+        }
+
+        Ok(backup_name)
+    }
+
+    /// Lists all backups of the specified file or directory located in the directory of the manager.
+    /// Returns the paths to the backups sorted from the oldest to the newest.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to the original file or directory. It does not have to exist.
+    ///
+    /// # Errors
+    ///
+    /// This function can return the following errors:
+    ///
+    /// * `BackupError::IsRoot`, `BackupError::EndsInDotDot` - If the `path` is not valid.
+    /// * `BackupError::Io` - If the directory of the manager can not be read.
+    ///
+    /// # Details
+    /// Only the name of the original is used, so the backups of files with the same name
+    /// located in different directories are not distinguished. If the directory of the manager
+    /// does not exist, no backups are returned.
+    pub fn list(&self, path: impl AsRef<Path>) -> Result<Vec<PathBuf>, BackupError> {
+        self.find(path.as_ref())
+    }
+
+    /// Removes the oldest backups of the specified file or directory located in the directory of the manager,
+    /// keeping the configured number of backups.
+    /// Returns information about the removed backups (from the oldest to the newest) if successful,
+    /// otherwise returns an error.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to the original file or directory. It does not have to exist.
+    ///
+    /// # Errors
+    ///
+    /// This function can return the same errors as [`BackupManager::list`] and additionally:
+    ///
+    /// * `BackupError::Io` - If a backup can not be removed.
+    ///
+    /// # Details
+    /// If the number of backups is not limited, nothing is removed. Directory backups are removed recursively.
+    /// The reason of every removal is [`PruneReason::ExceededCount`].
+    pub fn prune(&self, path: impl AsRef<Path>) -> Result<Vec<PrunedBackup>, BackupError> {
+        let Some(keep) = self.keep else {
+            return Ok(Vec::new());
+        };
+
+        let backups = self.find_timed(path.as_ref())?;
+
+        let n_remove = backups.len().saturating_sub(keep);
+        let mut removed = Vec::with_capacity(n_remove);
+        for (time, old) in backups.into_iter().take(n_remove) {
+            remove_path(&old)?;
+            removed.push(PrunedBackup::new(old, &time, PruneReason::ExceededCount));
+        }
+
+        Ok(removed)
+    }
+
+    /// Restores the most recent backup of the specified file or directory located in the directory of the manager.
+    /// Returns the path to the restored file if successful, otherwise returns an error.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to the original file or directory.
+    ///
+    /// # Errors
+    ///
+    /// This function can return the same errors as [`crate::restore`].
+    ///
+    /// # Details
+    /// The most recent backup is **renamed** back to `path`, same as in [`crate::restore`].
+    pub fn restore(&self, path: impl AsRef<Path>) -> Result<PathBuf, BackupError> {
+        let path = trim_trailing_separators(path.as_ref());
+        restore_newest_from(path, &self.destination, &self.prefix, &self.suffix, false)
+    }
+
+    /// Finds all backups of `path` located in the directory of the manager, sorted from the oldest to the newest.
+    fn find(&self, path: &Path) -> Result<Vec<PathBuf>, BackupError> {
+        Ok(self.find_timed(path)?.into_iter().map(|(_, x)| x).collect())
+    }
+
+    /// Finds all backups of `path` located in the directory of the manager together with their times,
+    /// sorted from the oldest to the newest.
+    fn find_timed(&self, path: &Path) -> Result<Vec<(BackupTime, PathBuf)>, BackupError> {
+        let (_, filename) = split_path(trim_trailing_separators(path))?;
+
+        // no backup has been created yet
+        if !self.destination.is_dir() {
+            return Ok(Vec::new());
+        }
+
+        find_backups(&self.destination, filename, &self.prefix, &self.suffix)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::test_dir;
+    use std::fs::{self, File};
+    use std::io::prelude::*;
+
+    #[test]
+    fn backup_and_prune() {
+        let tmp = test_dir();
+        let manager = BackupManager::new(tmp.join("backups"))
+            .keep(2)
+            .copy(true)
+            .build();
+
+        let path = &tmp.join("test_file.txt");
+        assert!(manager.list(path).unwrap().is_empty());
+
+        let mut backups = Vec::new();
+        for i in 0..4 {
+            let mut file = File::create(path).unwrap();
+            file.write_all(format!("Unique string for file {}", i).as_bytes())
+                .unwrap();
+            drop(file);
+
+            let backup = match manager.backup(path) {
+                Ok(x) => x,
+                Err(_) => panic!("Backup failed."),
+            };
+            assert_eq!(backup.parent(), Some(tmp.join("backups").as_path()));
+            backups.push(backup);
+
+            // the oldest backups are removed automatically
+            assert_eq!(manager.list(path).unwrap().len(), (i + 1).min(2));
+        }
+
+        // only the two newest backups remain (names freed by pruning may be reused)
+        assert!(path.is_file());
+        let mut contents = manager
+            .list(path)
+            .unwrap()
+            .iter()
+            .map(|x| fs::read_to_string(x).unwrap())
+            .collect::<Vec<_>>();
+        contents.sort();
+        assert_eq!(
+            contents,
+            ["Unique string for file 2", "Unique string for file 3"]
+        );
+        assert!(backups.last().unwrap().is_file());
+        assert!(manager.prune(path).unwrap().is_empty());
+
+        // restore the newest backup
+        fs::remove_file(path).unwrap();
+        let restored = match manager.restore(path) {
+            Ok(x) => x,
+            Err(_) => panic!("Restoring failed."),
+        };
+        assert_eq!(&restored, path);
+        assert!(contents.contains(&fs::read_to_string(path).unwrap()));
+        assert_eq!(manager.list(path).unwrap().len(), 1);
+
+        match manager.restore(path) {
+            Ok(_) => panic!("Restoring should have failed, but it was successful."),
+            Err(e) => assert!(matches!(e, BackupError::AlreadyExists)),
+        }
+    }
+
+    #[test]
+    fn prune_explicitly() {
+        let tmp = test_dir();
+        fs::create_dir_all(tmp.join("test_dir_manager2/backups")).unwrap();
+        for name in [
+            "bak_test_file.txt-2023-06-27-21-01-13.old",
+            "bak_test_file.txt-2023-06-27-21-01-14.old",
+            "bak_test_file.txt-2023-06-27-21-01-15.old",
+            "#test_file.txt-2023-06-27-21-01-13#",
+        ] {
+            File::create(tmp.join("test_dir_manager2/backups").join(name)).unwrap();
+        }
+
+        let mut manager = BackupManager::new(tmp.join("test_dir_manager2/backups"));
+        manager.prefix("bak_").suffix(".old");
+
+        // all backups are kept by default
+        assert!(manager
+            .prune(tmp.join("test_dir_manager2/test_file.txt"))
+            .unwrap()
+            .is_empty());
+        assert_eq!(manager.list("test_file.txt").unwrap().len(), 3);
+
+        let removed = manager
+            .keep(1)
+            .prune(tmp.join("test_dir_manager2/test_file.txt"))
+            .unwrap();
+        assert!(removed
+            .iter()
+            .all(|x| x.reason == PruneReason::ExceededCount));
+        assert_eq!(
+            removed.into_iter().map(|x| x.path).collect::<Vec<_>>(),
+            vec![
+                tmp.join("test_dir_manager2/backups/bak_test_file.txt-2023-06-27-21-01-13.old"),
+                tmp.join("test_dir_manager2/backups/bak_test_file.txt-2023-06-27-21-01-14.old"),
+            ]
+        );
+
+        // backups with a different wrapper are not managed
+        assert!(tmp
+            .join("test_dir_manager2/backups/#test_file.txt-2023-06-27-21-01-13#")
+            .exists());
+
+        let restored = manager
+            .restore(tmp.join("test_dir_manager2/test_file.txt"))
+            .unwrap();
+        assert_eq!(restored, tmp.join("test_dir_manager2/test_file.txt"));
+        assert!(restored.is_file());
+        assert!(manager.list("test_file.txt").unwrap().is_empty());
+    }
+
+    #[test]
+    fn keep_zero() {
+        let tmp = test_dir();
+        File::create(tmp.join("test_file_manager_zero.txt")).unwrap();
+
+        match BackupManager::new(tmp.join("test_dir_manager3"))
+            .keep(0)
+            .backup(tmp.join("test_file_manager_zero.txt"))
+        {
+            Ok(_) => panic!("Backup should have failed, but it was successful."),
+            Err(e) => assert!(matches!(e, BackupError::InvalidArgument(_))),
+        }
+
+        assert!(!tmp.join("test_dir_manager3").exists());
+    }
+}
@@ -0,0 +1,300 @@
+// Released under MIT License.
+// Copyright (c) 2023 Ladislav Bartos
+
+//! Implementation of directory backups accompanied by a manifest.
+
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::fmt::Write as _;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Write as _};
+use std::path::{Path, PathBuf};
+
+use crate::verify::{collect_files, file_digest};
+use crate::{backup, write_via_temp, BackupError};
+
+/// Extension of the manifests of the backups.
+const MANIFEST_EXTENSION: &str = ".manifest.json";
+
+/// List of all files contained in a directory backup.
+///
+/// Written by [`backup_directory_with_manifest`] as a JSON file, so it can be read
+/// (e.g. using `serde_json`) and compared with the manifest of another backup.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Manifest {
+    /// Files of the backup sorted by their paths.
+    pub files: Vec<ManifestEntry>,
+}
+
+/// Information about a single file of a directory backup.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ManifestEntry {
+    /// Path to the file relative to the backup, with components separated by `/`.
+    pub path: String,
+    /// Size of the file in bytes.
+    pub size: u64,
+    /// SHA-256 digest of the content of the file as a lowercase hexadecimal string.
+    pub sha256: String,
+}
+
+/// Difference between a directory backup and its manifest. Returned by [`verify_manifest`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ManifestMismatch {
+    /// The file is listed in the manifest, but it is missing from the backup.
+    Missing(String),
+    /// The file is present in the backup, but it is not listed in the manifest.
+    Added(String),
+    /// The size or the content of the file does not match the manifest.
+    Modified(String),
+}
+
+/// Creates a backup of the specified directory accompanied by a manifest of its files.
+/// Returns the path to the backup and the path to the manifest if successful, otherwise returns an error.
+///
+/// # Arguments
+///
+/// * `path` - The path to the directory to be backed up.
+///
+/// # Errors
+///
+/// This function can return the same errors as [`crate::backup`] and additionally:
+///
+/// * `BackupError::Unsupported` - If the `path` is not a directory.
+/// * `BackupError::Io` - If the backup can not be read or the manifest can not be written.
+///   The backup itself is kept in this case.
+///
+/// # Details
+/// The backup is created in the same way as with [`crate::backup`]. All files of the backup are then listed
+/// together with their sizes and SHA-256 digests (see [`Manifest`]) in a JSON file named `<backup>.manifest.json`
+/// located next to the backup, so the content of the backup is never modified.
+/// Use [`verify_manifest`] to check the backup against the manifest later.
+///
+/// Note that the manifests are not removed when the backups are pruned.
+///
+/// # Examples
+///
+/// ```no_run
+/// use crate::backitup::backup_directory_with_manifest;
+///
+/// match backup_directory_with_manifest("output_dir") {
+///     Ok((backup_path, manifest)) => println!("Backup created: {:?} ({:?})", backup_path, manifest),
+///     Err(err) => eprintln!("Failed to create backup: {:?}", err),
+/// }
+/// ```
+pub fn backup_directory_with_manifest(
+    path: impl AsRef<Path>,
+) -> Result<(PathBuf, PathBuf), BackupError> {
+    let path = path.as_ref();
+
+    // check if the path exists
+    if !path.exists() {
+        return Err(BackupError::NotFound);
+    }
+
+    if !path.is_dir() {
+        return Err(BackupError::Unsupported(String::from(
+            "Manifests can only be created for directories.",
+        )));
+    }
+
+    let backup_name = backup(path)?;
+    let manifest = create_manifest(&backup_name)?;
+
+    let manifest_path = manifest_path(&backup_name);
+    write_via_temp(&manifest_path, |temp| {
+        let mut writer = BufWriter::new(File::create_new(temp)?);
+        serde_json::to_writer_pretty(&mut writer, &manifest)?;
+        writer.flush()
+    })?;
+
+    Ok((backup_name, manifest_path))
+}
+
+/// Checks that the content of a directory backup matches the manifest written by [`backup_directory_with_manifest`].
+/// Returns the differences between the backup and the manifest (sorted by the paths of the files) if successful,
+/// otherwise returns an error. No differences are returned for an intact backup.
+///
+/// # Arguments
+///
+/// * `backup_dir` - The path to the directory backup.
+///
+/// # Errors
+///
+/// This function can return the following errors:
+///
+/// * `BackupError::NotFound` - If the backup or its manifest (`<backup>.manifest.json`) does not exist.
+/// * `BackupError::Io` - If the backup can not be read or the manifest is not valid.
+///
+/// # Details
+/// The sizes and SHA-256 digests of all files of the backup are calculated again
+/// and compared with the entries of the manifest.
+///
+/// # Examples
+///
+/// ```no_run
+/// use crate::backitup::{backup_directory_with_manifest, verify_manifest};
+///
+/// let (backup_path, _) = backup_directory_with_manifest("output_dir").unwrap();
+/// match verify_manifest(&backup_path) {
+///     Ok(mismatches) if mismatches.is_empty() => println!("Backup is intact."),
+///     Ok(mismatches) => eprintln!("Backup has been modified: {:?}", mismatches),
+///     Err(err) => eprintln!("Failed to verify backup: {:?}", err),
+/// }
+/// ```
+pub fn verify_manifest(backup_dir: impl AsRef<Path>) -> Result<Vec<ManifestMismatch>, BackupError> {
+    let backup_dir = backup_dir.as_ref();
+
+    let expected: Manifest = match File::open(manifest_path(backup_dir)) {
+        Ok(file) => serde_json::from_reader(BufReader::new(file)).map_err(io::Error::from)?,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Err(BackupError::NotFound),
+        Err(e) => return Err(BackupError::Io(e)),
+    };
+
+    if !backup_dir.is_dir() {
+        return Err(BackupError::NotFound);
+    }
+
+    let mut actual = create_manifest(backup_dir)?
+        .files
+        .into_iter()
+        .map(|x| (x.path.clone(), x))
+        .collect::<BTreeMap<_, _>>();
+
+    // the files are reported sorted by their paths
+    let mut mismatches = BTreeMap::new();
+    for entry in expected.files {
+        let mismatch = match actual.remove(&entry.path) {
+            None => ManifestMismatch::Missing(entry.path.clone()),
+            Some(x) if x != entry => ManifestMismatch::Modified(entry.path.clone()),
+            Some(_) => continue,
+        };
+        mismatches.insert(entry.path, mismatch);
+    }
+
+    for path in actual.into_keys() {
+        mismatches.insert(path.clone(), ManifestMismatch::Added(path));
+    }
+
+    Ok(mismatches.into_values().collect())
+}
+
+/// Returns the path to the manifest of the backup at `backup_path`.
+fn manifest_path(backup_path: &Path) -> PathBuf {
+    let mut path = OsString::from(backup_path);
+    path.push(MANIFEST_EXTENSION);
+    PathBuf::from(path)
+}
+
+/// Lists all files of the directory at `path` together with their sizes and SHA-256 digests.
+fn create_manifest(path: &Path) -> Result<Manifest, io::Error> {
+    let mut files = Vec::new();
+    collect_files(path, PathBuf::new(), &mut files)?;
+    files.sort();
+
+    let mut entries = Vec::with_capacity(files.len());
+    for (name, file) in files {
+        let mut sha256 = String::new();
+        for byte in file_digest(&file)? {
+            // writing into a `String` never fails
+            let _ = write!(sha256, "{:02x}", byte);
+        }
+
+        // the paths are written with forward slashes on all platforms
+        let path = name
+            .components()
+            .map(|x| x.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+
+        entries.push(ManifestEntry {
+            path,
+            size: fs::metadata(&file)?.len(),
+            sha256,
+        });
+    }
+
+    Ok(Manifest { files: entries })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::test_dir;
+
+    #[test]
+    fn manifest() {
+        let tmp = test_dir();
+        fs::create_dir_all(tmp.join("dir/nested")).unwrap();
+        fs::write(tmp.join("dir/a.txt"), "Some content to test.").unwrap();
+        fs::write(tmp.join("dir/nested/b.txt"), "File B.").unwrap();
+        fs::write(tmp.join("dir/nested/c.txt"), "File C.").unwrap();
+
+        let (backup_path, manifest) = match backup_directory_with_manifest(tmp.join("dir")) {
+            Ok(x) => x,
+            Err(_) => panic!("Backup failed."),
+        };
+
+        assert!(!tmp.join("dir").exists());
+        assert_eq!(manifest, manifest_path(&backup_path));
+
+        let content: Manifest =
+            serde_json::from_str(&fs::read_to_string(&manifest).unwrap()).unwrap();
+        assert_eq!(
+            content.files[0],
+            ManifestEntry {
+                path: String::from("a.txt"),
+                size: 21,
+                sha256: String::from(
+                    "6f816441245f75165986ca7210ae36c71325e9ccc52d9d27d6d7d16f0a24dcb6"
+                ),
+            }
+        );
+        assert_eq!(
+            content
+                .files
+                .iter()
+                .map(|x| x.path.as_str())
+                .collect::<Vec<_>>(),
+            ["a.txt", "nested/b.txt", "nested/c.txt"]
+        );
+
+        assert!(verify_manifest(&backup_path).unwrap().is_empty());
+
+        // modify, remove, and add files
+        fs::write(backup_path.join("a.txt"), "Some content to test!").unwrap();
+        fs::remove_file(backup_path.join("nested/b.txt")).unwrap();
+        fs::write(backup_path.join("nested/d.txt"), "File D.").unwrap();
+
+        assert_eq!(
+            verify_manifest(&backup_path).unwrap(),
+            vec![
+                ManifestMismatch::Modified(String::from("a.txt")),
+                ManifestMismatch::Missing(String::from("nested/b.txt")),
+                ManifestMismatch::Added(String::from("nested/d.txt")),
+            ]
+        );
+    }
+
+    #[test]
+    fn manifest_errors() {
+        let tmp = test_dir();
+        fs::create_dir(tmp.join("test_dir_manifest2")).unwrap();
+        fs::write(
+            tmp.join("test_dir_manifest2/test_file.txt"),
+            "Some content to test.",
+        )
+        .unwrap();
+
+        match backup_directory_with_manifest(tmp.join("test_dir_manifest2/test_file.txt")) {
+            Ok(_) => panic!("Backup should have failed, but it was successful."),
+            Err(e) => assert!(matches!(e, BackupError::Unsupported(_))),
+        }
+
+        match verify_manifest(tmp.join("test_dir_manifest2")) {
+            Ok(_) => panic!("Verification should have failed, but it was successful."),
+            Err(e) => assert!(matches!(e, BackupError::NotFound)),
+        }
+
+        assert!(tmp.join("test_dir_manifest2/test_file.txt").exists());
+    }
+}
@@ -0,0 +1,141 @@
+// Released under MIT License.
+// Copyright (c) 2023 Ladislav Bartos
+
+//! Implementation of the counters of the created backups.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Statistics of the backups created by the process. Returned by [`backup_stats`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BackupStats {
+    /// Number of the successfully created backups.
+    pub count: u64,
+    /// Total size of the created backups in bytes.
+    pub bytes: u64,
+}
+
+/// Lock-free counters of the created backups.
+struct Counters {
+    count: AtomicU64,
+    bytes: AtomicU64,
+}
+
+impl Counters {
+    const fn new() -> Self {
+        Counters {
+            count: AtomicU64::new(0),
+            bytes: AtomicU64::new(0),
+        }
+    }
+
+    fn record(&self, bytes: u64) {
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.bytes.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    fn stats(&self) -> BackupStats {
+        BackupStats {
+            count: self.count.load(Ordering::Relaxed),
+            bytes: self.bytes.load(Ordering::Relaxed),
+        }
+    }
+
+    fn reset(&self) {
+        self.count.store(0, Ordering::Relaxed);
+        self.bytes.store(0, Ordering::Relaxed);
+    }
+}
+
+/// Counters shared by all threads of the process.
+static COUNTERS: Counters = Counters::new();
+
+/// Returns the number and the total size of the backups created since the start of the process
+/// (or since the last call to [`reset_backup_stats`]).
+///
+/// # Details
+/// All backups created by [`crate::backup`] and the other functions based on [`crate::BackupOptions`]
+/// (including [`crate::BackupManager`]) are counted. The size of a backup is the size of the backed up file,
+/// or the sum of the sizes of all files in the backed up directory.
+///
+/// The counters are updated using atomic operations without any locking. The number and the size
+/// are read separately, so they may not correspond to each other if backups are being created concurrently.
+///
+/// Requires the `metrics` feature.
+///
+/// # Examples
+///
+/// ```no_run
+/// use crate::backitup::{backup, backup_stats};
+///
+/// backup("data.txt").unwrap();
+/// let stats = backup_stats();
+/// println!("{} backups created ({} bytes)", stats.count, stats.bytes);
+/// ```
+pub fn backup_stats() -> BackupStats {
+    COUNTERS.stats()
+}
+
+/// Sets the number and the total size of the created backups (see [`backup_stats`]) to zero.
+///
+/// # Examples
+///
+/// ```no_run
+/// use crate::backitup::{backup_stats, reset_backup_stats};
+///
+/// reset_backup_stats();
+/// assert_eq!(backup_stats().count, 0);
+/// ```
+pub fn reset_backup_stats() {
+    COUNTERS.reset();
+}
+
+/// Counts a successfully created backup of the size `bytes`.
+pub(crate) fn record_backup(bytes: u64) {
+    COUNTERS.record(bytes);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::test_dir;
+    use std::fs;
+
+    #[test]
+    fn counters() {
+        let counters = Counters::new();
+        assert_eq!(counters.stats(), BackupStats::default());
+
+        counters.record(10);
+        counters.record(5);
+        assert_eq!(
+            counters.stats(),
+            BackupStats {
+                count: 2,
+                bytes: 15
+            }
+        );
+
+        counters.reset();
+        assert_eq!(counters.stats(), BackupStats::default());
+    }
+
+    #[test]
+    fn backups_counted() {
+        let tmp = test_dir();
+        fs::create_dir(tmp.join("dir")).unwrap();
+        fs::write(tmp.join("dir/test_file.txt"), "Some content.").unwrap();
+
+        // other tests may create backups concurrently
+        let before = backup_stats();
+        for _ in 0..3 {
+            fs::write(tmp.join("test_file.txt"), "Some content to test.").unwrap();
+            crate::backup(tmp.join("test_file.txt")).unwrap();
+        }
+        crate::backup(tmp.join("dir")).unwrap();
+        let after = backup_stats();
+
+        assert!(after.count >= before.count + 4);
+        assert!(after.bytes >= before.bytes + 3 * 21 + 13);
+    }
+}
@@ -0,0 +1,504 @@
+// Released under MIT License.
+// Copyright (c) 2023 Ladislav Bartos
+
+//! Implementation of the `NamingStrategy` trait.
+
+use std::ffi::{OsStr, OsString};
+use std::fmt::Write as _;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use chrono::prelude::*;
+
+use crate::list::is_valid_label;
+use crate::numbered::{find_highest, numbered_name};
+use crate::{
+    check_directory_link, check_file_type, check_name_length, check_writable, move_path,
+    split_path, trim_trailing_separators, BackupError, DEFAULT_FORMAT, DEFAULT_PREFIX,
+    DEFAULT_SEPARATOR, DEFAULT_SUFFIX,
+};
+
+/// Strategy for naming the backups used by [`backup_with_strategy`].
+///
+/// Implement this trait to name the backups in your own way, e.g. using the hash of the current git commit.
+/// The crate provides [`TimestampNaming`], [`NumberedNaming`], and, with the `uuid` feature, `UuidNaming`.
+///
+/// # Examples
+///
+/// ```
+/// use std::ffi::OsStr;
+/// use std::path::{Path, PathBuf};
+/// use backitup::NamingStrategy;
+///
+/// struct VersionNaming(&'static str);
+///
+/// impl NamingStrategy for VersionNaming {
+///     fn name(&self, parent: &Path, filename: &OsStr) -> PathBuf {
+///         let mut name = filename.to_owned();
+///         name.push(format!(".v{}", self.0));
+///         parent.join(name)
+///     }
+/// }
+/// ```
+pub trait NamingStrategy {
+    /// Returns the path to the backup of a file named `filename` located in `parent`.
+    fn name(&self, parent: &Path, filename: &OsStr) -> PathBuf;
+
+    /// Returns an alternate path to the backup, if the path returned by [`NamingStrategy::name`]
+    /// (or by the previous call of this method) is already taken. `attempt` starts at 1.
+    ///
+    /// By default, `-<attempt>` is appended to the name returned by [`NamingStrategy::name`].
+    fn alternate_name(&self, parent: &Path, filename: &OsStr, attempt: u32) -> PathBuf {
+        let mut name = self.name(parent, filename).into_os_string();
+        name.push(format!("-{}", attempt));
+        PathBuf::from(name)
+    }
+}
+
+/// Naming of the backups used by [`crate::backup`], i.e. `#<filename>-<timestamp>#` in local time.
+///
+/// Alternate names contain the microseconds (and a counter) after the timestamp.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TimestampNaming;
+
+impl TimestampNaming {
+    /// Returns the name of the backup created at `time`, optionally with microseconds and a counter.
+    fn name_at(
+        &self,
+        parent: &Path,
+        filename: &OsStr,
+        time: DateTime<Local>,
+        attempt: u32,
+    ) -> PathBuf {
+        let mut name = OsString::from(DEFAULT_PREFIX);
+        name.push(filename);
+        name.push(DEFAULT_SEPARATOR);
+        // writing into an `OsString` never fails
+        let _ = write!(name, "{}", time.format(DEFAULT_FORMAT));
+        if attempt > 0 {
+            let _ = write!(name, "-{}", time.timestamp_subsec_micros());
+        }
+        if attempt > 1 {
+            let _ = write!(name, "-{}", attempt);
+        }
+        name.push(DEFAULT_SUFFIX);
+
+        parent.join(name)
+    }
+}
+
+impl NamingStrategy for TimestampNaming {
+    fn name(&self, parent: &Path, filename: &OsStr) -> PathBuf {
+        self.name_at(parent, filename, Local::now(), 0)
+    }
+
+    fn alternate_name(&self, parent: &Path, filename: &OsStr, attempt: u32) -> PathBuf {
+        self.name_at(parent, filename, Local::now(), attempt)
+    }
+}
+
+/// Naming of the backups used by [`crate::backup_numbered`], i.e. `<filename>.<N>`,
+/// where `N` is by one larger than the largest number of the existing backups.
+///
+/// Alternate names are obtained by looking for the largest number again.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NumberedNaming;
+
+impl NamingStrategy for NumberedNaming {
+    fn name(&self, parent: &Path, filename: &OsStr) -> PathBuf {
+        // if the directory can not be read, the backup fails anyway
+        let number = match find_highest(parent, filename) {
+            Ok(Some((n, _))) => n.saturating_add(1),
+            _ => 1,
+        };
+
+        parent.join(numbered_name(filename, number))
+    }
+
+    fn alternate_name(&self, parent: &Path, filename: &OsStr, _attempt: u32) -> PathBuf {
+        self.name(parent, filename)
+    }
+}
+
+/// Naming of the backups using random UUIDs, i.e. `#<filename>-<uuid>#`.
+/// Requires the `uuid` feature.
+///
+/// Alternate names use a new random UUID.
+#[cfg(feature = "uuid")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct UuidNaming;
+
+#[cfg(feature = "uuid")]
+impl NamingStrategy for UuidNaming {
+    fn name(&self, parent: &Path, filename: &OsStr) -> PathBuf {
+        let mut name = OsString::from(DEFAULT_PREFIX);
+        name.push(filename);
+        name.push(DEFAULT_SEPARATOR);
+        name.push(uuid::Uuid::new_v4().hyphenated().to_string());
+        name.push(DEFAULT_SUFFIX);
+
+        parent.join(name)
+    }
+
+    fn alternate_name(&self, parent: &Path, filename: &OsStr, _attempt: u32) -> PathBuf {
+        self.name(parent, filename)
+    }
+}
+
+/// Number of names tried before the backup using a naming strategy fails.
+const STRATEGY_ATTEMPTS: u32 = 100;
+
+/// Creates a backup of the specified file or directory named using a custom naming strategy.
+/// Returns the path to the backup file if successful, otherwise returns an error.
+///
+/// # Arguments
+///
+/// * `path` - The path to the file or directory to be backed up.
+/// * `strategy` - The strategy providing the names of the backups.
+///
+/// # Errors
+///
+/// This function can return the following errors:
+///
+/// * `BackupError::NotFound` - If the specified `path` does not exist.
+/// * `BackupError::IsRoot`, `BackupError::EndsInDotDot` - If the `path` is not valid.
+/// * `BackupError::Unsupported` - If the `path` is a special file (e.g. a FIFO or a socket)
+///   or a directory symbolic link or junction on Windows.
+/// * `BackupError::InvalidPath` - If the name provided by the `strategy` has no filename
+///   (or, on Windows, contains characters not allowed in filenames).
+/// * `BackupError::NameTooLong` - If the name provided by the `strategy` exceeds the limit of the filesystem.
+/// * `BackupError::NoUniqueName` - If all names provided by the `strategy` are taken.
+/// * `BackupError::NotWritable` - If the directory of the backup (or of the original) is not writable.
+/// * `BackupError::Io` - If an I/O error occurs during the backup process.
+///
+/// # Details
+/// The original file (or directory) is **renamed** to the path returned by [`NamingStrategy::name`].
+/// If that path already exists, [`NamingStrategy::alternate_name`] is called with an increasing `attempt`
+/// until a free name is found (at most 100 times). An existing file is never overwritten.
+/// The directory of the backup is created if it does not exist.
+///
+/// # Examples
+///
+/// ```no_run
+/// use crate::backitup::{backup_with_strategy, NumberedNaming};
+///
+/// let path = "data.txt";
+/// match backup_with_strategy(path, &NumberedNaming) {
+///     Ok(backup_path) => println!("Backup created: {:?}", backup_path),
+///     Err(err) => eprintln!("Failed to create backup: {:?}", err),
+/// }
+/// ```
+pub fn backup_with_strategy(
+    path: impl AsRef<Path>,
+    strategy: &impl NamingStrategy,
+) -> Result<PathBuf, BackupError> {
+    backup_with_names(path.as_ref(), |parent, filename, attempt| match attempt {
+        0 => Ok(strategy.name(parent, filename)),
+        _ => Ok(strategy.alternate_name(parent, filename, attempt)),
+    })
+}
+
+/// Creates a backup of the specified file or directory with a suffix provided by `suffix_fn` appended to its name.
+/// Returns the path to the backup file if successful, otherwise returns an error.
+///
+/// # Arguments
+///
+/// * `path` - The path to the file or directory to be backed up.
+/// * `suffix_fn` - The function providing the suffixes, e.g. a node ID or a ULID.
+///
+/// # Errors
+///
+/// This function can return the same errors as [`backup_with_strategy`] and additionally:
+///
+/// * `BackupError::InvalidArgument` - If a suffix is empty or contains path separators or the characters `#`.
+///
+/// # Details
+/// The original file (or directory) is **renamed** to `#<filename>-<timestamp>-<suffix>#`.
+/// If the suffix is globally unique, the names of the backups do not collide even if backups from several hosts
+/// are later merged into a single directory. If the name is already taken, `suffix_fn` is called again
+/// and a counter is appended to the new suffix (at most 100 times). An existing file is never overwritten.
+///
+/// Note that the backups with a suffix are not recognized by [`crate::list_backups`] and the related functions.
+///
+/// # Examples
+///
+/// ```no_run
+/// use crate::backitup::backup_with_suffix_fn;
+///
+/// let path = "data.txt";
+/// match backup_with_suffix_fn(path, || String::from("node1")) {
+///     Ok(backup_path) => println!("Backup created: {:?}", backup_path),
+///     Err(err) => eprintln!("Failed to create backup: {:?}", err),
+/// }
+/// ```
+pub fn backup_with_suffix_fn(
+    path: impl AsRef<Path>,
+    suffix_fn: impl Fn() -> String,
+) -> Result<PathBuf, BackupError> {
+    let time = Local::now();
+
+    backup_with_names(path.as_ref(), |parent, filename, attempt| {
+        let suffix = suffix_fn();
+        if !is_valid_label(&suffix, DEFAULT_PREFIX, DEFAULT_SUFFIX) {
+            return Err(BackupError::InvalidArgument(format!(
+                "Suffix '{}' is empty or contains path separators or wrapper characters.",
+                suffix
+            )));
+        }
+
+        let mut name = OsString::from(DEFAULT_PREFIX);
+        name.push(filename);
+        name.push(DEFAULT_SEPARATOR);
+        // writing into an `OsString` never fails
+        let _ = write!(name, "{}-{}", time.format(DEFAULT_FORMAT), suffix);
+        if attempt > 0 {
+            let _ = write!(name, "-{}", attempt);
+        }
+        name.push(DEFAULT_SUFFIX);
+
+        Ok(parent.join(name))
+    })
+}
+
+/// Renames the file or directory at `path` to the first free name returned by `next_name`.
+/// `next_name` is called with an increasing `attempt` starting at 0.
+fn backup_with_names(
+    path: &Path,
+    mut next_name: impl FnMut(&Path, &OsStr, u32) -> Result<PathBuf, BackupError>,
+) -> Result<PathBuf, BackupError> {
+    let path = trim_trailing_separators(path);
+
+    // check if the path exists (broken symbolic links also exist)
+    if fs::symlink_metadata(path).is_err() {
+        return Err(BackupError::NotFound);
+    }
+
+    // special files and directory links on Windows can not be backed up
+    check_file_type(path, false)?;
+    check_directory_link(path, false)?;
+
+    let (parent, filename) = split_path(path)?;
+    check_writable(parent)?;
+
+    let mut backup_name = next_name(parent, filename, 0)?;
+    for attempt in 1..=STRATEGY_ATTEMPTS {
+        let Some(name) = backup_name.file_name() else {
+            return Err(BackupError::InvalidPath(format!(
+                "Name of the backup '{}' has no filename.",
+                backup_name.display()
+            )));
+        };
+
+        // check that the backup can be created on Windows
+        #[cfg(windows)]
+        crate::check_windows_name(&name.to_string_lossy())?;
+        #[cfg(not(windows))]
+        let _ = name;
+
+        check_name_length(&backup_name)?;
+
+        let dir = backup_name.parent().unwrap_or(Path::new(""));
+        if !dir.as_os_str().is_empty() {
+            fs::create_dir_all(dir)?;
+            check_writable(dir)?;
+        }
+
+        match move_path(path, &backup_name) {
+            Ok(()) => return Ok(backup_name),
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                backup_name = next_name(parent, filename, attempt)?
+            }
+            Err(e) => return Err(BackupError::Io(e)),
+        }
+    }
+
+    Err(BackupError::NoUniqueName)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::list::parse_backup_time;
+    use crate::testutil::test_dir;
+    use std::cell::Cell;
+    use std::fs::File;
+
+    #[test]
+    fn timestamp() {
+        let tmp = test_dir();
+        fs::create_dir(tmp.join("test_dir_strategy")).unwrap();
+        let path = &tmp.join("test_dir_strategy/test_file.txt");
+
+        let mut backups = Vec::new();
+        for i in 0..3 {
+            fs::write(path, format!("Unique string for file {}", i)).unwrap();
+
+            let backup = match backup_with_strategy(path, &TimestampNaming) {
+                Ok(x) => x,
+                Err(_) => panic!("Backup failed."),
+            };
+
+            assert!(!path.exists());
+            assert_eq!(
+                backup.parent(),
+                Some(tmp.join("test_dir_strategy").as_path())
+            );
+            let name = backup.file_name().unwrap().to_str().unwrap();
+            assert!(name.starts_with("#test_file.txt-"));
+            assert!(parse_backup_time(&backup).is_some());
+            assert_eq!(
+                fs::read_to_string(&backup).unwrap(),
+                format!("Unique string for file {}", i)
+            );
+            backups.push(backup);
+        }
+
+        // the names are unique even within the same second
+        assert_eq!(crate::list_backups(path).unwrap().len(), 3);
+
+        let time = Local.with_ymd_and_hms(2023, 6, 27, 21, 1, 13).unwrap();
+        let dir = Path::new("dir");
+        let filename = OsStr::new("data.txt");
+        assert_eq!(
+            TimestampNaming.name_at(dir, filename, time, 0),
+            dir.join("#data.txt-2023-06-27-21-01-13#")
+        );
+        assert_eq!(
+            TimestampNaming.name_at(dir, filename, time, 1),
+            dir.join("#data.txt-2023-06-27-21-01-13-0#")
+        );
+        assert_eq!(
+            TimestampNaming.name_at(dir, filename, time, 2),
+            dir.join("#data.txt-2023-06-27-21-01-13-0-2#")
+        );
+    }
+
+    #[test]
+    fn numbered() {
+        let tmp = test_dir();
+        let path = &tmp.join("test_file.txt");
+
+        for i in 1..=3 {
+            File::create(path).unwrap();
+            let backup = match backup_with_strategy(path, &NumberedNaming) {
+                Ok(x) => x,
+                Err(_) => panic!("Backup failed."),
+            };
+
+            assert_eq!(backup, tmp.join(format!("test_file.txt.{}", i)));
+        }
+    }
+
+    #[test]
+    fn suffix_fn() {
+        let tmp = test_dir();
+        let path = &tmp.join("test_file.txt");
+
+        let counter = Cell::new(0);
+        let suffix = || {
+            counter.set(counter.get() + 1);
+            format!("node{}", counter.get())
+        };
+
+        for i in 1..=3 {
+            fs::write(path, format!("Unique string for file {}", i)).unwrap();
+            let backup = match backup_with_suffix_fn(path, suffix) {
+                Ok(x) => x,
+                Err(_) => panic!("Backup failed."),
+            };
+
+            assert!(!path.exists());
+            let name = backup.file_name().unwrap().to_str().unwrap();
+            assert!(name.starts_with("#test_file.txt-"));
+            assert!(name.ends_with(&format!("-node{}#", i)));
+            assert_eq!(
+                fs::read_to_string(&backup).unwrap(),
+                format!("Unique string for file {}", i)
+            );
+        }
+
+        // a taken name is never overwritten
+        File::create(path).unwrap();
+        let first = backup_with_suffix_fn(path, || String::from("node")).unwrap();
+        File::create(path).unwrap();
+        let second = backup_with_suffix_fn(path, || String::from("node")).unwrap();
+        assert_ne!(first, second);
+        assert!(first.exists() && second.exists());
+
+        for invalid in ["", "node/1", "node#1"] {
+            File::create(path).unwrap();
+            match backup_with_suffix_fn(path, || String::from(invalid)) {
+                Ok(_) => panic!("Backup should have failed, but it was successful."),
+                Err(e) => assert!(matches!(e, BackupError::InvalidArgument(_))),
+            }
+            assert!(path.exists());
+        }
+    }
+
+    #[cfg(feature = "uuid")]
+    #[test]
+    fn uuid() {
+        let tmp = test_dir();
+        let path = &tmp.join("test_file.txt");
+
+        let mut backups = Vec::new();
+        for _ in 0..2 {
+            File::create(path).unwrap();
+            let backup = match backup_with_strategy(path, &UuidNaming) {
+                Ok(x) => x,
+                Err(_) => panic!("Backup failed."),
+            };
+
+            let name = backup.file_name().unwrap().to_str().unwrap();
+            let uuid = name
+                .strip_prefix("#test_file.txt-")
+                .and_then(|x| x.strip_suffix('#'))
+                .unwrap();
+            assert!(uuid::Uuid::parse_str(uuid).is_ok());
+            backups.push(backup);
+        }
+
+        assert_ne!(backups[0], backups[1]);
+    }
+
+    /// Strategy always returning the same name.
+    struct FixedNaming;
+
+    impl NamingStrategy for FixedNaming {
+        fn name(&self, parent: &Path, _filename: &OsStr) -> PathBuf {
+            parent.join("backup")
+        }
+    }
+
+    #[test]
+    fn collisions() {
+        let tmp = test_dir();
+        let path = &tmp.join("test_file.txt");
+
+        File::create(tmp.join("backup")).unwrap();
+        File::create(tmp.join("backup-1")).unwrap();
+
+        fs::write(path, "Some content to test.").unwrap();
+        let backup = match backup_with_strategy(path, &FixedNaming) {
+            Ok(x) => x,
+            Err(_) => panic!("Backup failed."),
+        };
+
+        // the default alternate names are used
+        assert_eq!(backup, tmp.join("backup-2"));
+        assert_eq!(
+            fs::read_to_string(&backup).unwrap(),
+            "Some content to test."
+        );
+        assert_eq!(fs::read_to_string(tmp.join("backup")).unwrap(), "");
+    }
+
+    #[test]
+    fn strategy_nonexistent() {
+        match backup_with_strategy("nonexistent.txt", &TimestampNaming) {
+            Ok(_) => panic!("Backup should have failed, but it was successful."),
+            Err(e) => assert!(matches!(e, BackupError::NotFound)),
+        }
+    }
+}
@@ -0,0 +1,300 @@
+// Released under MIT License.
+// Copyright (c) 2023 Ladislav Bartos
+
+//! Implementation of numbered backups.
+
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use crate::{
+    check_directory_link, check_file_type, dir_or_current, rename_noreplace, split_path,
+    BackupError,
+};
+
+/// Creates a numbered backup of the specified file or directory.
+/// Returns the path to the backup file if successful, otherwise returns an error.
+///
+/// # Arguments
+///
+/// * `path` - The path to the file or directory to be backed up.
+///
+/// # Errors
+///
+/// This function can return the following errors:
+///
+/// * `BackupError::NotFound` - If the specified `path` does not exist.
+/// * `BackupError::IsRoot`, `BackupError::EndsInDotDot` - If the `path` is not valid.
+/// * `BackupError::Unsupported` - If the `path` is a special file (e.g. a FIFO or a socket)
+///   or a directory symbolic link or junction on Windows.
+/// * `BackupError::Io` - If an I/O error occurs during the backup process.
+///
+/// # Details
+/// The original file (or directory) is renamed to `<filename>.<N>`, where `N` is by one larger
+/// than the largest number of the existing backups of the file (or 1, if there are no backups).
+/// The backups are located in the parent directory of `path`.
+/// The current time is not used, so the names of the backups do not depend on the system clock.
+///
+/// For instance, backing up `data.txt` three times creates `data.txt.1`, `data.txt.2`, and `data.txt.3`.
+///
+/// # Examples
+///
+/// ```no_run
+/// use crate::backitup::backup_numbered;
+///
+/// let path = "data.txt";
+/// match backup_numbered(path) {
+///     Ok(backup_path) => println!("Backup created: {:?}", backup_path),
+///     Err(err) => eprintln!("Failed to create backup: {:?}", err),
+/// }
+/// ```
+pub fn backup_numbered(path: impl AsRef<Path>) -> Result<PathBuf, BackupError> {
+    let path = path.as_ref();
+
+    // check if the path exists
+    if !path.exists() {
+        return Err(BackupError::NotFound);
+    }
+
+    // special files and directory links on Windows can not be backed up
+    check_file_type(path, false)?;
+    check_directory_link(path, false)?;
+
+    let (parent, filename) = split_path(path)?;
+
+    loop {
+        let number = match find_highest(parent, filename)? {
+            Some((n, _)) => n.checked_add(1).ok_or(BackupError::NoUniqueName)?,
+            None => 1,
+        };
+
+        let backup_name = parent.join(numbered_name(filename, number));
+
+        // check that the backup can be created on Windows
+        #[cfg(windows)]
+        if let Some(name) = backup_name.file_name() {
+            crate::check_windows_name(&name.to_string_lossy())?;
+        }
+
+        // if the name has been taken in the meantime, look for the highest number again
+        match rename_noreplace(path, &backup_name) {
+            Ok(()) => return Ok(backup_name),
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(BackupError::Io(e)),
+        }
+    }
+}
+
+/// Restores the numbered backup of the specified file or directory with the largest number.
+/// Returns the path to the restored file if successful, otherwise returns an error.
+///
+/// # Arguments
+///
+/// * `path` - The path to the original file or directory.
+///
+/// # Errors
+///
+/// This function can return the following errors:
+///
+/// * `BackupError::AlreadyExists` - If the specified `path` already exists.
+/// * `BackupError::NoBackup` - If no numbered backup of the `path` exists.
+/// * `BackupError::IsRoot`, `BackupError::EndsInDotDot` - If the `path` is not valid.
+/// * `BackupError::Io` - If an I/O error occurs during the restoration.
+///
+/// # Details
+/// The backup named `<filename>.<N>` with the largest `N` (see [`backup_numbered`])
+/// is **renamed** back to `path`.
+///
+/// # Examples
+///
+/// ```no_run
+/// use crate::backitup::restore_numbered;
+///
+/// let path = "data.txt";
+/// match restore_numbered(path) {
+///     Ok(restored) => println!("Backup restored: {:?}", restored),
+///     Err(err) => eprintln!("Failed to restore backup: {:?}", err),
+/// }
+/// ```
+pub fn restore_numbered(path: impl AsRef<Path>) -> Result<PathBuf, BackupError> {
+    let path = path.as_ref();
+    let (parent, filename) = split_path(path)?;
+
+    if path.exists() {
+        return Err(BackupError::AlreadyExists);
+    }
+
+    let highest = match find_highest(parent, filename)? {
+        Some((_, x)) => x,
+        None => return Err(BackupError::NoBackup),
+    };
+
+    match rename_noreplace(&highest, path) {
+        Ok(()) => Ok(path.to_path_buf()),
+        Err(e) if e.kind() == ErrorKind::AlreadyExists => Err(BackupError::AlreadyExists),
+        Err(e) => Err(BackupError::Io(e)),
+    }
+}
+
+/// Returns the name of the numbered backup of a file named `filename`.
+pub(crate) fn numbered_name(filename: &OsStr, number: u64) -> OsString {
+    let mut name = filename.to_owned();
+    name.push(format!(".{}", number));
+    name
+}
+
+/// Parses the name of a numbered backup of a file named `filename`.
+/// Returns `None` if `name` does not match the pattern `<filename>.<N>`.
+fn parse_numbered_name(name: &OsStr, filename: &OsStr) -> Option<u64> {
+    let number = name
+        .as_encoded_bytes()
+        .strip_prefix(filename.as_encoded_bytes())?
+        .strip_prefix(b".")?;
+
+    // numbers with leading zeros are never created
+    if number.is_empty() || number[0] == b'0' || !number.iter().all(u8::is_ascii_digit) {
+        return None;
+    }
+
+    std::str::from_utf8(number).ok()?.parse::<u64>().ok()
+}
+
+/// Finds the numbered backup of a file named `filename` located in `dir` with the largest number.
+pub(crate) fn find_highest(
+    dir: &Path,
+    filename: &OsStr,
+) -> Result<Option<(u64, PathBuf)>, BackupError> {
+    let mut highest: Option<(u64, PathBuf)> = None;
+
+    for entry in fs::read_dir(dir_or_current(dir))? {
+        let entry = entry?;
+        let name = entry.file_name();
+
+        if let Some(number) = parse_numbered_name(&name, filename) {
+            if highest.as_ref().is_none_or(|(n, _)| number > *n) {
+                highest = Some((number, dir.join(name)));
+            }
+        }
+    }
+
+    Ok(highest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::test_dir;
+    use std::fs::File;
+    use std::io::prelude::*;
+
+    #[test]
+    fn parse() {
+        let filename = OsStr::new("data.txt");
+
+        assert_eq!(
+            parse_numbered_name(OsStr::new("data.txt.1"), filename),
+            Some(1)
+        );
+        assert_eq!(
+            parse_numbered_name(OsStr::new("data.txt.42"), filename),
+            Some(42)
+        );
+
+        for name in [
+            "data.txt",
+            "data.txt.",
+            "data.txt.0",
+            "data.txt.01",
+            "data.txt.-1",
+            "data.txt.1a",
+            "data.txt1",
+            "other.txt.1",
+            "#data.txt-2023-06-27-21-01-13#",
+        ] {
+            assert_eq!(
+                parse_numbered_name(OsStr::new(name), filename),
+                None,
+                "{}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn numbered() {
+        let tmp = test_dir();
+
+        for i in 1..=3 {
+            let mut file = File::create(tmp.join("test_file.txt")).unwrap();
+            let text = format!("Unique string for file {}", i);
+            file.write_all(text.as_bytes()).unwrap();
+
+            let backup = match backup_numbered(tmp.join("test_file.txt")) {
+                Ok(x) => x,
+                Err(_) => panic!("Backup failed."),
+            };
+
+            assert_eq!(backup, tmp.join(format!("test_file.txt.{}", i)));
+            assert!(!tmp.join("test_file.txt").exists());
+        }
+
+        for i in 1..=3 {
+            let content = fs::read_to_string(tmp.join(format!("test_file.txt.{}", i))).unwrap();
+            assert_eq!(content, format!("Unique string for file {}", i));
+        }
+
+        let restored = match restore_numbered(tmp.join("test_file.txt")) {
+            Ok(x) => x,
+            Err(_) => panic!("Restoring failed."),
+        };
+
+        assert_eq!(restored, tmp.join("test_file.txt"));
+        let content = fs::read_to_string(tmp.join("test_file.txt")).unwrap();
+        assert_eq!(content, "Unique string for file 3");
+        assert!(!tmp.join("test_file.txt.3").exists());
+
+        // the next backup gets the number of the restored backup
+        let backup = backup_numbered(tmp.join("test_file.txt")).unwrap();
+        assert_eq!(backup, tmp.join("test_file.txt.3"));
+    }
+
+    #[test]
+    fn numbered_gap() {
+        let tmp = test_dir();
+        File::create(tmp.join("test_file.txt")).unwrap();
+        File::create(tmp.join("test_file.txt.2")).unwrap();
+        File::create(tmp.join("test_file.txt.10")).unwrap();
+
+        let backup = backup_numbered(tmp.join("test_file.txt")).unwrap();
+        assert_eq!(backup, tmp.join("test_file.txt.11"));
+    }
+
+    #[test]
+    fn restore_numbered_errors() {
+        let tmp = test_dir();
+
+        match restore_numbered(tmp.join("test_file.txt")) {
+            Ok(_) => panic!("Restoring should have failed, but it was successful."),
+            Err(e) => assert!(matches!(e, BackupError::NoBackup)),
+        }
+
+        File::create(tmp.join("test_file.txt")).unwrap();
+        File::create(tmp.join("test_file.txt.1")).unwrap();
+
+        match restore_numbered(tmp.join("test_file.txt")) {
+            Ok(_) => panic!("Restoring should have failed, but it was successful."),
+            Err(e) => assert!(matches!(e, BackupError::AlreadyExists)),
+        }
+
+        assert!(tmp.join("test_file.txt.1").exists());
+    }
+
+    #[test]
+    fn numbered_nonexistent() {
+        match backup_numbered("nonexistent.txt") {
+            Ok(_) => panic!("Backup should have failed, but it was successful."),
+            Err(e) => assert!(matches!(e, BackupError::NotFound)),
+        }
+    }
+}
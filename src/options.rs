@@ -0,0 +1,1892 @@
+// Released under MIT License.
+// Copyright (c) 2023 Ladislav Bartos
+
+//! Implementation of the `BackupOptions` structure.
+
+use std::ffi::OsStr;
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
+
+use chrono::prelude::*;
+
+use crate::clock::{Clock, FixedClock, SystemClock};
+use crate::list::{is_valid_label, split_backup_name_separated};
+use crate::{
+    check_directory_link, check_file_type, check_writable, copy_recursive, dir_or_current,
+    format_time, is_illegal_char, lock_file, move_path, move_path_with, parent_dir,
+    rename_noreplace, replace_path, split_extension, split_path, sync_dir, temp_path,
+    trim_trailing_separators, write_temp_and_rename, write_via_temp, Backup, BackupError,
+    DEFAULT_FORMAT, DEFAULT_PREFIX, DEFAULT_SEPARATOR, DEFAULT_SUFFIX,
+};
+
+/// Options and flags which can be used to configure how a backup is created.
+///
+/// Options are set by chaining the methods on a `BackupOptions` structure,
+/// similarly to [`std::fs::OpenOptions`]. The backup is then created by calling [`BackupOptions::backup`].
+///
+/// By default, the options correspond to the behavior of [`crate::backup`],
+/// i.e. the original file is renamed (never copied across filesystems), local time is used for the timestamp,
+/// the backup name is wrapped in `#` and the timestamp has the format "YYYY-MM-DD-HH-MM-SS".
+///
+/// # Examples
+///
+/// ```no_run
+/// use crate::backitup::BackupOptions;
+///
+/// let options = BackupOptions::new()
+///     .copy(true)
+///     .utc(true)
+///     .prefix("bak_")
+///     .suffix("")
+///     .build();
+///
+/// match options.backup("data.txt") {
+///     Ok(backup_path) => println!("Backup created: {:?}", backup_path),
+///     Err(err) => eprintln!("Failed to create backup: {:?}", err),
+/// }
+/// ```
+///
+/// With the `serde` feature enabled, the options can be serialized and deserialized.
+/// Missing fields are set to their default values.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct BackupOptions {
+    copy: bool,
+    utc: bool,
+    prefix: String,
+    suffix: String,
+    separator: String,
+    label: Option<String>,
+    keep_extension: bool,
+    format: String,
+    destination: Option<PathBuf>,
+    reject_backups: bool,
+    follow_symlinks: bool,
+    retries: u32,
+    retry_delay: Duration,
+    durable: bool,
+    lock: Option<Duration>,
+    overwrite_same_second: bool,
+    allow_special_files: bool,
+    allow_directory_links: bool,
+    max_collisions: u32,
+    cross_device: bool,
+    extension: String,
+    canonicalize: bool,
+}
+
+impl Default for BackupOptions {
+    fn default() -> Self {
+        BackupOptions {
+            copy: false,
+            utc: false,
+            prefix: String::from(DEFAULT_PREFIX),
+            suffix: String::from(DEFAULT_SUFFIX),
+            separator: String::from(DEFAULT_SEPARATOR),
+            label: None,
+            keep_extension: false,
+            format: String::from(DEFAULT_FORMAT),
+            destination: None,
+            reject_backups: false,
+            follow_symlinks: false,
+            retries: 0,
+            retry_delay: Duration::ZERO,
+            durable: false,
+            lock: None,
+            overwrite_same_second: false,
+            allow_special_files: false,
+            allow_directory_links: false,
+            max_collisions: DEFAULT_MAX_COLLISIONS,
+            cross_device: false,
+            extension: String::new(),
+            canonicalize: false,
+        }
+    }
+}
+
+impl BackupOptions {
+    /// Creates a new set of options with the default values.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets whether the original file (or directory) should be copied instead of renamed.
+    ///
+    /// Directories are copied recursively. The copy is written into a temporary sibling of the backup
+    /// which is renamed to the name of the backup once complete. Default: `false`.
+    pub fn copy(&mut self, copy: bool) -> &mut Self {
+        self.copy = copy;
+        self
+    }
+
+    /// Sets whether the timestamp should be in UTC instead of local time.
+    ///
+    /// Default: `false`.
+    pub fn utc(&mut self, utc: bool) -> &mut Self {
+        self.utc = utc;
+        self
+    }
+
+    /// Sets the string placed in front of the name of the backup.
+    ///
+    /// Default: `"#"`.
+    pub fn prefix(&mut self, prefix: &str) -> &mut Self {
+        self.prefix = prefix.to_owned();
+        self
+    }
+
+    /// Sets the string placed at the end of the name of the backup.
+    ///
+    /// Default: `"#"`.
+    pub fn suffix(&mut self, suffix: &str) -> &mut Self {
+        self.suffix = suffix.to_owned();
+        self
+    }
+
+    /// Sets the string placed between the filename and the timestamp.
+    ///
+    /// A separator that does not appear in the filenames (e.g. `"@@"`) makes the names of the backups
+    /// easier to read and split. Use [`crate::parse_backup_time_with_separator`] to parse such names.
+    /// Default: `"-"`.
+    pub fn separator(&mut self, separator: &str) -> &mut Self {
+        self.separator = separator.to_owned();
+        self
+    }
+
+    /// Sets the label placed between the filename and the timestamp, e.g. `"before-migration"`.
+    ///
+    /// The backup is then named `#<filename>-<label>-<timestamp>#`. The label must not be empty
+    /// and must not contain path separators or the characters of the prefix and suffix.
+    /// Default: `None`, i.e. no label.
+    pub fn label(&mut self, label: &str) -> &mut Self {
+        self.label = Some(label.to_owned());
+        self
+    }
+
+    /// Sets whether the extension of the original file should be kept at the end of the name of the backup.
+    ///
+    /// If set, the timestamp is inserted before the extension (as returned by [`std::path::Path::extension`]),
+    /// so that the backup can still be opened by the applications associated with the extension.
+    /// For instance, `data.txt` is backed up as `#data-2023-06-27-21-01-13#.txt`.
+    /// Files without an extension (including dotfiles such as `.bashrc`) are named as usual. Default: `false`.
+    pub fn keep_extension(&mut self, keep: bool) -> &mut Self {
+        self.keep_extension = keep;
+        self
+    }
+
+    /// Sets the format of the timestamp. See [`chrono::format::strftime`] for the supported specifiers.
+    ///
+    /// Default: `"%Y-%m-%d-%H-%M-%S"`.
+    pub fn format(&mut self, fmt: &str) -> &mut Self {
+        self.format = fmt.to_owned();
+        self
+    }
+
+    /// Sets the directory into which the backup should be placed.
+    ///
+    /// The directory is created if it does not exist.
+    /// If the directory is located on a different filesystem than the original file, the backup fails,
+    /// unless [`BackupOptions::cross_device`] is set. In that case, the original is copied into a temporary file
+    /// in the directory which is then renamed to the name of the backup, so a crash never leaves a partially
+    /// written backup behind. The original is removed afterwards.
+    /// Default: `None`, i.e. the backup is placed next to the original.
+    pub fn destination(&mut self, dir: impl AsRef<Path>) -> &mut Self {
+        self.destination = Some(dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// Sets whether paths that are already backups should be rejected.
+    ///
+    /// If set, backing up a path whose name matches the pattern of the backup names
+    /// (e.g. `#data.txt-2023-06-27-21-01-13#`) fails with `BackupError::IsBackup`,
+    /// so that the names of the backups are never nested. Default: `false`.
+    pub fn reject_backups(&mut self, reject: bool) -> &mut Self {
+        self.reject_backups = reject;
+        self
+    }
+
+    /// Sets whether symbolic links should be followed.
+    ///
+    /// If not set, a symbolic link is backed up as is, i.e. the link itself is renamed (or copied
+    /// into a new link pointing to the same path) and the file it points to is never touched.
+    /// If set, the target of the link is resolved (using [`std::fs::canonicalize`]) and the target
+    /// is backed up instead. The target is always **copied**, so the link keeps pointing to it,
+    /// and the backup is placed next to the target (unless a destination is set). Default: `false`.
+    ///
+    /// Only the link at the backed up path is followed. Symbolic links inside copied directories
+    /// are always copied as links.
+    pub fn follow_symlinks(&mut self, follow: bool) -> &mut Self {
+        self.follow_symlinks = follow;
+        self
+    }
+
+    /// Sets whether paths without a filename should be canonicalized instead of rejected.
+    ///
+    /// If not set, paths ending in `..` (or consisting only of `.`) can not be backed up.
+    /// If set, such paths are resolved (using [`std::fs::canonicalize`]) into absolute paths
+    /// with a real filename and parent, which are then backed up. Other paths are not affected.
+    /// The root of the filesystem is still rejected. Default: `false`.
+    pub fn canonicalize(&mut self, canonicalize: bool) -> &mut Self {
+        self.canonicalize = canonicalize;
+        self
+    }
+
+    /// Sets how many times the creation of the backup should be retried after a transient I/O error.
+    ///
+    /// On networked filesystems, the final rename (or copy) of the backup may fail with errors
+    /// that succeed on retry. If the creation of the backup fails with `ErrorKind::Interrupted`,
+    /// `ErrorKind::WouldBlock`, or `ErrorKind::TimedOut`, the thread sleeps for `delay`,
+    /// a new unique name for the backup is generated, and the creation is tried again,
+    /// at most `attempts` times. Other errors are returned immediately. Default: `0`, i.e. no retries.
+    pub fn retries(&mut self, attempts: u32, delay: Duration) -> &mut Self {
+        self.retries = attempts;
+        self.retry_delay = delay;
+        self
+    }
+
+    /// Sets whether the backup should be flushed to the storage device once it is created.
+    ///
+    /// A rename is not guaranteed to survive a crash (or a power loss) until the directory containing
+    /// the renamed entry is flushed. If set, the parent directory of the backup (and of the original,
+    /// if it has been moved) is `fsync`ed after the backup is created and copied files are flushed as well,
+    /// so that the backup is durable once [`BackupOptions::backup`] returns. On Windows, only the files are flushed,
+    /// since the directory metadata are journaled by the filesystem. This is slower, so the default is `false`.
+    pub fn durable(&mut self, durable: bool) -> &mut Self {
+        self.durable = durable;
+        self
+    }
+
+    /// Sets how long to wait for an advisory lock on the original file before the backup is created.
+    ///
+    /// If set, a shared lock (`flock` on Unix, `LockFileEx` on Windows) is acquired on the original file
+    /// before it is renamed (or copied) and released once the backup is created, so that writers
+    /// locking the file exclusively can not modify it in the meantime. If the lock can not be acquired
+    /// within `timeout`, the backup fails with `BackupError::Locked`. The lock is advisory on Unix,
+    /// i.e. only writers which lock the file themselves are blocked. Directories can not be locked.
+    /// Default: not set, i.e. the original is not locked.
+    pub fn lock(&mut self, timeout: Duration) -> &mut Self {
+        self.lock = Some(timeout);
+        self
+    }
+
+    /// Sets the maximal number of alternative names tried if the name of the backup is already taken.
+    ///
+    /// Every name with microseconds (or with a counter) generated after the first name counts as one attempt.
+    /// Once `max` attempts are exhausted, the backup fails with `BackupError::NoUniqueName`
+    /// (of kind `ErrorKind::AlreadyExists`), which bounds the time spent looking for a unique name,
+    /// e.g. if the names are occupied on purpose. If set to 0, the backup fails whenever the first name is taken.
+    /// Default: 1000.
+    pub fn max_collisions(&mut self, max: u32) -> &mut Self {
+        self.max_collisions = max;
+        self
+    }
+
+    /// Sets whether the original can be moved to a backup located on a different filesystem.
+    ///
+    /// Renaming only works within a single filesystem. If set, an original which can not be renamed
+    /// to its backup (e.g. because of bind mounts or a [`BackupOptions::destination`] on another drive)
+    /// is copied to the backup and then removed, which is slower and not atomic. Otherwise, the backup fails
+    /// with `BackupError::Io` of kind `ErrorKind::CrossesDevices` and the original is left untouched,
+    /// so a backup is either an atomic rename or nothing. Has no effect if [`BackupOptions::copy`] is set.
+    /// Default: `false`, same as [`crate::backup`] (use [`crate::backup_move`] to allow copying).
+    pub fn cross_device(&mut self, cross_device: bool) -> &mut Self {
+        self.cross_device = cross_device;
+        self
+    }
+
+    /// Sets whether an existing backup with the same timestamp should be overwritten.
+    ///
+    /// If set, the backup is always named using the timestamp without microseconds, and an existing
+    /// file (or directory) with this name (i.e. a backup created within the same second) is replaced
+    /// by the new backup. The new backup is first created at a temporary `.tmp` sibling and the
+    /// existing backup is only replaced once the new one is complete, so a failed backup keeps the
+    /// existing one. This is useful for idempotent operations that may be repeated within the same
+    /// second, since only one backup is kept instead of one per repetition.
+    /// Default: `false`, i.e. microseconds are appended to the name of the backup if the name is taken.
+    pub fn overwrite_same_second(&mut self, overwrite: bool) -> &mut Self {
+        self.overwrite_same_second = overwrite;
+        self
+    }
+
+    /// Sets whether special files (FIFOs, sockets, and device files) can be backed up.
+    ///
+    /// Backing up a special file is rarely intended, and reading from it (e.g. from a FIFO) may block indefinitely,
+    /// so by default, the backup of a special file fails with `BackupError::Unsupported`.
+    /// If set, the node itself is renamed in the same way as a regular file. Special files are never copied,
+    /// so the backup still fails if [`BackupOptions::copy`] (or [`BackupOptions::follow_symlinks`]) is set.
+    /// Default: `false`.
+    pub fn allow_special_files(&mut self, allow: bool) -> &mut Self {
+        self.allow_special_files = allow;
+        self
+    }
+
+    /// Sets whether directory symbolic links and junctions can be backed up on Windows.
+    ///
+    /// Directory links are common on Windows, and renaming them is easily confused with renaming
+    /// the linked directory, so by default, the backup of a directory link fails with `BackupError::Unsupported`.
+    /// If set, the link node itself is renamed, while the linked directory and its content are left untouched.
+    /// If [`BackupOptions::copy`] is set, the content of the linked directory is copied instead.
+    /// Other reparse points (e.g. cloud placeholders) and file symbolic links are always backed up as usual.
+    ///
+    /// On other platforms, symbolic links are always backed up as link nodes, so this option has no effect.
+    /// Default: `false`.
+    pub fn allow_directory_links(&mut self, allow: bool) -> &mut Self {
+        self.allow_directory_links = allow;
+        self
+    }
+
+    /// Sets the extension placed after the timestamp (inside the prefix and suffix).
+    /// Used by backups which change the format of the file, e.g. `".gz"`.
+    pub(crate) fn extension(&mut self, extension: &str) -> &mut Self {
+        self.extension = extension.to_owned();
+        self
+    }
+
+    /// Returns the configured options.
+    pub fn build(&self) -> Self {
+        self.clone()
+    }
+
+    /// Creates a backup of the specified file or directory using these options.
+    /// Returns the path to the backup file if successful, otherwise returns an error.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to the file or directory to be backed up.
+    ///
+    /// # Errors
+    ///
+    /// This function can return the following errors:
+    ///
+    /// * `BackupError::NotFound` - If the specified `path` does not exist.
+    /// * `BackupError::IsRoot`, `BackupError::EndsInDotDot` - If the `path` is not valid.
+    /// * `BackupError::InvalidFormat` - If the format of the timestamp is not valid.
+    /// * `BackupError::InvalidPath` - If the timestamp, prefix, or suffix contains characters not allowed in filenames.
+    /// * `BackupError::IsBackup` - If the `path` is already a backup and [`BackupOptions::reject_backups`] is set.
+    /// * `BackupError::Unsupported` - If the `path` is a special file (see [`BackupOptions::allow_special_files`])
+    ///   or a directory link on Windows (see [`BackupOptions::allow_directory_links`]).
+    /// * `BackupError::NameTooLong` - If the name of the backup exceeds the limit of the filesystem.
+    /// * `BackupError::NotWritable` - If the directory of the backup (or of the renamed original) is not writable.
+    /// * `BackupError::DestinationMissing` - If the directory of the backup is removed while the backup is being created.
+    /// * `BackupError::Locked` - If the original file can not be locked within the timeout set by [`BackupOptions::lock`].
+    /// * `BackupError::Unsupported` - If [`BackupOptions::lock`] is set and the `path` is a directory.
+    /// * `BackupError::Io` - If an I/O error occurs during the backup process.
+    pub fn backup(&self, path: impl AsRef<Path>) -> Result<PathBuf, BackupError> {
+        self.backup_detailed(path).map(|x| x.backup_path)
+    }
+
+    /// Creates a backup of the specified file or directory using these options.
+    /// Returns information about the created backup if successful, otherwise returns an error.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to the file or directory to be backed up.
+    ///
+    /// # Errors
+    ///
+    /// This function can return the same errors as [`BackupOptions::backup`].
+    pub fn backup_detailed(&self, path: impl AsRef<Path>) -> Result<Backup, BackupError> {
+        self.backup_hooked(path.as_ref(), |_, _| Ok(()), &SystemClock)
+    }
+
+    /// Creates a backup of the specified file or directory using these options,
+    /// taking the time of the backup from `clock`.
+    /// Returns the path to the backup file if successful, otherwise returns an error.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to the file or directory to be backed up.
+    /// * `clock` - The source of the current time.
+    ///
+    /// # Errors
+    ///
+    /// This function can return the same errors as [`BackupOptions::backup`].
+    ///
+    /// # Details
+    /// The `clock` is queried whenever a new candidate name for the backup is generated.
+    /// If the `clock` always returns the same time, the names of the backups created within
+    /// the same second are distinguished by the microseconds and then by a counter.
+    pub fn backup_with_clock(
+        &self,
+        path: impl AsRef<Path>,
+        clock: &impl Clock,
+    ) -> Result<PathBuf, BackupError> {
+        self.backup_hooked(path.as_ref(), |_, _| Ok(()), clock)
+            .map(|x| x.backup_path)
+    }
+
+    /// Creates a backup of the specified file or directory using these options,
+    /// calling `hook` right before the backup is created.
+    /// Returns the path to the backup file if successful, otherwise returns an error.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to the file or directory to be backed up.
+    /// * `hook` - Function called with the original path and the path to the backup.
+    ///
+    /// # Errors
+    ///
+    /// This function can return the same errors as [`BackupOptions::backup`] and additionally:
+    ///
+    /// * `BackupError::Io` - If the `hook` returns an error.
+    ///
+    /// # Details
+    /// The `hook` is called after a unique name for the backup has been found, but before
+    /// anything is renamed, copied, or created. If the `hook` returns an error, the backup is aborted
+    /// and the filesystem is left untouched.
+    pub fn backup_with_hook(
+        &self,
+        path: impl AsRef<Path>,
+        hook: impl FnOnce(&Path, &Path) -> std::io::Result<()>,
+    ) -> Result<PathBuf, BackupError> {
+        self.backup_hooked(path.as_ref(), hook, &SystemClock)
+            .map(|x| x.backup_path)
+    }
+
+    /// Creates a backup of the specified file or directory using these options,
+    /// calling `hook` right before the backup is created.
+    fn backup_hooked(
+        &self,
+        path: &Path,
+        hook: impl FnOnce(&Path, &Path) -> std::io::Result<()>,
+        clock: &dyn Clock,
+    ) -> Result<Backup, BackupError> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::info_span!("backup", path = %path.display()).entered();
+        #[cfg(feature = "tracing")]
+        tracing::debug!(path = %path.display(), "creating backup");
+
+        let result = self.create_backup(path, hook, clock).map_err(|e| match e {
+            // the original has been moved away during the backup (e.g. by a concurrent backup of the same path)
+            BackupError::Io(x)
+                if x.kind() == io::ErrorKind::NotFound && fs::symlink_metadata(path).is_err() =>
+            {
+                BackupError::NotFound
+            }
+            e => e,
+        });
+
+        #[cfg(feature = "tracing")]
+        match &result {
+            Ok(x) => tracing::info!(
+                backup_path = %x.backup_path.display(),
+                collision = x.had_microsecond_collision,
+                "backup created"
+            ),
+            Err(e) => tracing::warn!(error = %e, "backup failed"),
+        }
+
+        // the size of a backup which can not be read is not counted
+        #[cfg(feature = "metrics")]
+        if let Ok(x) = &result {
+            crate::metrics::record_backup(crate::path_size(&x.backup_path, false).unwrap_or(0));
+        }
+
+        result
+    }
+
+    /// Creates a backup of the specified file or directory using these options.
+    fn create_backup(
+        &self,
+        path: &Path,
+        hook: impl FnOnce(&Path, &Path) -> std::io::Result<()>,
+        clock: &dyn Clock,
+    ) -> Result<Backup, BackupError> {
+        // `data/` and `data` are backed up in the same way
+        let path = trim_trailing_separators(path);
+
+        // resolve paths without a filename (e.g. `data/..`), the root is still rejected later
+        let canonical;
+        let path = if self.canonicalize && split_path(path).is_err() {
+            canonical = fs::canonicalize(path).map_err(|e| match e.kind() {
+                io::ErrorKind::NotFound => BackupError::NotFound,
+                _ => BackupError::Io(e),
+            })?;
+            canonical.as_path()
+        } else {
+            path
+        };
+
+        // back up the target of the symbolic link instead of the link
+        let resolved;
+        let (path, copy) = if self.follow_symlinks && path.is_symlink() {
+            resolved = fs::canonicalize(path).map_err(|e| match e.kind() {
+                io::ErrorKind::NotFound => BackupError::NotFound,
+                _ => BackupError::Io(e),
+            })?;
+            // the target is copied
+            check_file_type(&resolved, false)?;
+            (resolved.as_path(), true)
+        } else {
+            (path, self.copy)
+        };
+
+        let name = self.get_unique_name(path, clock)?;
+        let was_directory = path.is_dir();
+
+        // the hook can abort the backup
+        hook(path, &name.path)?;
+
+        // the lock is held until the backup is created
+        let _lock = match self.lock {
+            Some(timeout) => Some(lock_file(path, timeout)?),
+            None => None,
+        };
+
+        if let Some(dir) = &self.destination {
+            fs::create_dir_all(dir)?;
+        }
+
+        // the backup is created in the parent directory of its path
+        check_writable(parent_dir(&name.path))?;
+        // the original is moved out of its parent directory
+        if !copy {
+            check_writable(split_path(path)?.0)?;
+        }
+
+        let name = self.create_unique(path, name, clock, |target| {
+            // the backup created within the same second is replaced once the new backup is complete
+            if self.overwrite_same_second && fs::symlink_metadata(target).is_ok() {
+                return self.replace_backup(path, target, copy);
+            }
+
+            if copy {
+                // copy the content of the original file (or directory) into the backup
+                write_via_temp(target, |temp| copy_recursive(path, temp))
+            } else {
+                // rename the original file to the backup name
+                // (the backup can be located on a different filesystem, e.g. due to bind mounts)
+                self.move_original(path, target)
+            }
+        })?;
+
+        if self.durable {
+            self.sync_backup(path, &name.path, copy)?;
+        }
+
+        Ok(Backup {
+            backup_path: name.path,
+            original_path: path.to_path_buf(),
+            timestamp: name.time.with_timezone(&Local),
+            was_directory,
+            had_microsecond_collision: name.collision,
+        })
+    }
+
+    /// Flushes the backup at `backup` (created from `path`) and the directories containing it to the storage device.
+    fn sync_backup(&self, path: &Path, backup: &Path, copy: bool) -> Result<(), BackupError> {
+        // the content of a copied file may still be cached
+        if copy && backup.is_file() {
+            fs::File::open(backup)?.sync_all()?;
+        }
+
+        let backup_dir = parent_dir(backup);
+        sync_dir(backup_dir)?;
+
+        // the original has been removed from its directory
+        if !copy {
+            let original_dir = dir_or_current(split_path(path)?.0);
+            if original_dir != backup_dir {
+                sync_dir(original_dir)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the path the backup of the specified file or directory would have with these options,
+    /// without creating the backup.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to the file or directory to be backed up.
+    ///
+    /// # Errors
+    ///
+    /// This function can return the same errors as [`BackupOptions::backup`].
+    ///
+    /// # Details
+    /// The `path` is validated and the name of the backup is checked against the filesystem
+    /// in the same way as in [`BackupOptions::backup`], so the returned path is not taken
+    /// at the time of the call. Nothing is renamed, copied, or created (not even the destination directory).
+    pub fn dry_run(&self, path: impl AsRef<Path>) -> Result<PathBuf, BackupError> {
+        self.get_unique_name(path.as_ref(), &SystemClock)
+            .map(|x| x.path)
+    }
+
+    /// Returns the name the backup of the specified file or directory created at `time` would have with these options.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to the file or directory to be backed up. It does not have to exist.
+    /// * `time` - The time of the backup.
+    ///
+    /// # Errors
+    ///
+    /// This function can return the same errors as [`BackupOptions::backup`], except for
+    /// `BackupError::NotFound` and `BackupError::Io`.
+    ///
+    /// # Details
+    /// The returned name is the first name tried by [`BackupOptions::backup`], i.e. it never contains microseconds.
+    /// The filesystem is never accessed, so the name may already be taken.
+    pub fn backup_name(
+        &self,
+        path: impl AsRef<Path>,
+        time: DateTime<Local>,
+    ) -> Result<PathBuf, BackupError> {
+        let clock = FixedClock(time);
+        self.backup_names(path.as_ref(), &clock)?.next_name()
+    }
+
+    /// Creates the backup of `path` by calling `create` with the unique `name` of the backup.
+    ///
+    /// `create` must fail with `ErrorKind::AlreadyExists` instead of overwriting an existing file.
+    /// If that happens (i.e. the name has been taken by someone else since it was generated),
+    /// a new unique name is generated and `create` is called again.
+    /// If `create` fails with a transient error, it is retried as configured by [`BackupOptions::retries`].
+    pub(crate) fn create_unique(
+        &self,
+        path: &Path,
+        mut name: UniqueName,
+        clock: &dyn Clock,
+        mut create: impl FnMut(&Path) -> io::Result<()>,
+    ) -> Result<UniqueName, BackupError> {
+        let mut collisions = 0;
+        let mut retries = 0;
+
+        while collisions < CREATE_ATTEMPTS {
+            match create(&name.path) {
+                Ok(()) => return Ok(name),
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                    collisions += 1;
+                    name = self.get_unique_name(path, clock)?
+                }
+                Err(e) if is_transient(e.kind()) && retries < self.retries => {
+                    retries += 1;
+                    #[cfg(feature = "tracing")]
+                    tracing::debug!(error = %e, attempt = retries, "retrying backup");
+
+                    std::thread::sleep(self.retry_delay);
+                    name = self.get_unique_name(path, clock)?
+                }
+                // the directory of the backup has been removed since the name was generated
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                    let dir = parent_dir(&name.path);
+                    return Err(match dir.is_dir() {
+                        true => BackupError::Io(e),
+                        false => BackupError::DestinationMissing(dir.to_path_buf()),
+                    });
+                }
+                Err(e) => return Err(BackupError::from(e)),
+            }
+        }
+
+        Err(BackupError::NoUniqueName)
+    }
+
+    /// Validates the `path` and generates a unique name for its backup.
+    /// Also returns the time used in the name and whether microseconds had to be appended.
+    pub(crate) fn get_unique_name(
+        &self,
+        path: &Path,
+        clock: &dyn Clock,
+    ) -> Result<UniqueName, BackupError> {
+        let path = trim_trailing_separators(path);
+
+        // check if the path exists (broken symbolic links also exist)
+        if fs::symlink_metadata(path).is_err() {
+            return Err(BackupError::NotFound);
+        }
+
+        // special files can only be renamed
+        check_file_type(path, self.allow_special_files && !self.copy)?;
+        check_directory_link(path, self.allow_directory_links)?;
+
+        let mut names = self.backup_names(path, clock)?;
+
+        // if a file with the same name already exists, append microseconds
+        // repeat until the name of the backup is unique
+        // (unless the existing backup should be overwritten)
+        let mut backup_name = names.next_name()?;
+        while !self.overwrite_same_second && backup_name.exists() {
+            backup_name = names.next_name()?;
+        }
+
+        // deep paths can only be created as extended-length paths on Windows
+        #[cfg(windows)]
+        let backup_name = crate::extend_long_path(backup_name);
+
+        Ok(UniqueName {
+            path: backup_name,
+            time: names.time(),
+            collision: names.collision(),
+        })
+    }
+
+    /// Creates the backup of `path` at the temporary sibling of `target` and then replaces the existing `target` with it.
+    fn replace_backup(&self, path: &Path, target: &Path, copy: bool) -> Result<(), io::Error> {
+        if copy {
+            return write_temp_and_rename(target, |temp| copy_recursive(path, temp), replace_path);
+        }
+
+        let temp = temp_path(target);
+        self.move_original(path, &temp)?;
+
+        replace_path(&temp, target).inspect_err(|_| {
+            // the original must not be lost
+            let _ = move_path(&temp, path);
+        })
+    }
+
+    /// Renames the original at `path` to `target`.
+    /// Falls back to copying across filesystems only if [`BackupOptions::cross_device`] is set.
+    fn move_original(&self, path: &Path, target: &Path) -> Result<(), io::Error> {
+        self.move_original_with(path, target, rename_noreplace)
+    }
+
+    /// Same as [`BackupOptions::move_original`], but renames using `rename`.
+    fn move_original_with(
+        &self,
+        path: &Path,
+        target: &Path,
+        rename: impl FnOnce(&Path, &Path) -> Result<(), io::Error>,
+    ) -> Result<(), io::Error> {
+        match self.cross_device {
+            true => move_path_with(path, target, rename),
+            false => rename(path, target),
+        }
+    }
+
+    /// Validates the `path` and prepares the generation of names for its backup.
+    /// Does not check whether the `path` exists.
+    pub(crate) fn backup_names<'a>(
+        &'a self,
+        path: &'a Path,
+        clock: &'a dyn Clock,
+    ) -> Result<BackupNames<'a>, BackupError> {
+        // get the parent directory and the filename from the path
+        let (parent, filename) = split_path(path)?;
+
+        self.backup_names_in(parent, filename, clock)
+    }
+
+    /// Prepares the generation of names for the backup of a file named `filename` located in `parent`.
+    /// Never accesses the filesystem.
+    pub(crate) fn backup_names_in<'a>(
+        &'a self,
+        parent: &'a Path,
+        filename: &'a OsStr,
+        clock: &'a dyn Clock,
+    ) -> Result<BackupNames<'a>, BackupError> {
+        // check that the wrapper can be used in a filename
+        if self
+            .prefix
+            .chars()
+            .chain(self.suffix.chars())
+            .any(is_illegal_char)
+        {
+            return Err(BackupError::InvalidPath(String::from(
+                "Prefix or suffix contains characters that are not allowed in filenames.",
+            )));
+        }
+
+        // check that the separator can be used in a filename
+        if self.separator.chars().any(is_illegal_char) {
+            return Err(BackupError::InvalidPath(String::from(
+                "Separator contains characters that are not allowed in filenames.",
+            )));
+        }
+
+        // check that the label can be used in the name of the backup
+        if let Some(label) = &self.label {
+            if !is_valid_label(label, &self.prefix, &self.suffix) {
+                return Err(BackupError::InvalidArgument(format!(
+                    "Label '{}' is empty or contains path separators or wrapper characters.",
+                    label
+                )));
+            }
+        }
+
+        // check that the path is not a backup itself
+        if self.reject_backups
+            && split_backup_name_separated(
+                filename.as_encoded_bytes(),
+                &self.prefix,
+                &self.separator,
+                &self.suffix,
+            )
+            .is_some()
+        {
+            return Err(BackupError::IsBackup);
+        }
+
+        // get the directory into which the backup should be placed
+        let parent = match &self.destination {
+            Some(dir) => dir.as_path(),
+            None => parent,
+        };
+
+        Ok(BackupNames {
+            options: self,
+            clock,
+            parent,
+            filename,
+            time: String::new(),
+            generated: false,
+            buffer: String::new(),
+            now: self.now(clock),
+            collision: false,
+            micro_attempts: 0,
+            collisions: 0,
+        })
+    }
+
+    /// Returns the current time obtained from `clock` in the configured time zone.
+    fn now(&self, clock: &dyn Clock) -> DateTime<FixedOffset> {
+        let now = clock.now();
+
+        if self.utc {
+            now.with_timezone(&Utc).fixed_offset()
+        } else {
+            now.fixed_offset()
+        }
+    }
+}
+
+/// Unique name of a backup.
+pub(crate) struct UniqueName {
+    pub(crate) path: PathBuf,
+    time: DateTime<FixedOffset>,
+    collision: bool,
+}
+
+/// Number of attempts to create the backup if its name is repeatedly taken right after being generated.
+const CREATE_ATTEMPTS: usize = 10;
+
+/// Returns `true` if an I/O error of this kind may succeed when the operation is retried.
+fn is_transient(kind: io::ErrorKind) -> bool {
+    matches!(
+        kind,
+        io::ErrorKind::Interrupted | io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+    )
+}
+
+/// Number of names with microseconds tried within the same second
+/// before a counter is appended to the name.
+const MICROSECOND_ATTEMPTS: usize = 3;
+
+/// Counter appended to the names of the backups once the microseconds are not enough to make them unique.
+/// Shared by all threads of the process, so concurrent backups never generate the same name.
+static COLLISION_COUNTER: AtomicU32 = AtomicU32::new(0);
+
+/// Returns the next value of [`COLLISION_COUNTER`]. Zero is skipped once the counter wraps around.
+fn next_counter() -> u32 {
+    loop {
+        let counter = COLLISION_COUNTER
+            .fetch_add(1, Ordering::Relaxed)
+            .wrapping_add(1);
+        if counter != 0 {
+            return counter;
+        }
+    }
+}
+
+/// Default maximal number of alternative names tried for a backup. See [`BackupOptions::max_collisions`].
+const DEFAULT_MAX_COLLISIONS: u32 = 1000;
+
+/// Generator of candidate names for a backup.
+pub(crate) struct BackupNames<'a> {
+    options: &'a BackupOptions,
+    clock: &'a dyn Clock,
+    parent: &'a Path,
+    filename: &'a OsStr,
+    /// Timestamp used by the previous candidate name (empty, if no name has been generated yet).
+    time: String,
+    /// Whether a candidate name has already been generated.
+    generated: bool,
+    /// Buffer into which the current timestamp is formatted. Reused to avoid allocations.
+    buffer: String,
+    /// Time at which the previous candidate name was generated.
+    now: DateTime<FixedOffset>,
+    /// Whether the previous candidate name contains microseconds.
+    collision: bool,
+    /// Number of names with microseconds generated for the current timestamp.
+    micro_attempts: usize,
+    /// Number of candidate names generated after the first one.
+    collisions: u32,
+}
+
+impl BackupNames<'_> {
+    /// Generates the next candidate name for the backup.
+    ///
+    /// The first name only contains the timestamp. If called again within the same second
+    /// (i.e. the previous name was already taken), microseconds are appended to the timestamp.
+    /// Once the timestamp changes, the name without microseconds is tried again,
+    /// which keeps the names of the backups ordered by their creation time.
+    ///
+    /// If the microseconds have not changed since the previous name (e.g. because the clock is frozen)
+    /// or the names with microseconds are taken repeatedly, a counter is appended after the microseconds.
+    /// The counter is shared by all threads of the process and only ever increases, so concurrent backups
+    /// never generate the same name, even at identical timestamps. Returns an error once
+    /// the number of generated names exceeds [`BackupOptions::max_collisions`], so that the generation always terminates.
+    pub(crate) fn next_name(&mut self) -> Result<PathBuf, BackupError> {
+        let options = self.options;
+
+        if self.generated {
+            if self.collisions >= options.max_collisions {
+                return Err(BackupError::NoUniqueName);
+            }
+            self.collisions += 1;
+        }
+
+        let now = options.now(self.clock);
+        format_time(&now, &options.format, &mut self.buffer)?;
+
+        // the microseconds and the counter (if any) appended to the timestamp
+        let (micros, counter) = if !self.generated || self.time != self.buffer {
+            self.now = now;
+            self.collision = false;
+            self.micro_attempts = 0;
+
+            self.generated = true;
+            std::mem::swap(&mut self.time, &mut self.buffer);
+            (None, None)
+        } else if self.micro_attempts < MICROSECOND_ATTEMPTS
+            // the same microseconds would only repeat the previous name
+            && !(self.collision && now.timestamp_subsec_micros() == self.now.timestamp_subsec_micros())
+        {
+            self.now = now;
+            self.collision = true;
+            self.micro_attempts += 1;
+
+            (Some(now.timestamp_subsec_micros()), None)
+        } else {
+            // the counter is appended to the previous name with microseconds
+            // (the counter is shared by all threads, so they never try the same name)
+            self.collision = true;
+            self.micro_attempts = MICROSECOND_ATTEMPTS;
+
+            (
+                Some(self.now.timestamp_subsec_micros()),
+                Some(next_counter()),
+            )
+        };
+
+        // the whole path is built in a single buffer
+        // (the microseconds and the counter take at most 18 characters)
+        let capacity = self.parent.as_os_str().len()
+            + 1
+            + options.prefix.len()
+            + self.filename.len()
+            + options.separator.len()
+            + options
+                .label
+                .as_ref()
+                .map_or(0, |x| x.len() + options.separator.len())
+            + self.time.len()
+            + 18
+            + options.extension.len()
+            + options.suffix.len();
+
+        let mut backup_name = PathBuf::with_capacity(capacity);
+        backup_name.push(self.parent);
+        // pushing an empty path only appends the separator of the path components
+        backup_name.push("");
+
+        // the extension of the original file is placed at the end of the name
+        let (stem, file_extension) = match options.keep_extension {
+            true => split_extension(self.filename),
+            false => (self.filename, None),
+        };
+
+        // the filename does not have to be a valid UTF-8, the rest of the name always is
+        let name = backup_name.as_mut_os_string();
+        name.push(&options.prefix);
+        name.push(stem);
+        name.push(&options.separator);
+        if let Some(label) = &options.label {
+            name.push(label);
+            name.push(&options.separator);
+        }
+        name.push(&self.time);
+        // writing into an `OsString` never fails
+        if let Some(micros) = micros {
+            let _ = write!(name, "-{}", micros);
+        }
+        if let Some(counter) = counter {
+            let _ = write!(name, "-{}", counter);
+        }
+        name.push(&options.extension);
+        name.push(&options.suffix);
+        if let Some(file_extension) = file_extension {
+            name.push(".");
+            name.push(file_extension);
+        }
+
+        // check that the backup can be created on Windows
+        #[cfg(windows)]
+        if let Some(name) = backup_name.file_name() {
+            crate::check_windows_name(&name.to_string_lossy())?;
+        }
+
+        // check that the name is not too long for the filesystem
+        crate::check_name_length(&backup_name)?;
+
+        Ok(backup_name)
+    }
+
+    /// Returns the time at which the previous candidate name was generated.
+    pub(crate) fn time(&self) -> DateTime<FixedOffset> {
+        self.now
+    }
+
+    /// Returns `true` if the previous candidate name contains microseconds.
+    pub(crate) fn collision(&self) -> bool {
+        self.collision
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::test_dir;
+    use std::collections::HashSet;
+    use std::fs::File;
+    use std::io::prelude::*;
+
+    #[test]
+    fn default_options() {
+        let options = BackupOptions::new();
+
+        assert!(!options.copy);
+        assert!(!options.utc);
+        assert_eq!(options.prefix, "#");
+        assert_eq!(options.suffix, "#");
+        assert_eq!(options.format, DEFAULT_FORMAT);
+        assert!(!options.reject_backups);
+        assert!(!options.follow_symlinks);
+        assert!(!options.canonicalize);
+    }
+
+    #[test]
+    fn builder() {
+        let options = BackupOptions::new()
+            .copy(true)
+            .utc(true)
+            .prefix("bak_")
+            .suffix(".old")
+            .format("%s")
+            .build();
+
+        assert!(options.copy);
+        assert!(options.utc);
+        assert_eq!(options.prefix, "bak_");
+        assert_eq!(options.suffix, ".old");
+        assert_eq!(options.format, "%s");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_options() {
+        let options = BackupOptions::new()
+            .copy(true)
+            .prefix("bak_")
+            .suffix(".old")
+            .destination("backups")
+            .build();
+
+        let json = serde_json::to_string(&options).unwrap();
+        let deserialized: BackupOptions = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized, options);
+
+        // missing fields are set to the default values
+        let partial: BackupOptions = serde_json::from_str(r#"{ "utc": true }"#).unwrap();
+        assert_eq!(partial, BackupOptions::new().utc(true).build());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_backup() {
+        let time = Local.with_ymd_and_hms(2023, 6, 27, 21, 1, 13).unwrap();
+        let backup = Backup {
+            backup_path: PathBuf::from("dir/#data.txt-2023-06-27-21-01-13#"),
+            original_path: PathBuf::from("dir/data.txt"),
+            timestamp: time,
+            was_directory: false,
+            had_microsecond_collision: true,
+        };
+
+        let json = serde_json::to_value(&backup).unwrap();
+        assert_eq!(json["backup_path"], "dir/#data.txt-2023-06-27-21-01-13#");
+        let timestamp = json["timestamp"].as_str().unwrap();
+        assert_eq!(DateTime::parse_from_rfc3339(timestamp).unwrap(), time);
+
+        let deserialized: Backup = serde_json::from_value(json).unwrap();
+        assert_eq!(deserialized.backup_path, backup.backup_path);
+        assert_eq!(deserialized.original_path, backup.original_path);
+        assert_eq!(deserialized.timestamp, backup.timestamp);
+        assert!(!deserialized.was_directory);
+        assert!(deserialized.had_microsecond_collision);
+    }
+
+    #[test]
+    fn prefix_suffix() {
+        let tmp = test_dir();
+        let mut file = File::create(tmp.join("test_options1.txt")).unwrap();
+        file.write_all(b"Some content to test.").unwrap();
+        drop(file);
+
+        let backup = match BackupOptions::new()
+            .prefix("bak_")
+            .suffix(".old")
+            .backup(tmp.join("test_options1.txt"))
+        {
+            Ok(x) => x,
+            Err(_) => panic!("Backup failed."),
+        };
+
+        let name = backup.file_name().unwrap().to_str().unwrap();
+        assert!(name.starts_with("bak_test_options1.txt-"));
+        assert!(name.ends_with(".old"));
+        assert!(!tmp.join("test_options1.txt").exists());
+
+        let mut content = String::new();
+        let mut read = File::open(&backup).unwrap();
+        read.read_to_string(&mut content).unwrap();
+
+        assert_eq!(content, "Some content to test.");
+    }
+
+    #[test]
+    fn copy_utc() {
+        let tmp = test_dir();
+        let mut file = File::create(tmp.join("test_options2.txt")).unwrap();
+        file.write_all(b"Some content to test.").unwrap();
+        drop(file);
+
+        let options = BackupOptions::new()
+            .copy(true)
+            .utc(true)
+            .format("%Y-%m-%d-%H")
+            .build();
+
+        let before = format!("#test_options2.txt-{}#", Utc::now().format("%Y-%m-%d-%H"));
+        let backup = match options.backup(tmp.join("test_options2.txt")) {
+            Ok(x) => x,
+            Err(_) => panic!("Backup failed."),
+        };
+        let after = format!("#test_options2.txt-{}#", Utc::now().format("%Y-%m-%d-%H"));
+
+        let name = backup.file_name().unwrap().to_str().unwrap();
+        assert!(name == before || name == after);
+
+        for path in [&tmp.join("test_options2.txt"), &backup] {
+            let mut content = String::new();
+            let mut read = File::open(path).unwrap();
+            read.read_to_string(&mut content).unwrap();
+
+            assert_eq!(content, "Some content to test.");
+        }
+    }
+
+    #[test]
+    fn destination() {
+        let tmp = test_dir();
+        let mut file = File::create(tmp.join("test_options4.txt")).unwrap();
+        file.write_all(b"Some content to test.").unwrap();
+        drop(file);
+
+        let backup = match BackupOptions::new()
+            .destination(tmp.join("test_options_dir4/backups"))
+            .backup(tmp.join("test_options4.txt"))
+        {
+            Ok(x) => x,
+            Err(_) => panic!("Backup failed."),
+        };
+
+        assert_eq!(
+            backup.parent().unwrap(),
+            tmp.join("test_options_dir4/backups")
+        );
+        assert!(!tmp.join("test_options4.txt").exists());
+    }
+
+    #[test]
+    fn dry_run() {
+        let tmp = test_dir();
+        File::create(tmp.join("test_options8.txt")).unwrap();
+
+        let backup = match BackupOptions::new()
+            .destination(tmp.join("test_options_dir8"))
+            .dry_run(tmp.join("test_options8.txt"))
+        {
+            Ok(x) => x,
+            Err(_) => panic!("Dry run failed."),
+        };
+
+        assert_eq!(backup.parent().unwrap(), tmp.join("test_options_dir8"));
+        assert!(!tmp.join("test_options_dir8").exists());
+        assert!(tmp.join("test_options8.txt").is_file());
+    }
+
+    #[test]
+    fn frozen_clock() {
+        let tmp = test_dir();
+
+        let time = Local
+            .with_ymd_and_hms(2023, 6, 27, 21, 1, 13)
+            .unwrap()
+            .with_nanosecond(45123000)
+            .unwrap();
+        let clock = FixedClock(time);
+        let options = BackupOptions::new();
+
+        // names with the timestamp and with microseconds are taken
+        File::create(tmp.join("#test_file.txt-2023-06-27-21-01-13#")).unwrap();
+        File::create(tmp.join("#test_file.txt-2023-06-27-21-01-13-45123#")).unwrap();
+
+        // the counter is shared with the other tests, so only its order is known
+        let mut counters = Vec::new();
+        for _ in 1..=3 {
+            File::create(tmp.join("test_file.txt")).unwrap();
+
+            let backup =
+                match options.backup_hooked(&tmp.join("test_file.txt"), |_, _| Ok(()), &clock) {
+                    Ok(x) => x,
+                    Err(_) => panic!("Backup failed."),
+                };
+
+            let name = backup.backup_path.file_name().unwrap().to_str().unwrap();
+            let counter = name
+                .strip_prefix("#test_file.txt-2023-06-27-21-01-13-45123-")
+                .and_then(|x| x.strip_suffix('#'))
+                .unwrap();
+            counters.push(counter.parse::<u32>().unwrap());
+            assert!(backup.had_microsecond_collision);
+        }
+
+        assert!(counters.windows(2).all(|x| x[0] < x[1]));
+    }
+
+    #[test]
+    fn concurrent_collisions() {
+        let tmp = test_dir();
+        let destination = &tmp.join("backups");
+
+        // all backups get the same timestamp and the same name of the original file
+        let time = Local.with_ymd_and_hms(2023, 6, 27, 21, 1, 13).unwrap();
+        let clock = FixedClock(time);
+
+        let backups = std::thread::scope(|scope| {
+            let handles = (0..16)
+                .map(|i| {
+                    let dir = tmp.join(format!("dir{}", i));
+                    fs::create_dir(&dir).unwrap();
+                    let clock = &clock;
+
+                    scope.spawn(move || {
+                        let options = BackupOptions::new().destination(destination).build();
+                        let mut backups = Vec::new();
+                        for j in 0..20 {
+                            let path = dir.join("data.txt");
+                            fs::write(&path, format!("{} {}", i, j)).unwrap();
+                            backups.push(options.backup_with_clock(&path, clock).unwrap());
+                        }
+                        backups
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            handles
+                .into_iter()
+                .flat_map(|x| x.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+
+        let unique = backups.iter().collect::<HashSet<_>>();
+        assert_eq!(unique.len(), 16 * 20);
+        assert_eq!(fs::read_dir(destination).unwrap().count(), 16 * 20);
+    }
+
+    #[test]
+    fn overwrite_same_second() {
+        let tmp = test_dir();
+
+        let time = Local
+            .with_ymd_and_hms(2023, 6, 27, 21, 1, 13)
+            .unwrap()
+            .with_nanosecond(45123000)
+            .unwrap();
+        let clock = FixedClock(time);
+        let path = &tmp.join("test_file.txt");
+
+        for copy in [false, true] {
+            for i in 0..2 {
+                fs::write(path, format!("Unique string for file {}", i)).unwrap();
+
+                let backup = match BackupOptions::new()
+                    .copy(copy)
+                    .overwrite_same_second(true)
+                    .backup_with_clock(path, &clock)
+                {
+                    Ok(x) => x,
+                    Err(_) => panic!("Backup failed."),
+                };
+
+                assert_eq!(backup, tmp.join("#test_file.txt-2023-06-27-21-01-13#"));
+                assert_eq!(
+                    fs::read_to_string(&backup).unwrap(),
+                    format!("Unique string for file {}", i)
+                );
+            }
+
+            assert_eq!(path.exists(), copy);
+            assert_eq!(crate::list_backups(path).unwrap().len(), 1);
+        }
+
+        // microseconds are appended by default
+        let backup = BackupOptions::new()
+            .backup_with_clock(path, &clock)
+            .unwrap();
+        assert_eq!(
+            backup,
+            tmp.join("#test_file.txt-2023-06-27-21-01-13-45123#")
+        );
+    }
+
+    #[test]
+    fn overwrite_same_second_safely() {
+        let tmp = test_dir();
+        let clock = FixedClock(Local.with_ymd_and_hms(2023, 6, 27, 21, 1, 13).unwrap());
+        let path = &tmp.join("test_dir_overwrite");
+        let backup = &tmp.join("#test_dir_overwrite-2023-06-27-21-01-13#");
+
+        // directories are replaced as well
+        for copy in [false, true] {
+            for i in 0..2 {
+                let _ = fs::remove_dir_all(path);
+                fs::create_dir(path).unwrap();
+                fs::write(path.join(format!("file{}.txt", i)), "Some content.").unwrap();
+
+                BackupOptions::new()
+                    .copy(copy)
+                    .overwrite_same_second(true)
+                    .backup_with_clock(path, &clock)
+                    .unwrap();
+
+                assert!(backup.join(format!("file{}.txt", i)).is_file());
+                assert_eq!(fs::read_dir(backup).unwrap().count(), 1);
+            }
+            let _ = fs::remove_dir_all(path);
+        }
+        assert_eq!(fs::read_dir(&*tmp).unwrap().count(), 1);
+
+        // the existing backup is kept if the new backup can not be created
+        fs::create_dir(path).unwrap();
+        fs::write(path.join("file2.txt"), "Some content.").unwrap();
+        File::create(crate::temp_path(backup)).unwrap();
+        for copy in [false, true] {
+            assert!(BackupOptions::new()
+                .copy(copy)
+                .overwrite_same_second(true)
+                .backup_with_clock(path, &clock)
+                .is_err());
+
+            assert!(backup.join("file1.txt").is_file());
+            assert!(path.join("file2.txt").is_file());
+        }
+    }
+
+    #[test]
+    fn exhausted_names() {
+        let tmp = test_dir();
+        File::create(tmp.join("test_file.txt")).unwrap();
+
+        let time = Local.with_ymd_and_hms(2023, 6, 27, 21, 1, 13).unwrap();
+        let clock = FixedClock(time);
+        let options = BackupOptions::new();
+        let path = &tmp.join("test_file.txt");
+
+        // the names with counters are unique within the process, so they can not be occupied in advance
+        let mut names = options.backup_names(path, &clock).unwrap();
+        let mut generated = HashSet::new();
+        for _ in 0..=DEFAULT_MAX_COLLISIONS {
+            assert!(generated.insert(names.next_name().unwrap()));
+        }
+
+        match names.next_name() {
+            Ok(_) => panic!("Generating should have failed, but it was successful."),
+            Err(e) => {
+                assert!(matches!(e, BackupError::NoUniqueName));
+                assert_eq!(e.kind(), std::io::ErrorKind::AlreadyExists);
+            }
+        }
+
+        assert!(tmp.join("test_file.txt").exists());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn rename_only() {
+        let tmp = test_dir();
+        fs::write(tmp.join("test_file.txt"), "Some content to test.").unwrap();
+        let path = &tmp.join("test_file.txt");
+
+        // shared memory is usually mounted as a separate filesystem
+        let destination = Path::new("/dev/shm/test_options_dir_cross");
+        if crate::same_device(path, Path::new("/dev/shm")) != Some(false) {
+            return;
+        }
+
+        // the original is only renamed by default (same as with `backup`)
+        match BackupOptions::new().destination(destination).backup(path) {
+            Ok(_) => panic!("Backup should have failed, but it was successful."),
+            Err(e) => assert_eq!(e.kind(), io::ErrorKind::CrossesDevices),
+        }
+
+        assert_eq!(fs::read_to_string(path).unwrap(), "Some content to test.");
+        assert!(!destination.exists() || fs::read_dir(destination).unwrap().count() == 0);
+
+        // the original is copied if allowed (same as with `backup_move`)
+        let backup = match BackupOptions::new()
+            .destination(destination)
+            .cross_device(true)
+            .backup(path)
+        {
+            Ok(x) => x,
+            Err(_) => panic!("Backup failed."),
+        };
+
+        assert!(!path.exists());
+        assert_eq!(
+            fs::read_to_string(&backup).unwrap(),
+            "Some content to test."
+        );
+
+        fs::remove_dir_all(destination).unwrap();
+    }
+
+    #[test]
+    fn rename_only_by_default() {
+        let tmp = test_dir();
+        let path = &tmp.join("test_file_cross.txt");
+        let target = &tmp.join("test_file_cross_moved.txt");
+        fs::write(path, "Some content to test.").unwrap();
+
+        // simulate a rename across filesystems
+        let crosses = |_: &Path, _: &Path| Err(io::Error::from(io::ErrorKind::CrossesDevices));
+
+        let result = BackupOptions::new().move_original_with(path, target, crosses);
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::CrossesDevices);
+        assert_eq!(fs::read_to_string(path).unwrap(), "Some content to test.");
+        assert!(!target.exists());
+
+        BackupOptions::new()
+            .cross_device(true)
+            .move_original_with(path, target, crosses)
+            .unwrap();
+        assert!(!path.exists());
+        assert_eq!(fs::read_to_string(target).unwrap(), "Some content to test.");
+    }
+
+    #[test]
+    fn limited_collisions() {
+        let tmp = test_dir();
+        File::create(tmp.join("test_file.txt")).unwrap();
+
+        let time = Local.with_ymd_and_hms(2023, 6, 27, 21, 1, 13).unwrap();
+        let clock = FixedClock(time);
+        let path = &tmp.join("test_file.txt");
+
+        // occupy the names with the timestamp and with microseconds
+        // (the name with microseconds is only tried once, since the clock is frozen)
+        for name in [
+            "#test_file.txt-2023-06-27-21-01-13#",
+            "#test_file.txt-2023-06-27-21-01-13-0#",
+        ] {
+            File::create(tmp.join(name)).unwrap();
+        }
+
+        for max in [0, 1] {
+            match BackupOptions::new()
+                .max_collisions(max)
+                .backup_with_clock(path, &clock)
+            {
+                Ok(_) => panic!("Backup should have failed, but it was successful."),
+                Err(e) => {
+                    assert!(matches!(e, BackupError::NoUniqueName));
+                    assert_eq!(e.kind(), io::ErrorKind::AlreadyExists);
+                }
+            }
+        }
+
+        assert!(path.exists());
+
+        // one more attempt finds a free name
+        let backup = match BackupOptions::new()
+            .max_collisions(2)
+            .backup_with_clock(path, &clock)
+        {
+            Ok(x) => x,
+            Err(_) => panic!("Backup failed."),
+        };
+        assert!(backup.to_str().unwrap().starts_with(
+            tmp.join("#test_file.txt-2023-06-27-21-01-13-0-")
+                .to_str()
+                .unwrap()
+        ));
+
+        // no more names than allowed are generated
+        let options = BackupOptions::new().max_collisions(5).build();
+        let mut names = options.backup_names(path, &clock).unwrap();
+        for _ in 0..6 {
+            names.next_name().unwrap();
+        }
+        assert!(matches!(names.next_name(), Err(BackupError::NoUniqueName)));
+    }
+
+    #[test]
+    fn name_taken_before_rename() {
+        let tmp = test_dir();
+        let mut file = File::create(tmp.join("test_file.txt")).unwrap();
+        file.write_all(b"Some content to test.").unwrap();
+        drop(file);
+
+        let time = Local.with_ymd_and_hms(2023, 6, 27, 21, 1, 13).unwrap();
+        let clock = FixedClock(time);
+        let options = BackupOptions::new();
+
+        let path = &tmp.join("test_file.txt");
+        let name = options.get_unique_name(path, &clock).unwrap();
+        let taken = name.path.clone();
+
+        // another process creates a file with the same name right before the rename
+        let mut file = File::create(&taken).unwrap();
+        file.write_all(b"Some other content.").unwrap();
+        drop(file);
+
+        let name = match options
+            .create_unique(path, name, &clock, |target| rename_noreplace(path, target))
+        {
+            Ok(x) => x,
+            Err(_) => panic!("Backup failed."),
+        };
+
+        assert_ne!(name.path, taken);
+        assert!(name.collision);
+        assert_eq!(fs::read_to_string(&taken).unwrap(), "Some other content.");
+        assert_eq!(
+            fs::read_to_string(&name.path).unwrap(),
+            "Some content to test."
+        );
+    }
+
+    #[test]
+    fn durable() {
+        let tmp = test_dir();
+        let mut file = File::create(tmp.join("test_file.txt")).unwrap();
+        file.write_all(b"Some content to test.").unwrap();
+        drop(file);
+
+        let path = &tmp.join("test_file.txt");
+        for copy in [true, false] {
+            let backup = match BackupOptions::new()
+                .durable(true)
+                .copy(copy)
+                .destination(tmp.join("backups"))
+                .backup(path)
+            {
+                Ok(x) => x,
+                Err(_) => panic!("Backup failed."),
+            };
+
+            assert_eq!(
+                fs::read_to_string(&backup).unwrap(),
+                "Some content to test."
+            );
+            assert_eq!(path.exists(), copy);
+        }
+    }
+
+    #[test]
+    fn destination_removed() {
+        let tmp = test_dir();
+        fs::create_dir(tmp.join("test_options_dir_removed")).unwrap();
+        File::create(tmp.join("test_options_dir_removed/test_file.txt")).unwrap();
+
+        // the directory is removed right before the rename
+        match BackupOptions::new().backup_with_hook(
+            tmp.join("test_options_dir_removed/test_file.txt"),
+            |_, _| fs::remove_dir_all(tmp.join("test_options_dir_removed")),
+        ) {
+            Ok(_) => panic!("Backup should have failed, but it was successful."),
+            Err(e) => {
+                assert!(matches!(
+                    &e,
+                    BackupError::DestinationMissing(x) if *x == tmp.join("test_options_dir_removed")
+                ));
+                assert_eq!(
+                    e.to_string(),
+                    format!(
+                        "Backup destination directory '{}' no longer exists.",
+                        tmp.join("test_options_dir_removed").display()
+                    )
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn retry_transient() {
+        let tmp = test_dir();
+        let mut file = File::create(tmp.join("test_file.txt")).unwrap();
+        file.write_all(b"Some content to test.").unwrap();
+        drop(file);
+
+        let path = &tmp.join("test_file.txt");
+        let options = BackupOptions::new()
+            .retries(2, Duration::from_millis(1))
+            .build();
+        let name = options.get_unique_name(path, &SystemClock).unwrap();
+
+        // the first rename fails with a transient error
+        let mut calls = 0;
+        let name = match options.create_unique(path, name, &SystemClock, |target| {
+            calls += 1;
+            match calls {
+                1 => Err(io::Error::from(io::ErrorKind::TimedOut)),
+                _ => rename_noreplace(path, target),
+            }
+        }) {
+            Ok(x) => x,
+            Err(_) => panic!("Backup failed."),
+        };
+
+        assert_eq!(calls, 2);
+        assert!(!path.exists());
+        assert_eq!(
+            fs::read_to_string(&name.path).unwrap(),
+            "Some content to test."
+        );
+    }
+
+    #[test]
+    fn retry_exhausted() {
+        let tmp = test_dir();
+        File::create(tmp.join("test_options_retry.txt")).unwrap();
+        let path = &tmp.join("test_options_retry.txt");
+
+        let options = BackupOptions::new().retries(2, Duration::ZERO).build();
+        let name = options.get_unique_name(path, &SystemClock).unwrap();
+
+        let mut calls = 0;
+        match options.create_unique(path, name, &SystemClock, |_| {
+            calls += 1;
+            Err(io::Error::from(io::ErrorKind::Interrupted))
+        }) {
+            Ok(_) => panic!("Backup should have failed, but it was successful."),
+            Err(e) => assert_eq!(e.kind(), io::ErrorKind::Interrupted),
+        }
+        assert_eq!(calls, 3);
+
+        // non-transient errors are not retried
+        let name = options.get_unique_name(path, &SystemClock).unwrap();
+        let mut calls = 0;
+        match options.create_unique(path, name, &SystemClock, |_| {
+            calls += 1;
+            Err(io::Error::from(io::ErrorKind::PermissionDenied))
+        }) {
+            Ok(_) => panic!("Backup should have failed, but it was successful."),
+            Err(e) => assert_eq!(e.kind(), io::ErrorKind::PermissionDenied),
+        }
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn illegal_prefix() {
+        let tmp = test_dir();
+        let mut file = File::create(tmp.join("test_options3.txt")).unwrap();
+        file.write_all(b"Some content to test.").unwrap();
+        drop(file);
+
+        match BackupOptions::new()
+            .prefix("bak/")
+            .backup(tmp.join("test_options3.txt"))
+        {
+            Ok(_) => panic!("Backup should have failed, but it was successful."),
+            Err(e) => assert!(matches!(e, BackupError::InvalidPath(_))),
+        };
+
+        assert!(tmp.join("test_options3.txt").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn special_files() {
+        let tmp = test_dir();
+        let path = &tmp.join("fifo");
+        let status = std::process::Command::new("mkfifo")
+            .arg(path)
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        // special files are refused by default
+        match BackupOptions::new().backup(path) {
+            Ok(_) => panic!("Backup should have failed, but it was successful."),
+            Err(e) => {
+                assert_eq!(e.kind(), io::ErrorKind::Unsupported);
+                assert!(e.to_string().contains("FIFO"));
+            }
+        }
+
+        // special files are never copied
+        match BackupOptions::new()
+            .copy(true)
+            .allow_special_files(true)
+            .backup(path)
+        {
+            Ok(_) => panic!("Backup should have failed, but it was successful."),
+            Err(e) => assert!(matches!(e, BackupError::Unsupported(_))),
+        }
+
+        assert!(path.exists());
+
+        // the node itself can be renamed
+        let backup = match BackupOptions::new().allow_special_files(true).backup(path) {
+            Ok(x) => x,
+            Err(_) => panic!("Backup failed."),
+        };
+
+        assert!(!path.exists());
+        {
+            use std::os::unix::fs::FileTypeExt;
+            assert!(fs::symlink_metadata(backup).unwrap().file_type().is_fifo());
+        }
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn directory_links() {
+        let tmp = test_dir();
+        fs::create_dir_all(tmp.join("target")).unwrap();
+        fs::write(tmp.join("target/file.txt"), "Some content to test.").unwrap();
+
+        let path = &tmp.join("junction");
+        // junctions can be created without elevated privileges
+        let status = std::process::Command::new("cmd")
+            .args(["/C", "mklink", "/J"])
+            .arg(path)
+            .arg(tmp.join("target"))
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        // directory links are refused by default
+        match BackupOptions::new().backup(path) {
+            Ok(_) => panic!("Backup should have failed, but it was successful."),
+            Err(e) => {
+                assert!(matches!(e, BackupError::Unsupported(_)));
+                assert!(e.to_string().contains("junction"));
+            }
+        }
+
+        assert!(path.exists());
+
+        // the link node itself can be renamed
+        let backup = match BackupOptions::new()
+            .allow_directory_links(true)
+            .backup(path)
+        {
+            Ok(x) => x,
+            Err(_) => panic!("Backup failed."),
+        };
+
+        assert!(fs::symlink_metadata(path).is_err());
+        assert!(fs::symlink_metadata(&backup)
+            .unwrap()
+            .file_type()
+            .is_symlink());
+        // the linked directory is left untouched
+        assert_eq!(
+            fs::read_to_string(tmp.join("target/file.txt")).unwrap(),
+            "Some content to test."
+        );
+        assert_eq!(
+            fs::read_to_string(backup.join("file.txt")).unwrap(),
+            "Some content to test."
+        );
+    }
+
+    #[test]
+    fn reject_backups() {
+        let tmp = test_dir();
+        File::create(tmp.join("bak_test_options9.txt-2023-06-27-21-01-13.old")).unwrap();
+
+        match BackupOptions::new()
+            .prefix("bak_")
+            .suffix(".old")
+            .reject_backups(true)
+            .backup(tmp.join("bak_test_options9.txt-2023-06-27-21-01-13.old"))
+        {
+            Ok(_) => panic!("Backup should have failed, but it was successful."),
+            Err(e) => assert!(matches!(e, BackupError::IsBackup)),
+        };
+
+        assert!(tmp
+            .join("bak_test_options9.txt-2023-06-27-21-01-13.old")
+            .exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn copy_symlink() {
+        let tmp = test_dir();
+        fs::write(tmp.join("test_options10.txt"), "Some content to test.").unwrap();
+        std::os::unix::fs::symlink("test_options10.txt", tmp.join("test_options10_link.txt"))
+            .unwrap();
+
+        // the link is copied as a link
+        let backup = match BackupOptions::new()
+            .copy(true)
+            .backup(tmp.join("test_options10_link.txt"))
+        {
+            Ok(x) => x,
+            Err(_) => panic!("Backup failed."),
+        };
+
+        let name = backup.file_name().unwrap().to_str().unwrap();
+        assert!(name.starts_with("#test_options10_link.txt-"));
+        assert!(fs::symlink_metadata(&backup).unwrap().is_symlink());
+        assert_eq!(
+            fs::read_link(&backup).unwrap(),
+            Path::new("test_options10.txt")
+        );
+        assert!(fs::symlink_metadata(tmp.join("test_options10_link.txt"))
+            .unwrap()
+            .is_symlink());
+
+        // the target of the link is copied into a regular file
+        let backup = match BackupOptions::new()
+            .copy(true)
+            .follow_symlinks(true)
+            .backup(tmp.join("test_options10_link.txt"))
+        {
+            Ok(x) => x,
+            Err(_) => panic!("Backup failed."),
+        };
+
+        let name = backup.file_name().unwrap().to_str().unwrap();
+        assert!(name.starts_with("#test_options10.txt-"));
+        assert!(fs::symlink_metadata(&backup).unwrap().is_file());
+        assert_eq!(
+            fs::read_to_string(&backup).unwrap(),
+            "Some content to test."
+        );
+    }
+}
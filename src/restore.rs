@@ -0,0 +1,885 @@
+// Released under MIT License.
+// Copyright (c) 2023 Ladislav Bartos
+
+//! Implementation of functions for restoring backups.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::prelude::*;
+
+use crate::list::find_backups;
+use crate::{
+    backup, remove_path, rename_back, rename_noreplace, split_path, BackupError, DEFAULT_PREFIX,
+    DEFAULT_SUFFIX,
+};
+
+/// Resolution of conflicts between the files of a directory backup and the files of an existing directory.
+/// Used by [`restore_dir_with_strategy`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RestoreStrategy {
+    /// The files of the backup replace the existing files.
+    Overwrite,
+    /// The existing files are kept.
+    SkipExisting,
+    /// The file with the more recent modification time is kept. Existing files are kept if the times are equal.
+    KeepNewer,
+}
+
+/// Restores the most recent backup of the specified file or directory.
+/// Returns the path to the restored file if successful, otherwise returns an error.
+///
+/// # Arguments
+///
+/// * `path` - The path to the original file or directory.
+///
+/// # Errors
+///
+/// This function can return the following errors:
+///
+/// * `BackupError::AlreadyExists` - If the specified `path` already exists.
+/// * `BackupError::NoBackup` - If no backup of the `path` exists.
+/// * `BackupError::IsRoot`, `BackupError::EndsInDotDot` - If the `path` is not valid.
+/// * `BackupError::Io` - If an I/O error occurs during the restoration.
+///
+/// # Details
+/// The most recent backup is determined from the timestamps in the names of the backups
+/// (see [`crate::list_backups`]). The backup is **renamed** back to `path`.
+/// Use [`restore_overwrite`] if you want to replace an existing file.
+///
+/// # Examples
+///
+/// ```no_run
+/// use crate::backitup::restore;
+///
+/// let path = "data.txt";
+/// match restore(path) {
+///     Ok(restored) => println!("Backup restored: {:?}", restored),
+///     Err(err) => eprintln!("Failed to restore backup: {:?}", err),
+/// }
+/// ```
+pub fn restore(path: impl AsRef<Path>) -> Result<PathBuf, BackupError> {
+    restore_newest(path.as_ref(), false)
+}
+
+/// Restores the most recent backup of the specified file or directory,
+/// replacing the file (or directory) at `path` if it exists.
+/// Returns the path to the restored file if successful, otherwise returns an error.
+///
+/// # Arguments
+///
+/// * `path` - The path to the original file or directory.
+///
+/// # Errors
+///
+/// This function can return the same errors as [`restore`], except for `BackupError::AlreadyExists`.
+///
+/// # Warning
+/// The current content of `path` is **removed** and can not be recovered.
+/// Directories are removed recursively.
+///
+/// # Examples
+///
+/// ```no_run
+/// use crate::backitup::restore_overwrite;
+///
+/// let path = "data.txt";
+/// match restore_overwrite(path) {
+///     Ok(restored) => println!("Backup restored: {:?}", restored),
+///     Err(err) => eprintln!("Failed to restore backup: {:?}", err),
+/// }
+/// ```
+pub fn restore_overwrite(path: impl AsRef<Path>) -> Result<PathBuf, BackupError> {
+    restore_newest(path.as_ref(), true)
+}
+
+/// Restores the most recent backup of the specified file or directory,
+/// backing up the file (or directory) at `path` first if it exists.
+/// Returns the path to the restored file and the path to the backup of the replaced file if successful,
+/// otherwise returns an error.
+///
+/// # Arguments
+///
+/// * `path` - The path to the original file or directory.
+///
+/// # Errors
+///
+/// This function can return the same errors as [`crate::backup`] and [`restore`], except for `BackupError::AlreadyExists`.
+///
+/// # Details
+/// The most recent backup is determined in the same way as in [`restore`] before anything is changed.
+/// If `path` exists, it is then backed up in the same way as with [`crate::backup`], so the current content
+/// of `path` is never lost. Finally, the chosen backup is **renamed** back to `path`.
+///
+/// The first returned path is the restored `path`. The second returned path is the new backup of the file
+/// that has been replaced, or `None` if `path` did not exist. Since the replaced file is now the most recent
+/// backup, calling [`restore_safe`] again swaps the two versions back, i.e. the restore is always reversible.
+///
+/// If the backup can not be renamed back to `path`, the replaced file is moved back to `path` (on a best-effort basis).
+///
+/// # Examples
+///
+/// ```no_run
+/// use crate::backitup::restore_safe;
+///
+/// let path = "data.txt";
+/// match restore_safe(path) {
+///     Ok((restored, Some(replaced))) => println!("Backup restored: {:?}, previous version: {:?}", restored, replaced),
+///     Ok((restored, None)) => println!("Backup restored: {:?}", restored),
+///     Err(err) => eprintln!("Failed to restore backup: {:?}", err),
+/// }
+/// ```
+pub fn restore_safe(path: impl AsRef<Path>) -> Result<(PathBuf, Option<PathBuf>), BackupError> {
+    let path = path.as_ref();
+    let (parent, filename) = split_path(path)?;
+
+    // the backup must be chosen before the current file becomes the most recent backup
+    let newest = match find_backups(parent, filename, DEFAULT_PREFIX, DEFAULT_SUFFIX)?.pop() {
+        Some((_, x)) => x,
+        None => return Err(BackupError::NoBackup),
+    };
+
+    // broken symbolic links are also preserved
+    let replaced = match fs::symlink_metadata(path) {
+        Ok(_) => Some(backup(path)?),
+        Err(_) => None,
+    };
+
+    if let Err(e) = rename_noreplace(&newest, path) {
+        if let Some(replaced) = &replaced {
+            let _ = rename_noreplace(replaced, path);
+        }
+
+        return Err(map_already_exists(e));
+    }
+
+    Ok((path.to_path_buf(), replaced))
+}
+
+/// Restores the backup of the specified file or directory created at the specified time.
+/// Returns the path to the restored file if successful, otherwise returns an error.
+///
+/// # Arguments
+///
+/// * `path` - The path to the original file or directory.
+/// * `timestamp` - The time of the backup to restore, e.g. as returned by [`crate::parse_backup_time`].
+///
+/// # Errors
+///
+/// This function can return the same errors as [`restore`] and additionally:
+///
+/// * `BackupError::NoBackup` - If no backup of the `path` created at `timestamp` exists.
+///
+/// # Details
+/// The backups are found in the same way as with [`crate::list_backups`]. The timestamp in the name of a backup
+/// is compared with `timestamp` (in local time) to the second. If several backups have been created within
+/// the same second, the microseconds of `timestamp` are used to choose between them (a `timestamp` without
+/// microseconds matches the backup without microseconds in its name). If several backups still match,
+/// the newest of them is restored. The backup is **renamed** back to `path`.
+/// Use [`restore_version_overwrite`] if you want to replace an existing file.
+///
+/// # Examples
+///
+/// ```no_run
+/// use crate::backitup::{list_backups, parse_backup_time, restore_version};
+///
+/// let path = "data.txt";
+/// let backups = list_backups(path).unwrap();
+/// let oldest = parse_backup_time(&backups[0]).unwrap();
+///
+/// match restore_version(path, oldest) {
+///     Ok(restored) => println!("Backup restored: {:?}", restored),
+///     Err(err) => eprintln!("Failed to restore backup: {:?}", err),
+/// }
+/// ```
+pub fn restore_version(
+    path: impl AsRef<Path>,
+    timestamp: DateTime<Local>,
+) -> Result<PathBuf, BackupError> {
+    restore_at(path.as_ref(), timestamp, false)
+}
+
+/// Restores the backup of the specified file or directory created at the specified time,
+/// replacing the file (or directory) at `path` if it exists.
+/// Returns the path to the restored file if successful, otherwise returns an error.
+///
+/// # Arguments
+///
+/// * `path` - The path to the original file or directory.
+/// * `timestamp` - The time of the backup to restore.
+///
+/// # Errors
+///
+/// This function can return the same errors as [`restore_version`], except for `BackupError::AlreadyExists`.
+///
+/// # Details
+/// The backup is chosen in the same way as in [`restore_version`].
+///
+/// # Warning
+/// The current content of `path` is **removed** and can not be recovered.
+/// Directories are removed recursively.
+///
+/// # Examples
+///
+/// ```no_run
+/// use crate::backitup::restore_version_overwrite;
+/// use chrono::prelude::*;
+///
+/// let time = Local.with_ymd_and_hms(2023, 6, 27, 21, 1, 13).unwrap();
+/// match restore_version_overwrite("data.txt", time) {
+///     Ok(restored) => println!("Backup restored: {:?}", restored),
+///     Err(err) => eprintln!("Failed to restore backup: {:?}", err),
+/// }
+/// ```
+pub fn restore_version_overwrite(
+    path: impl AsRef<Path>,
+    timestamp: DateTime<Local>,
+) -> Result<PathBuf, BackupError> {
+    restore_at(path.as_ref(), timestamp, true)
+}
+
+/// Restores the most recent backups of multiple files or directories. Either all of them are restored or none.
+/// Returns the restored paths in the same order as `paths` if successful, otherwise returns an error.
+///
+/// # Arguments
+///
+/// * `paths` - The paths to the original files or directories.
+///
+/// # Errors
+///
+/// This function can return the same errors as [`restore`]. The first error that occurs is returned.
+///
+/// # Details
+/// The paths are restored one by one in the same way as with [`restore`], so this function
+/// undoes [`crate::backup_many`]. If the restoration of any path fails, the paths that have already been
+/// restored are renamed back to their backups (in reverse order) before the error is returned,
+/// so the backups keep their original names. Rolling back is performed on a best-effort basis:
+/// if a path can not be renamed back, the remaining paths are still renamed and the original error is returned.
+/// A path is never renamed over a file that has been created at the path of its backup in the meantime;
+/// such a path stays restored (and a warning is emitted if the `tracing` feature is enabled).
+///
+/// Note that the backups are not restored atomically, i.e. other processes may observe
+/// a state in which only some of the paths have been restored.
+///
+/// # Examples
+///
+/// ```no_run
+/// use crate::backitup::restore_many;
+///
+/// match restore_many(&["data.txt", "metadata.json"]) {
+///     Ok(restored) => println!("Backups restored: {:?}", restored),
+///     Err(err) => eprintln!("Failed to restore backups: {:?}", err),
+/// }
+/// ```
+pub fn restore_many<P: AsRef<Path>>(paths: &[P]) -> Result<Vec<PathBuf>, BackupError> {
+    let mut backups = Vec::with_capacity(paths.len());
+
+    for path in paths {
+        let path = path.as_ref();
+        let result = split_path(path).and_then(|(parent, _)| {
+            restore_newest_backup(path, parent, DEFAULT_PREFIX, DEFAULT_SUFFIX, false)
+        });
+
+        match result {
+            Ok(x) => backups.push(x),
+            Err(e) => {
+                // restore the original state
+                for (original, backup) in paths.iter().zip(backups).rev() {
+                    rename_back(original.as_ref(), &backup);
+                }
+
+                return Err(e);
+            }
+        }
+    }
+
+    Ok(paths.iter().map(|x| x.as_ref().to_path_buf()).collect())
+}
+
+/// Restores the most recent backup of the specified directory, merging it into the directory at `path` if it exists.
+/// Returns the path to the restored directory if successful, otherwise returns an error.
+///
+/// # Arguments
+///
+/// * `path` - The path to the original directory.
+/// * `strategy` - How to resolve files existing both in the backup and in `path`.
+///
+/// # Errors
+///
+/// This function can return the same errors as [`restore`], except for `BackupError::AlreadyExists`, and additionally:
+///
+/// * `BackupError::Unsupported` - If the most recent backup or the existing `path` is not a directory.
+///
+/// # Details
+/// The most recent backup is determined in the same way as in [`restore`].
+/// If `path` does not exist, the backup is simply **renamed** back to `path`.
+/// Otherwise, the entries of the backup are moved into `path` one by one (recursively). Entries which do not exist
+/// in `path` are always restored and entries existing only in `path` are never touched. Files (or directories)
+/// existing in both places are resolved according to `strategy`, comparing the modification times of the entries
+/// themselves for [`RestoreStrategy::KeepNewer`]. Directories existing in both places are merged.
+///
+/// Like with [`restore`], the backup is consumed: the entries which have not been restored
+/// are **removed** together with the backup in the end.
+///
+/// # Examples
+///
+/// ```no_run
+/// use crate::backitup::{restore_dir_with_strategy, RestoreStrategy};
+///
+/// match restore_dir_with_strategy("output_dir", RestoreStrategy::KeepNewer) {
+///     Ok(restored) => println!("Backup restored: {:?}", restored),
+///     Err(err) => eprintln!("Failed to restore backup: {:?}", err),
+/// }
+/// ```
+pub fn restore_dir_with_strategy(
+    path: impl AsRef<Path>,
+    strategy: RestoreStrategy,
+) -> Result<PathBuf, BackupError> {
+    let path = path.as_ref();
+    let (parent, filename) = split_path(path)?;
+
+    let newest = match find_backups(parent, filename, DEFAULT_PREFIX, DEFAULT_SUFFIX)?.pop() {
+        Some((_, x)) => x,
+        None => return Err(BackupError::NoBackup),
+    };
+
+    if !newest.is_dir() {
+        return Err(BackupError::Unsupported(String::from(
+            "Only directory backups can be restored with a strategy.",
+        )));
+    }
+
+    match fs::symlink_metadata(path) {
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            rename_noreplace(&newest, path)?;
+            return Ok(path.to_path_buf());
+        }
+        Err(e) => return Err(BackupError::Io(e)),
+        Ok(x) if !x.is_dir() => {
+            return Err(BackupError::Unsupported(String::from(
+                "Directory backups can only be merged into directories.",
+            )))
+        }
+        Ok(_) => (),
+    }
+
+    merge_dir(&newest, path, strategy)?;
+    fs::remove_dir_all(&newest)?;
+
+    Ok(path.to_path_buf())
+}
+
+/// Moves the entries of the directory `source` into the directory `target`, resolving conflicts using `strategy`.
+fn merge_dir(
+    source: &Path,
+    target: &Path,
+    strategy: RestoreStrategy,
+) -> Result<(), std::io::Error> {
+    for entry in fs::read_dir(source)? {
+        let entry = entry?;
+        let from = entry.path();
+        let to = target.join(entry.file_name());
+
+        let existing = match fs::symlink_metadata(&to) {
+            Ok(x) => x,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                rename_noreplace(&from, &to)?;
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+
+        let metadata = entry.metadata()?;
+        if metadata.is_dir() && existing.is_dir() {
+            merge_dir(&from, &to, strategy)?;
+            continue;
+        }
+
+        let replace = match strategy {
+            RestoreStrategy::Overwrite => true,
+            RestoreStrategy::SkipExisting => false,
+            RestoreStrategy::KeepNewer => metadata.modified()? > existing.modified()?,
+        };
+
+        if replace {
+            remove_path(&to)?;
+            rename_noreplace(&from, &to)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Renames the backup of `path` created at `timestamp` back to `path`.
+fn restore_at(
+    path: &Path,
+    timestamp: DateTime<Local>,
+    overwrite: bool,
+) -> Result<PathBuf, BackupError> {
+    let (parent, filename) = split_path(path)?;
+
+    if !overwrite && fs::symlink_metadata(path).is_ok() {
+        return Err(BackupError::AlreadyExists);
+    }
+
+    let time = timestamp.naive_local();
+    let second = time.with_nanosecond(0).unwrap_or(time);
+    // leap seconds are represented by nanoseconds above one second
+    let micros = (time.nanosecond() % 1_000_000_000) / 1000;
+
+    let candidates = find_backups(parent, filename, DEFAULT_PREFIX, DEFAULT_SUFFIX)?
+        .into_iter()
+        .filter(|((x, _, _), _)| *x == second)
+        .collect::<Vec<_>>();
+
+    // microseconds are only needed to distinguish backups created within the same second
+    let backup = if candidates.len() == 1 {
+        candidates.into_iter().next()
+    } else {
+        candidates.into_iter().rfind(|((_, x, _), _)| *x == micros)
+    };
+
+    let Some((_, backup)) = backup else {
+        return Err(BackupError::NoBackup);
+    };
+
+    if overwrite && fs::symlink_metadata(path).is_ok() {
+        remove_path(path)?;
+    }
+
+    // the original may have been created in the meantime
+    rename_noreplace(&backup, path).map_err(map_already_exists)?;
+    Ok(path.to_path_buf())
+}
+
+/// Renames the most recent backup of `path` back to `path`.
+fn restore_newest(path: &Path, overwrite: bool) -> Result<PathBuf, BackupError> {
+    let (parent, _) = split_path(path)?;
+    restore_newest_from(path, parent, DEFAULT_PREFIX, DEFAULT_SUFFIX, overwrite)
+}
+
+/// Renames the most recent backup of `path` located in `dir` and wrapped in `prefix` and `suffix` back to `path`.
+pub(crate) fn restore_newest_from(
+    path: &Path,
+    dir: &Path,
+    prefix: &str,
+    suffix: &str,
+    overwrite: bool,
+) -> Result<PathBuf, BackupError> {
+    restore_newest_backup(path, dir, prefix, suffix, overwrite)?;
+    Ok(path.to_path_buf())
+}
+
+/// Same as [`restore_newest_from`], but returns the path the restored backup had.
+fn restore_newest_backup(
+    path: &Path,
+    dir: &Path,
+    prefix: &str,
+    suffix: &str,
+    overwrite: bool,
+) -> Result<PathBuf, BackupError> {
+    let (_, filename) = split_path(path)?;
+
+    if !overwrite && fs::symlink_metadata(path).is_ok() {
+        return Err(BackupError::AlreadyExists);
+    }
+
+    let newest = match find_backups(dir, filename, prefix, suffix)?.pop() {
+        Some((_, x)) => x,
+        None => return Err(BackupError::NoBackup),
+    };
+
+    if overwrite && fs::symlink_metadata(path).is_ok() {
+        remove_path(path)?;
+    }
+
+    // the original may have been created in the meantime
+    rename_noreplace(&newest, path).map_err(map_already_exists)?;
+    Ok(newest)
+}
+
+/// Converts an error of a rename which does not overwrite its target into a [`BackupError`].
+fn map_already_exists(error: std::io::Error) -> BackupError {
+    match error.kind() {
+        std::io::ErrorKind::AlreadyExists => BackupError::AlreadyExists,
+        _ => BackupError::Io(error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backup;
+    use crate::testutil::test_dir;
+    use std::fs::File;
+    use std::io::prelude::*;
+
+    #[test]
+    fn restore_newest() {
+        let tmp = test_dir();
+        fs::create_dir(tmp.join("test_dir_restore")).unwrap();
+
+        for i in 0..5 {
+            let mut file = File::create(tmp.join("test_dir_restore/test_file.txt")).unwrap();
+            let text = format!("Unique string for file {}", i);
+            file.write_all(text.as_bytes()).unwrap();
+
+            backup(tmp.join("test_dir_restore/test_file.txt")).unwrap();
+        }
+
+        let restored = match restore(tmp.join("test_dir_restore/test_file.txt")) {
+            Ok(x) => x,
+            Err(_) => panic!("Restore failed."),
+        };
+
+        assert_eq!(restored, tmp.join("test_dir_restore/test_file.txt"));
+
+        let mut content = String::new();
+        let mut read = File::open(&restored).unwrap();
+        read.read_to_string(&mut content).unwrap();
+
+        assert_eq!(content, "Unique string for file 4");
+        assert_eq!(
+            fs::read_dir(tmp.join("test_dir_restore")).unwrap().count(),
+            5
+        );
+    }
+
+    #[test]
+    fn restore_directory() {
+        let tmp = test_dir();
+        fs::create_dir_all(tmp.join("test_dir_restore2/dir")).unwrap();
+        let mut file = File::create(tmp.join("test_dir_restore2/dir/test_file.txt")).unwrap();
+        file.write_all(b"Some content to test.").unwrap();
+        drop(file);
+
+        backup(tmp.join("test_dir_restore2/dir")).unwrap();
+        restore(tmp.join("test_dir_restore2/dir")).unwrap();
+
+        let mut content = String::new();
+        let mut read = File::open(tmp.join("test_dir_restore2/dir/test_file.txt")).unwrap();
+        read.read_to_string(&mut content).unwrap();
+
+        assert_eq!(content, "Some content to test.");
+        assert_eq!(
+            fs::read_dir(tmp.join("test_dir_restore2")).unwrap().count(),
+            1
+        );
+    }
+
+    #[test]
+    fn restore_existing() {
+        let tmp = test_dir();
+        fs::create_dir(tmp.join("test_dir_restore3")).unwrap();
+
+        let mut file = File::create(tmp.join("test_dir_restore3/test_file.txt")).unwrap();
+        file.write_all(b"Some content to test.").unwrap();
+        drop(file);
+
+        backup(tmp.join("test_dir_restore3/test_file.txt")).unwrap();
+
+        let mut file = File::create(tmp.join("test_dir_restore3/test_file.txt")).unwrap();
+        file.write_all(b"New content.").unwrap();
+        drop(file);
+
+        match restore(tmp.join("test_dir_restore3/test_file.txt")) {
+            Ok(_) => panic!("Restore should have failed, but it was successful."),
+            Err(e) => assert!(matches!(e, BackupError::AlreadyExists)),
+        }
+
+        let mut content = String::new();
+        let mut read = File::open(tmp.join("test_dir_restore3/test_file.txt")).unwrap();
+        read.read_to_string(&mut content).unwrap();
+        assert_eq!(content, "New content.");
+
+        restore_overwrite(tmp.join("test_dir_restore3/test_file.txt")).unwrap();
+
+        let mut content = String::new();
+        let mut read = File::open(tmp.join("test_dir_restore3/test_file.txt")).unwrap();
+        read.read_to_string(&mut content).unwrap();
+        assert_eq!(content, "Some content to test.");
+        assert_eq!(
+            fs::read_dir(tmp.join("test_dir_restore3")).unwrap().count(),
+            1
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn restore_over_link() {
+        let tmp = test_dir();
+        fs::create_dir(tmp.join("test_dir_restore9")).unwrap();
+        let path = &tmp.join("test_dir_restore9/test_file.txt");
+        fs::write(path, "Some content to test.").unwrap();
+        let first = backup(path).unwrap();
+        fs::write(path, "Other content.").unwrap();
+        backup(path).unwrap();
+
+        // a dangling link at the path of the original is never replaced
+        std::os::unix::fs::symlink("nonexistent.txt", path).unwrap();
+        match restore(path) {
+            Ok(_) => panic!("Restore should have failed, but it was successful."),
+            Err(e) => assert!(matches!(e, BackupError::AlreadyExists)),
+        }
+        let time = crate::parse_backup_time(&first).unwrap();
+        match restore_version(path, time) {
+            Ok(_) => panic!("Restore should have failed, but it was successful."),
+            Err(e) => assert!(matches!(e, BackupError::AlreadyExists)),
+        }
+        assert!(fs::symlink_metadata(path).unwrap().is_symlink());
+        assert_eq!(
+            fs::read_dir(tmp.join("test_dir_restore9")).unwrap().count(),
+            3
+        );
+
+        // unless the original should be overwritten
+        restore_version_overwrite(path, time).unwrap();
+        assert_eq!(fs::read_to_string(path).unwrap(), "Some content to test.");
+        assert!(!first.exists());
+    }
+
+    #[test]
+    fn restore_by_version() {
+        let tmp = test_dir();
+        fs::create_dir(tmp.join("test_dir_restore5")).unwrap();
+
+        let mut backups = Vec::new();
+        for i in 0..5 {
+            fs::write(
+                tmp.join("test_dir_restore5/test_file.txt"),
+                format!("Unique string for file {}", i),
+            )
+            .unwrap();
+
+            backups.push(backup(tmp.join("test_dir_restore5/test_file.txt")).unwrap());
+        }
+
+        let time = crate::parse_backup_time(&backups[2]).unwrap();
+        let restored = match restore_version(tmp.join("test_dir_restore5/test_file.txt"), time) {
+            Ok(x) => x,
+            Err(_) => panic!("Restore failed."),
+        };
+
+        assert_eq!(restored, tmp.join("test_dir_restore5/test_file.txt"));
+        assert_eq!(
+            fs::read_to_string(&restored).unwrap(),
+            "Unique string for file 2"
+        );
+        assert!(!backups[2].exists());
+        assert_eq!(
+            fs::read_dir(tmp.join("test_dir_restore5")).unwrap().count(),
+            5
+        );
+
+        // the restored file is not replaced by default
+        let time = crate::parse_backup_time(&backups[0]).unwrap();
+        match restore_version(tmp.join("test_dir_restore5/test_file.txt"), time) {
+            Ok(_) => panic!("Restore should have failed, but it was successful."),
+            Err(e) => assert!(matches!(e, BackupError::AlreadyExists)),
+        }
+
+        restore_version_overwrite(tmp.join("test_dir_restore5/test_file.txt"), time).unwrap();
+        assert_eq!(
+            fs::read_to_string(tmp.join("test_dir_restore5/test_file.txt")).unwrap(),
+            "Unique string for file 0"
+        );
+
+        // no backup was created at this time
+        let time = Local.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+        match restore_version_overwrite(tmp.join("test_dir_restore5/test_file.txt"), time) {
+            Ok(_) => panic!("Restore should have failed, but it was successful."),
+            Err(e) => {
+                assert!(matches!(e, BackupError::NoBackup));
+                assert_eq!(e.kind(), std::io::ErrorKind::NotFound);
+            }
+        }
+    }
+
+    #[test]
+    fn restore_swap() {
+        let tmp = test_dir();
+        let path = &tmp.join("test_file.txt");
+
+        fs::write(path, "Original content.").unwrap();
+        let original = backup(path).unwrap();
+
+        // nothing is replaced
+        let (restored, replaced) = match restore_safe(path) {
+            Ok(x) => x,
+            Err(_) => panic!("Restore failed."),
+        };
+
+        assert_eq!(&restored, path);
+        assert!(replaced.is_none());
+        assert!(!original.exists());
+
+        // the current content is preserved as a new backup
+        fs::write(path, "New content.").unwrap();
+        backup(path).unwrap();
+        fs::write(path, "Working copy.").unwrap();
+
+        let (restored, replaced) = match restore_safe(path) {
+            Ok(x) => x,
+            Err(_) => panic!("Restore failed."),
+        };
+
+        assert_eq!(&restored, path);
+        assert_eq!(fs::read_to_string(path).unwrap(), "New content.");
+        let replaced = replaced.unwrap();
+        assert_eq!(fs::read_to_string(&replaced).unwrap(), "Working copy.");
+        assert_eq!(crate::list_backups(path).unwrap(), vec![replaced]);
+
+        // restoring again swaps the versions back
+        let (_, replaced) = restore_safe(path).unwrap();
+        assert_eq!(fs::read_to_string(path).unwrap(), "Working copy.");
+        assert_eq!(
+            fs::read_to_string(replaced.unwrap()).unwrap(),
+            "New content."
+        );
+    }
+
+    #[test]
+    fn restore_multiple() {
+        let tmp = test_dir();
+        let paths = ["test_file1.txt", "test_file2.txt", "test_file3.txt"].map(|x| tmp.join(x));
+        for (i, path) in paths.iter().enumerate() {
+            fs::write(path, format!("Unique string for file {}", i)).unwrap();
+        }
+
+        crate::backup_many(&paths).unwrap();
+
+        let restored = match restore_many(&paths) {
+            Ok(x) => x,
+            Err(_) => panic!("Restore failed."),
+        };
+
+        assert_eq!(restored, paths);
+        for (i, path) in paths.iter().enumerate() {
+            assert_eq!(
+                fs::read_to_string(path).unwrap(),
+                format!("Unique string for file {}", i)
+            );
+            assert!(crate::list_backups(path).unwrap().is_empty());
+        }
+    }
+
+    #[test]
+    fn restore_multiple_rollback() {
+        let tmp = test_dir();
+        let paths = ["test_file1.txt", "test_file2.txt", "test_file3.txt"].map(|x| tmp.join(x));
+        for path in &paths {
+            File::create(path).unwrap();
+        }
+
+        let backups = crate::backup_many(&paths).unwrap();
+
+        // the third file has already been restored
+        fs::rename(&backups[2], &paths[2]).unwrap();
+
+        match restore_many(&paths) {
+            Ok(_) => panic!("Restore should have failed, but it was successful."),
+            Err(e) => assert!(matches!(e, BackupError::AlreadyExists)),
+        }
+
+        // the restored files have been renamed back to their backups
+        for (path, backup) in paths.iter().zip(&backups).take(2) {
+            assert!(!path.exists());
+            assert!(backup.is_file());
+        }
+        assert!(paths[2].is_file());
+
+        // a file created at the path of the backup in the meantime is not overwritten
+        restore(&paths[0]).unwrap();
+        fs::write(&paths[0], "Restored.").unwrap();
+        fs::write(&backups[0], "New backup.").unwrap();
+        rename_back(&paths[0], &backups[0]);
+        assert_eq!(fs::read_to_string(&paths[0]).unwrap(), "Restored.");
+        assert_eq!(fs::read_to_string(&backups[0]).unwrap(), "New backup.");
+    }
+
+    #[test]
+    fn restore_with_strategy() {
+        let old = filetime::FileTime::from_unix_time(946_684_800, 0);
+        let middle = filetime::FileTime::from_unix_time(1_262_304_000, 0);
+        let new = filetime::FileTime::from_unix_time(1_577_836_800, 0);
+
+        for (strategy, a, b) in [
+            (RestoreStrategy::Overwrite, "Backup A.", "Backup B."),
+            (RestoreStrategy::SkipExisting, "Current A.", "Current B."),
+            (RestoreStrategy::KeepNewer, "Backup A.", "Current B."),
+        ] {
+            let tmp = test_dir();
+            let dir = &tmp.join("dir");
+            fs::create_dir_all(dir.join("nested")).unwrap();
+            fs::write(dir.join("a.txt"), "Backup A.").unwrap();
+            fs::write(dir.join("nested/b.txt"), "Backup B.").unwrap();
+            fs::write(dir.join("c.txt"), "Backup C.").unwrap();
+            for name in ["a.txt", "nested/b.txt"] {
+                filetime::set_file_mtime(dir.join(name), middle).unwrap();
+            }
+
+            let backup_dir = backup(dir).unwrap();
+
+            // the directory is partially populated again
+            fs::create_dir_all(dir.join("nested")).unwrap();
+            fs::write(dir.join("a.txt"), "Current A.").unwrap();
+            fs::write(dir.join("nested/b.txt"), "Current B.").unwrap();
+            fs::write(dir.join("d.txt"), "Current D.").unwrap();
+            filetime::set_file_mtime(dir.join("a.txt"), old).unwrap();
+            filetime::set_file_mtime(dir.join("nested/b.txt"), new).unwrap();
+
+            match restore_dir_with_strategy(dir, strategy) {
+                Ok(x) => assert_eq!(&x, dir),
+                Err(_) => panic!("Restore failed."),
+            }
+
+            assert_eq!(fs::read_to_string(dir.join("a.txt")).unwrap(), a);
+            assert_eq!(fs::read_to_string(dir.join("nested/b.txt")).unwrap(), b);
+            assert_eq!(fs::read_to_string(dir.join("c.txt")).unwrap(), "Backup C.");
+            assert_eq!(fs::read_to_string(dir.join("d.txt")).unwrap(), "Current D.");
+            assert!(!backup_dir.exists());
+        }
+    }
+
+    #[test]
+    fn restore_with_strategy_errors() {
+        let tmp = test_dir();
+        let dir = &tmp.join("dir");
+
+        match restore_dir_with_strategy(dir, RestoreStrategy::Overwrite) {
+            Ok(_) => panic!("Restore should have failed, but it was successful."),
+            Err(e) => assert!(matches!(e, BackupError::NoBackup)),
+        }
+
+        // the backup is renamed if the directory does not exist
+        fs::create_dir(dir).unwrap();
+        fs::write(dir.join("a.txt"), "Backup A.").unwrap();
+        backup(dir).unwrap();
+        restore_dir_with_strategy(dir, RestoreStrategy::SkipExisting).unwrap();
+        assert_eq!(fs::read_to_string(dir.join("a.txt")).unwrap(), "Backup A.");
+
+        // directory backups can not be merged into files and file backups are not supported
+        let backup_dir = backup(dir).unwrap();
+        fs::write(dir, "Some content to test.").unwrap();
+        match restore_dir_with_strategy(dir, RestoreStrategy::Overwrite) {
+            Ok(_) => panic!("Restore should have failed, but it was successful."),
+            Err(e) => assert!(matches!(e, BackupError::Unsupported(_))),
+        }
+        assert!(backup_dir.is_dir());
+
+        fs::remove_dir_all(backup_dir).unwrap();
+        backup(dir).unwrap();
+        match restore_dir_with_strategy(dir, RestoreStrategy::Overwrite) {
+            Ok(_) => panic!("Restore should have failed, but it was successful."),
+            Err(e) => assert!(matches!(e, BackupError::Unsupported(_))),
+        }
+    }
+
+    #[test]
+    fn restore_no_backup() {
+        match restore("test_file_restore4.txt") {
+            Ok(_) => panic!("Restore should have failed, but it was successful."),
+            Err(e) => assert!(matches!(e, BackupError::NoBackup)),
+        }
+    }
+}
@@ -0,0 +1,71 @@
+// Released under MIT License.
+// Copyright (c) 2023 Ladislav Bartos
+
+//! Helpers shared by the tests of the crate.
+
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+
+use tempfile::TempDir;
+
+/// Isolated directory in which a single test creates its files.
+///
+/// The directory is located in the temporary directory of the system and is removed
+/// (including its content) when dropped, even if the test fails. Dereferences to its path,
+/// so the files of the test are created using e.g. `dir.join("test_file.txt")`.
+pub(crate) struct TestDir(TempDir);
+
+impl Deref for TestDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        self.0.path()
+    }
+}
+
+/// Creates a new isolated directory for a test.
+pub(crate) fn test_dir() -> TestDir {
+    match tempfile::Builder::new().prefix("backitup-test-").tempdir() {
+        Ok(dir) => TestDir(dir),
+        Err(e) => panic!("Could not create a temporary directory: {}", e),
+    }
+}
+
+/// Lock serializing the tests which change the current working directory.
+static CURRENT_DIR: Mutex<()> = Mutex::new(());
+
+/// Guard changing the current working directory of the process for a single test.
+///
+/// Only one guard exists at a time, so the tests changing the working directory do not interfere
+/// with each other. The original working directory is restored when dropped, even if the test fails.
+pub(crate) struct CurrentDir {
+    original: PathBuf,
+    _lock: MutexGuard<'static, ()>,
+}
+
+impl Drop for CurrentDir {
+    fn drop(&mut self) {
+        let _ = std::env::set_current_dir(&self.original);
+    }
+}
+
+/// Changes the current working directory to `dir` until the returned guard is dropped.
+/// The guard must be dropped before `dir` is removed.
+pub(crate) fn set_current_dir(dir: &Path) -> CurrentDir {
+    // a test failing while holding the lock does not affect the other tests
+    let lock = CURRENT_DIR.lock().unwrap_or_else(|e| e.into_inner());
+    let original = match std::env::current_dir() {
+        Ok(x) => x,
+        Err(e) => panic!("Could not get the current working directory: {}", e),
+    };
+
+    if let Err(e) = std::env::set_current_dir(dir) {
+        panic!("Could not change the current working directory: {}", e);
+    }
+
+    CurrentDir {
+        original,
+        _lock: lock,
+    }
+}
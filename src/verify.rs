@@ -0,0 +1,493 @@
+// Released under MIT License.
+// Copyright (c) 2023 Ladislav Bartos
+
+//! Implementation of backups that compare the content of files.
+
+use std::ffi::OsString;
+use std::fmt::Write as _;
+use std::fs::{self, File};
+use std::io::{self, Write as _};
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+
+use crate::{backup, backup_copy, remove_path, write_via_temp, BackupError};
+
+/// Extension of the files containing the checksums of the backups.
+const CHECKSUM_EXTENSION: &str = ".sha256";
+
+/// Creates a backup copy of the specified file or directory and verifies that it matches the original.
+/// Returns the path to the backup file if successful, otherwise returns an error.
+///
+/// # Arguments
+///
+/// * `path` - The path to the file or directory to be backed up.
+///
+/// # Errors
+///
+/// This function can return the same errors as [`crate::backup_copy`] and additionally:
+///
+/// * `BackupError::VerificationFailed` - If the content of the backup does not match the content of the original.
+///
+/// # Details
+/// The backup is created in the same way as with [`crate::backup_copy`]. The original and the backup
+/// are then read again and their SHA-256 digests are compared. For directories, each nested file is compared
+/// with the corresponding file of the backup and both directories must contain the same entries.
+///
+/// If the verification fails (or if the files can not be read), the backup is **removed**.
+/// The original file (or directory) is never modified.
+///
+/// # Examples
+///
+/// ```no_run
+/// use crate::backitup::backup_copy_verified;
+///
+/// let path = "data.txt";
+/// match backup_copy_verified(path) {
+///     Ok(backup_path) => println!("Backup created: {:?}", backup_path),
+///     Err(err) => eprintln!("Failed to create backup: {:?}", err),
+/// }
+/// ```
+pub fn backup_copy_verified(path: impl AsRef<Path>) -> Result<PathBuf, BackupError> {
+    let path = path.as_ref();
+    let backup_name = backup_copy(path)?;
+
+    match same_content(path, &backup_name) {
+        Ok(true) => Ok(backup_name),
+        Ok(false) => {
+            let _ = remove_path(&backup_name);
+            Err(BackupError::VerificationFailed)
+        }
+        Err(e) => {
+            let _ = remove_path(&backup_name);
+            Err(BackupError::Io(e))
+        }
+    }
+}
+
+/// Creates a backup of the specified file or directory, unless its content matches the content of `against`.
+/// Returns the path to the backup file, if a backup was created, `None`, if it was not necessary,
+/// or an error, if the backup failed.
+///
+/// # Arguments
+///
+/// * `path` - The path to the file or directory to be backed up.
+/// * `against` - The path to the reference file or directory (e.g. the previous backup).
+///
+/// # Errors
+///
+/// This function can return the same errors as [`crate::backup`] and additionally:
+///
+/// * `BackupError::Io` - If the files can not be read.
+///
+/// # Details
+/// The content of `path` is compared with the content of `against` (using SHA-256 digests).
+/// Directories are compared recursively. If the contents are identical, no backup is created
+/// and `Ok(None)` is returned. Otherwise, the backup is created in the same way as with [`crate::backup`].
+/// If `against` does not exist, the backup is always created.
+///
+/// # Examples
+///
+/// ```no_run
+/// use crate::backitup::{backup_if_changed, list_backups};
+///
+/// let path = "data.txt";
+/// let previous = list_backups(path).unwrap().pop().unwrap();
+/// match backup_if_changed(path, previous) {
+///     Ok(Some(backup_path)) => println!("Backup created: {:?}", backup_path),
+///     Ok(None) => println!("File has not changed."),
+///     Err(err) => eprintln!("Failed to create backup: {:?}", err),
+/// }
+/// ```
+pub fn backup_if_changed(
+    path: impl AsRef<Path>,
+    against: impl AsRef<Path>,
+) -> Result<Option<PathBuf>, BackupError> {
+    let (path, against) = (path.as_ref(), against.as_ref());
+
+    // check if the path exists
+    if !path.exists() {
+        return Err(BackupError::NotFound);
+    }
+
+    if against.exists() && same_content(path, against)? {
+        return Ok(None);
+    }
+
+    backup(path).map(Some)
+}
+
+/// Creates a backup of the specified file or directory accompanied by a file containing its SHA-256 checksum.
+/// Returns the path to the backup file if successful, otherwise returns an error.
+///
+/// # Arguments
+///
+/// * `path` - The path to the file or directory to be backed up.
+///
+/// # Errors
+///
+/// This function can return the same errors as [`crate::backup`] and additionally:
+///
+/// * `BackupError::Io` - If the backup can not be read or the checksum can not be written.
+///   The backup itself is kept in this case.
+///
+/// # Details
+/// The backup is created in the same way as with [`crate::backup`]. The SHA-256 digest of the backup
+/// is then written into `<backup>.sha256` in the format of `sha256sum` (`<digest>  <name>`).
+/// For directories, the file contains one line for each nested file, with the path relative to the backup.
+/// Use [`verify_backup`] to check the backup against the checksum later.
+///
+/// Note that the checksum files are not removed when the backups are pruned.
+///
+/// # Examples
+///
+/// ```no_run
+/// use crate::backitup::backup_with_checksum;
+///
+/// let path = "data.txt";
+/// match backup_with_checksum(path) {
+///     Ok(backup_path) => println!("Backup created: {:?}", backup_path),
+///     Err(err) => eprintln!("Failed to create backup: {:?}", err),
+/// }
+/// ```
+pub fn backup_with_checksum(path: impl AsRef<Path>) -> Result<PathBuf, BackupError> {
+    let backup_name = backup(path)?;
+
+    let manifest = checksum_manifest(&backup_name)?;
+    write_via_temp(&checksum_path(&backup_name), |temp| {
+        File::create_new(temp)?.write_all(manifest.as_bytes())
+    })?;
+
+    Ok(backup_name)
+}
+
+/// Checks that the content of a backup matches the checksum written by [`backup_with_checksum`].
+/// Returns `true` if the backup has not been modified, `false` if it has, or an error, if the check failed.
+///
+/// # Arguments
+///
+/// * `backup_path` - The path to the backup.
+///
+/// # Errors
+///
+/// This function can return the following errors:
+///
+/// * `BackupError::NotFound` - If the backup or its checksum file (`<backup>.sha256`) does not exist.
+/// * `BackupError::Io` - If the backup or its checksum can not be read.
+///
+/// # Details
+/// The SHA-256 digest of the backup is calculated again and compared with the content of `<backup>.sha256`.
+/// For directories, added, removed, and modified nested files all cause the verification to fail.
+///
+/// # Examples
+///
+/// ```no_run
+/// use crate::backitup::{backup_with_checksum, verify_backup};
+///
+/// let backup_path = backup_with_checksum("data.txt").unwrap();
+/// match verify_backup(&backup_path) {
+///     Ok(true) => println!("Backup is intact."),
+///     Ok(false) => eprintln!("Backup has been modified!"),
+///     Err(err) => eprintln!("Failed to verify backup: {:?}", err),
+/// }
+/// ```
+pub fn verify_backup(backup_path: impl AsRef<Path>) -> Result<bool, BackupError> {
+    let backup_path = backup_path.as_ref();
+
+    let expected = match fs::read_to_string(checksum_path(backup_path)) {
+        Ok(x) => x,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Err(BackupError::NotFound),
+        Err(e) => return Err(BackupError::Io(e)),
+    };
+
+    match checksum_manifest(backup_path) {
+        Ok(x) => Ok(x == expected),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Err(BackupError::NotFound),
+        Err(e) => Err(BackupError::Io(e)),
+    }
+}
+
+/// Returns the path to the file containing the checksum of the backup at `backup_path`.
+fn checksum_path(backup_path: &Path) -> PathBuf {
+    let mut path = OsString::from(backup_path);
+    path.push(CHECKSUM_EXTENSION);
+    PathBuf::from(path)
+}
+
+/// Returns the SHA-256 checksums of the file (or of all files in the directory) at `path`
+/// in the format of `sha256sum`. Nested files are sorted by their paths relative to `path`.
+fn checksum_manifest(path: &Path) -> Result<String, io::Error> {
+    // pairs of the name written into the manifest and the path to the file
+    let mut files = Vec::new();
+    if fs::metadata(path)?.is_dir() {
+        collect_files(path, PathBuf::new(), &mut files)?;
+        files.sort();
+    } else {
+        let name = PathBuf::from(path.file_name().unwrap_or_default());
+        files.push((name, path.to_path_buf()));
+    }
+
+    let mut manifest = String::new();
+    for (name, file) in files {
+        for byte in file_digest(&file)? {
+            // writing into a `String` never fails
+            let _ = write!(manifest, "{:02x}", byte);
+        }
+
+        // the paths are written with forward slashes on all platforms
+        let name = name
+            .components()
+            .map(|x| x.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        let _ = writeln!(manifest, "  {}", name);
+    }
+
+    Ok(manifest)
+}
+
+/// Collects the paths (relative to `root` and full) of all files located in `root.join(relative)`.
+pub(crate) fn collect_files(
+    root: &Path,
+    relative: PathBuf,
+    files: &mut Vec<(PathBuf, PathBuf)>,
+) -> Result<(), io::Error> {
+    for entry in fs::read_dir(root.join(&relative))? {
+        let entry = entry?;
+        let name = relative.join(entry.file_name());
+        let path = root.join(&name);
+
+        if fs::metadata(&path)?.is_dir() {
+            collect_files(root, name, files)?;
+        } else {
+            files.push((name, path));
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns `true` if the files (or directories) at `a` and `b` have the same content.
+/// Directories are compared recursively.
+pub(crate) fn same_content(a: &Path, b: &Path) -> Result<bool, io::Error> {
+    let (meta_a, meta_b) = (fs::metadata(a)?, fs::metadata(b)?);
+
+    match (meta_a.is_dir(), meta_b.is_dir()) {
+        (true, true) => {
+            let mut entries_a = fs::read_dir(a)?
+                .map(|x| x.map(|x| x.file_name()))
+                .collect::<Result<Vec<_>, _>>()?;
+            let mut entries_b = fs::read_dir(b)?
+                .map(|x| x.map(|x| x.file_name()))
+                .collect::<Result<Vec<_>, _>>()?;
+
+            entries_a.sort();
+            entries_b.sort();
+            if entries_a != entries_b {
+                return Ok(false);
+            }
+
+            for name in entries_a {
+                if !same_content(&a.join(&name), &b.join(&name))? {
+                    return Ok(false);
+                }
+            }
+
+            Ok(true)
+        }
+        (false, false) => {
+            // files of different sizes can not have the same content
+            if meta_a.len() != meta_b.len() {
+                return Ok(false);
+            }
+
+            Ok(file_digest(a)? == file_digest(b)?)
+        }
+        _ => Ok(false),
+    }
+}
+
+/// Calculates the SHA-256 digest of the content of the file at `path`.
+pub(crate) fn file_digest(path: &Path) -> Result<Vec<u8>, io::Error> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+
+    Ok(hasher.finalize().to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::test_dir;
+
+    #[test]
+    fn compare_files() {
+        let tmp = test_dir();
+        fs::create_dir(tmp.join("test_dir_compare")).unwrap();
+
+        for (name, content) in [
+            ("test_file1.txt", "Some content to test."),
+            ("test_file2.txt", "Some content to test."),
+            ("test_file3.txt", "Some content to test!"),
+            ("test_file4.txt", "Some content."),
+        ] {
+            let mut file = File::create(tmp.join("test_dir_compare").join(name)).unwrap();
+            file.write_all(content.as_bytes()).unwrap();
+        }
+
+        let dir = &tmp.join("test_dir_compare");
+        assert!(same_content(&dir.join("test_file1.txt"), &dir.join("test_file2.txt")).unwrap());
+        assert!(!same_content(&dir.join("test_file1.txt"), &dir.join("test_file3.txt")).unwrap());
+        assert!(!same_content(&dir.join("test_file1.txt"), &dir.join("test_file4.txt")).unwrap());
+        assert!(!same_content(&dir.join("test_file1.txt"), dir).unwrap());
+    }
+
+    #[test]
+    fn compare_directories() {
+        let tmp = test_dir();
+        let (a, b) = (&tmp.join("a"), &tmp.join("b"));
+        for dir in [a, b] {
+            fs::create_dir_all(dir.join("nested")).unwrap();
+            let mut file = File::create(dir.join("nested/test_file.txt")).unwrap();
+            file.write_all(b"Some content to test.").unwrap();
+        }
+
+        assert!(same_content(a, b).unwrap());
+
+        File::create(b.join("nested/extra.txt")).unwrap();
+        assert!(!same_content(a, b).unwrap());
+    }
+
+    #[test]
+    fn if_changed() {
+        let tmp = test_dir();
+        fs::create_dir(tmp.join("test_dir_if_changed")).unwrap();
+
+        for (name, content) in [
+            ("test_file.txt", "Some content to test."),
+            ("reference1.txt", "Some content to test."),
+            ("reference2.txt", "Some other content."),
+        ] {
+            let mut file = File::create(tmp.join("test_dir_if_changed").join(name)).unwrap();
+            file.write_all(content.as_bytes()).unwrap();
+        }
+
+        // identical files
+        match backup_if_changed(
+            tmp.join("test_dir_if_changed/test_file.txt"),
+            tmp.join("test_dir_if_changed/reference1.txt"),
+        ) {
+            Ok(x) => assert_eq!(x, None),
+            Err(_) => panic!("Backup failed."),
+        }
+        assert!(tmp.join("test_dir_if_changed/test_file.txt").is_file());
+
+        // different files
+        let backup = match backup_if_changed(
+            tmp.join("test_dir_if_changed/test_file.txt"),
+            tmp.join("test_dir_if_changed/reference2.txt"),
+        ) {
+            Ok(Some(x)) => x,
+            Ok(None) => panic!("Backup should have been created."),
+            Err(_) => panic!("Backup failed."),
+        };
+
+        assert!(!tmp.join("test_dir_if_changed/test_file.txt").exists());
+        let content = fs::read_to_string(backup).unwrap();
+        assert_eq!(content, "Some content to test.");
+    }
+
+    #[test]
+    fn checksum() {
+        let tmp = test_dir();
+        let mut file = File::create(tmp.join("test_file_checksum.txt")).unwrap();
+        file.write_all(b"Some content to test.").unwrap();
+
+        let backup = match backup_with_checksum(tmp.join("test_file_checksum.txt")) {
+            Ok(x) => x,
+            Err(_) => panic!("Backup failed."),
+        };
+
+        let sidecar = checksum_path(&backup);
+        let expected = format!(
+            "{}  {}\n",
+            "6f816441245f75165986ca7210ae36c71325e9ccc52d9d27d6d7d16f0a24dcb6",
+            backup.file_name().unwrap().to_str().unwrap()
+        );
+        assert_eq!(fs::read_to_string(&sidecar).unwrap(), expected);
+        assert!(matches!(verify_backup(&backup), Ok(true)));
+
+        // tamper with the backup
+        fs::write(&backup, "Some modified content.").unwrap();
+        assert!(matches!(verify_backup(&backup), Ok(false)));
+
+        // the checksum is missing
+        fs::remove_file(&sidecar).unwrap();
+        assert!(matches!(verify_backup(&backup), Err(BackupError::NotFound)));
+    }
+
+    #[test]
+    fn directory_checksum() {
+        let tmp = test_dir();
+        fs::create_dir_all(tmp.join("test_dir_checksum/nested")).unwrap();
+        fs::write(
+            tmp.join("test_dir_checksum/test_file.txt"),
+            "Some content to test.",
+        )
+        .unwrap();
+        fs::write(
+            tmp.join("test_dir_checksum/nested/test_file.txt"),
+            "Nested content.",
+        )
+        .unwrap();
+
+        let backup = match backup_with_checksum(tmp.join("test_dir_checksum")) {
+            Ok(x) => x,
+            Err(_) => panic!("Backup failed."),
+        };
+
+        let manifest = fs::read_to_string(checksum_path(&backup)).unwrap();
+        let names = manifest
+            .lines()
+            .map(|x| x.split_once("  ").unwrap().1)
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["nested/test_file.txt", "test_file.txt"]);
+        assert!(matches!(verify_backup(&backup), Ok(true)));
+
+        // an added file is detected
+        File::create(backup.join("nested/extra.txt")).unwrap();
+        assert!(matches!(verify_backup(&backup), Ok(false)));
+    }
+
+    #[test]
+    fn copy_verified() {
+        let tmp = test_dir();
+        let mut file = File::create(tmp.join("test_file_verified.txt")).unwrap();
+        file.write_all(b"Some content to test.").unwrap();
+
+        let backup = match backup_copy_verified(tmp.join("test_file_verified.txt")) {
+            Ok(x) => x,
+            Err(_) => panic!("Backup failed."),
+        };
+
+        let content = fs::read_to_string(&backup).unwrap();
+        assert_eq!(content, "Some content to test.");
+        assert!(tmp.join("test_file_verified.txt").is_file());
+    }
+
+    #[test]
+    fn directory_copy_verified() {
+        let tmp = test_dir();
+        fs::create_dir_all(tmp.join("test_dir_verified/nested")).unwrap();
+        let mut file = File::create(tmp.join("test_dir_verified/nested/test_file.txt")).unwrap();
+        file.write_all(b"Some content to test.").unwrap();
+
+        let backup = match backup_copy_verified(tmp.join("test_dir_verified")) {
+            Ok(x) => x,
+            Err(_) => panic!("Backup failed."),
+        };
+
+        let content = fs::read_to_string(backup.join("nested/test_file.txt")).unwrap();
+        assert_eq!(content, "Some content to test.");
+    }
+}